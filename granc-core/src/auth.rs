@@ -0,0 +1,119 @@
+//! # Authentication
+//!
+//! This module defines the [`Credentials`] trait, a pluggable source of authentication metadata
+//! attached to every request sent by [`crate::grpc::client::GrpcClient`] and
+//! [`crate::reflection::client::ReflectionClient`].
+//!
+//! Credentials are resolved right before each request is sent, so providers can refresh expired
+//! tokens transparently. Metadata set explicitly on a request takes precedence over the metadata
+//! of the credentials.
+//!
+//! ## Providers
+//!
+//! * [`token::StaticToken`] - A fixed bearer token.
+//! * [`token::TokenCommand`] - A bearer token printed by a command (e.g. `gcloud auth print-access-token`).
+//! * [`oauth2::ClientCredentials`] - A token obtained with the OAuth2 client credentials flow.
+use crate::BoxError;
+use std::{
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    process::ExitStatus,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use tonic::metadata::{AsciiMetadataKey, MetadataMap, MetadataValue};
+
+pub mod oauth2;
+pub mod token;
+
+/// Errors that can occur when resolving credentials.
+#[derive(Debug, thiserror::Error)]
+pub enum CredentialsError {
+    #[error("Failed to run token command '{0}': {1}")]
+    CommandSpawn(String, #[source] std::io::Error),
+    #[error("Token command '{command}' failed with {status}: {stderr}")]
+    CommandFailed {
+        command: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    #[error("Token command '{0}' did not print a token")]
+    EmptyToken(String),
+    #[error("Invalid token URL '{0}'")]
+    InvalidTokenUrl(String),
+    #[error("Token request to '{0}' failed: {1}")]
+    TokenRequest(String, #[source] BoxError),
+    #[error("Token endpoint '{url}' returned {status}: {body}")]
+    TokenEndpoint {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    #[error("Invalid token response from '{0}': {1}")]
+    InvalidTokenResponse(String, String),
+    #[error("Invalid credentials metadata '{0}'")]
+    InvalidMetadata(String),
+}
+
+/// The future returned by [`Credentials::metadata`].
+pub type CredentialsFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<Vec<(String, String)>, CredentialsError>> + Send + 'a>>;
+
+/// A source of authentication metadata.
+///
+/// Implementors return the ASCII metadata (e.g. `authorization: Bearer <token>`) to attach to the
+/// next request. It is called before every request, so providers fetching tokens should cache them.
+pub trait Credentials: Debug + Send + Sync {
+    /// Returns the metadata authenticating the next request.
+    fn metadata(&self) -> CredentialsFuture<'_>;
+}
+
+/// Resolves the credentials and adds their metadata to a request, skipping the keys it already has.
+pub(crate) async fn apply(
+    credentials: &dyn Credentials,
+    metadata: &mut MetadataMap,
+) -> Result<(), CredentialsError> {
+    for (key, value) in credentials.metadata().await? {
+        let invalid = || CredentialsError::InvalidMetadata(key.clone());
+
+        let parsed_key = AsciiMetadataKey::from_str(&key).map_err(|_| invalid())?;
+        if metadata.contains_key(&parsed_key) {
+            continue;
+        }
+
+        let parsed_value = MetadataValue::from_str(&value).map_err(|_| invalid())?;
+        metadata.insert(parsed_key, parsed_value);
+    }
+
+    Ok(())
+}
+
+/// A bearer token, cached until it expires.
+#[derive(Debug, Clone)]
+struct CachedToken {
+    token: String,
+    expires_at: Option<Instant>,
+}
+
+impl CachedToken {
+    fn new(token: String, lifetime: Option<Duration>) -> Self {
+        Self {
+            token,
+            expires_at: lifetime.map(|lifetime| Instant::now() + lifetime),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.expires_at.is_none_or(|at| Instant::now() < at)
+    }
+
+    fn metadata(&self) -> Vec<(String, String)> {
+        vec![bearer(&self.token)]
+    }
+}
+
+/// The `authorization` metadata of a bearer token.
+fn bearer(token: &str) -> (String, String) {
+    ("authorization".to_string(), format!("Bearer {token}"))
+}
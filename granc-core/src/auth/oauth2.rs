@@ -0,0 +1,184 @@
+//! # OAuth2
+//!
+//! Access tokens obtained with the OAuth2 client credentials flow
+//! ([RFC 6749, section 4.4](https://datatracker.ietf.org/doc/html/rfc6749#section-4.4)).
+//!
+//! The client authenticates to the token endpoint with HTTP basic authentication, and the token is
+//! cached until shortly before it expires. The token endpoint is reached over plain HTTP, as TLS
+//! is not supported yet.
+use super::{CachedToken, Credentials, CredentialsError, CredentialsFuture};
+use crate::connector::{Proxy, TcpConnector};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::Bytes;
+use http::{
+    Request, Uri,
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+};
+use http_body_util::{BodyExt, Full};
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Characters escaped in form values: everything but the unreserved characters of RFC 3986.
+const FORM: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Tokens are refreshed this long before they expire, so they do not expire in flight.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// Sends an access token obtained from an OAuth2 token endpoint with the client credentials grant.
+pub struct ClientCredentials {
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scopes: Vec<String>,
+    proxy: Option<Proxy>,
+    cache: Mutex<Option<CachedToken>>,
+}
+
+impl std::fmt::Debug for ClientCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientCredentials")
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("scopes", &self.scopes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClientCredentials {
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scopes: Vec::new(),
+            proxy: None,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Requests the given scope. Can be called multiple times to request several scopes.
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scopes.push(scope.into());
+        self
+    }
+
+    /// Reaches the token endpoint through a proxy.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    async fn fetch(&self) -> Result<CachedToken, CredentialsError> {
+        let uri: Uri = self
+            .token_url
+            .parse()
+            .map_err(|_| CredentialsError::InvalidTokenUrl(self.token_url.clone()))?;
+
+        let mut form = vec![("grant_type", "client_credentials".to_string())];
+        if !self.scopes.is_empty() {
+            form.push(("scope", self.scopes.join(" ")));
+        }
+        let body = form
+            .iter()
+            .map(|(key, value)| format!("{key}={}", encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        // The client id and secret are form-encoded before being joined (RFC 6749, section 2.3.1)
+        let basic = STANDARD.encode(format!(
+            "{}:{}",
+            encode(&self.client_id),
+            encode(&self.client_secret)
+        ));
+
+        let request = Request::post(uri)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, format!("Basic {basic}"))
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| CredentialsError::TokenRequest(self.token_url.clone(), e.into()))?;
+
+        let client = Client::builder(TokioExecutor::new())
+            .build::<_, Full<Bytes>>(TcpConnector::new(self.proxy.clone(), None));
+
+        let request_failed =
+            |e: crate::BoxError| CredentialsError::TokenRequest(self.token_url.clone(), e);
+
+        let response = client
+            .request(request)
+            .await
+            .map_err(|e| request_failed(e.into()))?;
+
+        let status = response.status();
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| request_failed(e.into()))?
+            .to_bytes();
+
+        if !status.is_success() {
+            return Err(CredentialsError::TokenEndpoint {
+                url: self.token_url.clone(),
+                status: status.as_u16(),
+                body: String::from_utf8_lossy(&body).trim().to_string(),
+            });
+        }
+
+        let invalid = |message: &str| {
+            CredentialsError::InvalidTokenResponse(self.token_url.clone(), message.to_string())
+        };
+
+        let response: serde_json::Value =
+            serde_json::from_slice(&body).map_err(|e| invalid(&e.to_string()))?;
+
+        let token = response["access_token"]
+            .as_str()
+            .ok_or_else(|| invalid("missing access_token"))?;
+
+        if let Some(token_type) = response["token_type"].as_str()
+            && !token_type.eq_ignore_ascii_case("bearer")
+        {
+            return Err(invalid(&format!("unsupported token type '{token_type}'")));
+        }
+
+        let lifetime = response["expires_in"]
+            .as_u64()
+            .map(|seconds| Duration::from_secs(seconds).saturating_sub(EXPIRY_MARGIN));
+
+        Ok(CachedToken::new(token.to_string(), lifetime))
+    }
+}
+
+impl Credentials for ClientCredentials {
+    fn metadata(&self) -> CredentialsFuture<'_> {
+        Box::pin(async {
+            // Held while the token is fetched, so concurrent requests share a single fetch
+            let mut cache = self.cache.lock().await;
+
+            if let Some(cached) = cache.as_ref().filter(|cached| cached.is_valid()) {
+                return Ok(cached.metadata());
+            }
+
+            let cached = self.fetch().await?;
+            let metadata = cached.metadata();
+            *cache = Some(cached);
+
+            Ok(metadata)
+        })
+    }
+}
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, FORM).to_string()
+}
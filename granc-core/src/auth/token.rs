@@ -0,0 +1,114 @@
+//! # Token Providers
+//!
+//! Bearer tokens given directly, or printed by a command.
+use super::{CachedToken, Credentials, CredentialsError, CredentialsFuture, bearer};
+use std::time::Duration;
+use tokio::{process::Command, sync::Mutex};
+
+/// How long the token printed by a [`TokenCommand`] is reused by default.
+const DEFAULT_COMMAND_CACHE: Duration = Duration::from_secs(5 * 60);
+
+/// Sends a fixed bearer token: `authorization: Bearer <token>`.
+#[derive(Clone)]
+pub struct StaticToken {
+    token: String,
+}
+
+impl std::fmt::Debug for StaticToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StaticToken").finish_non_exhaustive()
+    }
+}
+
+impl StaticToken {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+        }
+    }
+}
+
+impl Credentials for StaticToken {
+    fn metadata(&self) -> CredentialsFuture<'_> {
+        Box::pin(async { Ok(vec![bearer(&self.token)]) })
+    }
+}
+
+/// Sends the bearer token printed by a shell command, such as `gcloud auth print-access-token`.
+///
+/// The command is run with `sh -c` (`cmd /C` on Windows), and its output is trimmed. The token is
+/// reused for 5 minutes by default, see [`TokenCommand::cache_for`].
+#[derive(Debug)]
+pub struct TokenCommand {
+    command: String,
+    cache_for: Duration,
+    cache: Mutex<Option<CachedToken>>,
+}
+
+impl TokenCommand {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            cache_for: DEFAULT_COMMAND_CACHE,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Sets how long a token is reused before the command is run again.
+    pub fn cache_for(mut self, duration: Duration) -> Self {
+        self.cache_for = duration;
+        self
+    }
+
+    async fn run(&self) -> Result<String, CredentialsError> {
+        let mut command = if cfg!(windows) {
+            let mut command = Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c");
+            command
+        };
+
+        let output = command
+            .arg(&self.command)
+            .output()
+            .await
+            .map_err(|e| CredentialsError::CommandSpawn(self.command.clone(), e))?;
+
+        if !output.status.success() {
+            return Err(CredentialsError::CommandFailed {
+                command: self.command.clone(),
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if token.is_empty() {
+            return Err(CredentialsError::EmptyToken(self.command.clone()));
+        }
+
+        Ok(token)
+    }
+}
+
+impl Credentials for TokenCommand {
+    fn metadata(&self) -> CredentialsFuture<'_> {
+        Box::pin(async {
+            // Held while the command runs, so concurrent requests share a single run
+            let mut cache = self.cache.lock().await;
+
+            if let Some(cached) = cache.as_ref().filter(|cached| cached.is_valid()) {
+                return Ok(cached.metadata());
+            }
+
+            let cached = CachedToken::new(self.run().await?, Some(self.cache_for));
+            let metadata = cached.metadata();
+            *cache = Some(cached);
+
+            Ok(metadata)
+        })
+    }
+}
@@ -32,6 +32,7 @@
 //! # Ok(())
 //! # }
 //! ```
+pub mod interceptor;
 pub mod offline;
 pub mod online;
 pub mod online_without_reflection;
@@ -40,8 +41,9 @@ mod types;
 pub use types::*;
 
 use crate::{grpc::client::GrpcClient, reflection::client::ReflectionClient};
+use interceptor::{Interceptor, Interceptors};
 use prost_reflect::DescriptorPool;
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 use tonic::transport::Channel;
 
 /// The main client for interacting with gRPC servers dynamically.
@@ -50,11 +52,24 @@ use tonic::transport::Channel;
 #[derive(Clone, Debug)]
 pub struct GrancClient<T> {
     state: T,
+    interceptors: Interceptors,
 }
 
 impl<T> GrancClient<T> {
     pub(crate) fn new(state: T) -> Self {
-        Self { state }
+        Self {
+            state,
+            interceptors: Interceptors::default(),
+        }
+    }
+
+    /// Registers an [`Interceptor`] called around every dynamic call made by this client.
+    ///
+    /// Interceptors are kept when the client changes state, and run in registration order
+    /// before a call (in reverse order after it).
+    pub fn with_interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
     }
 }
 
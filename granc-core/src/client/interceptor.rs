@@ -0,0 +1,129 @@
+//! # Interceptors
+//!
+//! This module defines the [`Interceptor`] trait, a hook around every dynamic call made by a
+//! [`GrancClient`](super::GrancClient), e.g. to log calls, collect metrics or propagate tracing context.
+//!
+//! Interceptors are registered with [`GrancClient::with_interceptor`](super::GrancClient::with_interceptor)
+//! in any state, and are kept across state transitions. They run in registration order before a
+//! call, and in reverse order after it.
+//!
+//! ## Example
+//!
+//! ```rust
+//! use granc_core::client::DynamicRequest;
+//! use granc_core::client::interceptor::{CallOutcome, Interceptor};
+//! use granc_core::prost_reflect::MethodDescriptor;
+//! use granc_core::tonic::Status;
+//!
+//! #[derive(Debug)]
+//! struct Logger;
+//!
+//! impl Interceptor for Logger {
+//!     fn before_call(
+//!         &self,
+//!         method: &MethodDescriptor,
+//!         request: &mut DynamicRequest,
+//!     ) -> Result<(), Status> {
+//!         request.headers.push(("x-request-id".to_string(), "42".to_string()));
+//!         println!("-> {}", method.full_name());
+//!         Ok(())
+//!     }
+//!
+//!     fn after_call(&self, method: &MethodDescriptor, _: &DynamicRequest, outcome: &CallOutcome<'_>) {
+//!         println!("<- {} in {:?}", method.full_name(), outcome.elapsed);
+//!     }
+//! }
+//! ```
+use super::{DynamicRequest, DynamicResponse, ResponseMetadata};
+use prost_reflect::MethodDescriptor;
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+/// A hook around the dynamic calls of a [`GrancClient`](super::GrancClient).
+///
+/// Both methods have a default implementation doing nothing, so implementors only override the
+/// ones they need. Credentials that must be fetched asynchronously are better served by
+/// [`crate::auth::Credentials`].
+pub trait Interceptor: Debug + Send + Sync {
+    /// Called once the method is resolved, before the request is sent.
+    ///
+    /// The request metadata and JSON payload can be modified. Returning an error aborts the call,
+    /// which then fails with the returned status without reaching the server, and skips the
+    /// interceptors registered after this one.
+    fn before_call(
+        &self,
+        method: &MethodDescriptor,
+        request: &mut DynamicRequest,
+    ) -> Result<(), tonic::Status> {
+        let _ = (method, request);
+        Ok(())
+    }
+
+    /// Called with the outcome of the call, if [`Interceptor::before_call`] was called. This
+    /// includes calls aborted by this interceptor or a later one.
+    ///
+    /// It is not called when no status is received, e.g. when the payload is invalid or the
+    /// transport fails, in which case the call returns an error.
+    fn after_call(
+        &self,
+        method: &MethodDescriptor,
+        request: &DynamicRequest,
+        outcome: &CallOutcome<'_>,
+    ) {
+        let _ = (method, request, outcome);
+    }
+}
+
+/// The outcome of a call, as seen by [`Interceptor::after_call`].
+#[derive(Debug, Clone, Copy)]
+pub struct CallOutcome<'a> {
+    /// The response, or the status the call failed with.
+    pub response: &'a DynamicResponse,
+    /// The headers and trailers sent by the server.
+    pub metadata: &'a ResponseMetadata,
+    /// The time the call took, excluding the interceptors.
+    pub elapsed: Duration,
+}
+
+/// The interceptors registered on a client, in registration order.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Interceptors(Vec<Arc<dyn Interceptor>>);
+
+impl Interceptors {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.0.push(interceptor);
+    }
+
+    /// Runs the interceptors before the call, stopping at the first one aborting it.
+    ///
+    /// Returns how many interceptors ran, which are the ones to run after the call.
+    pub(crate) fn before_call(
+        &self,
+        method: &MethodDescriptor,
+        request: &mut DynamicRequest,
+    ) -> (usize, Result<(), tonic::Status>) {
+        for (index, interceptor) in self.0.iter().enumerate() {
+            if let Err(status) = interceptor.before_call(method, request) {
+                return (index + 1, Err(status));
+            }
+        }
+
+        (self.0.len(), Ok(()))
+    }
+
+    /// Runs the first `ran` interceptors after the call, in reverse order.
+    pub(crate) fn after_call(
+        &self,
+        ran: usize,
+        method: &MethodDescriptor,
+        request: &DynamicRequest,
+        outcome: &CallOutcome<'_>,
+    ) {
+        for interceptor in self.0[..ran].iter().rev() {
+            interceptor.after_call(method, request, outcome);
+        }
+    }
+}
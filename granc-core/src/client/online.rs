@@ -4,20 +4,19 @@
 //! and using Server Reflection for schema resolution.
 use super::{
     Descriptor, DynamicRequest, DynamicResponse, GrancClient, Online, OnlineWithoutReflection,
+    ResponseMetadata,
 };
 use crate::{
     BoxError,
     client::Offline,
+    connector::{self, ConnectOptions, Connection, ProxyError},
     grpc::client::GrpcClient,
     reflection::client::{ReflectionClient, ReflectionResolveError},
 };
 use http_body::Body as HttpBody;
 use prost_reflect::{DescriptorError, DescriptorPool};
-use std::fmt::Debug;
-use tonic::{
-    Code,
-    transport::{Channel, Endpoint},
-};
+use std::{collections::HashMap, fmt::Debug};
+use tonic::{Code, transport::Channel};
 
 /// Errors that can occur when connecting to a gRPC server.
 #[derive(Debug, thiserror::Error)]
@@ -26,6 +25,16 @@ pub enum ClientConnectError {
     InvalidUri(String, #[source] tonic::transport::Error),
     #[error("Failed to connect to '{0}': {1}")]
     ConnectionFailed(String, #[source] tonic::transport::Error),
+    #[error("Cannot connect to '{0}': {1}")]
+    UnsupportedTransport(String, &'static str),
+    #[error(transparent)]
+    InvalidProxy(#[from] ProxyError),
+    #[error("Invalid authority '{0}'")]
+    InvalidAuthority(String),
+    #[error("Invalid address '{0}', expected host:port")]
+    InvalidAddress(String),
+    #[error("Failed to resolve '{0}': {1}")]
+    ResolutionFailed(String, #[source] std::io::Error),
 }
 
 /// Errors that can occur during a dynamic call in Online mode.
@@ -58,22 +67,58 @@ impl GrancClient<Online<Channel>> {
     ///
     /// # Arguments
     ///
-    /// * `addr` - The server URI (e.g., `http://localhost:50051`).
+    /// * `addr` - The server URI (e.g., `http://localhost:50051`), or a Unix domain socket
+    ///   (`unix:///path/to.sock` or `unix-abstract:name`).
     ///
     /// # Returns
     ///
     /// * `Ok(GrancClient<Online>)` - A connected client ready to make dynamic requests via reflection.
-    /// * `Err(ClientConnectError)` - If the URI is invalid or the TCP connection cannot be established.
+    /// * `Err(ClientConnectError)` - If the URI is invalid or the connection cannot be established.
     pub async fn connect(addr: &str) -> Result<Self, ClientConnectError> {
-        let endpoint = Endpoint::new(addr.to_string())
-            .map_err(|e| ClientConnectError::InvalidUri(addr.to_string(), e))?;
+        let channel = connector::connect(addr).await?;
+        Ok(GrancClient::from(channel))
+    }
+}
 
-        let channel = endpoint
-            .connect()
-            .await
-            .map_err(|e| ClientConnectError::ConnectionFailed(addr.to_string(), e))?;
+impl GrancClient<Online<Connection>> {
+    /// Connects to a gRPC server with the given [`ConnectOptions`], e.g. to speak gRPC-Web or
+    /// to compress requests.
+    ///
+    /// Behaves like [`GrancClient::connect`], with the protocol and settings selected in the options.
+    /// With [`ConnectOptions::retry_on_connect`], failing connections are retried.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(GrancClient<Online<Connection>>)` - A connected client ready to make dynamic requests via reflection.
+    /// * `Err(ClientConnectError)` - If the URI is invalid, the connection cannot be established, or
+    ///   the protocol is not supported over the requested transport.
+    pub async fn connect_with(
+        addr: &str,
+        options: ConnectOptions,
+    ) -> Result<Self, ClientConnectError> {
+        let mut attempts = 1;
+        let connection = loop {
+            let result = connector::open(addr, &options).await;
 
-        Ok(GrancClient::from(channel))
+            if let Err(ClientConnectError::ConnectionFailed(..)) = result
+                && let Some(policy) = options.connect_retry_policy()
+                && attempts < policy.max_attempts()
+            {
+                tokio::time::sleep(policy.backoff(attempts)).await;
+                attempts += 1;
+                continue;
+            }
+
+            break result?;
+        };
+
+        let reflection_client = ReflectionClient::new(connection.clone());
+        let grpc_client = GrpcClient::new(connection);
+
+        Ok(Self::new(Online {
+            reflection_client: options.configure_reflection(reflection_client),
+            grpc_client: options.configure_grpc(grpc_client),
+        }))
     }
 }
 
@@ -86,12 +131,10 @@ where
     fn from(service: S) -> Self {
         let reflection_client = ReflectionClient::new(service.clone());
         let grpc_client = GrpcClient::new(service);
-        Self {
-            state: Online {
-                reflection_client,
-                grpc_client,
-            },
-        }
+        Self::new(Online {
+            reflection_client,
+            grpc_client,
+        })
     }
 }
 
@@ -125,10 +168,21 @@ where
     ) -> Result<GrancClient<OnlineWithoutReflection<S>>, DescriptorError> {
         let pool = DescriptorPool::decode(file_descriptor.as_slice())?;
 
-        Ok(GrancClient::new(OnlineWithoutReflection::new(
-            self.state.grpc_client,
-            pool,
-        )))
+        Ok(self.with_descriptor_pool(pool))
+    }
+
+    /// Transitions the client to the **OnlineWithoutReflection** state using an already built `DescriptorPool`.
+    ///
+    /// This is typically combined with [`GrancClient::fetch_descriptor_pool`] to resolve the schema
+    /// once via reflection and then reuse it for many calls over the same connection.
+    pub fn with_descriptor_pool(
+        self,
+        pool: DescriptorPool,
+    ) -> GrancClient<OnlineWithoutReflection<S>> {
+        GrancClient {
+            state: OnlineWithoutReflection::new(self.state.grpc_client, pool),
+            interceptors: self.interceptors,
+        }
     }
 
     /// Lists all services exposed by the server using the Reflection Protocol.
@@ -142,6 +196,31 @@ where
         self.state.reflection_client.list_services().await
     }
 
+    /// Resolves the schema of several symbols using Reflection and merges them into a single `DescriptorPool`.
+    ///
+    /// Files shared between symbols (e.g. common imports) are only included once.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbols` - The fully qualified names of the symbols to resolve (Services, Messages, or Enums).
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DescriptorPool)` - A pool containing every requested symbol and its transitive dependencies.
+    /// * `Err(GetDescriptorError)` - If any symbol is not found on the server or the reflection request fails.
+    pub async fn fetch_descriptor_pool(
+        &mut self,
+        symbols: &[String],
+    ) -> Result<DescriptorPool, GetDescriptorError> {
+        let mut files = HashMap::new();
+
+        for symbol in symbols {
+            files.extend(self.file_descriptors_by_symbol(symbol).await?);
+        }
+
+        Ok(decode_pool(files.into_values())?)
+    }
+
     /// Resolves and fetches the descriptor for a specific symbol using Reflection.
     ///
     /// This will query the server for the symbol, fetch the defining file, and recursively fetch
@@ -159,21 +238,9 @@ where
         &mut self,
         symbol: &str,
     ) -> Result<Descriptor, GetDescriptorError> {
-        let fd_set = self
-            .state
-            .reflection_client
-            .file_descriptor_set_by_symbol(symbol)
-            .await
-            .map_err(|err| match err {
-                ReflectionResolveError::ServerStreamFailure(status)
-                    if status.code() == Code::NotFound =>
-                {
-                    GetDescriptorError::NotFound(symbol.to_string())
-                }
-                err => GetDescriptorError::ReflectionResolve(err),
-            })?;
+        let files = self.file_descriptors_by_symbol(symbol).await?;
 
-        let pool = DescriptorPool::from_file_descriptor_set(fd_set)?;
+        let pool = decode_pool(files.into_values())?;
         let client = GrancClient::new(Offline::new(pool));
 
         client
@@ -206,19 +273,65 @@ where
         &mut self,
         request: DynamicRequest,
     ) -> Result<DynamicResponse, DynamicCallError> {
-        let fd_set = self
+        let (response, _) = self.dynamic_with_metadata(request).await?;
+        Ok(response)
+    }
+
+    /// Executes a dynamic gRPC request like [`GrancClient::dynamic`], also returning the
+    /// headers and trailers sent by the server.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((DynamicResponse, ResponseMetadata))` - The result of the call and its metadata.
+    /// * `Err(DynamicCallError)` - The same errors as [`GrancClient::dynamic`].
+    pub async fn dynamic_with_metadata(
+        &mut self,
+        request: DynamicRequest,
+    ) -> Result<(DynamicResponse, ResponseMetadata), DynamicCallError> {
+        let files = self
             .state
             .reflection_client
-            .file_descriptor_set_by_symbol(&request.service)
+            .encoded_file_descriptors_by_symbol(&request.service)
             .await?;
 
-        let pool = DescriptorPool::from_file_descriptor_set(fd_set)?;
+        let pool = decode_pool(files.into_values())?;
 
-        let mut client = GrancClient::new(OnlineWithoutReflection::new(
-            self.state.grpc_client.clone(),
-            pool,
-        ));
+        let mut client = GrancClient {
+            state: OnlineWithoutReflection::new(self.state.grpc_client.clone(), pool),
+            interceptors: self.interceptors.clone(),
+        };
 
-        Ok(client.dynamic(request).await?)
+        Ok(client.dynamic_with_metadata(request).await?)
     }
+
+    async fn file_descriptors_by_symbol(
+        &mut self,
+        symbol: &str,
+    ) -> Result<HashMap<String, Vec<u8>>, GetDescriptorError> {
+        self.state
+            .reflection_client
+            .encoded_file_descriptors_by_symbol(symbol)
+            .await
+            .map_err(|err| match err {
+                ReflectionResolveError::ServerStreamFailure(status)
+                    if status.code() == Code::NotFound =>
+                {
+                    GetDescriptorError::NotFound(symbol.to_string())
+                }
+                err => GetDescriptorError::ReflectionResolve(err),
+            })
+    }
+}
+
+/// Builds a pool from encoded `FileDescriptorProto`s, keeping their custom options.
+fn decode_pool(
+    files: impl IntoIterator<Item = Vec<u8>>,
+) -> Result<DescriptorPool, DescriptorError> {
+    // An encoded `FileDescriptorSet` is the concatenation of its `file` fields (number 1)
+    let mut fd_set = vec![];
+    for file in files {
+        prost::encoding::bytes::encode(1, &file, &mut fd_set);
+    }
+
+    DescriptorPool::decode(fd_set.as_slice())
 }
@@ -2,11 +2,22 @@
 //!
 //! This module defines the `GrancClient` behavior when it is connected to a server
 //! but uses a local, in-memory `DescriptorPool` (Static schema) to resolve messages.
-use super::{DynamicRequest, DynamicResponse, GrancClient, OnlineWithoutReflection};
-use crate::{BoxError, client::OfflineReflectionState, grpc::client::GrpcRequestError};
+use super::{
+    DynamicRequest, DynamicResponse, GrancClient, OnlineWithoutReflection, ResponseMetadata,
+};
+use crate::{
+    BoxError,
+    client::{OfflineReflectionState, interceptor::CallOutcome},
+    grpc::client::GrpcRequestError,
+};
 use futures_util::{Stream, StreamExt};
 use http_body::Body as HttpBody;
-use std::fmt::Debug;
+use prost_reflect::MethodDescriptor;
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+use tonic::{Streaming, metadata::MetadataMap};
 
 /// Errors that can occur during a dynamic call in OnlineWithoutReflection mode.
 #[derive(Debug, thiserror::Error)]
@@ -52,6 +63,21 @@ where
         &mut self,
         request: DynamicRequest,
     ) -> Result<DynamicResponse, DynamicCallError> {
+        let (response, _) = self.dynamic_with_metadata(request).await?;
+        Ok(response)
+    }
+
+    /// Executes a dynamic gRPC request like [`GrancClient::dynamic`], also returning the
+    /// headers and trailers sent by the server.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((DynamicResponse, ResponseMetadata))` - The result of the call and its metadata.
+    /// * `Err(DynamicCallError)` - The same errors as [`GrancClient::dynamic`].
+    pub async fn dynamic_with_metadata(
+        &mut self,
+        mut request: DynamicRequest,
+    ) -> Result<(DynamicResponse, ResponseMetadata), DynamicCallError> {
         let method = self
             .state
             .descriptor_pool()
@@ -61,6 +87,45 @@ where
             .find(|m| m.name() == request.method)
             .ok_or_else(|| DynamicCallError::MethodNotFound(request.method.clone()))?;
 
+        if self.interceptors.is_empty() {
+            return self.call(method, request).await;
+        }
+
+        // The request is kept for the interceptors running after the call
+        let (ran, result) = self.interceptors.before_call(&method, &mut request);
+        let (response, metadata, elapsed) = match result {
+            Ok(()) => {
+                let start = Instant::now();
+                let (response, metadata) = self.call(method.clone(), request.clone()).await?;
+                (response, metadata, start.elapsed())
+            }
+            Err(status) => {
+                let metadata = error_metadata(&status);
+                let response = if method.is_server_streaming() {
+                    DynamicResponse::Streaming(Err(status))
+                } else {
+                    DynamicResponse::Unary(Err(status))
+                };
+                (response, metadata, Duration::ZERO)
+            }
+        };
+
+        let outcome = CallOutcome {
+            response: &response,
+            metadata: &metadata,
+            elapsed,
+        };
+        self.interceptors
+            .after_call(ran, &method, &request, &outcome);
+
+        Ok((response, metadata))
+    }
+
+    async fn call(
+        &mut self,
+        method: MethodDescriptor,
+        request: DynamicRequest,
+    ) -> Result<(DynamicResponse, ResponseMetadata), DynamicCallError> {
         match (method.is_client_streaming(), method.is_server_streaming()) {
             (false, false) => {
                 let result = self
@@ -68,17 +133,16 @@ where
                     .grpc_client
                     .unary(method, request.body, request.headers)
                     .await?;
-                Ok(DynamicResponse::Unary(result))
+                Ok(unary_response(result))
+            }
+            (false, true) => {
+                let result = self
+                    .state
+                    .grpc_client
+                    .server_streaming(method, request.body, request.headers)
+                    .await?;
+                Ok(streaming_response(result).await)
             }
-            (false, true) => match self
-                .state
-                .grpc_client
-                .server_streaming(method, request.body, request.headers)
-                .await?
-            {
-                Ok(stream) => Ok(DynamicResponse::Streaming(Ok(stream.collect().await))),
-                Err(status) => Ok(DynamicResponse::Streaming(Err(status))),
-            },
             (true, false) => {
                 let input_stream =
                     json_array_to_stream(request.body).map_err(DynamicCallError::InvalidInput)?;
@@ -87,25 +151,71 @@ where
                     .grpc_client
                     .client_streaming(method, input_stream, request.headers)
                     .await?;
-                Ok(DynamicResponse::Unary(result))
+                Ok(unary_response(result))
             }
             (true, true) => {
                 let input_stream =
                     json_array_to_stream(request.body).map_err(DynamicCallError::InvalidInput)?;
-                match self
+                let result = self
                     .state
                     .grpc_client
                     .bidirectional_streaming(method, input_stream, request.headers)
-                    .await?
-                {
-                    Ok(stream) => Ok(DynamicResponse::Streaming(Ok(stream.collect().await))),
-                    Err(status) => Ok(DynamicResponse::Streaming(Err(status))),
-                }
+                    .await?;
+                Ok(streaming_response(result).await)
             }
         }
     }
 }
 
+fn unary_response(
+    result: Result<(tonic::Response<serde_json::Value>, MetadataMap), tonic::Status>,
+) -> (DynamicResponse, ResponseMetadata) {
+    match result {
+        Ok((response, trailers)) => {
+            let (headers, value, _) = response.into_parts();
+            let metadata = ResponseMetadata { headers, trailers };
+            (DynamicResponse::Unary(Ok(value)), metadata)
+        }
+        Err(status) => {
+            let metadata = error_metadata(&status);
+            (DynamicResponse::Unary(Err(status)), metadata)
+        }
+    }
+}
+
+async fn streaming_response(
+    result: Result<tonic::Response<Streaming<serde_json::Value>>, tonic::Status>,
+) -> (DynamicResponse, ResponseMetadata) {
+    let response = match result {
+        Ok(response) => response,
+        Err(status) => {
+            let metadata = error_metadata(&status);
+            return (DynamicResponse::Streaming(Err(status)), metadata);
+        }
+    };
+
+    let (headers, mut stream, _) = response.into_parts();
+    let items: Vec<_> = stream.by_ref().collect().await;
+
+    // A stream ending in an error carries its trailers in the returned status
+    let trailers = match items.last() {
+        Some(Err(status)) => status.metadata().clone(),
+        _ => stream.trailers().await.ok().flatten().unwrap_or_default(),
+    };
+
+    (
+        DynamicResponse::Streaming(Ok(items)),
+        ResponseMetadata { headers, trailers },
+    )
+}
+
+fn error_metadata(status: &tonic::Status) -> ResponseMetadata {
+    ResponseMetadata {
+        headers: MetadataMap::new(),
+        trailers: status.metadata().clone(),
+    }
+}
+
 fn json_array_to_stream(
     json: serde_json::Value,
 ) -> Result<impl Stream<Item = serde_json::Value> + Send + 'static, String> {
@@ -1,5 +1,6 @@
 use prost_reflect::{EnumDescriptor, MessageDescriptor, ServiceDescriptor};
 use std::fmt::Debug;
+use tonic::metadata::MetadataMap;
 
 /// A request object encapsulating all necessary information to perform a dynamic gRPC call.
 #[derive(Debug, Clone)]
@@ -25,6 +26,17 @@ pub enum DynamicResponse {
     Streaming(Result<Vec<Result<serde_json::Value, tonic::Status>>, tonic::Status>),
 }
 
+/// The metadata sent by the server alongside a [`DynamicResponse`].
+#[derive(Debug, Clone, Default)]
+pub struct ResponseMetadata {
+    /// The initial metadata (headers) of the response.
+    pub headers: MetadataMap,
+    /// The trailing metadata (trailers) of the response.
+    ///
+    /// When the call fails, these are the metadata attached to the returned `tonic::Status`.
+    pub trailers: MetadataMap,
+}
+
 /// A generic wrapper for different types of Protobuf descriptors.
 ///
 /// This enum allows the client to return a single type when resolving symbols,
@@ -0,0 +1,896 @@
+//! # Connector
+//!
+//! This module opens the connection used by [`crate::client::GrancClient::connect`] and
+//! [`crate::client::GrancClient::connect_with`].
+//!
+//! Besides the `http://` and `https://` URIs understood by `tonic`, it supports Unix domain sockets:
+//!
+//! * `unix:///absolute/path.sock` or `unix:relative/path.sock` - A socket on the filesystem.
+//! * `unix-abstract:name` - A socket in the Linux abstract namespace.
+//!
+//! Unix sockets are connected through a custom connector, and requests are sent with the
+//! `localhost` authority.
+//!
+//! ## Protocols
+//!
+//! Requests are sent with the native gRPC protocol by default. [`ConnectOptions`] can select
+//! gRPC-Web instead, for servers that are only reachable through a gRPC-Web proxy (e.g. Envoy's
+//! `grpc_web` filter) or a `tonic-web` layer. Both variants of gRPC-Web are supported:
+//!
+//! * [`Protocol::GrpcWeb`] - Binary messages (`application/grpc-web+proto`).
+//! * [`Protocol::GrpcWebText`] - Base64 encoded messages (`application/grpc-web-text+proto`).
+//!
+//! The [Connect protocol](https://connectrpc.com/docs/protocol) is supported as well:
+//!
+//! * [`Protocol::Connect`] - Protobuf messages (`application/proto`).
+//! * [`Protocol::ConnectJson`] - JSON messages (`application/json`).
+//!
+//! Requests are translated from gRPC, and responses back to gRPC: the trailers sent at the end of
+//! a gRPC-Web body, and the errors and trailers of Connect, are turned back into HTTP trailers.
+//! The rest of the clients work the same way with any protocol.
+//!
+//! gRPC-Web and Connect requests are sent over HTTP/1.1 unless [`ConnectOptions::http2`] is set.
+//!
+//! ## Proxies
+//!
+//! TCP connections can be tunneled through an HTTP (`CONNECT`) or SOCKS5 [`Proxy`], set with
+//! [`ConnectOptions::proxy`], or read from the usual `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY`
+//! environment variables with [`ConnectOptions::proxy_from_env`].
+//!
+//! ## Virtual Hosts
+//!
+//! Servers behind an ingress or Envoy are often selected by the authority of the requests rather
+//! than by the address connected to. [`ConnectOptions::authority`] overrides the authority, and
+//! [`ConnectOptions::connect_to`] dials another address while keeping the authority of the URI.
+//!
+//! ## Load Balancing
+//!
+//! `dns:///host:port` targets resolve every A and AAAA record of `host`, e.g. the replicas
+//! behind a headless service. Requests carry the `host:port` authority, and are sent to the
+//! addresses following the [`LoadBalancing`] policy of the options. [`resolve`] returns the
+//! addresses, to reach each of them with [`ConnectOptions::connect_to`].
+//!
+//! Only native gRPC connections are dialed when opened, so the addresses of a `dns:///` target
+//! can only be selected with native gRPC. With gRPC-Web and Connect, a single address must be
+//! given with [`ConnectOptions::connect_to`].
+use crate::{
+    BoxError,
+    auth::Credentials,
+    client::online::ClientConnectError,
+    grpc::{
+        client::GrpcClient,
+        retry::{RetryPolicy, ServiceConfig},
+    },
+    reflection::client::ReflectionClient,
+};
+use connect::ConnectClient;
+use grpc_web::GrpcWebClient;
+use http::uri::Authority;
+use http_body::Body as HttpBody;
+use http_client::HttpClient;
+use hyper_util::rt::TokioIo;
+use std::{
+    collections::HashSet,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+};
+use tonic::{
+    client::GrpcService,
+    codec::CompressionEncoding,
+    transport::{Channel, Endpoint, Uri},
+};
+use tower::Service;
+
+mod connect;
+mod frame;
+mod grpc_web;
+mod http_client;
+mod proxy;
+
+pub(crate) use proxy::TcpConnector;
+pub use proxy::{Proxy, ProxyError};
+
+/// The wire protocol used to send requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// Native gRPC over HTTP/2.
+    #[default]
+    Grpc,
+    /// gRPC-Web with binary messages.
+    GrpcWeb,
+    /// gRPC-Web with base64 encoded messages.
+    GrpcWebText,
+    /// Connect with protobuf messages.
+    Connect,
+    /// Connect with JSON messages.
+    ConnectJson,
+}
+
+/// How calls are spread over the addresses a `dns:///` target resolves to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadBalancing {
+    /// Sends every call to the first address accepting a connection.
+    #[default]
+    PickFirst,
+    /// Connects to every address, and sends each call to the next connected address in turn.
+    RoundRobin,
+}
+
+/// Options controlling how [`crate::client::GrancClient::connect_with`] reaches the server.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    credentials: Option<Arc<dyn Credentials>>,
+    protocol: Protocol,
+    http2: bool,
+    send_compressed: Option<CompressionEncoding>,
+    accept_compressed: Vec<CompressionEncoding>,
+    max_decoding_message_size: Option<usize>,
+    max_encoding_message_size: Option<usize>,
+    proxy: Option<Proxy>,
+    proxy_from_env: bool,
+    authority: Option<String>,
+    connect_to: Option<String>,
+    reflection_host: Option<String>,
+    retry_policy: Option<RetryPolicy>,
+    service_config: Option<ServiceConfig>,
+    retry_on_connect: bool,
+    load_balancing: LoadBalancing,
+}
+
+impl ConnectOptions {
+    /// Creates the default options: native gRPC.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the wire protocol.
+    pub fn protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Sends gRPC-Web and Connect requests over HTTP/2 (with prior knowledge) instead of HTTP/1.1.
+    ///
+    /// Native gRPC always uses HTTP/2, so this has no effect on it.
+    pub fn http2(mut self, http2: bool) -> Self {
+        self.http2 = http2;
+        self
+    }
+
+    /// Compresses requests with the given encoding.
+    ///
+    /// Compression is not supported with the Connect protocol.
+    pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.send_compressed = Some(encoding);
+        self
+    }
+
+    /// Advertises support for responses compressed with the given encoding. Can be called
+    /// multiple times to accept several encodings.
+    pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.accept_compressed.push(encoding);
+        self
+    }
+
+    /// Limits the size of a decoded response message. Defaults to 4MB.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limits the size of an encoded request message. Defaults to `usize::MAX`.
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.max_encoding_message_size = Some(limit);
+        self
+    }
+
+    /// Tunnels the connection through a proxy.
+    ///
+    /// Proxies are only supported over TCP, not with Unix domain sockets.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Looks up the proxy in the environment with [`Proxy::from_env`], unless one is set with
+    /// [`ConnectOptions::proxy`].
+    pub fn proxy_from_env(mut self, enabled: bool) -> Self {
+        self.proxy_from_env = enabled;
+        self
+    }
+
+    /// Overrides the authority of the requests (`:authority` over HTTP/2, `Host` over HTTP/1.1),
+    /// independently of the address connected to. Ingresses and Envoy route on it to select the
+    /// upstream server.
+    pub fn authority(mut self, authority: impl Into<String>) -> Self {
+        self.authority = Some(authority.into());
+        self
+    }
+
+    /// Connects to `host:port` instead of the address of the URI. The requests keep the authority
+    /// of the URI, unless it is overridden with [`ConnectOptions::authority`].
+    ///
+    /// Address overrides are only supported over TCP, not with Unix domain sockets.
+    pub fn connect_to(mut self, address: impl Into<String>) -> Self {
+        self.connect_to = Some(address.into());
+        self
+    }
+
+    /// Sets the `host` field of the server reflection requests, empty by default.
+    pub fn reflection_host(mut self, host: impl Into<String>) -> Self {
+        self.reflection_host = Some(host.into());
+        self
+    }
+
+    /// Authenticates every request, including the reflection requests, with the given credentials.
+    pub fn credentials(mut self, credentials: Arc<dyn Credentials>) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Sets how calls are spread over the addresses of a `dns:///` target. Defaults to
+    /// [`LoadBalancing::PickFirst`].
+    pub fn load_balancing(mut self, load_balancing: LoadBalancing) -> Self {
+        self.load_balancing = load_balancing;
+        self
+    }
+
+    /// Retries failed unary and client streaming calls with the given policy, see
+    /// [`crate::grpc::retry`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Retries failed calls with the retry policies of a service config, taking precedence over
+    /// [`ConnectOptions::retry_policy`] for the methods it configures.
+    pub fn service_config(mut self, config: ServiceConfig) -> Self {
+        self.service_config = Some(config);
+        self
+    }
+
+    /// Retries establishing the connection with the backoff of [`ConnectOptions::retry_policy`],
+    /// e.g. to wait for a server that is starting.
+    ///
+    /// gRPC-Web and Connect connections are established by the first call, so they are retried
+    /// like calls instead.
+    pub fn retry_on_connect(mut self, enabled: bool) -> Self {
+        self.retry_on_connect = enabled;
+        self
+    }
+
+    /// The policy to retry establishing the connection with, if enabled.
+    pub(crate) fn connect_retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref().filter(|_| self.retry_on_connect)
+    }
+
+    /// Applies the credentials, compression, message size and retry settings to a gRPC client.
+    pub(crate) fn configure_grpc<S>(&self, mut client: GrpcClient<S>) -> GrpcClient<S>
+    where
+        S: GrpcService<tonic::body::Body>,
+        S::Error: Into<BoxError>,
+        S::ResponseBody: HttpBody<Data = tonic::codegen::Bytes> + Send + 'static,
+        <S::ResponseBody as HttpBody>::Error: Into<BoxError> + Send,
+    {
+        if let Some(credentials) = &self.credentials {
+            client = client.credentials(credentials.clone());
+        }
+        if let Some(encoding) = self.send_compressed {
+            client = client.send_compressed(encoding);
+        }
+        for encoding in &self.accept_compressed {
+            client = client.accept_compressed(*encoding);
+        }
+        if let Some(limit) = self.max_decoding_message_size {
+            client = client.max_decoding_message_size(limit);
+        }
+        if let Some(limit) = self.max_encoding_message_size {
+            client = client.max_encoding_message_size(limit);
+        }
+        if let Some(policy) = &self.retry_policy {
+            client = client.retry_policy(policy.clone());
+        }
+        if let Some(config) = &self.service_config {
+            client = client.service_config(config.clone());
+        }
+        client
+    }
+
+    /// Applies the credentials, message size and host settings to a reflection client.
+    pub(crate) fn configure_reflection<S>(
+        &self,
+        mut client: ReflectionClient<S>,
+    ) -> ReflectionClient<S>
+    where
+        S: GrpcService<tonic::body::Body>,
+        S::Error: Into<BoxError>,
+        S::ResponseBody: HttpBody<Data = tonic::codegen::Bytes> + Send + 'static,
+        <S::ResponseBody as HttpBody>::Error: Into<BoxError> + Send,
+    {
+        if let Some(credentials) = &self.credentials {
+            client = client.credentials(credentials.clone());
+        }
+        if let Some(limit) = self.max_decoding_message_size {
+            client = client.max_decoding_message_size(limit);
+        }
+        if let Some(host) = &self.reflection_host {
+            client = client.host(host.clone());
+        }
+        client.half_duplex(self.protocol != Protocol::Grpc)
+    }
+
+    fn compressed(&self) -> bool {
+        self.send_compressed.is_some() || !self.accept_compressed.is_empty()
+    }
+}
+
+/// A connection to a gRPC server, speaking the protocol selected in [`ConnectOptions`].
+///
+/// It implements the `tower` service expected by the generated clients, so it can be used with
+/// [`crate::grpc::client::GrpcClient`] and
+/// [`crate::reflection::client::ReflectionClient`] like a [`Channel`].
+#[derive(Debug, Clone)]
+pub struct Connection(Transport);
+
+#[derive(Debug, Clone)]
+enum Transport {
+    Grpc(Channel),
+    RoundRobin(RoundRobin),
+    GrpcWeb(Box<GrpcWebClient>),
+    Connect(Box<ConnectClient>),
+}
+
+/// Native gRPC channels to several addresses, taking the calls in turn.
+#[derive(Debug)]
+struct RoundRobin {
+    channels: Vec<Channel>,
+    /// Shared by the clones of the connection, so that they take turns as well.
+    next: Arc<AtomicUsize>,
+    /// The channel made ready by `poll_ready`, which the next call is sent to.
+    ready: Option<usize>,
+}
+
+impl RoundRobin {
+    fn new(channels: Vec<Channel>) -> Self {
+        Self {
+            channels,
+            next: Arc::default(),
+            ready: None,
+        }
+    }
+}
+
+impl Clone for RoundRobin {
+    // A clone has to be made ready before its first call, like the channels it holds
+    fn clone(&self) -> Self {
+        Self {
+            channels: self.channels.clone(),
+            next: self.next.clone(),
+            ready: None,
+        }
+    }
+}
+
+impl Service<http::Request<tonic::body::Body>> for Connection {
+    type Response = http::Response<tonic::body::Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.0 {
+            Transport::Grpc(channel) => Service::poll_ready(channel, cx).map_err(Into::into),
+            Transport::RoundRobin(balancer) => {
+                let index = *balancer.ready.get_or_insert_with(|| {
+                    balancer.next.fetch_add(1, Ordering::Relaxed) % balancer.channels.len()
+                });
+                Service::poll_ready(&mut balancer.channels[index], cx).map_err(Into::into)
+            }
+            Transport::GrpcWeb(_) | Transport::Connect(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn call(&mut self, request: http::Request<tonic::body::Body>) -> Self::Future {
+        match &mut self.0 {
+            Transport::Grpc(channel) => {
+                let response = Service::call(channel, request);
+                Box::pin(async move { Ok(response.await?) })
+            }
+            Transport::RoundRobin(balancer) => {
+                let index = balancer
+                    .ready
+                    .take()
+                    .expect("poll_ready is called before call");
+                let response = Service::call(&mut balancer.channels[index], request);
+                Box::pin(async move { Ok(response.await?) })
+            }
+            Transport::GrpcWeb(client) => Box::pin(client.call(request)),
+            Transport::Connect(client) => Box::pin(client.call(request)),
+        }
+    }
+}
+
+/// Opens a connection to the server at `addr` with the given options.
+///
+/// gRPC-Web and Connect connections are established lazily, when the first request is sent, so
+/// they cannot select one of the addresses of a `dns:///` target.
+pub(crate) async fn open(
+    addr: &str,
+    options: &ConnectOptions,
+) -> Result<Connection, ClientConnectError> {
+    let Target::Dns(name) = Target::parse(addr) else {
+        return open_uri(addr, options).await;
+    };
+
+    dns_address(addr, name)?;
+    let uri = format!("http://{name}");
+
+    // A single address is dialed, e.g. to reach one of the servers returned by `resolve`
+    if options.connect_to.is_some() {
+        return open_uri(&uri, options).await;
+    }
+
+    if options.protocol != Protocol::Grpc {
+        return Err(ClientConnectError::UnsupportedTransport(
+            addr.to_string(),
+            "the addresses of DNS targets can only be selected with native gRPC",
+        ));
+    }
+
+    let addresses = resolve(addr).await?;
+
+    match options.load_balancing {
+        LoadBalancing::PickFirst => {
+            let mut error = None;
+
+            for address in addresses {
+                match dial(&uri, address, options).await {
+                    Ok(channel) => return Ok(Connection(Transport::Grpc(channel))),
+                    Err(e) => error = Some(e),
+                }
+            }
+
+            Err(error.expect("at least one address is resolved"))
+        }
+        LoadBalancing::RoundRobin => {
+            let results = futures_util::future::join_all(
+                addresses
+                    .into_iter()
+                    .map(|address| dial(&uri, address, options)),
+            )
+            .await;
+
+            // Addresses that cannot be reached are left out, the connection fails if none can be
+            let mut error = None;
+            let channels: Vec<_> = results
+                .into_iter()
+                .filter_map(|result| result.map_err(|e| error = Some(e)).ok())
+                .collect();
+
+            if channels.is_empty() {
+                return Err(error.expect("at least one address is resolved"));
+            }
+
+            Ok(Connection(Transport::RoundRobin(RoundRobin::new(channels))))
+        }
+    }
+}
+
+/// Connects to one of the addresses of the `dns:///` target, with the authority of its `uri`.
+async fn dial(
+    uri: &str,
+    address: SocketAddr,
+    options: &ConnectOptions,
+) -> Result<Channel, ClientConnectError> {
+    let options = options.clone().connect_to(address.to_string());
+    connect_via(uri, Route::new(uri, &options)?).await
+}
+
+/// Resolves the addresses of the servers behind `addr`: every A and AAAA record of a
+/// `dns:///host:port` target, or the addresses of the host of an `http://` URI.
+///
+/// # Returns
+///
+/// * `Ok(Vec<SocketAddr>)` - The resolved addresses, never empty.
+/// * `Err(ClientConnectError)` - If the target is not a TCP address, or it cannot be resolved.
+pub async fn resolve(addr: &str) -> Result<Vec<SocketAddr>, ClientConnectError> {
+    let (host, port) = match Target::parse(addr) {
+        Target::Dns(name) => dns_address(addr, name)?,
+        Target::Tcp(uri) => {
+            let uri: Uri = uri
+                .parse()
+                .map_err(|_| ClientConnectError::InvalidAddress(addr.to_string()))?;
+            let host = uri
+                .host()
+                .ok_or_else(|| ClientConnectError::InvalidAddress(addr.to_string()))?;
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            (host.to_string(), uri.port_u16().unwrap_or(80))
+        }
+        Target::Unix(_) | Target::UnixAbstract(_) => {
+            return Err(ClientConnectError::UnsupportedTransport(
+                addr.to_string(),
+                "only TCP addresses can be resolved",
+            ));
+        }
+    };
+
+    let failed = |e| ClientConnectError::ResolutionFailed(addr.to_string(), e);
+
+    // The same address can be returned for several records, only the first one is kept so that
+    // the order of the resolver is preserved
+    let mut seen = HashSet::new();
+    let addresses: Vec<_> = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(failed)?
+        .filter(|address| seen.insert(*address))
+        .collect();
+
+    if addresses.is_empty() {
+        return Err(failed(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no address found",
+        )));
+    }
+
+    Ok(addresses)
+}
+
+/// Opens a connection to the server at the URI `addr`.
+async fn open_uri(addr: &str, options: &ConnectOptions) -> Result<Connection, ClientConnectError> {
+    let is_connect = matches!(options.protocol, Protocol::Connect | Protocol::ConnectJson);
+    if is_connect && options.compressed() {
+        return Err(ClientConnectError::UnsupportedTransport(
+            addr.to_string(),
+            "compression is not supported with the Connect protocol",
+        ));
+    }
+
+    let route = Route::new(addr, options)?;
+    let http = || http_client(addr, options.http2, route.clone());
+
+    let transport = match options.protocol {
+        Protocol::Grpc => Transport::Grpc(connect_via(addr, route.clone()).await?),
+        Protocol::GrpcWeb => Transport::GrpcWeb(Box::new(GrpcWebClient::new(http()?, false))),
+        Protocol::GrpcWebText => Transport::GrpcWeb(Box::new(GrpcWebClient::new(http()?, true))),
+        Protocol::Connect => Transport::Connect(Box::new(ConnectClient::new(http()?, false))),
+        Protocol::ConnectJson => Transport::Connect(Box::new(ConnectClient::new(http()?, true))),
+    };
+
+    Ok(Connection(transport))
+}
+
+/// Where the connections go, and the authority their requests carry.
+#[derive(Debug, Clone, Default)]
+struct Route {
+    proxy: Option<Proxy>,
+    authority: Option<Authority>,
+    connect_to: Option<(String, u16)>,
+}
+
+impl Route {
+    fn new(addr: &str, options: &ConnectOptions) -> Result<Self, ClientConnectError> {
+        let proxy = match &options.proxy {
+            Some(proxy) => Some(proxy.clone()),
+            None if options.proxy_from_env => Proxy::from_env(addr)?,
+            None => None,
+        };
+
+        let authority = options
+            .authority
+            .as_deref()
+            .map(|authority| {
+                authority
+                    .parse::<Authority>()
+                    .map_err(|_| ClientConnectError::InvalidAuthority(authority.to_string()))
+            })
+            .transpose()?;
+
+        let connect_to = options
+            .connect_to
+            .as_deref()
+            .map(parse_address)
+            .transpose()?;
+
+        Ok(Self {
+            proxy,
+            authority,
+            connect_to,
+        })
+    }
+
+    /// The connector dialing the connections, if the default one of `tonic` cannot be used.
+    fn connector(&self, uri: &Uri) -> Option<TcpConnector> {
+        if self.proxy.is_none() && self.connect_to.is_none() && self.authority.is_none() {
+            return None;
+        }
+
+        // The requests are sent to the overridden authority, so the address of the URI must be
+        // dialed explicitly
+        let address = self.connect_to.clone().or_else(|| {
+            let host = uri.host()?.trim_start_matches('[').trim_end_matches(']');
+            Some((host.to_string(), uri.port_u16().unwrap_or(80)))
+        });
+
+        Some(TcpConnector::new(self.proxy.clone(), address))
+    }
+}
+
+/// Parses a `host:port` address.
+fn parse_address(address: &str) -> Result<(String, u16), ClientConnectError> {
+    let invalid = || ClientConnectError::InvalidAddress(address.to_string());
+
+    let authority = address.parse::<Authority>().map_err(|_| invalid())?;
+    let port = authority.port_u16().ok_or_else(invalid)?;
+    let host = authority
+        .host()
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+
+    Ok((host.to_string(), port))
+}
+
+/// Parses the `host:port` name of a `dns:` target.
+fn dns_address(addr: &str, name: &str) -> Result<(String, u16), ClientConnectError> {
+    if name.starts_with("//") {
+        return Err(ClientConnectError::UnsupportedTransport(
+            addr.to_string(),
+            "DNS servers cannot be selected, expected dns:///host:port",
+        ));
+    }
+
+    parse_address(name)
+}
+
+/// Replaces the authority of a URI, keeping its scheme and path.
+fn with_authority(uri: &Uri, authority: &Authority) -> Uri {
+    let mut parts = uri.clone().into_parts();
+    parts.authority = Some(authority.clone());
+    Uri::from_parts(parts).expect("URI with a scheme and an authority")
+}
+
+/// Builds the HTTP client used by the protocols translated from gRPC.
+fn http_client(addr: &str, http2: bool, route: Route) -> Result<HttpClient, ClientConnectError> {
+    let Target::Tcp(uri) = Target::parse(addr) else {
+        return Err(ClientConnectError::UnsupportedTransport(
+            addr.to_string(),
+            "gRPC-Web and Connect are only supported over TCP",
+        ));
+    };
+
+    // Validated the same way as native gRPC URIs
+    Endpoint::new(uri.to_string())
+        .map_err(|e| ClientConnectError::InvalidUri(addr.to_string(), e))?;
+
+    let origin: Uri = uri.parse().expect("URI accepted by the endpoint");
+    let connector = route.connector(&origin).unwrap_or_default();
+
+    let origin = match &route.authority {
+        Some(authority) => with_authority(&origin, authority),
+        None => origin,
+    };
+
+    Ok(HttpClient::new(origin, http2, connector))
+}
+
+/// The transport addressed by a URI.
+enum Target<'a> {
+    Tcp(&'a str),
+    Dns(&'a str),
+    Unix(&'a str),
+    UnixAbstract(&'a str),
+}
+
+impl<'a> Target<'a> {
+    fn parse(addr: &'a str) -> Self {
+        if let Some(name) = addr.strip_prefix("dns:") {
+            // The authority of `dns://server/name` selects a DNS server, which is not supported
+            Target::Dns(name.strip_prefix("///").unwrap_or(name))
+        } else if let Some(name) = addr.strip_prefix("unix-abstract:") {
+            Target::UnixAbstract(name)
+        } else if let Some(path) = addr.strip_prefix("unix://").or(addr.strip_prefix("unix:")) {
+            Target::Unix(path)
+        } else {
+            Target::Tcp(addr)
+        }
+    }
+}
+
+/// Connects to the server at `addr`.
+pub(crate) async fn connect(addr: &str) -> Result<Channel, ClientConnectError> {
+    if !matches!(Target::parse(addr), Target::Dns(_)) {
+        return connect_via(addr, Route::default()).await;
+    }
+
+    match open(addr, &ConnectOptions::default()).await?.0 {
+        Transport::Grpc(channel) => Ok(channel),
+        _ => unreachable!("native gRPC picks the first address by default"),
+    }
+}
+
+/// Connects to the server at `addr` along the given route.
+async fn connect_via(addr: &str, route: Route) -> Result<Channel, ClientConnectError> {
+    let target = Target::parse(addr);
+
+    if !matches!(target, Target::Tcp(_)) {
+        if route.proxy.is_some() {
+            return Err(ClientConnectError::UnsupportedTransport(
+                addr.to_string(),
+                "proxies are only supported over TCP",
+            ));
+        }
+        if route.connect_to.is_some() {
+            return Err(ClientConnectError::UnsupportedTransport(
+                addr.to_string(),
+                "address overrides are only supported over TCP",
+            ));
+        }
+    }
+
+    match target {
+        Target::Tcp(uri) => {
+            let mut endpoint = Endpoint::new(uri.to_string())
+                .map_err(|e| ClientConnectError::InvalidUri(addr.to_string(), e))?;
+
+            if let Some(authority) = &route.authority {
+                let origin = with_authority(endpoint.uri(), authority);
+                endpoint = endpoint.origin(origin);
+            }
+
+            match route.connector(endpoint.uri()) {
+                Some(connector) => endpoint.connect_with_connector(connector).await,
+                None => endpoint.connect().await,
+            }
+            .map_err(|e| ClientConnectError::ConnectionFailed(addr.to_string(), e))
+        }
+        Target::Dns(_) => Err(ClientConnectError::UnsupportedTransport(
+            addr.to_string(),
+            "DNS targets are resolved before connecting",
+        )),
+        Target::Unix(path) => {
+            let path = path.to_string();
+            connect_with(addr, route.authority, move || unix::connect(path.clone())).await
+        }
+        Target::UnixAbstract(name) => {
+            let name = name.to_string();
+            connect_with(addr, route.authority, move || {
+                unix::connect_abstract(name.clone())
+            })
+            .await
+        }
+    }
+}
+
+async fn connect_with<F, Fut, IO>(
+    addr: &str,
+    authority: Option<Authority>,
+    connect: F,
+) -> Result<Channel, ClientConnectError>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = std::io::Result<IO>> + Send + 'static,
+    IO: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    // The URI is ignored by the connector, it only sets the authority of the requests
+    let connector = tower::service_fn(move |_: Uri| {
+        let stream = connect();
+        async move { Ok::<_, std::io::Error>(TokioIo::new(stream.await?)) }
+    });
+
+    let authority = authority.map_or("localhost".to_string(), |authority| authority.to_string());
+
+    Endpoint::new(format!("http://{authority}"))
+        .map_err(|e| ClientConnectError::InvalidUri(addr.to_string(), e))?
+        .connect_with_connector(connector)
+        .await
+        .map_err(|e| ClientConnectError::ConnectionFailed(addr.to_string(), e))
+}
+
+#[cfg(unix)]
+mod unix {
+    use tokio::net::UnixStream;
+
+    pub(super) async fn connect(path: String) -> std::io::Result<UnixStream> {
+        UnixStream::connect(path).await
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(super) async fn connect_abstract(name: String) -> std::io::Result<UnixStream> {
+        use std::os::linux::net::SocketAddrExt;
+
+        let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+
+        // Connecting to a local socket does not block for long, and tokio has no abstract address API
+        let stream = std::os::unix::net::UnixStream::connect_addr(&addr)?;
+        stream.set_nonblocking(true)?;
+
+        UnixStream::from_std(stream)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(super) async fn connect_abstract(_name: String) -> std::io::Result<UnixStream> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "abstract Unix sockets are only supported on Linux",
+        ))
+    }
+}
+
+#[cfg(not(unix))]
+mod unix {
+    use tokio::io::DuplexStream;
+
+    fn unsupported() -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Unix sockets are not supported on this platform",
+        )
+    }
+
+    pub(super) async fn connect(_path: String) -> std::io::Result<DuplexStream> {
+        Err(unsupported())
+    }
+
+    pub(super) async fn connect_abstract(_name: String) -> std::io::Result<DuplexStream> {
+        Err(unsupported())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::GrancClient;
+    use granc_test_support::echo_service::FILE_DESCRIPTOR_SET;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tower::util::MapRequestLayer;
+
+    /// Serves the reflection service, counting the requests it receives. Returns its address.
+    async fn serve(requests: Arc<AtomicUsize>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let reflection_service = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+            .build_v1()
+            .unwrap();
+
+        let count = MapRequestLayer::new(move |request: http::Request<_>| {
+            requests.fetch_add(1, Ordering::Relaxed);
+            request
+        });
+
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .layer(count)
+                .add_service(reflection_service)
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let requests: [Arc<AtomicUsize>; 2] = Default::default();
+        let mut channels = vec![];
+        for requests in &requests {
+            let address = serve(requests.clone()).await;
+            channels.push(connect(&format!("http://{address}")).await.unwrap());
+        }
+
+        let connection = Connection(Transport::RoundRobin(RoundRobin::new(channels)));
+        let mut client = GrancClient::from(connection);
+
+        for _ in 0..4 {
+            client.list_services().await.unwrap();
+        }
+
+        let requests = requests.map(|requests| requests.load(Ordering::Relaxed));
+        assert_eq!(requests, [2, 2]);
+    }
+}
@@ -0,0 +1,385 @@
+//! # Connect Client
+//!
+//! A client for the [Connect protocol](https://connectrpc.com/docs/protocol), translating the gRPC
+//! requests produced by `tonic` into Connect requests and the responses back into gRPC responses.
+//!
+//! * **Unary** calls are sent as a plain HTTP `POST` with the bare message as body
+//!   (`application/proto` or `application/json`). Errors are returned as a JSON body with a non-200
+//!   status, and trailers as headers prefixed with `trailer-`.
+//! * **Streaming** calls use enveloped messages (`application/connect+proto` or
+//!   `application/connect+json`), ending with an end-stream message carrying the error and the
+//!   trailers as JSON.
+//!
+//! The shape and types of a call are read from the [`MethodDescriptor`] that
+//! [`crate::grpc::client::GrpcClient`] attaches to every request. Requests without a descriptor
+//! (e.g. reflection) are sent as streams with the proto codec.
+//!
+//! Like gRPC-Web, the whole request stream is sent before the response is read.
+use super::{
+    frame::{self, FrameBuffer, HEADER_SIZE},
+    http_client::HttpClient,
+};
+use crate::BoxError;
+use bytes::Bytes;
+use http::{
+    HeaderMap, HeaderName, HeaderValue, Request, Response, StatusCode,
+    header::{CONTENT_TYPE, TE},
+    response::Parts,
+};
+use http_body::{Body, Frame};
+use http_body_util::{BodyExt, Full, StreamBody};
+use prost::Message;
+use prost_reflect::{DynamicMessage, MessageDescriptor, MethodDescriptor};
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+use tonic::Code;
+
+/// Set in the flags of the message ending a stream.
+const END_STREAM_FLAG: u8 = 0x02;
+
+/// gRPC headers that have no meaning in Connect.
+const GRPC_HEADERS: [&str; 3] = ["grpc-timeout", "grpc-encoding", "grpc-accept-encoding"];
+
+#[derive(Debug, Clone)]
+pub(super) struct ConnectClient {
+    http: HttpClient,
+    json: bool,
+}
+
+/// How messages are serialized on the wire.
+#[derive(Debug, Clone)]
+enum Codec {
+    Proto,
+    /// JSON needs the message types to transcode from and to the protobuf messages built by `tonic`.
+    Json(MethodDescriptor),
+}
+
+impl ConnectClient {
+    pub(super) fn new(http: HttpClient, json: bool) -> Self {
+        Self { http, json }
+    }
+
+    pub(super) fn call(
+        &self,
+        request: Request<tonic::body::Body>,
+    ) -> impl Future<Output = Result<Response<tonic::body::Body>, BoxError>> + Send + 'static {
+        let http = self.http.clone();
+        let json = self.json;
+
+        async move {
+            let (mut parts, body) = request.into_parts();
+
+            let method = parts.extensions.get::<MethodDescriptor>().cloned();
+            let unary = method
+                .as_ref()
+                .is_some_and(|m| !m.is_client_streaming() && !m.is_server_streaming());
+
+            let codec = match method {
+                Some(method) if json => Codec::Json(method),
+                _ => Codec::Proto,
+            };
+
+            let content_type = match unary {
+                true => format!("application/{}", codec.name()),
+                false => format!("application/connect+{}", codec.name()),
+            };
+
+            parts.headers.remove(TE);
+            for header in GRPC_HEADERS {
+                parts.headers.remove(header);
+            }
+            parts
+                .headers
+                .insert(CONTENT_TYPE, HeaderValue::from_str(&content_type)?);
+            parts
+                .headers
+                .insert("connect-protocol-version", HeaderValue::from_static("1"));
+
+            let mut frames = FrameBuffer::default();
+            frames.extend(&body.collect().await?.to_bytes());
+
+            let body = if unary {
+                let message = frames.next_frame().ok_or("Missing request message")?;
+                codec.encode_request(&message[HEADER_SIZE..])?
+            } else {
+                let mut body = vec![];
+                while let Some(message) = frames.next_frame() {
+                    let payload = codec.encode_request(&message[HEADER_SIZE..])?;
+                    body.extend_from_slice(&frame::encode(message[0], &payload));
+                }
+                Bytes::from(body)
+            };
+
+            let response = http
+                .send(parts, tonic::body::Body::new(Full::new(body)))
+                .await?;
+
+            let (parts, body) = response.into_parts();
+
+            if unary || !parts.status.is_success() {
+                let bytes = body.collect().await?.to_bytes();
+                unary_response(parts, bytes, &codec)
+            } else {
+                let body = ConnectStreamBody::new(body, codec);
+                Ok(Response::from_parts(parts, tonic::body::Body::new(body)))
+            }
+        }
+    }
+}
+
+impl Codec {
+    fn name(&self) -> &'static str {
+        match self {
+            Codec::Proto => "proto",
+            Codec::Json(_) => "json",
+        }
+    }
+
+    /// Serializes a protobuf encoded request message with this codec.
+    fn encode_request(&self, message: &[u8]) -> Result<Bytes, BoxError> {
+        match self {
+            Codec::Proto => Ok(Bytes::copy_from_slice(message)),
+            Codec::Json(method) => {
+                let message = DynamicMessage::decode(method.input(), message)?;
+                Ok(serde_json::to_vec(&message)?.into())
+            }
+        }
+    }
+
+    /// Converts a response message serialized with this codec to protobuf.
+    fn decode_response(&self, message: &[u8]) -> Result<Bytes, BoxError> {
+        match self {
+            Codec::Proto => Ok(Bytes::copy_from_slice(message)),
+            Codec::Json(method) => Ok(json_to_proto(method.output(), message)?.into()),
+        }
+    }
+}
+
+fn json_to_proto(descriptor: MessageDescriptor, json: &[u8]) -> Result<Vec<u8>, BoxError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let message = DynamicMessage::deserialize(descriptor, &mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(message.encode_to_vec())
+}
+
+/// Builds the gRPC response of a unary call, or of any call that failed before streaming.
+fn unary_response(
+    mut parts: Parts,
+    body: Bytes,
+    codec: &Codec,
+) -> Result<Response<tonic::body::Body>, BoxError> {
+    // Trailers are sent as headers with a `trailer-` prefix
+    let mut headers = HeaderMap::new();
+    let mut trailers = HeaderMap::new();
+    for (name, value) in &parts.headers {
+        match name.as_str().strip_prefix("trailer-") {
+            Some(name) => trailers.append(HeaderName::from_bytes(name.as_bytes())?, value.clone()),
+            None => headers.append(name, value.clone()),
+        };
+    }
+    parts.headers = headers;
+
+    if !parts.status.is_success() {
+        let error = serde_json::from_slice(&body).unwrap_or_default();
+        let (code, message) = error_status(Some(parts.status), &error);
+
+        // Returned as a trailers-only response, with the status in the headers
+        parts.status = StatusCode::OK;
+        parts.headers.extend(trailers);
+        insert_status(&mut parts.headers, code, &message)?;
+
+        return Ok(Response::from_parts(parts, tonic::body::Body::empty()));
+    }
+
+    insert_status(&mut trailers, Code::Ok, "")?;
+
+    let frames = [
+        Ok::<_, Infallible>(Frame::data(frame::encode(
+            0,
+            &codec.decode_response(&body)?,
+        ))),
+        Ok(Frame::trailers(trailers)),
+    ];
+
+    let body = StreamBody::new(futures_util::stream::iter(frames));
+    Ok(Response::from_parts(parts, tonic::body::Body::new(body)))
+}
+
+/// A response body that converts Connect envelopes into gRPC data frames and HTTP trailers.
+struct ConnectStreamBody<B> {
+    inner: B,
+    codec: Codec,
+    frames: FrameBuffer,
+    done: bool,
+}
+
+impl<B> ConnectStreamBody<B> {
+    fn new(inner: B, codec: Codec) -> Self {
+        Self {
+            inner,
+            codec,
+            frames: FrameBuffer::default(),
+            done: false,
+        }
+    }
+
+    /// Takes the next complete message out of the buffer.
+    fn next_frame(&mut self) -> Result<Option<Frame<Bytes>>, BoxError> {
+        let Some(message) = self.frames.next_frame() else {
+            return Ok(None);
+        };
+
+        let payload = &message[HEADER_SIZE..];
+
+        if message[0] & END_STREAM_FLAG != 0 {
+            self.done = true;
+            return Ok(Some(Frame::trailers(end_stream_trailers(payload)?)));
+        }
+
+        match self.codec {
+            Codec::Proto => Ok(Some(Frame::data(message))),
+            Codec::Json(_) => {
+                let payload = self.codec.decode_response(payload)?;
+                Ok(Some(Frame::data(frame::encode(0, &payload))))
+            }
+        }
+    }
+}
+
+impl<B> Body for ConnectStreamBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(frame) = this.next_frame().transpose() {
+                return Poll::Ready(Some(frame));
+            }
+
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => {
+                    if let Ok(data) = frame.into_data() {
+                        this.frames.extend(&data);
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                None => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(
+                        "Connect stream ended without an end-stream message".into(),
+                    )));
+                }
+            }
+        }
+    }
+}
+
+/// Parses the end-stream message: `{"error": {"code": ..., "message": ...}, "metadata": {"key": ["value"]}}`.
+fn end_stream_trailers(payload: &[u8]) -> Result<HeaderMap, BoxError> {
+    let end_stream: serde_json::Value = serde_json::from_slice(payload)?;
+    let mut trailers = HeaderMap::new();
+
+    if let Some(metadata) = end_stream["metadata"].as_object() {
+        for (key, values) in metadata {
+            let name = HeaderName::from_bytes(key.as_bytes())?;
+            for value in values.as_array().into_iter().flatten() {
+                let value = value.as_str().ok_or("Invalid Connect trailer value")?;
+                trailers.append(name.clone(), HeaderValue::from_str(value)?);
+            }
+        }
+    }
+
+    let (code, message) = match end_stream.get("error") {
+        Some(error) => error_status(None, error),
+        None => (Code::Ok, String::new()),
+    };
+
+    insert_status(&mut trailers, code, &message)?;
+    Ok(trailers)
+}
+
+/// Reads a Connect error (`{"code": "not_found", "message": "..."}`), falling back to the
+/// HTTP status when the body is not a Connect error.
+fn error_status(status: Option<StatusCode>, error: &serde_json::Value) -> (Code, String) {
+    let code = error["code"]
+        .as_str()
+        .and_then(code_from_name)
+        .or(status.map(code_from_http))
+        .unwrap_or(Code::Unknown);
+
+    let message = match error["message"].as_str() {
+        Some(message) => message.to_string(),
+        None => status
+            .map(|s| format!("HTTP status {s}"))
+            .unwrap_or_default(),
+    };
+
+    (code, message)
+}
+
+fn code_from_name(name: &str) -> Option<Code> {
+    Some(match name {
+        "canceled" => Code::Cancelled,
+        "unknown" => Code::Unknown,
+        "invalid_argument" => Code::InvalidArgument,
+        "deadline_exceeded" => Code::DeadlineExceeded,
+        "not_found" => Code::NotFound,
+        "already_exists" => Code::AlreadyExists,
+        "permission_denied" => Code::PermissionDenied,
+        "resource_exhausted" => Code::ResourceExhausted,
+        "failed_precondition" => Code::FailedPrecondition,
+        "aborted" => Code::Aborted,
+        "out_of_range" => Code::OutOfRange,
+        "unimplemented" => Code::Unimplemented,
+        "internal" => Code::Internal,
+        "unavailable" => Code::Unavailable,
+        "data_loss" => Code::DataLoss,
+        "unauthenticated" => Code::Unauthenticated,
+        _ => return None,
+    })
+}
+
+/// Maps an HTTP status to a code, as defined by the Connect protocol.
+fn code_from_http(status: StatusCode) -> Code {
+    match status.as_u16() {
+        400 => Code::Internal,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::Unimplemented,
+        429 | 502 | 503 | 504 => Code::Unavailable,
+        _ => Code::Unknown,
+    }
+}
+
+/// Sets `grpc-status` and `grpc-message`, percent-encoding the message as gRPC requires.
+fn insert_status(headers: &mut HeaderMap, code: Code, message: &str) -> Result<(), BoxError> {
+    let encoded: String = message
+        .bytes()
+        .map(|b| match b {
+            b' '..=b'~' if b != b'%' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+
+    headers.insert("grpc-status", HeaderValue::from(code as i32));
+    headers.insert("grpc-message", HeaderValue::from_str(&encoded)?);
+    Ok(())
+}
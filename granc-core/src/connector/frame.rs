@@ -0,0 +1,53 @@
+//! # Frames
+//!
+//! gRPC, gRPC-Web and Connect streams share the same envelope: a flags byte, the length of the
+//! payload as a big-endian `u32`, and the payload itself.
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Size of the flags and length prefix of every frame.
+pub(super) const HEADER_SIZE: usize = 5;
+
+/// Accumulates the chunks of a body and splits them into complete frames.
+#[derive(Debug, Default)]
+pub(super) struct FrameBuffer {
+    buffer: BytesMut,
+}
+
+impl FrameBuffer {
+    pub(super) fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Takes the next complete frame, including its header, out of the buffer.
+    pub(super) fn next_frame(&mut self) -> Option<Bytes> {
+        if self.buffer.len() < HEADER_SIZE {
+            return None;
+        }
+
+        let len = u32::from_be_bytes([
+            self.buffer[1],
+            self.buffer[2],
+            self.buffer[3],
+            self.buffer[4],
+        ]) as usize;
+
+        if self.buffer.len() < HEADER_SIZE + len {
+            return None;
+        }
+
+        Some(self.buffer.split_to(HEADER_SIZE + len).freeze())
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+}
+
+/// Wraps a payload in a frame.
+pub(super) fn encode(flags: u8, payload: &[u8]) -> Bytes {
+    let mut frame = BytesMut::with_capacity(HEADER_SIZE + payload.len());
+    frame.put_u8(flags);
+    frame.put_u32(payload.len() as u32);
+    frame.put_slice(payload);
+    frame.freeze()
+}
@@ -0,0 +1,226 @@
+//! # gRPC-Web Client
+//!
+//! A minimal client for the [gRPC-Web protocol](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md).
+//!
+//! Requests produced by `tonic` are rewritten before being sent:
+//!
+//! * The `content-type` becomes `application/grpc-web+proto` (or `application/grpc-web-text+proto`).
+//! * In text mode, the body is collected and encoded in base64.
+//!
+//! Responses are turned back into regular gRPC responses by [`GrpcWebBody`], which decodes base64
+//! bodies and converts the trailers frame at the end of the body into HTTP trailers.
+//!
+//! gRPC-Web only supports half-duplex streams: the whole request stream is sent before the response
+//! is read.
+use super::{
+    frame::{FrameBuffer, HEADER_SIZE},
+    http_client::HttpClient,
+};
+use crate::BoxError;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use bytes::{Buf, Bytes, BytesMut};
+use http::{
+    HeaderMap, HeaderName, HeaderValue, Request, Response,
+    header::{ACCEPT, CONTENT_TYPE, TE},
+};
+use http_body::{Body, Frame};
+use http_body_util::{BodyExt, Full};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+const GRPC_WEB: &str = "application/grpc-web+proto";
+const GRPC_WEB_TEXT: &str = "application/grpc-web-text+proto";
+
+/// Set in the flags of the frame carrying the trailers.
+const TRAILERS_FLAG: u8 = 0x80;
+
+#[derive(Debug, Clone)]
+pub(super) struct GrpcWebClient {
+    http: HttpClient,
+    text: bool,
+}
+
+impl GrpcWebClient {
+    pub(super) fn new(http: HttpClient, text: bool) -> Self {
+        Self { http, text }
+    }
+
+    pub(super) fn call(
+        &self,
+        request: Request<tonic::body::Body>,
+    ) -> impl Future<Output = Result<Response<tonic::body::Body>, BoxError>> + Send + 'static {
+        let http = self.http.clone();
+        let text = self.text;
+
+        async move {
+            let (mut parts, body) = request.into_parts();
+
+            let content_type =
+                HeaderValue::from_static(if text { GRPC_WEB_TEXT } else { GRPC_WEB });
+            parts.headers.insert(CONTENT_TYPE, content_type.clone());
+            parts.headers.insert(ACCEPT, content_type);
+            parts
+                .headers
+                .insert("x-grpc-web", HeaderValue::from_static("1"));
+            parts.headers.remove(TE);
+
+            let body = if text {
+                let bytes = body.collect().await?.to_bytes();
+                tonic::body::Body::new(Full::new(Bytes::from(STANDARD.encode(bytes))))
+            } else {
+                body
+            };
+
+            let response = http.send(parts, body).await?;
+
+            let text = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.starts_with("application/grpc-web-text"));
+
+            Ok(response.map(|body| tonic::body::Body::new(GrpcWebBody::new(body, text))))
+        }
+    }
+}
+
+/// A response body that decodes gRPC-Web frames into gRPC data frames and HTTP trailers.
+struct GrpcWebBody<B> {
+    inner: B,
+    text: bool,
+    /// Base64 characters received but not decoded yet (text mode only).
+    encoded: BytesMut,
+    /// Decoded bytes not forming a complete frame yet.
+    frames: FrameBuffer,
+    done: bool,
+}
+
+impl<B> GrpcWebBody<B> {
+    fn new(inner: B, text: bool) -> Self {
+        Self {
+            inner,
+            text,
+            encoded: BytesMut::new(),
+            frames: FrameBuffer::default(),
+            done: false,
+        }
+    }
+
+    /// Adds a chunk of the response body to the buffer.
+    fn push(&mut self, data: Bytes) -> Result<(), BoxError> {
+        if !self.text {
+            self.frames.extend(&data);
+            return Ok(());
+        }
+
+        self.encoded
+            .extend(data.iter().filter(|b| !b.is_ascii_whitespace()));
+
+        // Only complete groups of 4 characters can be decoded
+        let encoded = self.encoded.split_to(self.encoded.len() / 4 * 4);
+
+        // Every message is encoded separately, so padding can appear in the middle of the body
+        let mut start = 0;
+        for end in (4..=encoded.len()).step_by(4) {
+            if encoded[end - 1] == b'=' || end == encoded.len() {
+                self.frames.extend(&STANDARD.decode(&encoded[start..end])?);
+                start = end;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Takes the next complete frame out of the buffer.
+    fn next_frame(&mut self) -> Result<Option<Frame<Bytes>>, BoxError> {
+        let Some(mut frame) = self.frames.next_frame() else {
+            return Ok(None);
+        };
+
+        if frame[0] & TRAILERS_FLAG != 0 {
+            frame.advance(HEADER_SIZE);
+            self.done = true;
+            return Ok(Some(Frame::trailers(parse_trailers(&frame)?)));
+        }
+
+        Ok(Some(Frame::data(frame)))
+    }
+}
+
+impl<B> Body for GrpcWebBody<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(frame) = this.next_frame().transpose() {
+                return Poll::Ready(Some(frame));
+            }
+
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => {
+                        if let Err(e) = this.push(data) {
+                            return Poll::Ready(Some(Err(e)));
+                        }
+                    }
+                    // Some servers send regular HTTP/2 trailers instead of a trailers frame
+                    Err(frame) => {
+                        if let Ok(trailers) = frame.into_trailers() {
+                            this.done = true;
+                            return Poll::Ready(Some(Ok(Frame::trailers(trailers))));
+                        }
+                    }
+                },
+                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                None => {
+                    this.done = true;
+
+                    if !this.frames.is_empty() || !this.encoded.is_empty() {
+                        return Poll::Ready(Some(Err("Incomplete gRPC-Web frame".into())));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Parses the trailers frame, encoded as HTTP/1 headers (`key: value\r\n`).
+fn parse_trailers(bytes: &[u8]) -> Result<HeaderMap, BoxError> {
+    let mut trailers = HeaderMap::new();
+
+    for line in bytes.split(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+
+        let colon = line
+            .iter()
+            .position(|b| *b == b':')
+            .ok_or("Invalid gRPC-Web trailer")?;
+
+        let name = HeaderName::from_bytes(line[..colon].trim_ascii())?;
+        let value = HeaderValue::from_bytes(line[colon + 1..].trim_ascii())?;
+
+        trailers.append(name, value);
+    }
+
+    Ok(trailers)
+}
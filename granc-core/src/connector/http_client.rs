@@ -0,0 +1,69 @@
+//! # HTTP Client
+//!
+//! The plain HTTP client shared by the protocols that are translated from gRPC (gRPC-Web and Connect).
+//!
+//! Requests are sent over HTTP/1.1, or over HTTP/2 with prior knowledge.
+use super::proxy::TcpConnector;
+use crate::BoxError;
+use http::{Request, Response, Version, request::Parts, uri::PathAndQuery};
+use hyper::body::Incoming;
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use tonic::transport::Uri;
+
+#[derive(Debug, Clone)]
+pub(super) struct HttpClient {
+    client: Client<TcpConnector, tonic::body::Body>,
+    origin: Uri,
+    http2: bool,
+}
+
+impl HttpClient {
+    pub(super) fn new(origin: Uri, http2: bool, connector: TcpConnector) -> Self {
+        let client = Client::builder(TokioExecutor::new())
+            .http2_only(http2)
+            .build(connector);
+
+        Self {
+            client,
+            origin,
+            http2,
+        }
+    }
+
+    /// Sends a request to the server, resolving its path against the server URI.
+    pub(super) async fn send(
+        &self,
+        mut parts: Parts,
+        body: tonic::body::Body,
+    ) -> Result<Response<Incoming>, BoxError> {
+        parts.uri = absolute_uri(&self.origin, &parts.uri)?;
+        parts.version = if self.http2 {
+            Version::HTTP_2
+        } else {
+            Version::HTTP_11
+        };
+
+        Ok(self
+            .client
+            .request(Request::from_parts(parts, body))
+            .await?)
+    }
+}
+
+/// Joins the path of a request (`tonic` only sets the path) with the scheme, authority and
+/// path prefix of the server URI.
+fn absolute_uri(origin: &Uri, uri: &Uri) -> Result<Uri, BoxError> {
+    let prefix = origin.path().trim_end_matches('/');
+    let path = uri.path_and_query().map_or("/", PathAndQuery::as_str);
+
+    let mut builder = Uri::builder().path_and_query(format!("{prefix}{path}"));
+
+    if let Some(scheme) = origin.scheme() {
+        builder = builder.scheme(scheme.clone());
+    }
+    if let Some(authority) = origin.authority() {
+        builder = builder.authority(authority.clone());
+    }
+
+    Ok(builder.build()?)
+}
@@ -8,3 +8,4 @@
 //! transcoding them to Protobuf binary format on the fly.
 pub mod client;
 pub mod codec;
+pub mod retry;
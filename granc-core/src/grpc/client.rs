@@ -14,18 +14,38 @@
 //!
 //! * **Dynamic Pathing**: Constructs the HTTP/2 path (e.g., `/package.Service/Method`) at runtime.
 //! * **Metadata Handling**: Converts standard Rust string tuples into Tonic's `MetadataMap` for headers.
+//!   Repeated keys are appended, and keys ending in `-bin` expect base64 encoded binary values.
+//! * **Method Extension**: Attaches the `MethodDescriptor` to the extensions of every request, so
+//!   transports translating gRPC to other protocols (e.g. Connect) know the types of the messages.
+//! * **Credentials**: Resolves the [`crate::auth::Credentials`] of the client before every request,
+//!   adding their metadata unless the request already sets the same keys.
+//! * **Compression & Limits**: Exposes the compression and message size settings of `tonic::client::Grpc`.
+//! * **Retries**: Retries unary and client streaming calls following a [`RetryPolicy`] or the
+//!   policies of a [`ServiceConfig`], see [`super::retry`].
 //! * **Access Patterns**: Provides specific methods for Unary, Server Streaming, Client Streaming,
 //!   and Bidirectional Streaming calls.
-use super::codec::JsonCodec;
-use crate::BoxError;
-use futures_util::Stream;
+use super::{
+    codec::JsonCodec,
+    retry::{self, Retries, RetryPolicy, ServiceConfig},
+};
+use crate::{
+    BoxError,
+    auth::{self, Credentials, CredentialsError},
+};
+use base64::{
+    Engine,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+};
+use futures_util::{Stream, StreamExt};
 use http_body::Body as HttpBody;
 use prost_reflect::MethodDescriptor;
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 use tonic::{
+    Streaming,
     client::GrpcService,
+    codec::CompressionEncoding,
     metadata::{
-        MetadataKey, MetadataValue,
+        AsciiMetadataKey, BinaryMetadataKey, MetadataMap, MetadataValue,
         errors::{InvalidMetadataKey, InvalidMetadataValue},
     },
     transport::Channel,
@@ -45,12 +65,27 @@ pub enum GrpcRequestError {
         key: String,
         source: InvalidMetadataValue,
     },
+    #[error("Invalid binary metadata (header) value for key '{key}', expected base64: '{source}'")]
+    InvalidBinaryMetadataValue {
+        key: String,
+        source: base64::DecodeError,
+    },
+    #[error("Failed to resolve credentials: '{0}'")]
+    Credentials(#[from] CredentialsError),
 }
 
+/// Standard base64, accepting values with or without padding.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
 /// A generic client for the gRPC Server Reflection Protocol.
 #[derive(Debug, Clone)]
 pub struct GrpcClient<S = Channel> {
     client: tonic::client::Grpc<S>,
+    credentials: Option<Arc<dyn Credentials>>,
+    retries: Retries,
 }
 
 impl<S> GrpcClient<S>
@@ -62,13 +97,68 @@ where
 {
     pub fn new(service: S) -> Self {
         let client = tonic::client::Grpc::new(service);
-        Self { client }
+        Self {
+            client,
+            credentials: None,
+            retries: Retries::default(),
+        }
+    }
+
+    /// Retries the unary and client streaming calls of every method with the given policy.
+    ///
+    /// Client streaming calls buffer their messages to send them again, so their request
+    /// streams must end.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retries.policy = Some(policy);
+        self
+    }
+
+    /// Retries calls with the retry policies of a service config. Methods without a policy in the
+    /// service config fall back to the one set with [`GrpcClient::retry_policy`].
+    pub fn service_config(mut self, config: ServiceConfig) -> Self {
+        self.retries.service_config = Some(config);
+        self
+    }
+
+    /// Authenticates every request with the given credentials.
+    pub fn credentials(mut self, credentials: Arc<dyn Credentials>) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Compresses requests with the given encoding.
+    ///
+    /// The server must support it, otherwise calls fail with `Unimplemented`.
+    pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.client = self.client.send_compressed(encoding);
+        self
+    }
+
+    /// Advertises support for responses compressed with the given encoding.
+    pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+        self.client = self.client.accept_compressed(encoding);
+        self
+    }
+
+    /// Limits the size of a decoded response message. Defaults to 4MB.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.client = self.client.max_decoding_message_size(limit);
+        self
+    }
+
+    /// Limits the size of an encoded request message. Defaults to `usize::MAX`.
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.client = self.client.max_encoding_message_size(limit);
+        self
     }
 
     /// Performs a Unary gRPC call (Single Request -> Single Response).
     ///
+    /// The response metadata holds the headers sent by the server, while its trailers are
+    /// returned separately.
+    ///
     /// # Returns
-    /// * `Ok(Ok(Value))` - Successful RPC execution.
+    /// * `Ok(Ok((Response, Trailers)))` - Successful RPC execution.
     /// * `Ok(Err(Status))` - RPC executed, but server returned an error.
     /// * `Err(ClientError)` - Failed to send request or connect.
     pub async fn unary(
@@ -76,27 +166,57 @@ where
         method: MethodDescriptor,
         payload: serde_json::Value,
         headers: Vec<(String, String)>,
-    ) -> Result<Result<serde_json::Value, tonic::Status>, GrpcRequestError> {
-        self.client
-            .ready()
-            .await
-            .map_err(|e| GrpcRequestError::ClientNotReady(e.into()))?;
+    ) -> Result<
+        Result<(tonic::Response<serde_json::Value>, MetadataMap), tonic::Status>,
+        GrpcRequestError,
+    > {
+        let policy = self.retries.policy(&method).cloned();
+        let mut previous_attempts = 0;
 
-        let codec = JsonCodec::new(method.input(), method.output());
-        let path = http_path(&method);
-        let request = build_request(payload, headers)?;
+        loop {
+            let mut request = build_request(
+                &method,
+                payload.clone(),
+                headers.clone(),
+                self.credentials.as_deref(),
+            )
+            .await?;
+            retry::mark_attempt(request.metadata_mut(), previous_attempts);
+
+            self.client
+                .ready()
+                .await
+                .map_err(|e| GrpcRequestError::ClientNotReady(e.into()))?;
+
+            let codec = JsonCodec::new(method.input(), method.output());
+            let path = http_path(&method);
+
+            // On the wire, a unary response is a stream with a single message.
+            // Reading it as a stream keeps the headers and the trailers apart,
+            // which `tonic::client::Grpc::unary` would merge together.
+            // Failures after the headers are received are committed, so never retried.
+            let status = match self.client.server_streaming(request, path, codec).await {
+                Ok(response) => {
+                    let result = single_message(response).await;
+                    return Ok(retry::record_attempts(result, previous_attempts));
+                }
+                Err(status) => status,
+            };
 
-        match self.client.unary(request, path, codec).await {
-            Ok(response) => Ok(Ok(response.into_inner())),
-            Err(status) => Ok(Err(status)),
+            if !retry::wait(policy.as_ref(), &status, previous_attempts + 1).await {
+                return Ok(retry::record_attempts(Err(status), previous_attempts));
+            }
+            previous_attempts += 1;
         }
     }
 
     /// Performs a Server Streaming gRPC call (Single Request -> Stream of Responses).
     ///
+    /// The trailers can be read from the returned [`Streaming`] once it has been fully consumed.
+    ///
     /// # Returns
     ///
-    /// * `Ok(Ok(Stream))` - Successful RPC execution.
+    /// * `Ok(Ok(Response<Streaming>))` - Successful RPC execution.
     /// * `Ok(Err(Status))` - RPC executed, but server returned an error.
     /// * `Err(ClientError)` - Failed to send request or connect.
     pub async fn server_streaming(
@@ -105,9 +225,11 @@ where
         payload: serde_json::Value,
         headers: Vec<(String, String)>,
     ) -> Result<
-        Result<impl Stream<Item = Result<serde_json::Value, tonic::Status>>, tonic::Status>,
+        Result<tonic::Response<Streaming<serde_json::Value>>, tonic::Status>,
         GrpcRequestError,
     > {
+        let request = build_request(&method, payload, headers, self.credentials.as_deref()).await?;
+
         self.client
             .ready()
             .await
@@ -115,19 +237,18 @@ where
 
         let codec = JsonCodec::new(method.input(), method.output());
         let path = http_path(&method);
-        let request = build_request(payload, headers)?;
 
-        match self.client.server_streaming(request, path, codec).await {
-            Ok(response) => Ok(Ok(response.into_inner())),
-            Err(status) => Ok(Err(status)),
-        }
+        Ok(self.client.server_streaming(request, path, codec).await)
     }
 
     /// Performs a Client Streaming gRPC call (Stream of Requests -> Single Response).
     ///
+    /// The response metadata holds the headers sent by the server, while its trailers are
+    /// returned separately.
+    ///
     /// # Returns
     ///
-    /// * `Ok(Ok(Value))` - Successful RPC execution.
+    /// * `Ok(Ok((Response, Trailers)))` - Successful RPC execution.
     /// * `Ok(Err(Status))` - RPC executed, but server returned an error.
     /// * `Err(ClientError)` - Failed to send request or connect.
     pub async fn client_streaming(
@@ -135,27 +256,78 @@ where
         method: MethodDescriptor,
         payload_stream: impl Stream<Item = serde_json::Value> + Send + 'static,
         headers: Vec<(String, String)>,
-    ) -> Result<Result<serde_json::Value, tonic::Status>, GrpcRequestError> {
-        self.client
-            .ready()
-            .await
-            .map_err(|e| GrpcRequestError::ClientNotReady(e.into()))?;
+    ) -> Result<
+        Result<(tonic::Response<serde_json::Value>, MetadataMap), tonic::Status>,
+        GrpcRequestError,
+    > {
+        let Some(policy) = self.retries.policy(&method).cloned() else {
+            let request = build_request(
+                &method,
+                payload_stream,
+                headers,
+                self.credentials.as_deref(),
+            )
+            .await?;
 
-        let codec = JsonCodec::new(method.input(), method.output());
-        let path = http_path(&method);
-        let request = build_request(payload_stream, headers)?;
+            self.client
+                .ready()
+                .await
+                .map_err(|e| GrpcRequestError::ClientNotReady(e.into()))?;
+
+            let codec = JsonCodec::new(method.input(), method.output());
+            let path = http_path(&method);
+
+            // See `unary` on why the response is read as a stream.
+            return match self.client.streaming(request, path, codec).await {
+                Ok(response) => Ok(single_message(response).await),
+                Err(status) => Ok(Err(status)),
+            };
+        };
+
+        // Retried calls send the same messages again, so they are buffered
+        let messages: Vec<_> = payload_stream.collect().await;
+        let mut previous_attempts = 0;
+
+        loop {
+            let mut request = build_request(
+                &method,
+                tokio_stream::iter(messages.clone()),
+                headers.clone(),
+                self.credentials.as_deref(),
+            )
+            .await?;
+            retry::mark_attempt(request.metadata_mut(), previous_attempts);
+
+            self.client
+                .ready()
+                .await
+                .map_err(|e| GrpcRequestError::ClientNotReady(e.into()))?;
 
-        match self.client.client_streaming(request, path, codec).await {
-            Ok(response) => Ok(Ok(response.into_inner())),
-            Err(status) => Ok(Err(status)),
+            let codec = JsonCodec::new(method.input(), method.output());
+            let path = http_path(&method);
+
+            let status = match self.client.streaming(request, path, codec).await {
+                Ok(response) => {
+                    let result = single_message(response).await;
+                    return Ok(retry::record_attempts(result, previous_attempts));
+                }
+                Err(status) => status,
+            };
+
+            if !retry::wait(Some(&policy), &status, previous_attempts + 1).await {
+                return Ok(retry::record_attempts(Err(status), previous_attempts));
+            }
+            previous_attempts += 1;
         }
     }
 
     /// Performs a Bidirectional Streaming gRPC call (Stream of Requests -> Stream of Responses).
     ///
+    /// The trailers can be read from the returned [`Streaming`] once it has been fully consumed.
+    ///
     /// # Returns
     ///
-    /// * `Ok(Ok(Stream))` - Successful RPC execution.
+    /// * `Ok(Ok(Response<Streaming>))` - Successful RPC execution.
     /// * `Ok(Err(Status))` - RPC executed, but server returned an error.
     /// * `Err(ClientError)` - Failed to send request or connect.
     pub async fn bidirectional_streaming(
@@ -164,9 +336,17 @@ where
         payload_stream: impl Stream<Item = serde_json::Value> + Send + 'static,
         headers: Vec<(String, String)>,
     ) -> Result<
-        Result<impl Stream<Item = Result<serde_json::Value, tonic::Status>>, tonic::Status>,
+        Result<tonic::Response<Streaming<serde_json::Value>>, tonic::Status>,
         GrpcRequestError,
     > {
+        let request = build_request(
+            &method,
+            payload_stream,
+            headers,
+            self.credentials.as_deref(),
+        )
+        .await?;
+
         self.client
             .ready()
             .await
@@ -174,34 +354,74 @@ where
 
         let codec = JsonCodec::new(method.input(), method.output());
         let path = http_path(&method);
-        let request = build_request(payload_stream, headers)?;
 
-        match self.client.streaming(request, path, codec).await {
-            Ok(response) => Ok(Ok(response.into_inner())),
-            Err(status) => Ok(Err(status)),
-        }
+        Ok(self.client.streaming(request, path, codec).await)
     }
 }
 
+/// Reads the only message of a response stream, followed by its trailers.
+async fn single_message(
+    response: tonic::Response<Streaming<serde_json::Value>>,
+) -> Result<(tonic::Response<serde_json::Value>, MetadataMap), tonic::Status> {
+    let (headers, mut stream, extensions) = response.into_parts();
+
+    let message = stream
+        .message()
+        .await?
+        .ok_or_else(|| tonic::Status::internal("Missing response message."))?;
+
+    let trailers = stream.trailers().await?.unwrap_or_default();
+
+    Ok((
+        tonic::Response::from_parts(headers, message, extensions),
+        trailers,
+    ))
+}
+
 fn http_path(method: &MethodDescriptor) -> http::uri::PathAndQuery {
     let path = format!("/{}/{}", method.parent_service().full_name(), method.name());
     http::uri::PathAndQuery::from_str(&path).expect("valid gRPC path")
 }
 
-fn build_request<T>(
+/// Builds a request with the given headers, adding the metadata of the credentials.
+async fn build_request<T>(
+    method: &MethodDescriptor,
     payload: T,
     headers: Vec<(String, String)>,
+    credentials: Option<&dyn Credentials>,
 ) -> Result<tonic::Request<T>, GrpcRequestError> {
     let mut request = tonic::Request::new(payload);
+
+    // Lets the transport know the shape and types of the call, see `crate::connector`
+    request.extensions_mut().insert(method.clone());
+
     for (k, v) in headers {
-        let key =
-            MetadataKey::from_str(&k).map_err(|source| GrpcRequestError::InvalidMetadataKey {
-                key: k.clone(),
-                source,
+        let invalid_key = |source| GrpcRequestError::InvalidMetadataKey {
+            key: k.clone(),
+            source,
+        };
+
+        // Repeated keys are appended, so every value is sent
+        if k.ends_with("-bin") {
+            let key = BinaryMetadataKey::from_str(&k).map_err(invalid_key)?;
+            let bytes = BASE64.decode(v.trim()).map_err(|source| {
+                GrpcRequestError::InvalidBinaryMetadataValue { key: k, source }
             })?;
-        let val = MetadataValue::from_str(&v)
-            .map_err(|source| GrpcRequestError::InvalidMetadataValue { key: k, source })?;
-        request.metadata_mut().insert(key, val);
+            request
+                .metadata_mut()
+                .append_bin(key, MetadataValue::from_bytes(&bytes));
+        } else {
+            let key = AsciiMetadataKey::from_str(&k).map_err(invalid_key)?;
+            let val = MetadataValue::from_str(&v)
+                .map_err(|source| GrpcRequestError::InvalidMetadataValue { key: k, source })?;
+            request.metadata_mut().append(key, val);
+        }
+    }
+
+    // Explicit headers take precedence over the credentials
+    if let Some(credentials) = credentials {
+        auth::apply(credentials, request.metadata_mut()).await?;
     }
+
     Ok(request)
 }
@@ -0,0 +1,349 @@
+//! # Retries
+//!
+//! Retry policies following the semantics of the gRPC retry design
+//! ([gRFC A6](https://github.com/grpc/proposal/blob/master/A6-client-retries.md)):
+//!
+//! * A call is retried when it fails with one of the retryable status codes, up to a maximum
+//!   number of attempts (including the original one).
+//! * Only calls failing before any response header was received are retried: once the server
+//!   starts responding, the call is committed.
+//! * Retries wait for an exponential backoff with full jitter: a random delay between zero and
+//!   `min(initial_backoff * backoff_multiplier ^ (n - 1), max_backoff)` before the `n`th retry.
+//! * Servers can push back with the `grpc-retry-pushback-ms` trailer, either delaying the next
+//!   attempt by the given number of milliseconds or, with a negative value, stopping the retries.
+//! * Retry attempts carry the number of previous attempts in the `grpc-previous-rpc-attempts`
+//!   request header, which is also added to the metadata of the response returned after a retry.
+//!
+//! Policies are set for every method with [`RetryPolicy`], or per method with the `retryPolicy`
+//! of a [`ServiceConfig`].
+use prost_reflect::MethodDescriptor;
+use std::time::Duration;
+use tonic::{Code, Status, metadata::MetadataMap};
+
+/// The metadata key holding the number of previous attempts of a call.
+pub const PREVIOUS_ATTEMPTS: &str = "grpc-previous-rpc-attempts";
+
+/// The trailer servers set to delay or stop retries.
+const PUSHBACK: &str = "grpc-retry-pushback-ms";
+
+/// Service configs cannot ask for more attempts than this, larger values are treated as this.
+const MAX_CONFIG_ATTEMPTS: u32 = 5;
+
+/// Errors that can occur when parsing a service config.
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceConfigError {
+    #[error("Invalid service config JSON: '{0}'")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("Invalid service config: {0}")]
+    Invalid(String),
+}
+
+/// When and how often to retry failed calls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    backoff_multiplier: f64,
+    retryable_status_codes: Vec<Code>,
+}
+
+impl RetryPolicy {
+    /// Makes up to `max_attempts` attempts (including the original one) at calls failing with
+    /// `UNAVAILABLE`, backing off from 100ms up to 5s.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            backoff_multiplier: 2.0,
+            retryable_status_codes: vec![Code::Unavailable],
+        }
+    }
+
+    /// Sets the upper bound of the delay before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Caps the upper bound of the delay between attempts.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Sets the factor the upper bound of the delay grows by after every retry.
+    pub fn backoff_multiplier(mut self, multiplier: f64) -> Self {
+        self.backoff_multiplier = multiplier;
+        self
+    }
+
+    /// Sets the status codes worth retrying.
+    pub fn retryable_status_codes(mut self, codes: impl IntoIterator<Item = Code>) -> Self {
+        self.retryable_status_codes = codes.into_iter().collect();
+        self
+    }
+
+    /// The maximum number of attempts at a call, including the original one.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns how long to wait before the next attempt at a call that failed `attempts` times
+    /// with `status`, or `None` if it must not be retried.
+    pub(crate) fn retry_delay(&self, status: &Status, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts || !self.retryable_status_codes.contains(&status.code()) {
+            return None;
+        }
+
+        match pushback(status.metadata()) {
+            Some(Some(delay)) => Some(delay),
+            Some(None) => None,
+            None => Some(self.backoff(attempts)),
+        }
+    }
+
+    /// Returns a random delay before the `retry`th retry, with full jitter.
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let exponent = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        let bound = (self.initial_backoff.as_secs_f64() * self.backoff_multiplier.powi(exponent))
+            .min(self.max_backoff.as_secs_f64());
+
+        Duration::from_secs_f64(fastrand::f64() * bound)
+    }
+
+    /// Parses the `retryPolicy` object of a method config.
+    fn from_json(value: &serde_json::Value) -> Result<Self, ServiceConfigError> {
+        let invalid = |message: &str| ServiceConfigError::Invalid(format!("retryPolicy {message}"));
+
+        let max_attempts = value["maxAttempts"]
+            .as_u64()
+            .filter(|attempts| *attempts > 1)
+            .ok_or_else(|| invalid("requires maxAttempts greater than 1"))?;
+
+        let duration = |key: &str| {
+            value[key]
+                .as_str()
+                .and_then(parse_duration)
+                .filter(|duration| !duration.is_zero())
+                .ok_or_else(|| invalid(&format!("requires a positive {key}, e.g. \"0.5s\"")))
+        };
+
+        let backoff_multiplier = value["backoffMultiplier"]
+            .as_f64()
+            .filter(|multiplier| *multiplier > 0.0)
+            .ok_or_else(|| invalid("requires a positive backoffMultiplier"))?;
+
+        let retryable_status_codes = value["retryableStatusCodes"]
+            .as_array()
+            .filter(|codes| !codes.is_empty())
+            .ok_or_else(|| invalid("requires a non-empty retryableStatusCodes"))?
+            .iter()
+            .map(|code| {
+                parse_code(code)
+                    .ok_or_else(|| invalid(&format!("has an unknown status code {code}")))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            max_attempts: u32::try_from(max_attempts)
+                .unwrap_or(u32::MAX)
+                .min(MAX_CONFIG_ATTEMPTS),
+            initial_backoff: duration("initialBackoff")?,
+            max_backoff: duration("maxBackoff")?,
+            backoff_multiplier,
+            retryable_status_codes,
+        })
+    }
+}
+
+/// A gRPC [service config](https://github.com/grpc/grpc/blob/master/doc/service_config.md),
+/// of which only the `retryPolicy` of the method configs is used.
+///
+/// ```json
+/// {
+///   "methodConfig": [{
+///     "name": [{ "service": "echo.EchoService", "method": "UnaryEcho" }],
+///     "retryPolicy": {
+///       "maxAttempts": 4,
+///       "initialBackoff": "0.1s",
+///       "maxBackoff": "1s",
+///       "backoffMultiplier": 2,
+///       "retryableStatusCodes": ["UNAVAILABLE"]
+///     }
+///   }]
+/// }
+/// ```
+///
+/// A name without a method applies to every method of the service, and an empty name to every
+/// method. The most specific name matching a method selects its policy.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServiceConfig {
+    policies: Vec<(MethodName, RetryPolicy)>,
+}
+
+/// The methods a method config applies to.
+#[derive(Debug, Clone, PartialEq)]
+enum MethodName {
+    All,
+    Service(String),
+    Method(String, String),
+}
+
+impl ServiceConfig {
+    /// Parses a service config from its JSON representation.
+    pub fn from_json(json: &str) -> Result<Self, ServiceConfigError> {
+        let config: serde_json::Value = serde_json::from_str(json)?;
+        let mut policies = Vec::new();
+
+        let method_configs = match &config["methodConfig"] {
+            serde_json::Value::Null => &Vec::new(),
+            value => value.as_array().ok_or_else(|| {
+                ServiceConfigError::Invalid("methodConfig must be an array".to_string())
+            })?,
+        };
+
+        for method_config in method_configs {
+            let policy = match &method_config["retryPolicy"] {
+                serde_json::Value::Null => continue,
+                value => RetryPolicy::from_json(value)?,
+            };
+
+            let names = method_config["name"].as_array().ok_or_else(|| {
+                ServiceConfigError::Invalid("every methodConfig requires a name".to_string())
+            })?;
+
+            for name in names {
+                let service = name["service"].as_str().unwrap_or_default();
+                let method = name["method"].as_str().unwrap_or_default();
+
+                let name = match (service, method) {
+                    ("", "") => MethodName::All,
+                    ("", _) => {
+                        return Err(ServiceConfigError::Invalid(format!(
+                            "method '{method}' has no service"
+                        )));
+                    }
+                    (service, "") => MethodName::Service(service.to_string()),
+                    (service, method) => {
+                        MethodName::Method(service.to_string(), method.to_string())
+                    }
+                };
+
+                policies.push((name, policy.clone()));
+            }
+        }
+
+        Ok(Self { policies })
+    }
+
+    /// Returns the retry policy of a method, if any.
+    pub fn retry_policy(&self, method: &MethodDescriptor) -> Option<&RetryPolicy> {
+        let service = method.parent_service().full_name().to_string();
+        let method = method.name().to_string();
+
+        let find = |name: &MethodName| {
+            self.policies
+                .iter()
+                .find(|(candidate, _)| candidate == name)
+                .map(|(_, policy)| policy)
+        };
+
+        find(&MethodName::Method(service.clone(), method))
+            .or_else(|| find(&MethodName::Service(service)))
+            .or_else(|| find(&MethodName::All))
+    }
+}
+
+/// The retry settings of a [`super::client::GrpcClient`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Retries {
+    pub(crate) policy: Option<RetryPolicy>,
+    pub(crate) service_config: Option<ServiceConfig>,
+}
+
+impl Retries {
+    /// Returns the policy of a method: the one of the service config, or the default one.
+    pub(crate) fn policy(&self, method: &MethodDescriptor) -> Option<&RetryPolicy> {
+        self.service_config
+            .as_ref()
+            .and_then(|config| config.retry_policy(method))
+            .or(self.policy.as_ref())
+    }
+}
+
+/// Waits before the next attempt at a call that failed `attempts` times with `status`.
+///
+/// Returns `false` without waiting if the call must not be retried.
+pub(crate) async fn wait(policy: Option<&RetryPolicy>, status: &Status, attempts: u32) -> bool {
+    match policy.and_then(|policy| policy.retry_delay(status, attempts)) {
+        Some(delay) => {
+            tokio::time::sleep(delay).await;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Tells the server how many attempts preceded a retry attempt.
+pub(crate) fn mark_attempt(metadata: &mut MetadataMap, previous_attempts: u32) {
+    if previous_attempts > 0 {
+        metadata.insert(PREVIOUS_ATTEMPTS, previous_attempts.into());
+    }
+}
+
+/// Adds the number of attempts preceding the last one to the metadata of its result, so callers
+/// can tell the call was retried.
+pub(crate) fn record_attempts<T>(
+    result: Result<(tonic::Response<T>, MetadataMap), Status>,
+    previous_attempts: u32,
+) -> Result<(tonic::Response<T>, MetadataMap), Status> {
+    if previous_attempts == 0 {
+        return result;
+    }
+
+    match result {
+        Ok((mut response, trailers)) => {
+            mark_attempt(response.metadata_mut(), previous_attempts);
+            Ok((response, trailers))
+        }
+        Err(mut status) => {
+            mark_attempt(status.metadata_mut(), previous_attempts);
+            Err(status)
+        }
+    }
+}
+
+/// Reads the pushback of the server: `Some(Some(delay))` to retry after the given delay,
+/// `Some(None)` to stop retrying, and `None` when the server did not push back.
+fn pushback(trailers: &MetadataMap) -> Option<Option<Duration>> {
+    let value = trailers.get(PUSHBACK)?;
+
+    Some(
+        value
+            .to_str()
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_millis),
+    )
+}
+
+/// Parses a JSON duration, e.g. `"1.5s"`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let seconds: f64 = value.strip_suffix('s')?.parse().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Parses a status code, given by name (e.g. `"UNAVAILABLE"`) or number.
+fn parse_code(value: &serde_json::Value) -> Option<Code> {
+    if let Some(number) = value.as_i64() {
+        let number = i32::try_from(number).ok()?;
+        return (0..=16).contains(&number).then(|| Code::from_i32(number));
+    }
+
+    let name = value.as_str()?.replace('_', "").to_ascii_lowercase();
+    (0..=16)
+        .map(Code::from_i32)
+        .find(|code| format!("{code:?}").to_ascii_lowercase() == name)
+}
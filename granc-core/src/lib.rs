@@ -33,7 +33,9 @@
 //! use compatible versions of these underlying dependencies.
 //!
 //! See the README.md for more details about usage.
+pub mod auth;
 pub mod client;
+pub mod connector;
 pub mod grpc;
 pub mod reflection;
 
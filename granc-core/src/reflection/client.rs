@@ -16,16 +16,23 @@
 //! ## References
 //!
 //! * [gRPC Server Reflection Protocol](https://github.com/grpc/grpc/blob/master/doc/server-reflection.md)
-use crate::BoxError;
-use futures_util::stream::once;
+use crate::{
+    BoxError,
+    auth::{self, Credentials, CredentialsError},
+};
+use futures_util::Stream;
+use futures_util::stream::{iter, once};
 use http_body::Body as HttpBody;
 use prost::Message;
 use prost_types::{FileDescriptorProto, FileDescriptorSet};
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
+use tonic::client::GrpcService;
 use tonic::transport::Channel;
-use tonic::{Streaming, client::GrpcService};
 use tonic_reflection::pb::v1::{
     ServerReflectionRequest, ServerReflectionResponse,
     server_reflection_client::ServerReflectionClient, server_reflection_request::MessageRequest,
@@ -46,9 +53,6 @@ pub enum ReflectionResolveError {
     #[error("Reflection stream closed unexpectedly")]
     StreamClosed,
 
-    #[error("Internal error: Failed to send request to stream")]
-    SendFailed,
-
     #[error("Server returned reflection error code {code}: {message}")]
     ServerError { code: i32, message: String },
 
@@ -57,17 +61,18 @@ pub enum ReflectionResolveError {
 
     #[error("Failed to decode FileDescriptorProto: {0}")]
     DecodeError(#[from] prost::DecodeError),
-}
 
-// The host defined in the reflection requests doesn't seem to be a mandatory field
-// and there is no documentation about what it is about.
-// So we won't enforce it from the user.
-const EMPTY_HOST: &str = "";
+    #[error("Failed to resolve credentials: '{0}'")]
+    Credentials(#[from] CredentialsError),
+}
 
 /// A client for interacting with the gRPC Server Reflection Service.
 #[derive(Debug, Clone)]
 pub struct ReflectionClient<T = Channel> {
     client: ServerReflectionClient<T>,
+    host: String,
+    credentials: Option<Arc<dyn Credentials>>,
+    half_duplex: bool,
 }
 
 impl<S> ReflectionClient<S>
@@ -80,7 +85,42 @@ where
     /// Creates a new `ReflectionClient` using the provided gRPC service (e.g., a `Channel`).
     pub fn new(channel: S) -> Self {
         let client = ServerReflectionClient::new(channel);
-        Self { client }
+        Self {
+            client,
+            host: String::new(),
+            credentials: None,
+            half_duplex: false,
+        }
+    }
+
+    /// Authenticates every reflection request with the given credentials.
+    pub fn credentials(mut self, credentials: Arc<dyn Credentials>) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Sets the `host` field of the reflection requests.
+    ///
+    /// The field is not mandatory and most servers ignore it, so it is empty by default. Servers
+    /// multiplexing several virtual hosts can use it to select the schema to reflect.
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// Fetches the dependencies of a file one level at a time, each level in its own finite request
+    /// stream, for transports that cannot interleave requests and responses (gRPC-Web, Connect).
+    ///
+    /// By default, every file is requested over a single stream, as soon as its dependent is received.
+    pub fn half_duplex(mut self, enabled: bool) -> Self {
+        self.half_duplex = enabled;
+        self
+    }
+
+    /// Limits the size of a decoded reflection response. Defaults to 4MB.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.client = self.client.max_decoding_message_size(limit);
+        self
     }
 
     /// Fetches the complete `FileDescriptorSet` containing the definition for the given symbol.
@@ -106,35 +146,40 @@ where
         &mut self,
         symbol: &str,
     ) -> Result<FileDescriptorSet, ReflectionResolveError> {
-        // Initialize Stream
-        let (tx, rx) = mpsc::channel(100);
-
-        let mut response_stream = self
-            .client
-            .server_reflection_info(ReceiverStream::new(rx))
-            .await
-            .map_err(ReflectionResolveError::ServerStreamInitFailed)?
-            .into_inner();
-
-        // Send Initial Request
-        let req = ServerReflectionRequest {
-            host: EMPTY_HOST.to_string(),
-            message_request: Some(MessageRequest::FileContainingSymbol(symbol.to_string())),
-        };
-
-        tx.send(req)
-            .await
-            .map_err(|_| ReflectionResolveError::SendFailed)?;
-
-        // Fetch all transitive dependencies
-        let file_map = collect_descriptors(&mut response_stream, tx).await?;
+        let file = self
+            .encoded_file_descriptors_by_symbol(symbol)
+            .await?
+            .into_values()
+            .map(|raw| FileDescriptorProto::decode(raw.as_slice()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(FileDescriptorSet { file })
+    }
 
-        // Build Registry directly
-        let fd_set = FileDescriptorSet {
-            file: file_map.into_values().collect(),
-        };
+    /// Fetches the files defining the given symbol and its transitive dependencies, like
+    /// [`ReflectionClient::file_descriptor_set_by_symbol`], but keeps them encoded as sent by the server.
+    ///
+    /// `prost_types` drops the fields it does not know when decoding, so custom options (extensions such as
+    /// `google.api.http`) are only preserved by decoding these files with `prost_reflect`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(HashMap<String, Vec<u8>>)` - The encoded `FileDescriptorProto`s, by file name.
+    /// * `Err(ReflectionResolveError)` - If the symbol is not found, the server doesn't support reflection, or a protocol error occurs.
+    pub async fn encoded_file_descriptors_by_symbol(
+        &mut self,
+        symbol: &str,
+    ) -> Result<HashMap<String, Vec<u8>>, ReflectionResolveError> {
+        let mut files = CollectedFiles::default();
+        let request = MessageRequest::FileContainingSymbol(symbol.to_string());
+
+        if self.half_duplex {
+            self.collect_by_level(request, &mut files).await?;
+        } else {
+            self.collect_over_stream(request, &mut files).await?;
+        }
 
-        Ok(fd_set)
+        Ok(files.collected)
     }
 
     /// Lists all services exposed by the server.
@@ -147,13 +192,15 @@ where
     /// * `Err(ReflectionResolveError)` - If the server doesn't support reflection or a protocol error occurs.
     pub async fn list_services(&mut self) -> Result<Vec<String>, ReflectionResolveError> {
         let req = ServerReflectionRequest {
-            host: EMPTY_HOST.to_string(),
+            host: self.host.clone(),
             message_request: Some(MessageRequest::ListServices(String::new())),
         };
 
+        let request = self.request(once(async { req })).await?;
+
         let mut response_stream = self
             .client
-            .server_reflection_info(once(async { req }))
+            .server_reflection_info(request)
             .await
             .map_err(ReflectionResolveError::ServerStreamInitFailed)?
             .into_inner();
@@ -181,37 +228,143 @@ where
             )),
         }
     }
-}
 
-async fn collect_descriptors(
-    response_stream: &mut Streaming<ServerReflectionResponse>,
-    request_channel: mpsc::Sender<ServerReflectionRequest>,
-) -> Result<HashMap<String, FileDescriptorProto>, ReflectionResolveError> {
-    let mut inflight = 1;
-    let mut collected_files = HashMap::new();
-    let mut requested = HashSet::new();
+    /// Wraps a stream of reflection requests, adding the metadata of the credentials.
+    async fn request<R>(&self, requests: R) -> Result<tonic::Request<R>, ReflectionResolveError>
+    where
+        R: Stream<Item = ServerReflectionRequest>,
+    {
+        let mut request = tonic::Request::new(requests);
 
-    while inflight > 0 {
-        let response = response_stream
-            .message()
+        if let Some(credentials) = &self.credentials {
+            auth::apply(credentials.as_ref(), request.metadata_mut()).await?;
+        }
+
+        Ok(request)
+    }
+
+    /// Requests every file over a single stream, asking for the dependencies of a file as soon as it is received.
+    async fn collect_over_stream(
+        &mut self,
+        request: MessageRequest,
+        files: &mut CollectedFiles,
+    ) -> Result<(), ReflectionResolveError> {
+        let (tx, rx) = mpsc::channel(100);
+        let request_stream = self.request(ReceiverStream::new(rx)).await?;
+
+        let mut response_stream = self
+            .client
+            .server_reflection_info(request_stream)
             .await
-            .map_err(ReflectionResolveError::ServerStreamFailure)?
-            .ok_or(ReflectionResolveError::StreamClosed)?;
+            .map_err(ReflectionResolveError::ServerStreamInitFailed)?
+            .into_inner();
 
-        inflight -= 1;
+        let mut pending = vec![request];
+        let mut inflight = 0;
+
+        loop {
+            for message_request in pending.drain(..) {
+                let request = ServerReflectionRequest {
+                    host: self.host.clone(),
+                    message_request: Some(message_request),
+                };
+
+                // The receiver is only dropped once the server closed the stream
+                tx.send(request)
+                    .await
+                    .map_err(|_| ReflectionResolveError::StreamClosed)?;
+                inflight += 1;
+            }
 
-        match response.message_response {
-            Some(MessageResponse::FileDescriptorResponse(res)) => {
-                let sent_count = process_descriptor_batch(
-                    res.file_descriptor_proto,
-                    &mut collected_files,
-                    &mut requested,
-                    &request_channel,
-                )
-                .await?;
-
-                inflight += sent_count;
+            if inflight == 0 {
+                return Ok(());
+            }
+
+            let response = response_stream
+                .message()
+                .await
+                .map_err(ReflectionResolveError::ServerStreamFailure)?
+                .ok_or(ReflectionResolveError::StreamClosed)?;
+
+            inflight -= 1;
+            pending = files.add(response)?;
+        }
+    }
+
+    /// Requests the files one level of dependencies at a time, each level in its own finite request stream.
+    ///
+    /// Keeping a single stream open while waiting for responses would deadlock on half-duplex transports.
+    async fn collect_by_level(
+        &mut self,
+        request: MessageRequest,
+        files: &mut CollectedFiles,
+    ) -> Result<(), ReflectionResolveError> {
+        let mut pending = vec![request];
+
+        while !pending.is_empty() {
+            for response in self.batch(std::mem::take(&mut pending)).await? {
+                pending.extend(files.add(response)?);
             }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the given requests in a single stream and waits for one response to each of them.
+    async fn batch(
+        &mut self,
+        requests: Vec<MessageRequest>,
+    ) -> Result<Vec<ServerReflectionResponse>, ReflectionResolveError> {
+        let count = requests.len();
+        let host = self.host.clone();
+        let requests = requests
+            .into_iter()
+            .map(move |message_request| ServerReflectionRequest {
+                host: host.clone(),
+                message_request: Some(message_request),
+            });
+
+        let request = self.request(iter(requests)).await?;
+
+        let mut response_stream = self
+            .client
+            .server_reflection_info(request)
+            .await
+            .map_err(ReflectionResolveError::ServerStreamInitFailed)?
+            .into_inner();
+
+        let mut responses = Vec::with_capacity(count);
+
+        while responses.len() < count {
+            let response = response_stream
+                .message()
+                .await
+                .map_err(ReflectionResolveError::ServerStreamFailure)?
+                .ok_or(ReflectionResolveError::StreamClosed)?;
+
+            responses.push(response);
+        }
+
+        Ok(responses)
+    }
+}
+
+/// The encoded files received so far, by name, and the files already requested.
+#[derive(Default)]
+struct CollectedFiles {
+    collected: HashMap<String, Vec<u8>>,
+    requested: HashSet<String>,
+}
+
+impl CollectedFiles {
+    /// Adds the files of a reflection response, returning the requests for their dependencies not
+    /// requested yet.
+    fn add(
+        &mut self,
+        response: ServerReflectionResponse,
+    ) -> Result<Vec<MessageRequest>, ReflectionResolveError> {
+        let raw_protos = match response.message_response {
+            Some(MessageResponse::FileDescriptorResponse(res)) => res.file_descriptor_proto,
             Some(MessageResponse::ErrorResponse(e)) => {
                 return Err(ReflectionResolveError::ServerError {
                     message: e.error_message,
@@ -229,56 +382,26 @@ async fn collect_descriptors(
                     "Empty Message".into(),
                 ));
             }
-        }
-    }
-
-    Ok(collected_files)
-}
+        };
 
-async fn process_descriptor_batch(
-    raw_protos: Vec<Vec<u8>>,
-    collected_files: &mut HashMap<String, FileDescriptorProto>,
-    requested: &mut HashSet<String>,
-    tx: &mpsc::Sender<ServerReflectionRequest>,
-) -> Result<usize, ReflectionResolveError> {
-    let mut sent_count = 0;
+        let mut dependencies = vec![];
 
-    for raw in raw_protos {
-        let fd = FileDescriptorProto::decode(raw.as_ref())?;
+        for raw in raw_protos {
+            let fd = FileDescriptorProto::decode(raw.as_ref())?;
 
-        if let Some(name) = &fd.name
-            && !collected_files.contains_key(name)
-        {
-            sent_count += queue_dependencies(&fd, collected_files, requested, tx).await?;
+            if let Some(name) = &fd.name
+                && !self.collected.contains_key(name)
+            {
+                for dep in &fd.dependency {
+                    if !self.collected.contains_key(dep) && self.requested.insert(dep.clone()) {
+                        dependencies.push(MessageRequest::FileByFilename(dep.clone()));
+                    }
+                }
 
-            collected_files.insert(name.clone(), fd);
+                self.collected.insert(name.clone(), raw);
+            }
         }
-    }
-
-    Ok(sent_count)
-}
 
-async fn queue_dependencies(
-    fd: &FileDescriptorProto,
-    collected_files: &HashMap<String, FileDescriptorProto>,
-    requested: &mut HashSet<String>,
-    tx: &mpsc::Sender<ServerReflectionRequest>,
-) -> Result<usize, ReflectionResolveError> {
-    let mut count = 0;
-
-    for dep in &fd.dependency {
-        if !collected_files.contains_key(dep) && requested.insert(dep.clone()) {
-            let req = ServerReflectionRequest {
-                host: EMPTY_HOST.to_string(),
-                message_request: Some(MessageRequest::FileByFilename(dep.clone())),
-            };
-
-            tx.send(req)
-                .await
-                .map_err(|_| ReflectionResolveError::SendFailed)?;
-            count += 1;
-        }
+        Ok(dependencies)
     }
-
-    Ok(count)
 }
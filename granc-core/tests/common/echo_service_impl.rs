@@ -19,9 +19,32 @@ impl EchoService for EchoServiceImpl {
         &self,
         request: Request<EchoRequest>,
     ) -> Result<Response<EchoResponse>, Status> {
-        Ok(Response::new(EchoResponse {
+        let echo_values: Vec<_> = request
+            .metadata()
+            .get_all("x-echo")
+            .iter()
+            .cloned()
+            .collect();
+        let echo_bin_values: Vec<_> = request
+            .metadata()
+            .get_all_bin("x-echo-bin")
+            .iter()
+            .cloned()
+            .collect();
+
+        let mut response = Response::new(EchoResponse {
             message: request.into_inner().message,
-        }))
+        });
+
+        // Echo back every `x-echo` and `x-echo-bin` value, so tests can verify response metadata
+        for value in echo_values {
+            response.metadata_mut().append("x-echo", value);
+        }
+        for value in echo_bin_values {
+            response.metadata_mut().append_bin("x-echo-bin", value);
+        }
+
+        Ok(response)
     }
 
     async fn server_streaming_echo(
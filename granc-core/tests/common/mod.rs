@@ -0,0 +1,124 @@
+//! Fixtures shared by the integration tests: servers running the echo service, and requests to it.
+
+// Each test crate only uses some of the fixtures
+#![allow(dead_code)]
+
+pub mod echo_service_impl;
+
+use echo_service_impl::EchoServiceImpl;
+use granc_core::client::{DynamicRequest, DynamicResponse, GrancClient};
+use granc_core::connector::ConnectOptions;
+use granc_test_support::echo_service::{EchoServiceServer, FILE_DESCRIPTOR_SET};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::service::Routes;
+use tonic::transport::Server;
+use tonic_web::GrpcWebLayer;
+use tower::util::MapRequestLayer;
+
+/// The authorities of the requests received by a server.
+pub type AuthorityLog = Arc<Mutex<Vec<String>>>;
+
+/// The reflection service, describing the echo service.
+pub fn reflection_routes() -> Routes {
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .build_v1()
+        .unwrap();
+
+    Routes::new(reflection_service)
+}
+
+/// The echo service along with the reflection service.
+pub fn echo_routes() -> Routes {
+    reflection_routes().add_service(EchoServiceServer::new(EchoServiceImpl))
+}
+
+/// Runs `serve` on a local TCP port, e.g. a `tonic` server with its own layers. Returns the address.
+pub async fn spawn_server<F>(serve: impl FnOnce(TcpListenerStream) -> F) -> SocketAddr
+where
+    F: Future<Output = Result<(), tonic::transport::Error>> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(serve(TcpListenerStream::new(listener)));
+
+    addr
+}
+
+/// Serves the echo service with reflection, over native gRPC and gRPC-Web. Returns the address.
+pub async fn serve() -> SocketAddr {
+    spawn_server(|incoming| {
+        Server::builder()
+            .accept_http1(true)
+            .layer(GrpcWebLayer::new())
+            .add_routes(echo_routes())
+            .serve_with_incoming(incoming)
+    })
+    .await
+}
+
+/// Like [`serve`], recording the authority of every request in `log`: `:authority` over HTTP/2,
+/// `Host` over HTTP/1.1.
+pub async fn serve_with_authority_log(log: AuthorityLog) -> SocketAddr {
+    let record = MapRequestLayer::new(move |request: http::Request<_>| {
+        let authority = request
+            .uri()
+            .authority()
+            .map(ToString::to_string)
+            .or_else(|| {
+                let host = request.headers().get("host")?;
+                Some(host.to_str().unwrap().to_string())
+            });
+        log.lock().unwrap().push(authority.unwrap_or_default());
+        request
+    });
+
+    spawn_server(|incoming| {
+        Server::builder()
+            .accept_http1(true)
+            .layer(record)
+            .layer(GrpcWebLayer::new())
+            .add_routes(echo_routes())
+            .serve_with_incoming(incoming)
+    })
+    .await
+}
+
+/// Asserts that every request received by the server carried the `expected` authority.
+pub fn assert_authorities(log: &AuthorityLog, expected: &str) {
+    let log = log.lock().unwrap();
+    assert!(!log.is_empty());
+    assert!(log.iter().all(|authority| authority == expected), "{log:?}");
+}
+
+/// A request to a method of the echo service.
+pub fn echo_request(method: &str, body: serde_json::Value) -> DynamicRequest {
+    DynamicRequest {
+        service: "echo.EchoService".to_string(),
+        method: method.to_string(),
+        body,
+        headers: vec![],
+    }
+}
+
+/// A `UnaryEcho` request, echoing `message`.
+pub fn unary_echo(message: &str) -> DynamicRequest {
+    echo_request("UnaryEcho", serde_json::json!({ "message": message }))
+}
+
+/// Connects to the echo server at `uri`, and asserts that reflection lists the echo service and
+/// that `UnaryEcho` echoes `message`.
+pub async fn assert_echo(uri: &str, options: ConnectOptions, message: &str) {
+    let mut client = GrancClient::connect_with(uri, options).await.unwrap();
+
+    let services = client.list_services().await.unwrap();
+    assert!(services.contains(&"echo.EchoService".to_string()));
+
+    let res = client.dynamic(unary_echo(message)).await.unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == message));
+}
@@ -0,0 +1,82 @@
+use common::{AuthorityLog, assert_authorities, assert_echo, serve_with_authority_log};
+use granc_core::client::{GrancClient, online};
+use granc_core::connector::{ConnectOptions, Protocol};
+
+mod common;
+
+#[tokio::test]
+async fn test_authority_override() {
+    for protocol in [Protocol::Grpc, Protocol::GrpcWeb] {
+        let log = AuthorityLog::default();
+        let port = serve_with_authority_log(log.clone()).await.port();
+
+        let options = ConnectOptions::new()
+            .protocol(protocol)
+            .authority("api.example.com");
+
+        assert_echo(&format!("http://127.0.0.1:{port}"), options, "routed").await;
+        assert_authorities(&log, "api.example.com");
+    }
+}
+
+#[tokio::test]
+async fn test_connect_to() {
+    for protocol in [Protocol::Grpc, Protocol::GrpcWeb] {
+        let log = AuthorityLog::default();
+        let port = serve_with_authority_log(log.clone()).await.port();
+
+        // The `.test` domain never resolves, so the server is only reachable through the override
+        let options = ConnectOptions::new()
+            .protocol(protocol)
+            .connect_to(format!("127.0.0.1:{port}"));
+
+        assert_echo("http://echo.test:8080", options, "routed").await;
+        assert_authorities(&log, "echo.test:8080");
+    }
+}
+
+#[tokio::test]
+async fn test_connect_to_with_authority() {
+    let log = AuthorityLog::default();
+    let port = serve_with_authority_log(log.clone()).await.port();
+
+    let options = ConnectOptions::new()
+        .connect_to(format!("localhost:{port}"))
+        .authority("api.example.com");
+
+    assert_echo("http://echo.test", options, "routed").await;
+    assert_authorities(&log, "api.example.com");
+}
+
+#[tokio::test]
+async fn test_invalid_overrides() {
+    let result = GrancClient::connect_with(
+        "http://localhost:50051",
+        ConnectOptions::new().authority("not an authority"),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(online::ClientConnectError::InvalidAuthority(_))
+    ));
+
+    let result = GrancClient::connect_with(
+        "http://localhost:50051",
+        ConnectOptions::new().connect_to("localhost"),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(online::ClientConnectError::InvalidAddress(_))
+    ));
+
+    let result = GrancClient::connect_with(
+        "unix:///tmp/granc.sock",
+        ConnectOptions::new().connect_to("localhost:50051"),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(online::ClientConnectError::UnsupportedTransport(..))
+    ));
+}
@@ -0,0 +1,124 @@
+use common::echo_service_impl::EchoServiceImpl;
+use common::{reflection_routes, spawn_server, unary_echo};
+use granc_core::client::{DynamicResponse, GrancClient, online};
+use granc_core::connector::{ConnectOptions, Protocol};
+use granc_test_support::echo_service::{EchoServiceServer, FILE_DESCRIPTOR_SET};
+use tonic::Code;
+use tonic::codec::CompressionEncoding;
+
+mod common;
+
+/// Serves the echo service, accepting requests compressed with `accept` and compressing responses with `send`
+/// when the client accepts it. Returns the server URI.
+async fn serve(accept: &[CompressionEncoding], send: CompressionEncoding) -> String {
+    let mut echo_service = EchoServiceServer::new(EchoServiceImpl).send_compressed(send);
+    for encoding in accept {
+        echo_service = echo_service.accept_compressed(*encoding);
+    }
+
+    let addr = spawn_server(|incoming| {
+        tonic::transport::Server::builder()
+            .add_routes(reflection_routes().add_service(echo_service))
+            .serve_with_incoming(incoming)
+    })
+    .await;
+
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn test_compressed_roundtrip() {
+    let all = [CompressionEncoding::Gzip, CompressionEncoding::Zstd];
+
+    for encoding in all {
+        let uri = serve(&all, encoding).await;
+
+        let options = ConnectOptions::new()
+            .send_compressed(encoding)
+            .accept_compressed(encoding);
+
+        let mut client = GrancClient::connect_with(&uri, options).await.unwrap();
+        let pool = client
+            .fetch_descriptor_pool(&["echo.EchoService".to_string()])
+            .await
+            .unwrap();
+        let mut client = client.with_descriptor_pool(pool);
+
+        let (res, metadata) = client
+            .dynamic_with_metadata(unary_echo(&"compressible ".repeat(100)))
+            .await
+            .unwrap();
+
+        assert!(matches!(res, DynamicResponse::Unary(Ok(_))));
+
+        // The server compressed the response with the negotiated encoding
+        let expected = match encoding {
+            CompressionEncoding::Gzip => "gzip",
+            _ => "zstd",
+        };
+        assert_eq!(metadata.headers.get("grpc-encoding").unwrap(), expected);
+    }
+}
+
+#[tokio::test]
+async fn test_unsupported_request_compression() {
+    let uri = serve(&[CompressionEncoding::Gzip], CompressionEncoding::Gzip).await;
+
+    let options = ConnectOptions::new().send_compressed(CompressionEncoding::Zstd);
+    let mut client = GrancClient::connect_with(&uri, options).await.unwrap();
+
+    let res = client.dynamic(unary_echo("hello")).await.unwrap();
+
+    assert!(
+        matches!(res, DynamicResponse::Unary(Err(status)) if status.code() == Code::Unimplemented)
+    );
+}
+
+#[tokio::test]
+async fn test_max_decoding_message_size() {
+    let uri = serve(&[], CompressionEncoding::Gzip).await;
+
+    let options = ConnectOptions::new().max_decoding_message_size(64);
+    let client = GrancClient::connect_with(&uri, options).await.unwrap();
+    let mut client = client
+        .with_file_descriptor(FILE_DESCRIPTOR_SET.to_vec())
+        .unwrap();
+
+    let res = client.dynamic(unary_echo("small")).await.unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(_))));
+
+    let res = client.dynamic(unary_echo(&"x".repeat(100))).await.unwrap();
+    assert!(
+        matches!(res, DynamicResponse::Unary(Err(status)) if status.code() == Code::OutOfRange)
+    );
+}
+
+#[tokio::test]
+async fn test_max_encoding_message_size() {
+    let uri = serve(&[], CompressionEncoding::Gzip).await;
+
+    let options = ConnectOptions::new().max_encoding_message_size(64);
+    let client = GrancClient::connect_with(&uri, options).await.unwrap();
+    let mut client = client
+        .with_file_descriptor(FILE_DESCRIPTOR_SET.to_vec())
+        .unwrap();
+
+    let res = client.dynamic(unary_echo(&"x".repeat(100))).await.unwrap();
+
+    // tonic aborts the request stream, so the status depends on the transport
+    assert!(matches!(res, DynamicResponse::Unary(Err(_))));
+}
+
+#[tokio::test]
+async fn test_compression_unsupported_with_connect() {
+    let options = ConnectOptions::new()
+        .protocol(Protocol::Connect)
+        .send_compressed(CompressionEncoding::Gzip);
+
+    let result = GrancClient::connect_with("http://localhost:50051", options).await;
+
+    assert!(matches!(
+        result,
+        Err(online::ClientConnectError::UnsupportedTransport(..))
+    ));
+}
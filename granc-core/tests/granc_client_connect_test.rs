@@ -0,0 +1,300 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use granc_core::client::{DynamicRequest, DynamicResponse, GrancClient, OnlineWithoutReflection};
+use granc_core::connector::{ConnectOptions, Connection, Protocol};
+use granc_test_support::echo_service::FILE_DESCRIPTOR_SET;
+use granc_test_support::echo_service::pb::{EchoRequest, EchoResponse};
+use http_body_util::{BodyExt, Full};
+use hyper::{Request, Response, StatusCode, body::Incoming, service::service_fn};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use prost::Message;
+use std::convert::Infallible;
+use tokio::net::TcpListener;
+use tonic::Code;
+
+/// A minimal Connect server implementing the echo service.
+///
+/// Echoing the message "fail" returns a `not_found` error.
+async fn handle(request: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let path = request.uri().path().to_string();
+    let content_type = request.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let json = content_type.ends_with("json");
+
+    if request.headers().get("connect-protocol-version").is_none() {
+        return Ok(error(
+            StatusCode::BAD_REQUEST,
+            "invalid_argument",
+            "Not Connect",
+        ));
+    }
+
+    let body = request.into_body().collect().await.unwrap().to_bytes();
+
+    let response = match path.as_str() {
+        "/echo.EchoService/UnaryEcho" => {
+            assert_eq!(content_type, format!("application/{}", codec(json)));
+
+            let message = decode(json, &body);
+            if message == "fail" {
+                return Ok(error(
+                    StatusCode::NOT_FOUND,
+                    "not_found",
+                    "Nothing to echo: ✗",
+                ));
+            }
+
+            Response::builder()
+                .header("content-type", content_type)
+                .header("x-echo", "header")
+                .header("trailer-x-echo", "trailer")
+                .body(Full::new(encode(json, &message)))
+        }
+        "/echo.EchoService/ServerStreamingEcho" => {
+            assert_eq!(content_type, format!("application/connect+{}", codec(json)));
+
+            let message = decode(json, &envelopes(&body)[0]);
+            let mut out = BytesMut::new();
+
+            for i in 0..3 {
+                envelope(&mut out, 0, &encode(json, &format!("{message} - seq {i}")));
+            }
+
+            let end = if message == "fail" {
+                serde_json::json!({ "error": { "code": "aborted", "message": "Stream failed" } })
+            } else {
+                serde_json::json!({ "metadata": { "x-echo": ["a", "b"] } })
+            };
+            envelope(&mut out, 0x02, &serde_json::to_vec(&end).unwrap());
+
+            Response::builder()
+                .header("content-type", content_type)
+                .body(Full::new(out.freeze()))
+        }
+        "/echo.EchoService/ClientStreamingEcho" => {
+            let message: String = envelopes(&body)
+                .iter()
+                .map(|payload| decode(json, payload))
+                .collect();
+
+            let mut out = BytesMut::new();
+            envelope(&mut out, 0, &encode(json, &message));
+            envelope(&mut out, 0x02, b"{}");
+
+            Response::builder()
+                .header("content-type", content_type)
+                .body(Full::new(out.freeze()))
+        }
+        _ => {
+            return Ok(error(
+                StatusCode::NOT_FOUND,
+                "unimplemented",
+                "Unknown method",
+            ));
+        }
+    };
+
+    Ok(response.unwrap())
+}
+
+fn codec(json: bool) -> &'static str {
+    if json { "json" } else { "proto" }
+}
+
+fn decode(json: bool, bytes: &[u8]) -> String {
+    if json {
+        let value: serde_json::Value = serde_json::from_slice(bytes).unwrap();
+        value["message"].as_str().unwrap_or_default().to_string()
+    } else {
+        EchoRequest::decode(bytes).unwrap().message
+    }
+}
+
+fn encode(json: bool, message: &str) -> Bytes {
+    if json {
+        serde_json::to_vec(&serde_json::json!({ "message": message }))
+            .unwrap()
+            .into()
+    } else {
+        EchoResponse {
+            message: message.to_string(),
+        }
+        .encode_to_vec()
+        .into()
+    }
+}
+
+fn envelope(out: &mut BytesMut, flags: u8, payload: &[u8]) {
+    out.put_u8(flags);
+    out.put_u32(payload.len() as u32);
+    out.put_slice(payload);
+}
+
+fn envelopes(mut body: &[u8]) -> Vec<Vec<u8>> {
+    let mut payloads = vec![];
+    while !body.is_empty() {
+        let len = u32::from_be_bytes(body[1..5].try_into().unwrap()) as usize;
+        payloads.push(body[5..5 + len].to_vec());
+        body = &body[5 + len..];
+    }
+    payloads
+}
+
+fn error(status: StatusCode, code: &str, message: &str) -> Response<Full<Bytes>> {
+    let body = serde_json::json!({ "code": code, "message": message });
+
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Full::new(serde_json::to_vec(&body).unwrap().into()))
+        .unwrap()
+}
+
+/// Serves the Connect echo service over HTTP/1.1 and HTTP/2, returning the server URI.
+async fn serve() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(
+                hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), service_fn(handle))
+                    .into_owned(),
+            );
+        }
+    });
+
+    format!("http://{addr}")
+}
+
+async fn setup_client(
+    protocol: Protocol,
+    http2: bool,
+) -> GrancClient<OnlineWithoutReflection<Connection>> {
+    let uri = serve().await;
+    let options = ConnectOptions::new().protocol(protocol).http2(http2);
+
+    GrancClient::connect_with(&uri, options)
+        .await
+        .unwrap()
+        .with_file_descriptor(FILE_DESCRIPTOR_SET.to_vec())
+        .unwrap()
+}
+
+fn request(method: &str, body: serde_json::Value) -> DynamicRequest {
+    DynamicRequest {
+        service: "echo.EchoService".to_string(),
+        method: method.to_string(),
+        body,
+        headers: vec![],
+    }
+}
+
+async fn assert_calls(mut client: GrancClient<OnlineWithoutReflection<Connection>>) {
+    let (res, metadata) = client
+        .dynamic_with_metadata(request(
+            "UnaryEcho",
+            serde_json::json!({ "message": "connect" }),
+        ))
+        .await
+        .unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "connect"));
+    assert_eq!(metadata.headers.get("x-echo").unwrap(), "header");
+    assert_eq!(metadata.trailers.get("x-echo").unwrap(), "trailer");
+
+    let (res, metadata) = client
+        .dynamic_with_metadata(request(
+            "ServerStreamingEcho",
+            serde_json::json!({ "message": "connect" }),
+        ))
+        .await
+        .unwrap();
+
+    match res {
+        DynamicResponse::Streaming(Ok(stream)) => {
+            let messages: Vec<_> = stream.into_iter().map(Result::unwrap).collect();
+            assert_eq!(messages.len(), 3);
+            assert_eq!(messages[2]["message"], "connect - seq 2");
+        }
+        _ => panic!("Expected a successful stream, got {res:?}"),
+    }
+
+    let values: Vec<_> = metadata.trailers.get_all("x-echo").iter().collect();
+    assert_eq!(values, ["a", "b"]);
+
+    let res = client
+        .dynamic(request(
+            "ClientStreamingEcho",
+            serde_json::json!([{ "message": "a" }, { "message": "b" }]),
+        ))
+        .await
+        .unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "ab"));
+}
+
+#[tokio::test]
+async fn test_connect_proto() {
+    assert_calls(setup_client(Protocol::Connect, false).await).await;
+}
+
+#[tokio::test]
+async fn test_connect_json() {
+    assert_calls(setup_client(Protocol::ConnectJson, false).await).await;
+}
+
+#[tokio::test]
+async fn test_connect_http2() {
+    assert_calls(setup_client(Protocol::Connect, true).await).await;
+    assert_calls(setup_client(Protocol::ConnectJson, true).await).await;
+}
+
+#[tokio::test]
+async fn test_connect_unary_error() {
+    for protocol in [Protocol::Connect, Protocol::ConnectJson] {
+        let mut client = setup_client(protocol, false).await;
+
+        let res = client
+            .dynamic(request(
+                "UnaryEcho",
+                serde_json::json!({ "message": "fail" }),
+            ))
+            .await
+            .unwrap();
+
+        match res {
+            DynamicResponse::Unary(Err(status)) => {
+                assert_eq!(status.code(), Code::NotFound);
+                assert_eq!(status.message(), "Nothing to echo: ✗");
+            }
+            _ => panic!("Expected an error, got {res:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_connect_stream_error() {
+    let mut client = setup_client(Protocol::ConnectJson, false).await;
+
+    let res = client
+        .dynamic(request(
+            "ServerStreamingEcho",
+            serde_json::json!({ "message": "fail" }),
+        ))
+        .await
+        .unwrap();
+
+    match res {
+        DynamicResponse::Streaming(Ok(stream)) => {
+            assert_eq!(stream.len(), 4);
+            assert!(stream[..3].iter().all(Result::is_ok));
+            assert!(
+                matches!(&stream[3], Err(status) if status.code() == Code::Aborted && status.message() == "Stream failed")
+            );
+        }
+        _ => panic!("Expected a stream, got {res:?}"),
+    }
+}
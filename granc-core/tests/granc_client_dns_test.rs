@@ -0,0 +1,119 @@
+use common::{AuthorityLog, assert_authorities, assert_echo, serve_with_authority_log};
+use granc_core::client::{GrancClient, online};
+use granc_core::connector::{self, ConnectOptions, LoadBalancing, Protocol};
+use std::net::SocketAddr;
+
+mod common;
+
+#[tokio::test]
+async fn test_dns_pick_first() {
+    let log = AuthorityLog::default();
+    let port = serve_with_authority_log(log.clone()).await.port();
+
+    assert_echo(
+        &format!("dns:///localhost:{port}"),
+        ConnectOptions::new(),
+        "pick first",
+    )
+    .await;
+    assert_authorities(&log, &format!("localhost:{port}"));
+}
+
+#[tokio::test]
+async fn test_dns_without_options() {
+    let log = AuthorityLog::default();
+    let port = serve_with_authority_log(log.clone()).await.port();
+
+    let mut client = GrancClient::connect(&format!("dns:localhost:{port}"))
+        .await
+        .unwrap();
+
+    let services = client.list_services().await.unwrap();
+    assert!(services.contains(&"echo.EchoService".to_string()));
+    assert_authorities(&log, &format!("localhost:{port}"));
+}
+
+#[tokio::test]
+async fn test_dns_round_robin() {
+    let log = AuthorityLog::default();
+    let port = serve_with_authority_log(log.clone()).await.port();
+
+    let options = ConnectOptions::new().load_balancing(LoadBalancing::RoundRobin);
+    assert_echo(&format!("dns:///127.0.0.1:{port}"), options, "round robin").await;
+    assert_authorities(&log, &format!("127.0.0.1:{port}"));
+
+    // The authority override applies to every address
+    let log = AuthorityLog::default();
+    let port = serve_with_authority_log(log.clone()).await.port();
+
+    let options = ConnectOptions::new()
+        .load_balancing(LoadBalancing::RoundRobin)
+        .authority("api.example.com");
+    assert_echo(&format!("dns:///127.0.0.1:{port}"), options, "round robin").await;
+    assert_authorities(&log, "api.example.com");
+}
+
+#[tokio::test]
+async fn test_dns_connect_to() {
+    let log = AuthorityLog::default();
+    let port = serve_with_authority_log(log.clone()).await.port();
+
+    // A single address is dialed without resolving the name, as done to reach each backend
+    let options = ConnectOptions::new().connect_to(format!("127.0.0.1:{port}"));
+    assert_echo("dns:///echo.test:8080", options, "routed").await;
+    assert_authorities(&log, "echo.test:8080");
+}
+
+#[tokio::test]
+async fn test_resolve() {
+    let expected: SocketAddr = "127.0.0.1:50051".parse().unwrap();
+
+    for target in [
+        "dns:///127.0.0.1:50051",
+        "dns:127.0.0.1:50051",
+        "http://127.0.0.1:50051",
+    ] {
+        assert_eq!(connector::resolve(target).await.unwrap(), [expected]);
+    }
+
+    let addresses = connector::resolve("dns:///localhost:50051").await.unwrap();
+    assert!(addresses.contains(&expected));
+}
+
+#[tokio::test]
+async fn test_invalid_dns_targets() {
+    assert!(matches!(
+        connector::resolve("dns:///localhost").await,
+        Err(online::ClientConnectError::InvalidAddress(_))
+    ));
+
+    assert!(matches!(
+        connector::resolve("dns://8.8.8.8/localhost:50051").await,
+        Err(online::ClientConnectError::UnsupportedTransport(..))
+    ));
+
+    assert!(matches!(
+        connector::resolve("unix:///tmp/granc.sock").await,
+        Err(online::ClientConnectError::UnsupportedTransport(..))
+    ));
+
+    assert!(matches!(
+        connector::resolve("dns:///echo.invalid:50051").await,
+        Err(online::ClientConnectError::ResolutionFailed(..))
+    ));
+
+    // gRPC-Web and Connect clients do not dial when opened, so they cannot pick an address
+    for load_balancing in [LoadBalancing::PickFirst, LoadBalancing::RoundRobin] {
+        let result = GrancClient::connect_with(
+            "dns:///localhost:50051",
+            ConnectOptions::new()
+                .protocol(Protocol::GrpcWeb)
+                .load_balancing(load_balancing),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(online::ClientConnectError::UnsupportedTransport(..))
+        ));
+    }
+}
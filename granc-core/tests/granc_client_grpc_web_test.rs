@@ -0,0 +1,143 @@
+use common::{echo_request, echo_routes, reflection_routes, spawn_server};
+use granc_core::client::{DynamicResponse, GrancClient, Online, online};
+use granc_core::connector::{ConnectOptions, Connection, Protocol};
+use granc_test_support::echo_service::FILE_DESCRIPTOR_SET;
+use tonic::Code;
+use tonic::service::Routes;
+use tonic_web::GrpcWebLayer;
+
+mod common;
+
+/// Serves the routes behind a `tonic-web` layer, returning the server URI.
+async fn serve(routes: Routes) -> String {
+    let addr = spawn_server(|incoming| {
+        tonic::transport::Server::builder()
+            .accept_http1(true)
+            .layer(GrpcWebLayer::new())
+            .add_routes(routes)
+            .serve_with_incoming(incoming)
+    })
+    .await;
+
+    format!("http://{addr}")
+}
+
+async fn setup_client(protocol: Protocol, http2: bool) -> GrancClient<Online<Connection>> {
+    let uri = serve(echo_routes()).await;
+
+    let options = ConnectOptions::new().protocol(protocol).http2(http2);
+    GrancClient::connect_with(&uri, options).await.unwrap()
+}
+
+async fn assert_calls(mut client: GrancClient<Online<Connection>>) {
+    let services = client.list_services().await.unwrap();
+    assert!(services.contains(&"echo.EchoService".to_string()));
+
+    let res = client
+        .dynamic(echo_request(
+            "UnaryEcho",
+            serde_json::json!({ "message": "web" }),
+        ))
+        .await
+        .unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "web"));
+
+    let res = client
+        .dynamic(echo_request(
+            "ServerStreamingEcho",
+            serde_json::json!({ "message": "web" }),
+        ))
+        .await
+        .unwrap();
+
+    match res {
+        DynamicResponse::Streaming(Ok(stream)) => {
+            let messages: Vec<_> = stream.into_iter().map(Result::unwrap).collect();
+            assert_eq!(messages.len(), 3);
+            assert_eq!(messages[2]["message"], "web - seq 2");
+        }
+        _ => panic!("Expected a successful stream, got {res:?}"),
+    }
+
+    // The whole request stream is sent before the response is read
+    let res = client
+        .dynamic(echo_request(
+            "ClientStreamingEcho",
+            serde_json::json!([{ "message": "a" }, { "message": "b" }]),
+        ))
+        .await
+        .unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "ab"));
+}
+
+#[tokio::test]
+async fn test_grpc_web_binary() {
+    assert_calls(setup_client(Protocol::GrpcWeb, false).await).await;
+}
+
+#[tokio::test]
+async fn test_grpc_web_text() {
+    assert_calls(setup_client(Protocol::GrpcWebText, false).await).await;
+}
+
+#[tokio::test]
+async fn test_grpc_web_http2() {
+    assert_calls(setup_client(Protocol::GrpcWeb, true).await).await;
+    assert_calls(setup_client(Protocol::GrpcWebText, true).await).await;
+}
+
+#[tokio::test]
+async fn test_grpc_web_response_metadata() {
+    let client = setup_client(Protocol::GrpcWebText, false).await;
+    let mut client = client
+        .with_file_descriptor(FILE_DESCRIPTOR_SET.to_vec())
+        .unwrap();
+
+    let mut req = echo_request("UnaryEcho", serde_json::json!({ "message": "web" }));
+    req.headers = vec![("x-echo".to_string(), "trailer-test".to_string())];
+
+    let (res, metadata) = client.dynamic_with_metadata(req).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Ok(_))));
+    assert_eq!(metadata.headers.get("x-echo").unwrap(), "trailer-test");
+    // Parsed from the trailers frame at the end of the body
+    assert_eq!(metadata.trailers.get("grpc-status").unwrap(), "0");
+}
+
+#[tokio::test]
+async fn test_grpc_web_error_status() {
+    // Only reflection is served, so calling the echo service fails on the server
+    let uri = serve(reflection_routes()).await;
+
+    for protocol in [Protocol::GrpcWeb, Protocol::GrpcWebText] {
+        let client = GrancClient::connect_with(&uri, ConnectOptions::new().protocol(protocol))
+            .await
+            .unwrap();
+        let mut client = client
+            .with_file_descriptor(FILE_DESCRIPTOR_SET.to_vec())
+            .unwrap();
+
+        let res = client
+            .dynamic(echo_request(
+                "UnaryEcho",
+                serde_json::json!({ "message": "web" }),
+            ))
+            .await
+            .unwrap();
+
+        assert!(
+            matches!(res, DynamicResponse::Unary(Err(status)) if status.code() == Code::Unimplemented)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_grpc_web_unix_socket_unsupported() {
+    let options = ConnectOptions::new().protocol(Protocol::GrpcWeb);
+    let result = GrancClient::connect_with("unix:///tmp/granc.sock", options).await;
+
+    assert!(matches!(
+        result,
+        Err(online::ClientConnectError::UnsupportedTransport(..))
+    ));
+}
@@ -0,0 +1,168 @@
+use common::echo_service_impl::EchoServiceImpl;
+use common::{echo_request, echo_routes};
+use granc_core::client::interceptor::{CallOutcome, Interceptor};
+use granc_core::client::{DynamicRequest, DynamicResponse, GrancClient};
+use granc_core::prost_reflect::MethodDescriptor;
+use granc_test_support::echo_service::{EchoServiceServer, FILE_DESCRIPTOR_SET};
+use std::sync::{Arc, Mutex};
+use tonic::{Code, Status};
+
+mod common;
+
+/// The hooks run by the interceptors, in order.
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// Logs every call, and adds its name to the metadata of the requests.
+#[derive(Debug)]
+struct Recorder {
+    name: &'static str,
+    log: Log,
+}
+
+impl Interceptor for Recorder {
+    fn before_call(
+        &self,
+        method: &MethodDescriptor,
+        request: &mut DynamicRequest,
+    ) -> Result<(), Status> {
+        self.log
+            .lock()
+            .unwrap()
+            .push(format!("{} before {}", self.name, method.full_name()));
+
+        request
+            .headers
+            .push(("x-echo".to_string(), self.name.to_string()));
+        Ok(())
+    }
+
+    fn after_call(
+        &self,
+        _: &MethodDescriptor,
+        request: &DynamicRequest,
+        outcome: &CallOutcome<'_>,
+    ) {
+        let status = match outcome.response {
+            DynamicResponse::Unary(Ok(_)) | DynamicResponse::Streaming(Ok(_)) => Code::Ok,
+            DynamicResponse::Unary(Err(status)) | DynamicResponse::Streaming(Err(status)) => {
+                status.code()
+            }
+        };
+
+        self.log.lock().unwrap().push(format!(
+            "{} after {:?} with {} headers, echoed {:?}",
+            self.name,
+            status,
+            request.headers.len(),
+            outcome
+                .metadata
+                .headers
+                .get_all("x-echo")
+                .iter()
+                .collect::<Vec<_>>()
+        ));
+    }
+}
+
+/// Rejects every call.
+#[derive(Debug)]
+struct Reject;
+
+impl Interceptor for Reject {
+    fn before_call(&self, _: &MethodDescriptor, _: &mut DynamicRequest) -> Result<(), Status> {
+        Err(Status::permission_denied("Rejected by interceptor"))
+    }
+}
+
+fn recorder(name: &'static str, log: &Log) -> Recorder {
+    Recorder {
+        name,
+        log: log.clone(),
+    }
+}
+
+#[tokio::test]
+async fn test_interceptors_run_around_calls() {
+    let log = Log::default();
+
+    // Interceptors registered in the Online state are kept after resolving the schema
+    let mut client = GrancClient::from(echo_routes())
+        .with_interceptor(recorder("outer", &log))
+        .with_interceptor(recorder("inner", &log));
+
+    let request = echo_request("UnaryEcho", serde_json::json!({ "message": "hello" }));
+    let (res, metadata) = client.dynamic_with_metadata(request).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "hello"));
+    assert_eq!(metadata.headers.get_all("x-echo").iter().count(), 2);
+
+    let pool = client
+        .fetch_descriptor_pool(&["echo.EchoService".to_string()])
+        .await
+        .unwrap();
+    let mut client = client.with_descriptor_pool(pool);
+
+    let request = echo_request(
+        "ServerStreamingEcho",
+        serde_json::json!({ "message": "hi" }),
+    );
+    client.dynamic(request).await.unwrap();
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "outer before echo.EchoService.UnaryEcho",
+            "inner before echo.EchoService.UnaryEcho",
+            r#"inner after Ok with 2 headers, echoed ["outer", "inner"]"#,
+            r#"outer after Ok with 2 headers, echoed ["outer", "inner"]"#,
+            "outer before echo.EchoService.ServerStreamingEcho",
+            "inner before echo.EchoService.ServerStreamingEcho",
+            "inner after Ok with 2 headers, echoed []",
+            "outer after Ok with 2 headers, echoed []",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_interceptor_aborts_call() {
+    let log = Log::default();
+
+    let client = GrancClient::from(EchoServiceServer::new(EchoServiceImpl))
+        .with_file_descriptor(FILE_DESCRIPTOR_SET.to_vec())
+        .unwrap();
+
+    // Interceptors can also be registered after the state transition
+    let mut client = client
+        .with_interceptor(recorder("outer", &log))
+        .with_interceptor(Reject)
+        .with_interceptor(recorder("never", &log));
+
+    let request = echo_request("UnaryEcho", serde_json::json!({ "message": "hello" }));
+    let (res, metadata) = client.dynamic_with_metadata(request).await.unwrap();
+
+    assert!(
+        matches!(res, DynamicResponse::Unary(Err(status)) if status.code() == Code::PermissionDenied)
+    );
+    assert!(metadata.headers.is_empty());
+
+    let request = echo_request(
+        "BidirectionalEcho",
+        serde_json::json!([{ "message": "hi" }]),
+    );
+    let res = client.dynamic(request).await.unwrap();
+
+    assert!(
+        matches!(res, DynamicResponse::Streaming(Err(status)) if status.code() == Code::PermissionDenied)
+    );
+
+    // Interceptors after the rejecting one are skipped, the ones before still see the outcome
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            "outer before echo.EchoService.UnaryEcho",
+            "outer after PermissionDenied with 1 headers, echoed []",
+            "outer before echo.EchoService.BidirectionalEcho",
+            "outer after PermissionDenied with 1 headers, echoed []",
+        ]
+    );
+}
@@ -1,24 +1,22 @@
-use echo_service_impl::EchoServiceImpl;
+use common::echo_routes;
 use granc_core::client::{DynamicRequest, DynamicResponse, GrancClient, Online, online};
 use granc_core::reflection::client::ReflectionResolveError;
-use granc_test_support::echo_service::{EchoServiceServer, FILE_DESCRIPTOR_SET};
-use tonic::Code;
+use granc_test_support::compiler;
+use prost::Message;
+use std::pin::Pin;
+use tokio_stream::{Stream, StreamExt};
 use tonic::service::Routes;
+use tonic::{Code, Request, Response, Status, Streaming};
+use tonic_reflection::pb::v1::{
+    FileDescriptorResponse, ServerReflectionRequest, ServerReflectionResponse,
+    server_reflection_response::MessageResponse,
+};
+use tonic_reflection::server::v1::{ServerReflection, ServerReflectionServer};
 
-mod echo_service_impl;
+mod common;
 
 async fn setup_client() -> GrancClient<Online<Routes>> {
-    // Enable Reflection
-    let reflection_service = tonic_reflection::server::Builder::configure()
-        .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
-        .build_v1()
-        .unwrap();
-
-    let echo_service = EchoServiceServer::new(EchoServiceImpl);
-
-    let service = Routes::new(reflection_service).add_service(echo_service);
-
-    GrancClient::from(service)
+    GrancClient::from(echo_routes())
 }
 
 #[tokio::test]
@@ -175,3 +173,132 @@ async fn test_reflection_schema_mismatch() {
         Ok(DynamicResponse::Unary(Err(status))) if status.code() == Code::Internal
     ));
 }
+
+#[tokio::test]
+async fn test_reflection_fetch_descriptor_pool() {
+    let mut client = setup_client().await;
+
+    let pool = client
+        .fetch_descriptor_pool(&[
+            "echo.EchoService".to_string(),
+            "echo.EchoRequest".to_string(),
+        ])
+        .await
+        .unwrap();
+
+    assert!(pool.get_service_by_name("echo.EchoService").is_some());
+    assert!(pool.get_message_by_name("echo.EchoResponse").is_some());
+
+    // The fetched pool can be reused for calls without further reflection lookups
+    let mut client = client.with_descriptor_pool(pool);
+
+    let req = DynamicRequest {
+        service: "echo.EchoService".to_string(),
+        method: "UnaryEcho".to_string(),
+        body: serde_json::json!({ "message": "pooled" }),
+        headers: vec![],
+    };
+
+    let res = client.dynamic(req).await.unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "pooled"));
+}
+
+#[tokio::test]
+async fn test_reflection_fetch_descriptor_pool_not_found() {
+    let mut client = setup_client().await;
+
+    let result = client
+        .fetch_descriptor_pool(&["echo.GhostService".to_string()])
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(online::GetDescriptorError::NotFound(name)) if name == "echo.GhostService"
+    ));
+}
+
+/// An encoded `FileDescriptorSet`, keeping every file as raw bytes.
+#[derive(Clone, prost::Message)]
+struct RawFileDescriptorSet {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    file: Vec<Vec<u8>>,
+}
+
+/// A reflection service answering every request with all its files, as encoded by `protoc`.
+///
+/// Unlike `tonic_reflection`, which re-encodes the files with `prost_types`, custom options are preserved.
+struct RawReflection(Vec<Vec<u8>>);
+
+#[tonic::async_trait]
+impl ServerReflection for RawReflection {
+    type ServerReflectionInfoStream =
+        Pin<Box<dyn Stream<Item = Result<ServerReflectionResponse, Status>> + Send>>;
+
+    async fn server_reflection_info(
+        &self,
+        request: Request<Streaming<ServerReflectionRequest>>,
+    ) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        let files = self.0.clone();
+
+        // Every request is answered as soon as it is received, like the reflection service does
+        let responses = request.into_inner().map(move |request| {
+            Ok(ServerReflectionResponse {
+                valid_host: String::new(),
+                original_request: Some(request?),
+                message_response: Some(MessageResponse::FileDescriptorResponse(
+                    FileDescriptorResponse {
+                        file_descriptor_proto: files.clone(),
+                    },
+                )),
+            })
+        });
+
+        Ok(Response::new(Box::pin(responses)))
+    }
+}
+
+#[tokio::test]
+async fn test_reflection_preserves_custom_options() {
+    let proto = r#"
+        syntax = "proto3";
+        package custom;
+
+        import "google/protobuf/descriptor.proto";
+
+        extend google.protobuf.MethodOptions {
+            string route = 50000;
+        }
+
+        message Empty {}
+
+        service Api {
+            rpc Get(Empty) returns (Empty) {
+                option (route) = "/v1/get";
+            }
+        }
+    "#;
+
+    let bytes = compiler::compile_protos_to_bytes(&[("custom.proto", proto)]);
+    let files = RawFileDescriptorSet::decode(bytes.as_slice()).unwrap().file;
+
+    let mut client = GrancClient::from(ServerReflectionServer::new(RawReflection(files)));
+
+    let service = client
+        .get_descriptor_by_symbol("custom.Api")
+        .await
+        .unwrap()
+        .service_descriptor()
+        .cloned()
+        .unwrap();
+
+    let extension = service
+        .parent_pool()
+        .get_extension_by_name("custom.route")
+        .unwrap();
+    let method = service.methods().next().unwrap();
+
+    assert_eq!(
+        method.options().get_extension(&extension).as_str(),
+        Some("/v1/get")
+    );
+}
@@ -1,12 +1,13 @@
-use echo_service_impl::EchoServiceImpl;
+use common::echo_service_impl::EchoServiceImpl;
 use granc_core::client::{
     DynamicRequest, DynamicResponse, GrancClient, OnlineWithoutReflection,
     online_without_reflection,
 };
+use granc_core::grpc::client::GrpcRequestError;
 use granc_test_support::echo_service::{EchoServiceServer, FILE_DESCRIPTOR_SET};
 use tonic::Code;
 
-mod echo_service_impl;
+mod common;
 
 fn setup_client() -> GrancClient<OnlineWithoutReflection<EchoServiceServer<EchoServiceImpl>>> {
     let service = EchoServiceServer::new(EchoServiceImpl);
@@ -36,6 +37,102 @@ async fn test_dynamic_unary_success() {
     ));
 }
 
+#[tokio::test]
+async fn test_dynamic_with_metadata() {
+    let mut client = setup_client();
+
+    let req = DynamicRequest {
+        service: "echo.EchoService".to_string(),
+        method: "UnaryEcho".to_string(),
+        body: serde_json::json!({ "message": "hello" }),
+        headers: vec![("x-echo".to_string(), "metadata".to_string())],
+    };
+
+    let (res, metadata) = client.dynamic_with_metadata(req).await.unwrap();
+
+    assert!(matches!(
+        res,
+        DynamicResponse::Unary(Ok(val)) if val["message"] == "hello"
+    ));
+    assert_eq!(metadata.headers.get("x-echo").unwrap(), "metadata");
+    assert_eq!(metadata.trailers.get("grpc-status").unwrap(), "0");
+}
+
+#[tokio::test]
+async fn test_dynamic_repeated_and_binary_metadata() {
+    let mut client = setup_client();
+
+    let req = DynamicRequest {
+        service: "echo.EchoService".to_string(),
+        method: "UnaryEcho".to_string(),
+        body: serde_json::json!({ "message": "hello" }),
+        headers: vec![
+            ("x-echo".to_string(), "first".to_string()),
+            ("x-echo".to_string(), "second".to_string()),
+            // "\x00\xffgranc", with and without padding
+            ("x-echo-bin".to_string(), "AP9ncmFuYw==".to_string()),
+            ("x-echo-bin".to_string(), "AP9ncmFuYw".to_string()),
+        ],
+    };
+
+    let (_, metadata) = client.dynamic_with_metadata(req).await.unwrap();
+
+    let values: Vec<_> = metadata.headers.get_all("x-echo").iter().collect();
+    assert_eq!(values, ["first", "second"]);
+
+    let binary: Vec<_> = metadata
+        .headers
+        .get_all_bin("x-echo-bin")
+        .iter()
+        .map(|v| v.to_bytes().unwrap())
+        .collect();
+    assert_eq!(binary, [b"\x00\xffgranc".as_slice(), b"\x00\xffgranc"]);
+}
+
+#[tokio::test]
+async fn test_dynamic_invalid_binary_metadata() {
+    let mut client = setup_client();
+
+    let req = DynamicRequest {
+        service: "echo.EchoService".to_string(),
+        method: "UnaryEcho".to_string(),
+        body: serde_json::json!({ "message": "hello" }),
+        headers: vec![("x-echo-bin".to_string(), "not base64!".to_string())],
+    };
+
+    let result = client.dynamic(req).await;
+
+    assert!(matches!(
+        result,
+        Err(
+            online_without_reflection::DynamicCallError::GrpcRequestError(
+                GrpcRequestError::InvalidBinaryMetadataValue { .. }
+            )
+        )
+    ));
+}
+
+#[tokio::test]
+async fn test_dynamic_streaming_with_metadata() {
+    let mut client = setup_client();
+
+    let req = DynamicRequest {
+        service: "echo.EchoService".to_string(),
+        method: "ServerStreamingEcho".to_string(),
+        body: serde_json::json!({ "message": "hello" }),
+        headers: vec![],
+    };
+
+    let (res, metadata) = client.dynamic_with_metadata(req).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Streaming(Ok(stream)) if stream.len() == 3));
+    assert_eq!(
+        metadata.headers.get("content-type").unwrap(),
+        "application/grpc"
+    );
+    assert_eq!(metadata.trailers.get("grpc-status").unwrap(), "0");
+}
+
 #[tokio::test]
 async fn test_dynamic_server_streaming_success() {
     let mut client = setup_client();
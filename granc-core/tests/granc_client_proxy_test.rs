@@ -0,0 +1,231 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use common::{assert_echo, serve};
+use granc_core::client::{GrancClient, online};
+use granc_core::connector::{ConnectOptions, Protocol, Proxy, ProxyError};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+mod common;
+
+/// What a proxy was asked to do: the head of each `CONNECT` request, or the target and
+/// credentials of each SOCKS5 request.
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// A tiny HTTP proxy answering `CONNECT` requests with `status`. Returns its address.
+async fn http_proxy(status: &'static str, log: Log) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let log = log.clone();
+
+            tokio::spawn(async move {
+                let mut head = Vec::new();
+                while !head.ends_with(b"\r\n\r\n") {
+                    head.push(client.read_u8().await.unwrap());
+                }
+                let head = String::from_utf8(head).unwrap();
+                let target = head.split_whitespace().nth(1).unwrap().to_string();
+                log.lock().unwrap().push(head);
+
+                let response = format!("HTTP/1.1 {status}\r\n\r\n");
+                client.write_all(response.as_bytes()).await.unwrap();
+
+                if status.starts_with("200") {
+                    let mut server = TcpStream::connect(target).await.unwrap();
+                    let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+                }
+            });
+        }
+    });
+
+    addr.to_string()
+}
+
+/// A tiny SOCKS5 proxy, accepting anonymous and username/password clients. Returns its address.
+async fn socks5_proxy(log: Log) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        loop {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let log = log.clone();
+
+            tokio::spawn(async move {
+                let mut greeting = [0; 2];
+                client.read_exact(&mut greeting).await.unwrap();
+                let mut methods = vec![0; greeting[1] as usize];
+                client.read_exact(&mut methods).await.unwrap();
+
+                let mut credentials = String::from("anonymous");
+                if methods.contains(&0x02) {
+                    client.write_all(&[0x05, 0x02]).await.unwrap();
+
+                    let version = client.read_u8().await.unwrap();
+                    assert_eq!(version, 0x01);
+                    let mut username = vec![0; client.read_u8().await.unwrap() as usize];
+                    client.read_exact(&mut username).await.unwrap();
+                    let mut password = vec![0; client.read_u8().await.unwrap() as usize];
+                    client.read_exact(&mut password).await.unwrap();
+
+                    credentials = format!(
+                        "{}:{}",
+                        String::from_utf8(username).unwrap(),
+                        String::from_utf8(password).unwrap()
+                    );
+                    client.write_all(&[0x01, 0x00]).await.unwrap();
+                } else {
+                    client.write_all(&[0x05, 0x00]).await.unwrap();
+                }
+
+                let mut request = [0; 4];
+                client.read_exact(&mut request).await.unwrap();
+                let host = match request[3] {
+                    0x01 => {
+                        let mut ip = [0; 4];
+                        client.read_exact(&mut ip).await.unwrap();
+                        std::net::Ipv4Addr::from(ip).to_string()
+                    }
+                    0x03 => {
+                        let mut name = vec![0; client.read_u8().await.unwrap() as usize];
+                        client.read_exact(&mut name).await.unwrap();
+                        String::from_utf8(name).unwrap()
+                    }
+                    atyp => panic!("Unexpected address type {atyp}"),
+                };
+                let port = client.read_u16().await.unwrap();
+
+                log.lock()
+                    .unwrap()
+                    .push(format!("{credentials}@{host}:{port}"));
+
+                let mut server = TcpStream::connect((host.as_str(), port)).await.unwrap();
+                client
+                    .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+                    .await
+                    .unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+            });
+        }
+    });
+
+    addr.to_string()
+}
+
+#[tokio::test]
+async fn test_http_proxy() {
+    let port = serve().await.port();
+    let log = Log::default();
+    let proxy = http_proxy("200 Connection established", log.clone()).await;
+
+    for protocol in [Protocol::Grpc, Protocol::GrpcWeb] {
+        let options = ConnectOptions::new()
+            .protocol(protocol)
+            .proxy(Proxy::parse(&format!("http://{proxy}")).unwrap());
+
+        assert_echo(&format!("http://127.0.0.1:{port}"), options, "proxied").await;
+    }
+
+    let log = log.lock().unwrap();
+    assert!(!log.is_empty());
+    assert!(
+        log.iter()
+            .all(|head| head.starts_with(&format!("CONNECT 127.0.0.1:{port} HTTP/1.1\r\n")))
+    );
+}
+
+#[tokio::test]
+async fn test_http_proxy_credentials() {
+    let port = serve().await.port();
+    let log = Log::default();
+    let proxy = http_proxy("200 Connection established", log.clone()).await;
+
+    // Credentials are percent-decoded
+    let options =
+        ConnectOptions::new().proxy(Proxy::parse(&format!("http://user:p%40ss@{proxy}")).unwrap());
+
+    assert_echo(&format!("http://127.0.0.1:{port}"), options, "proxied").await;
+
+    let expected = format!(
+        "Proxy-Authorization: Basic {}\r\n",
+        STANDARD.encode("user:p@ss")
+    );
+    assert!(log.lock().unwrap()[0].contains(&expected));
+}
+
+#[tokio::test]
+async fn test_http_proxy_refused() {
+    let port = serve().await.port();
+    let proxy = http_proxy("407 Proxy Authentication Required", Log::default()).await;
+
+    let options = ConnectOptions::new().proxy(Proxy::parse(&proxy).unwrap());
+    let result = GrancClient::connect_with(&format!("http://127.0.0.1:{port}"), options).await;
+
+    assert!(matches!(
+        result,
+        Err(online::ClientConnectError::ConnectionFailed(..))
+    ));
+}
+
+#[tokio::test]
+async fn test_socks5_proxy() {
+    let port = serve().await.port();
+    let log = Log::default();
+    let proxy = socks5_proxy(log.clone()).await;
+
+    let options = ConnectOptions::new().proxy(Proxy::parse(&format!("socks5://{proxy}")).unwrap());
+    assert_echo(&format!("http://127.0.0.1:{port}"), options, "proxied").await;
+
+    // With socks5h, the host name is resolved by the proxy
+    let options = ConnectOptions::new()
+        .protocol(Protocol::GrpcWeb)
+        .proxy(Proxy::parse(&format!("socks5h://user:secret@{proxy}")).unwrap());
+    assert_echo(&format!("http://localhost:{port}"), options, "proxied").await;
+
+    let log = log.lock().unwrap();
+    assert_eq!(log[0], format!("anonymous@127.0.0.1:{port}"));
+    assert_eq!(
+        log.last().unwrap(),
+        &format!("user:secret@localhost:{port}")
+    );
+}
+
+#[tokio::test]
+async fn test_proxy_unix_socket_unsupported() {
+    let options = ConnectOptions::new().proxy(Proxy::parse("http://localhost:3128").unwrap());
+    let result = GrancClient::connect_with("unix:///tmp/granc.sock", options).await;
+
+    assert!(matches!(
+        result,
+        Err(online::ClientConnectError::UnsupportedTransport(..))
+    ));
+}
+
+#[test]
+fn test_proxy_parse() {
+    assert_eq!(
+        Proxy::parse("proxy:3128").unwrap(),
+        Proxy::parse("http://proxy:3128").unwrap()
+    );
+    assert_eq!(
+        Proxy::parse("socks5h://proxy").unwrap(),
+        Proxy::parse("socks5h://proxy:1080").unwrap()
+    );
+    assert_ne!(
+        Proxy::parse("socks5://proxy:1080").unwrap(),
+        Proxy::parse("socks5h://proxy:1080").unwrap()
+    );
+
+    assert!(matches!(
+        Proxy::parse("ftp://proxy:21"),
+        Err(ProxyError::UnsupportedScheme(_))
+    ));
+    assert!(matches!(
+        Proxy::parse("http://"),
+        Err(ProxyError::InvalidUri(_))
+    ));
+}
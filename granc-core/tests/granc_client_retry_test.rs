@@ -0,0 +1,417 @@
+use common::echo_service_impl::EchoServiceImpl;
+use common::{echo_request, spawn_server};
+use granc_core::client::{
+    DynamicRequest, DynamicResponse, GrancClient, OnlineWithoutReflection, online,
+};
+use granc_core::connector::{ConnectOptions, Connection, LoadBalancing};
+use granc_core::grpc::retry::{RetryPolicy, ServiceConfig, ServiceConfigError};
+use granc_test_support::echo_service::{EchoServiceServer, FILE_DESCRIPTOR_SET};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::service::InterceptorLayer;
+use tonic::{Code, Status};
+
+mod common;
+
+/// The `grpc-previous-rpc-attempts` header of every call received by the server.
+type Attempts = Arc<Mutex<Vec<Option<String>>>>;
+
+/// Serves the echo service, failing the first `failures` calls with the status returned by
+/// `fail`. Returns the server URI.
+async fn serve(failures: usize, fail: fn() -> Status, attempts: Attempts) -> String {
+    let flaky = move |request: tonic::Request<()>| {
+        let mut attempts = attempts.lock().unwrap();
+        let previous = request
+            .metadata()
+            .get("grpc-previous-rpc-attempts")
+            .map(|value| value.to_str().unwrap().to_string());
+        attempts.push(previous);
+
+        if attempts.len() <= failures {
+            Err(fail())
+        } else {
+            Ok(request)
+        }
+    };
+
+    let addr = spawn_server(|incoming| {
+        tonic::transport::Server::builder()
+            .layer(InterceptorLayer::new(flaky))
+            .add_service(EchoServiceServer::new(EchoServiceImpl))
+            .serve_with_incoming(incoming)
+    })
+    .await;
+
+    format!("http://{addr}")
+}
+
+fn unavailable() -> Status {
+    Status::unavailable("Deploying")
+}
+
+fn fast_policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::new(max_attempts)
+        .initial_backoff(Duration::from_millis(1))
+        .max_backoff(Duration::from_millis(5))
+}
+
+async fn connect(
+    uri: &str,
+    options: ConnectOptions,
+) -> GrancClient<OnlineWithoutReflection<Connection>> {
+    GrancClient::connect_with(uri, options)
+        .await
+        .unwrap()
+        .with_file_descriptor(FILE_DESCRIPTOR_SET.to_vec())
+        .unwrap()
+}
+
+fn unary_request() -> DynamicRequest {
+    echo_request("UnaryEcho", serde_json::json!({ "message": "retried" }))
+}
+
+fn recorded(attempts: &Attempts) -> Vec<Option<String>> {
+    attempts.lock().unwrap().clone()
+}
+
+#[tokio::test]
+async fn test_retry_unary() {
+    let attempts = Attempts::default();
+    let uri = serve(2, unavailable, attempts.clone()).await;
+
+    let mut client = connect(&uri, ConnectOptions::new().retry_policy(fast_policy(3))).await;
+    let (res, metadata) = client.dynamic_with_metadata(unary_request()).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "retried"));
+    assert_eq!(
+        metadata.headers.get("grpc-previous-rpc-attempts").unwrap(),
+        "2"
+    );
+    assert_eq!(
+        recorded(&attempts),
+        [None, Some("1".to_string()), Some("2".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn test_retry_client_streaming() {
+    let attempts = Attempts::default();
+    let uri = serve(1, unavailable, attempts.clone()).await;
+
+    let mut client = connect(&uri, ConnectOptions::new().retry_policy(fast_policy(2))).await;
+
+    // The messages are buffered, so the retry sends them again
+    let body = serde_json::json!([{ "message": "re" }, { "message": "sent" }]);
+    let res = client
+        .dynamic(echo_request("ClientStreamingEcho", body))
+        .await
+        .unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "resent"));
+    assert_eq!(recorded(&attempts).len(), 2);
+}
+
+#[tokio::test]
+async fn test_retry_attempts_exhausted() {
+    let attempts = Attempts::default();
+    let uri = serve(5, unavailable, attempts.clone()).await;
+
+    let mut client = connect(&uri, ConnectOptions::new().retry_policy(fast_policy(3))).await;
+    let (res, metadata) = client.dynamic_with_metadata(unary_request()).await.unwrap();
+
+    assert!(
+        matches!(res, DynamicResponse::Unary(Err(status)) if status.code() == Code::Unavailable)
+    );
+    assert_eq!(
+        metadata.trailers.get("grpc-previous-rpc-attempts").unwrap(),
+        "2"
+    );
+    assert_eq!(recorded(&attempts).len(), 3);
+}
+
+#[tokio::test]
+async fn test_no_retry() {
+    // Without a policy
+    let attempts = Attempts::default();
+    let uri = serve(1, unavailable, attempts.clone()).await;
+
+    let mut client = connect(&uri, ConnectOptions::new()).await;
+    let (res, metadata) = client.dynamic_with_metadata(unary_request()).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Err(_))));
+    assert!(
+        metadata
+            .trailers
+            .get("grpc-previous-rpc-attempts")
+            .is_none()
+    );
+    assert_eq!(recorded(&attempts).len(), 1);
+
+    // With a code that is not retryable
+    let attempts = Attempts::default();
+    let uri = serve(1, || Status::internal("Bug"), attempts.clone()).await;
+
+    let mut client = connect(&uri, ConnectOptions::new().retry_policy(fast_policy(3))).await;
+    let res = client.dynamic(unary_request()).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Err(status)) if status.code() == Code::Internal));
+    assert_eq!(recorded(&attempts).len(), 1);
+
+    // With a code retryable by another policy
+    let attempts = Attempts::default();
+    let uri = serve(1, || Status::internal("Bug"), attempts.clone()).await;
+
+    let policy = fast_policy(3).retryable_status_codes([Code::Internal, Code::Unavailable]);
+    let mut client = connect(&uri, ConnectOptions::new().retry_policy(policy)).await;
+    let res = client.dynamic(unary_request()).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Ok(_))));
+    assert_eq!(recorded(&attempts).len(), 2);
+}
+
+#[tokio::test]
+async fn test_server_pushback() {
+    let stop = || {
+        let mut status = unavailable();
+        status
+            .metadata_mut()
+            .insert("grpc-retry-pushback-ms", "-1".parse().unwrap());
+        status
+    };
+
+    let attempts = Attempts::default();
+    let uri = serve(1, stop, attempts.clone()).await;
+
+    let mut client = connect(&uri, ConnectOptions::new().retry_policy(fast_policy(3))).await;
+    let res = client.dynamic(unary_request()).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Err(_))));
+    assert_eq!(recorded(&attempts).len(), 1);
+
+    let delay = || {
+        let mut status = unavailable();
+        status
+            .metadata_mut()
+            .insert("grpc-retry-pushback-ms", "50".parse().unwrap());
+        status
+    };
+
+    let attempts = Attempts::default();
+    let uri = serve(1, delay, attempts.clone()).await;
+
+    let mut client = connect(&uri, ConnectOptions::new().retry_policy(fast_policy(3))).await;
+    let start = std::time::Instant::now();
+    let res = client.dynamic(unary_request()).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Ok(_))));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn test_service_config() {
+    let attempts = Attempts::default();
+    let uri = serve(2, unavailable, attempts.clone()).await;
+
+    let config = ServiceConfig::from_json(
+        r#"{
+            "methodConfig": [{
+                "name": [{ "service": "echo.EchoService", "method": "UnaryEcho" }],
+                "retryPolicy": {
+                    "maxAttempts": 3,
+                    "initialBackoff": "0.001s",
+                    "maxBackoff": "0.005s",
+                    "backoffMultiplier": 2,
+                    "retryableStatusCodes": ["UNAVAILABLE"]
+                }
+            }]
+        }"#,
+    )
+    .unwrap();
+
+    // The service config takes precedence over the default policy
+    let options = ConnectOptions::new()
+        .retry_policy(fast_policy(2))
+        .service_config(config);
+    let mut client = connect(&uri, options).await;
+    let res = client.dynamic(unary_request()).await.unwrap();
+
+    assert!(matches!(res, DynamicResponse::Unary(Ok(_))));
+    assert_eq!(recorded(&attempts).len(), 3);
+}
+
+#[test]
+fn test_service_config_lookup() {
+    let policy = |attempts: u64, codes: &str| {
+        format!(
+            r#"{{
+                "maxAttempts": {attempts},
+                "initialBackoff": "0.1s",
+                "maxBackoff": "1s",
+                "backoffMultiplier": 1.5,
+                "retryableStatusCodes": {codes}
+            }}"#
+        )
+    };
+
+    let json = format!(
+        r#"{{
+            "methodConfig": [
+                {{ "name": [{{}}], "retryPolicy": {} }},
+                {{ "name": [{{ "service": "echo.EchoService" }}], "retryPolicy": {} }},
+                {{ "name": [{{ "service": "echo.EchoService", "method": "UnaryEcho" }}], "retryPolicy": {} }},
+                {{ "name": [{{ "service": "echo.EchoService", "method": "BidirectionalEcho" }}], "timeout": "1s" }}
+            ]
+        }}"#,
+        policy(2, r#"["UNAVAILABLE"]"#),
+        policy(3, r#"["DEADLINE_EXCEEDED", 14]"#),
+        policy(u64::from(u32::MAX) + 2, r#"["unavailable"]"#),
+    );
+    let config = ServiceConfig::from_json(&json).unwrap();
+
+    let pool = granc_core::prost_reflect::DescriptorPool::decode(FILE_DESCRIPTOR_SET).unwrap();
+    let service = pool.get_service_by_name("echo.EchoService").unwrap();
+    let method = |name: &str| service.methods().find(|m| m.name() == name).unwrap();
+
+    let expected = |attempts: u32, codes: Vec<Code>| {
+        RetryPolicy::new(attempts)
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1))
+            .backoff_multiplier(1.5)
+            .retryable_status_codes(codes)
+    };
+
+    // More than 5 attempts are treated as 5, even beyond the range of u32
+    assert_eq!(
+        config.retry_policy(&method("UnaryEcho")),
+        Some(&expected(5, vec![Code::Unavailable]))
+    );
+    assert_eq!(
+        config.retry_policy(&method("ServerStreamingEcho")),
+        Some(&expected(
+            3,
+            vec![Code::DeadlineExceeded, Code::Unavailable]
+        ))
+    );
+    // Method configs without a retry policy are ignored
+    assert_eq!(
+        config.retry_policy(&method("BidirectionalEcho")),
+        Some(&expected(
+            3,
+            vec![Code::DeadlineExceeded, Code::Unavailable]
+        ))
+    );
+
+    let empty = ServiceConfig::from_json("{}").unwrap();
+    assert_eq!(empty.retry_policy(&method("UnaryEcho")), None);
+}
+
+#[test]
+fn test_invalid_service_config() {
+    let invalid = [
+        "not json",
+        r#"{ "methodConfig": {} }"#,
+        r#"{ "methodConfig": [{ "retryPolicy": {} }] }"#,
+        r#"{ "methodConfig": [{ "name": [{}], "retryPolicy": { "maxAttempts": 1 } }] }"#,
+        r#"{ "methodConfig": [{ "name": [{}], "retryPolicy": {
+            "maxAttempts": 2, "initialBackoff": "1", "maxBackoff": "1s",
+            "backoffMultiplier": 2, "retryableStatusCodes": ["UNAVAILABLE"] } }] }"#,
+        r#"{ "methodConfig": [{ "name": [{}], "retryPolicy": {
+            "maxAttempts": 2, "initialBackoff": "1s", "maxBackoff": "1s",
+            "backoffMultiplier": 2, "retryableStatusCodes": ["SLOW"] } }] }"#,
+        r#"{ "methodConfig": [{ "name": [{ "method": "UnaryEcho" }], "retryPolicy": {
+            "maxAttempts": 2, "initialBackoff": "1s", "maxBackoff": "1s",
+            "backoffMultiplier": 2, "retryableStatusCodes": ["UNAVAILABLE"] } }] }"#,
+    ];
+
+    assert!(matches!(
+        ServiceConfig::from_json(invalid[0]),
+        Err(ServiceConfigError::InvalidJson(_))
+    ));
+    for json in &invalid[1..] {
+        assert!(
+            matches!(
+                ServiceConfig::from_json(json),
+                Err(ServiceConfigError::Invalid(_))
+            ),
+            "{json}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_retry_on_connect() {
+    // Reserve a port, and start the server on it only after a while
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let uri = format!("http://{addr}");
+    let policy = RetryPolicy::new(20)
+        .initial_backoff(Duration::from_millis(20))
+        .max_backoff(Duration::from_millis(50));
+
+    // Without retries, the connection fails right away
+    let options = ConnectOptions::new().retry_policy(policy.clone());
+    assert!(matches!(
+        GrancClient::connect_with(&uri, options).await,
+        Err(online::ClientConnectError::ConnectionFailed(..))
+    ));
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let listener = TcpListener::bind(addr).await.unwrap();
+        tonic::transport::Server::builder()
+            .add_service(EchoServiceServer::new(EchoServiceImpl))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+    });
+
+    let options = ConnectOptions::new()
+        .retry_policy(policy)
+        .retry_on_connect(true);
+    let mut client = connect(&uri, options).await;
+
+    let res = client.dynamic(unary_request()).await.unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(_))));
+}
+
+#[tokio::test]
+async fn test_round_robin_retry_on_connect() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    drop(listener);
+
+    let uri = format!("dns:///{addr}");
+    let policy = RetryPolicy::new(20)
+        .initial_backoff(Duration::from_millis(20))
+        .max_backoff(Duration::from_millis(50));
+
+    // Every address is dialed when connecting, so failures are not left to the first call
+    let options = ConnectOptions::new()
+        .load_balancing(LoadBalancing::RoundRobin)
+        .retry_policy(policy.clone());
+    assert!(matches!(
+        GrancClient::connect_with(&uri, options).await,
+        Err(online::ClientConnectError::ConnectionFailed(..))
+    ));
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let listener = TcpListener::bind(addr).await.unwrap();
+        tonic::transport::Server::builder()
+            .add_service(EchoServiceServer::new(EchoServiceImpl))
+            .serve_with_incoming(TcpListenerStream::new(listener))
+            .await
+    });
+
+    let options = ConnectOptions::new()
+        .load_balancing(LoadBalancing::RoundRobin)
+        .retry_policy(policy)
+        .retry_on_connect(true);
+    let mut client = connect(&uri, options).await;
+
+    let res = client.dynamic(unary_request()).await.unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(_))));
+}
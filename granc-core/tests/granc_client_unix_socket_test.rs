@@ -0,0 +1,84 @@
+#![cfg(unix)]
+
+use common::{echo_routes, unary_echo};
+use granc_core::client::{DynamicResponse, GrancClient, online};
+use granc_test_support::echo_service::FILE_DESCRIPTOR_SET;
+use tokio::net::UnixListener;
+use tokio_stream::wrappers::UnixListenerStream;
+
+mod common;
+
+fn serve(listener: UnixListener) {
+    tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_routes(echo_routes())
+            .serve_with_incoming(UnixListenerStream::new(listener)),
+    );
+}
+
+#[tokio::test]
+async fn test_unix_socket_reflection_and_call() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("granc.sock");
+    serve(UnixListener::bind(&path).unwrap());
+
+    let mut client = GrancClient::connect(&format!("unix://{}", path.display()))
+        .await
+        .unwrap();
+
+    let services = client.list_services().await.unwrap();
+    assert!(services.contains(&"echo.EchoService".to_string()));
+
+    let res = client.dynamic(unary_echo("socket")).await.unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "socket"));
+}
+
+#[tokio::test]
+async fn test_unix_socket_with_file_descriptor() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("granc.sock");
+    serve(UnixListener::bind(&path).unwrap());
+
+    let client = GrancClient::connect(&format!("unix:{}", path.display()))
+        .await
+        .unwrap();
+
+    let mut client = client
+        .with_file_descriptor(FILE_DESCRIPTOR_SET.to_vec())
+        .unwrap();
+
+    let res = client.dynamic(unary_echo("socket")).await.unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "socket"));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_unix_abstract_socket() {
+    use std::os::linux::net::SocketAddrExt;
+
+    let name = format!("granc-test-{}", std::process::id());
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+    let listener = std::os::unix::net::UnixListener::bind_addr(&addr).unwrap();
+    listener.set_nonblocking(true).unwrap();
+    serve(UnixListener::from_std(listener).unwrap());
+
+    let mut client = GrancClient::connect(&format!("unix-abstract:{name}"))
+        .await
+        .unwrap();
+
+    let res = client.dynamic(unary_echo("socket")).await.unwrap();
+    assert!(matches!(res, DynamicResponse::Unary(Ok(val)) if val["message"] == "socket"));
+}
+
+#[tokio::test]
+async fn test_unix_socket_connection_failed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("missing.sock");
+
+    let result = GrancClient::connect(&format!("unix://{}", path.display())).await;
+
+    assert!(matches!(
+        result,
+        Err(online::ClientConnectError::ConnectionFailed(..))
+    ));
+}
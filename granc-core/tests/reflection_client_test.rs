@@ -1,11 +1,22 @@
-use echo_service_impl::EchoServiceImpl;
+use common::echo_service_impl::EchoServiceImpl;
 use granc_core::reflection::client::{ReflectionClient, ReflectionResolveError};
+use granc_test_support::compiler;
 use granc_test_support::echo_service::{EchoServiceServer, FILE_DESCRIPTOR_SET};
+use prost::Message;
 use prost_reflect::DescriptorPool;
-use tonic::Code;
-use tonic_reflection::server::v1::ServerReflectionServer;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Code, Request, Response, Status, Streaming};
+use tonic_reflection::pb::v1::{
+    FileDescriptorResponse, ListServiceResponse, ServerReflectionRequest, ServerReflectionResponse,
+    ServiceResponse, server_reflection_request::MessageRequest,
+    server_reflection_response::MessageResponse,
+};
+use tonic_reflection::server::v1::{ServerReflection, ServerReflectionServer};
 
-mod echo_service_impl;
+mod common;
 
 fn setup_reflection_client()
 -> ReflectionClient<ServerReflectionServer<impl tonic_reflection::server::v1::ServerReflection>> {
@@ -136,3 +147,132 @@ async fn test_server_does_not_support_reflection() {
         Ok(_) => panic!("Expected error, but got successful registry"),
     }
 }
+
+/// A reflection service listing a single service, named after the `host` of the request.
+struct HostEchoingReflection;
+
+#[tonic::async_trait]
+impl ServerReflection for HostEchoingReflection {
+    type ServerReflectionInfoStream = tokio_stream::Once<Result<ServerReflectionResponse, Status>>;
+
+    async fn server_reflection_info(
+        &self,
+        request: Request<Streaming<ServerReflectionRequest>>,
+    ) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        let request = request.into_inner().message().await?.unwrap();
+
+        let response = ServerReflectionResponse {
+            valid_host: request.host.clone(),
+            message_response: Some(MessageResponse::ListServicesResponse(ListServiceResponse {
+                service: vec![ServiceResponse {
+                    name: format!("host: '{}'", request.host),
+                }],
+            })),
+            original_request: Some(request),
+        };
+
+        Ok(Response::new(tokio_stream::once(Ok(response))))
+    }
+}
+
+#[tokio::test]
+async fn test_reflection_client_sends_host() {
+    let server = ServerReflectionServer::new(HostEchoingReflection);
+
+    let mut client = ReflectionClient::new(server.clone());
+    let services = client.list_services().await.unwrap();
+    assert_eq!(services, ["host: ''"]);
+
+    let mut client = ReflectionClient::new(server).host("api.example.com");
+    let services = client.list_services().await.unwrap();
+    assert_eq!(services, ["host: 'api.example.com'"]);
+}
+
+/// A reflection service counting the request streams it receives, answering every request with
+/// the file it names, or `library.proto` for symbols.
+#[derive(Clone)]
+struct CountingReflection {
+    files: Arc<Vec<prost_types::FileDescriptorProto>>,
+    streams: Arc<AtomicUsize>,
+}
+
+#[tonic::async_trait]
+impl ServerReflection for CountingReflection {
+    type ServerReflectionInfoStream =
+        Pin<Box<dyn Stream<Item = Result<ServerReflectionResponse, Status>> + Send>>;
+
+    async fn server_reflection_info(
+        &self,
+        request: Request<Streaming<ServerReflectionRequest>>,
+    ) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        self.streams.fetch_add(1, Ordering::SeqCst);
+        let files = self.files.clone();
+
+        let responses = request.into_inner().map(move |request| {
+            let request = request?;
+            let name = match &request.message_request {
+                Some(MessageRequest::FileByFilename(name)) => name.as_str(),
+                _ => "library.proto",
+            };
+            let file = files
+                .iter()
+                .find(|file| file.name() == name)
+                .ok_or_else(|| Status::not_found(name))?;
+
+            Ok(ServerReflectionResponse {
+                valid_host: String::new(),
+                original_request: Some(request),
+                message_response: Some(MessageResponse::FileDescriptorResponse(
+                    FileDescriptorResponse {
+                        file_descriptor_proto: vec![file.encode_to_vec()],
+                    },
+                )),
+            })
+        });
+
+        Ok(Response::new(Box::pin(responses)))
+    }
+}
+
+#[tokio::test]
+async fn test_reflection_client_dependency_streams() {
+    let files = compiler::compile_protos(&[
+        (
+            "author.proto",
+            r#"syntax = "proto3"; package library; message Author { string name = 1; }"#,
+        ),
+        (
+            "book.proto",
+            r#"syntax = "proto3"; package library; import "author.proto"; message Book { Author author = 1; }"#,
+        ),
+        (
+            "library.proto",
+            r#"syntax = "proto3"; package library; import "book.proto"; service Library { rpc Get(Book) returns (Book); }"#,
+        ),
+    ])
+    .file;
+
+    let reflection = CountingReflection {
+        files: Arc::new(files),
+        streams: Arc::new(AtomicUsize::new(0)),
+    };
+
+    // Dependencies are requested over the stream of the symbol
+    let mut client = ReflectionClient::new(ServerReflectionServer::new(reflection.clone()));
+    let files = client
+        .encoded_file_descriptors_by_symbol("library.Library")
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 3);
+    assert_eq!(reflection.streams.swap(0, Ordering::SeqCst), 1);
+
+    // Half-duplex transports use a finite stream for each level of dependencies
+    let mut client =
+        ReflectionClient::new(ServerReflectionServer::new(reflection.clone())).half_duplex(true);
+    let files = client
+        .encoded_file_descriptors_by_symbol("library.Library")
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 3);
+    assert_eq!(reflection.streams.load(Ordering::SeqCst), 3);
+}
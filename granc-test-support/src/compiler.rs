@@ -8,6 +8,16 @@ use std::fs;
 /// # Arguments
 /// * `files` - A list of tuples (filename, content). E.g. `[("test.proto", "syntax=...")]`
 pub fn compile_protos(files: &[(&str, &str)]) -> FileDescriptorSet {
+    let bytes = compile_protos_to_bytes(files);
+
+    FileDescriptorSet::decode(bytes.as_slice()).expect("Failed to decode File descriptor set")
+}
+
+/// Compiles inline proto strings into an encoded FileDescriptorSet at runtime.
+///
+/// Unlike [`compile_protos`], custom options (extensions like `google.api.http`) are preserved,
+/// as `prost_types` drops the fields it does not know when decoding.
+pub fn compile_protos_to_bytes(files: &[(&str, &str)]) -> Vec<u8> {
     let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
     let descriptor_path = temp_dir.path().join("descriptor.bin");
     let proto_dir = temp_dir.path().join("protos");
@@ -18,6 +28,10 @@ pub fn compile_protos(files: &[(&str, &str)]) -> FileDescriptorSet {
         .iter()
         .map(|(name, content)| {
             let path = proto_dir.join(name);
+            // Files can be nested to match their import paths (e.g. `google/api/http.proto`)
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).expect("Failed to create proto subdir");
+            }
             fs::write(&path, content).expect("Failed to write proto file");
             path
         })
@@ -30,7 +44,5 @@ pub fn compile_protos(files: &[(&str, &str)]) -> FileDescriptorSet {
         .compile_protos(&paths, &[proto_dir])
         .expect("Failed to compile protos");
 
-    let bytes = fs::read(descriptor_path).expect("Failed to read descriptor set");
-
-    FileDescriptorSet::decode(bytes.as_slice()).expect("Failed to decode File descriptor set")
+    fs::read(descriptor_path).expect("Failed to read descriptor set")
 }
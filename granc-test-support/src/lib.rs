@@ -1,2 +1,3 @@
 pub mod compiler;
 pub mod echo_service;
+pub mod session;
@@ -0,0 +1,254 @@
+//! # Session Server
+//!
+//! A mock server replaying a session recorded with `granc call --record <FILE>`, so recorded
+//! sessions can be committed and used as test fixtures.
+//!
+//! Every call to a method is answered with the next call recorded for that method, in order: the
+//! recorded response headers, messages and final status are sent back. The request messages are
+//! decoded, to check that they match the schema, but are not compared with the recorded ones.
+//!
+//! Recorded trailers are only sent back along with an error status, as the trailers of successful
+//! calls are written by `tonic`.
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor};
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::codegen::{Arc, Body, BoxFuture, Context, Poll, Service, StdError, http, tokio_stream};
+use tonic::metadata::MetadataMap;
+use tonic::server::{Grpc, StreamingService};
+use tonic::{Code, Request, Response, Status, Streaming};
+
+/// The version of the session file format that can be replayed.
+const VERSION: u32 = 1;
+
+/// A session file, keeping only what is sent back to the client.
+#[derive(Deserialize)]
+struct Session {
+    version: u32,
+    calls: Vec<RecordedCall>,
+}
+
+#[derive(Clone, Deserialize)]
+struct RecordedCall {
+    service: String,
+    method: String,
+    headers: Vec<(String, String)>,
+    responses: Vec<serde_json::Value>,
+    trailers: Vec<(String, String)>,
+    status: RecordedStatus,
+}
+
+#[derive(Clone, Deserialize)]
+struct RecordedStatus {
+    code: String,
+    message: String,
+}
+
+/// A `tower` service answering gRPC calls with the calls of a recorded session.
+///
+/// It can be handed to `GrancClient::from` like any generated server.
+#[derive(Clone)]
+pub struct SessionServer {
+    pool: DescriptorPool,
+    /// The calls left to replay, by gRPC path (`/package.Service/Method`).
+    calls: Arc<Mutex<HashMap<String, VecDeque<RecordedCall>>>>,
+}
+
+impl SessionServer {
+    /// Loads a session file, with the encoded file descriptor set describing its services.
+    ///
+    /// # Panics
+    ///
+    /// If the file cannot be read, is not a valid session, or the descriptors are invalid.
+    pub fn load(path: impl AsRef<Path>, file_descriptor_set: &[u8]) -> Self {
+        let json = std::fs::read_to_string(path).expect("Failed to read session file");
+        let session: Session = serde_json::from_str(&json).expect("Invalid session file");
+
+        assert_eq!(session.version, VERSION, "Unsupported session file version");
+
+        let pool =
+            DescriptorPool::decode(file_descriptor_set).expect("Failed to decode descriptor pool");
+
+        let mut calls: HashMap<_, VecDeque<_>> = HashMap::new();
+        for call in session.calls {
+            let path = format!("/{}/{}", call.service, call.method);
+            calls.entry(path).or_default().push_back(call);
+        }
+
+        Self {
+            pool,
+            calls: Arc::new(Mutex::new(calls)),
+        }
+    }
+
+    fn method(&self, call: &RecordedCall) -> Option<MethodDescriptor> {
+        self.pool
+            .get_service_by_name(&call.service)?
+            .methods()
+            .find(|method| method.name() == call.method)
+    }
+}
+
+impl<B> Service<http::Request<B>> for SessionServer
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = std::convert::Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let next = self
+            .calls
+            .lock()
+            .unwrap()
+            .get_mut(req.uri().path())
+            .and_then(VecDeque::pop_front);
+
+        let Some(call) = next else {
+            let status =
+                Status::unimplemented(format!("No recorded call left for '{}'", req.uri().path()));
+            return Box::pin(async move { Ok(status.into_http()) });
+        };
+
+        let Some(method) = self.method(&call) else {
+            let status = Status::unimplemented(format!(
+                "'{}/{}' is not described by the file descriptor set",
+                call.service, call.method
+            ));
+            return Box::pin(async move { Ok(status.into_http()) });
+        };
+
+        let replay = Replay {
+            call,
+            output: method.output(),
+        };
+
+        Box::pin(async move {
+            let mut grpc = Grpc::new(DynamicCodec(method.input()));
+            Ok(grpc.streaming(replay, req).await)
+        })
+    }
+}
+
+/// Answers a single call with a recorded call.
+struct Replay {
+    call: RecordedCall,
+    /// Schema of the response messages.
+    output: MessageDescriptor,
+}
+
+impl StreamingService<DynamicMessage> for Replay {
+    type Response = DynamicMessage;
+    type ResponseStream = tokio_stream::Iter<std::vec::IntoIter<Result<DynamicMessage, Status>>>;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        let call = self.call.clone();
+        let output = self.output.clone();
+
+        Box::pin(async move {
+            // Every request message is read before answering, as the client may still be sending
+            let mut requests = request.into_inner();
+            while requests.message().await?.is_some() {}
+
+            let mut items = vec![];
+            for value in call.responses {
+                let message = DynamicMessage::deserialize(output.clone(), value)
+                    .map_err(|e| Status::internal(format!("Invalid recorded response: {e}")))?;
+                items.push(Ok(message));
+            }
+
+            let code = parse_code(&call.status.code);
+            if code != Code::Ok {
+                let status =
+                    Status::with_metadata(code, call.status.message, metadata(&call.trailers));
+                items.push(Err(status));
+            }
+
+            let mut response = Response::new(tokio_stream::iter(items));
+            *response.metadata_mut() = metadata(&call.headers);
+            Ok(response)
+        })
+    }
+}
+
+/// Builds a metadata map from recorded pairs, leaving out the entries set by the gRPC protocol.
+///
+/// Binary (`-bin`) values are recorded in base64, as sent on the wire, so they are kept as is.
+fn metadata(pairs: &[(String, String)]) -> MetadataMap {
+    let headers = pairs
+        .iter()
+        .filter(|(key, _)| key != "content-type" && !key.starts_with("grpc-"))
+        .filter_map(|(key, value)| {
+            let key = http::HeaderName::from_bytes(key.as_bytes()).ok()?;
+            let value = http::HeaderValue::from_str(value).ok()?;
+            Some((key, value))
+        })
+        .collect();
+
+    MetadataMap::from_headers(headers)
+}
+
+/// Parses the canonical name of a status code (e.g. `NOT_FOUND`), as written in session files.
+fn parse_code(name: &str) -> Code {
+    (0..=16)
+        .map(Code::from_i32)
+        .find(|code| format!("{code:?}").to_uppercase() == name.replace('_', ""))
+        .unwrap_or(Code::Unknown)
+}
+
+/// Decodes requests as dynamic messages of the given schema, and encodes dynamic responses.
+struct DynamicCodec(MessageDescriptor);
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder(self.0.clone())
+    }
+}
+
+struct DynamicEncoder;
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode_raw(dst);
+        Ok(())
+    }
+}
+
+struct DynamicDecoder(MessageDescriptor);
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        let mut message = DynamicMessage::new(self.0.clone());
+        message
+            .merge(src)
+            .map_err(|e| Status::invalid_argument(format!("Invalid request message: {e}")))?;
+
+        Ok(Some(message))
+    }
+}
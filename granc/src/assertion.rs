@@ -0,0 +1,264 @@
+//! # Assertions
+//!
+//! This module checks a [`DynamicResponse`] against expectations on its status code and its fields.
+//!
+//! It powers the `--expect-status` and `--expect` options of `granc call`, as well as the `expect`
+//! section of workflow steps.
+use crate::jsonpath::JsonPath;
+use granc_core::{client::DynamicResponse, tonic::Code};
+use std::str::FromStr;
+
+const CODES: [Code; 17] = [
+    Code::Ok,
+    Code::Cancelled,
+    Code::Unknown,
+    Code::InvalidArgument,
+    Code::DeadlineExceeded,
+    Code::NotFound,
+    Code::AlreadyExists,
+    Code::PermissionDenied,
+    Code::ResourceExhausted,
+    Code::FailedPrecondition,
+    Code::Aborted,
+    Code::OutOfRange,
+    Code::Unimplemented,
+    Code::Internal,
+    Code::Unavailable,
+    Code::DataLoss,
+    Code::Unauthenticated,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Eq,
+    Ne,
+}
+
+/// An expectation on a single field of the response, e.g. `$.book.title == "Dune"`.
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    path: JsonPath,
+    operator: Operator,
+    value: serde_json::Value,
+}
+
+impl Expectation {
+    /// Creates an expectation that the field at `path` equals `value`.
+    pub fn equals(path: JsonPath, value: serde_json::Value) -> Self {
+        Self {
+            path,
+            operator: Operator::Eq,
+            value,
+        }
+    }
+
+    /// Returns a description of the failure if the expectation is not met.
+    ///
+    /// A missing field never equals any value, and is always different from any value.
+    fn check(&self, value: &serde_json::Value) -> Option<String> {
+        let Expectation {
+            path,
+            operator,
+            value: expected,
+        } = self;
+
+        match (operator, path.select(value)) {
+            (Operator::Eq, Some(actual)) if actual == expected => None,
+            (Operator::Eq, Some(actual)) => {
+                Some(format!("{path}: expected {expected} but got {actual}"))
+            }
+            (Operator::Eq, None) => Some(format!("{path}: expected {expected} but it is missing")),
+            (Operator::Ne, Some(actual)) if actual == expected => {
+                Some(format!("{path}: expected anything but {expected}"))
+            }
+            (Operator::Ne, _) => None,
+        }
+    }
+}
+
+impl FromStr for Expectation {
+    type Err = String;
+
+    /// Parses `jsonpath == value` or `jsonpath != value`.
+    ///
+    /// The value is parsed as JSON, falling back to a plain string when it is not valid JSON
+    /// (so `$.title == Dune` is equivalent to `$.title == "Dune"`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Split at the first operator, so values can contain operators themselves
+        let (path, operator, value) = [("==", Operator::Eq), ("!=", Operator::Ne)]
+            .into_iter()
+            .filter_map(|(token, operator)| s.find(token).map(|pos| (pos, operator)))
+            .min_by_key(|(pos, _)| *pos)
+            .map(|(pos, operator)| (&s[..pos], operator, s[pos + 2..].trim()))
+            .ok_or_else(|| {
+                format!(
+                    "Invalid expectation '{s}'. Expected 'jsonpath == value' or 'jsonpath != value'"
+                )
+            })?;
+
+        let value = serde_json::from_str(value)
+            .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+
+        Ok(Self {
+            path: path.parse()?,
+            operator,
+            value,
+        })
+    }
+}
+
+/// Checks a response against an optional expected status and a list of field expectations.
+///
+/// Returns a human readable description of every expectation that was not met.
+pub fn check(
+    status: Option<Code>,
+    expectations: &[Expectation],
+    response: &DynamicResponse,
+) -> Vec<String> {
+    let mut failures = vec![];
+
+    let code = response_code(response);
+    if let Some(expected) = status
+        && code != expected
+    {
+        failures.push(format!(
+            "expected status {} but got {}",
+            code_name(expected),
+            code_name(code)
+        ));
+    }
+
+    if expectations.is_empty() {
+        return failures;
+    }
+
+    match response_value(response) {
+        Some(value) => failures.extend(expectations.iter().filter_map(|e| e.check(&value))),
+        None => failures.push(format!(
+            "field expectations cannot be checked on a failed response ({})",
+            code_name(code)
+        )),
+    }
+
+    failures
+}
+
+/// Returns the status code of a response.
+/// Streaming responses report the first error found in the stream, if any.
+pub fn response_code(response: &DynamicResponse) -> Code {
+    match response {
+        DynamicResponse::Unary(Ok(_)) => Code::Ok,
+        DynamicResponse::Unary(Err(status)) | DynamicResponse::Streaming(Err(status)) => {
+            status.code()
+        }
+        DynamicResponse::Streaming(Ok(items)) => items
+            .iter()
+            .find_map(|item| item.as_ref().err())
+            .map_or(Code::Ok, |status| status.code()),
+    }
+}
+
+/// Returns the JSON value of a successful response.
+/// Streaming responses are represented as an array of messages.
+pub fn response_value(response: &DynamicResponse) -> Option<serde_json::Value> {
+    match response {
+        DynamicResponse::Unary(Ok(value)) => Some(value.clone()),
+        DynamicResponse::Streaming(Ok(items)) => items
+            .iter()
+            .map(|item| item.as_ref().ok().cloned())
+            .collect::<Option<Vec<_>>>()
+            .map(serde_json::Value::Array),
+        _ => None,
+    }
+}
+
+/// Parses a gRPC status code from its canonical name (`NOT_FOUND`), its Rust name (`NotFound`)
+/// or its numeric value (`5`).
+pub fn parse_code(value: &str) -> Result<Code, String> {
+    let normalized = value.replace('_', "").to_lowercase();
+
+    CODES
+        .into_iter()
+        .find(|code| {
+            format!("{code:?}").to_lowercase() == normalized
+                || (*code as i32).to_string() == normalized
+        })
+        .ok_or_else(|| format!("Unknown gRPC status code '{value}'"))
+}
+
+/// Returns the canonical name of a status code (e.g. `NOT_FOUND`).
+pub fn code_name(code: Code) -> String {
+    format!("{code:?}")
+        .chars()
+        .enumerate()
+        .fold(String::new(), |mut acc, (i, c)| {
+            if i > 0 && c.is_uppercase() {
+                acc.push('_');
+            }
+            acc.push(c.to_ascii_uppercase());
+            acc
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::tonic::Status;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_code() {
+        assert_eq!(parse_code("OK").unwrap(), Code::Ok);
+        assert_eq!(parse_code("NOT_FOUND").unwrap(), Code::NotFound);
+        assert_eq!(parse_code("NotFound").unwrap(), Code::NotFound);
+        assert_eq!(parse_code("14").unwrap(), Code::Unavailable);
+        assert!(parse_code("NOPE").is_err());
+    }
+
+    #[test]
+    fn test_code_name() {
+        assert_eq!(code_name(Code::Ok), "OK");
+        assert_eq!(code_name(Code::NotFound), "NOT_FOUND");
+        assert_eq!(code_name(Code::FailedPrecondition), "FAILED_PRECONDITION");
+    }
+
+    #[test]
+    fn test_parse_expectation() {
+        let expectation: Expectation = "$.title == \"Dune\"".parse().unwrap();
+        assert_eq!(expectation.operator, Operator::Eq);
+        assert_eq!(expectation.value, json!("Dune"));
+
+        let expectation: Expectation = "$.count != 3".parse().unwrap();
+        assert_eq!(expectation.operator, Operator::Ne);
+        assert_eq!(expectation.value, json!(3));
+
+        // Values that are not valid JSON are treated as strings
+        let expectation: Expectation = "$.title == Dune".parse().unwrap();
+        assert_eq!(expectation.value, json!("Dune"));
+
+        assert!("$.title".parse::<Expectation>().is_err());
+        assert!("title == Dune".parse::<Expectation>().is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let expectations = [
+            "$.title == Dune".parse().unwrap(),
+            "$.pages != 0".parse().unwrap(),
+        ];
+
+        let ok = DynamicResponse::Unary(Ok(json!({ "title": "Dune", "pages": 412 })));
+        assert!(check(Some(Code::Ok), &expectations, &ok).is_empty());
+
+        let mismatch = DynamicResponse::Unary(Ok(json!({ "title": "Emma", "pages": 0 })));
+        assert_eq!(check(None, &expectations, &mismatch).len(), 2);
+
+        let error = DynamicResponse::Unary(Err(Status::not_found("missing")));
+        assert!(check(Some(Code::NotFound), &[], &error).is_empty());
+        assert_eq!(check(Some(Code::Ok), &expectations, &error).len(), 2);
+
+        let stream_error =
+            DynamicResponse::Streaming(Ok(vec![Ok(json!({})), Err(Status::unavailable("gone"))]));
+        assert_eq!(response_code(&stream_error), Code::Unavailable);
+    }
+}
@@ -2,78 +2,641 @@
 //!
 //! This module defines the command-line interface of `granc` using `clap`.
 //! It enforces strict invariants for arguments using subcommands and argument groups.
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
-use clap::{Args, Parser, Subcommand};
+use crate::{
+    assertion::{self, Expectation},
+    completion,
+    config::Profile,
+    jsonpath::JsonPath,
+    metadata,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{ArgValueCandidates, ArgValueCompleter};
+use granc_core::{
+    auth::{
+        Credentials,
+        oauth2::ClientCredentials,
+        token::{StaticToken, TokenCommand},
+    },
+    connector::{self, ConnectOptions, Proxy},
+    grpc::retry::{RetryPolicy, ServiceConfig},
+    tonic::{Code, codec::CompressionEncoding},
+};
+use serde::Deserialize;
 
 #[derive(Parser, Debug)]
 #[command(name = "granc", version, about = "Dynamic gRPC CLI")]
 pub struct Cli {
+    /// Named profile from the config file (~/.config/granc/config.toml or .granc.toml) to take defaults from
+    #[arg(long, short = 'p', global = true)]
+    pub profile: Option<String>,
+
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
+/// Flags controlling how to talk to the server, shared by every command.
+#[derive(Args, Debug, Default)]
+#[command(next_help_heading = "Connection")]
+pub struct ConnectionArgs {
+    /// Protocol used to talk to the server [default: grpc]
+    #[arg(long, global = true, value_enum)]
+    pub protocol: Option<Protocol>,
+
+    /// Send gRPC-Web and Connect requests over HTTP/2 instead of HTTP/1.1
+    #[arg(long, global = true)]
+    pub http2: bool,
+
+    /// Compress requests with the given encoding
+    #[arg(long, global = true, value_enum, value_name = "ENCODING")]
+    pub compress: Option<Compression>,
+
+    /// Accept responses compressed with the given encodings. Can be repeated or comma separated.
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "ENCODING"
+    )]
+    pub accept_compression: Vec<Compression>,
+
+    /// Maximum size of a response message (e.g. 16MB) [default: 4MB]
+    #[arg(long, global = true, value_parser = parse_size, value_name = "SIZE")]
+    pub max_decoding_message_size: Option<usize>,
+
+    /// Maximum size of a request message (e.g. 16MB) [default: unlimited]
+    #[arg(long, global = true, value_parser = parse_size, value_name = "SIZE")]
+    pub max_encoding_message_size: Option<usize>,
+
+    /// Tunnel connections through an HTTP or SOCKS5 proxy (e.g. http://proxy:3128, socks5h://localhost:1080)
+    /// [default: HTTPS_PROXY or ALL_PROXY, unless NO_PROXY matches the server]
+    #[arg(long, global = true, value_parser = Proxy::parse, value_name = "URI")]
+    pub proxy: Option<Proxy>,
+
+    /// Connect directly, ignoring the proxy environment variables
+    #[arg(long, global = true, conflicts_with = "proxy")]
+    pub no_proxy: bool,
+
+    /// Override the :authority (or Host header) of the requests, e.g. to select a virtual host behind an ingress
+    #[arg(long, global = true)]
+    pub authority: Option<String>,
+
+    /// Connect to this address instead of the one in the URI, keeping the URI's authority
+    #[arg(long, global = true, value_name = "HOST:PORT")]
+    pub connect_to: Option<String>,
+
+    /// Set the host field of the server reflection requests
+    #[arg(long, global = true, value_name = "HOST")]
+    pub reflection_host: Option<String>,
+
+    /// Retry unary and client streaming calls failing with a retryable code up to N times, with exponential backoff
+    #[arg(long, global = true, value_name = "N")]
+    pub retry: Option<u32>,
+
+    /// Status codes worth retrying, comma separated [default: UNAVAILABLE]
+    #[arg(
+        long,
+        global = true,
+        value_parser = assertion::parse_code,
+        value_delimiter = ',',
+        value_name = "CODES",
+        requires = "retry"
+    )]
+    pub retry_codes: Vec<Code>,
+
+    /// How calls are spread over the addresses of a dns:///host:port URI [default: pick-first]
+    #[arg(long, global = true, value_enum, value_name = "POLICY")]
+    pub load_balancing: Option<LoadBalancing>,
+
+    /// Also retry establishing the connection, e.g. while the server starts
+    #[arg(long, global = true, requires = "retry")]
+    pub retry_on_connect: bool,
+
+    /// Retry calls with the per-method 'retryPolicy' of a gRPC service config JSON file, taking precedence over --retry
+    #[arg(long, global = true, value_parser = parse_service_config, value_name = "FILE")]
+    pub service_config: Option<ServiceConfig>,
+
+    #[command(flatten)]
+    pub auth: AuthArgs,
+}
+
+/// Flags providing the credentials sent with every request, including reflection requests.
+#[derive(Args, Debug, Default)]
+#[command(next_help_heading = "Authentication")]
+pub struct AuthArgs {
+    /// Send a bearer token. Accepts '@path' to read it from a file and '${VAR}' to read it from the environment.
+    #[arg(long, global = true, value_parser = metadata::resolve_secret, conflicts_with_all = ["token_cmd", "oauth2_token_url"])]
+    pub token: Option<String>,
+
+    /// Send the bearer token printed by a shell command (e.g. 'gcloud auth print-access-token'), rerun every 5 minutes
+    #[arg(
+        long,
+        global = true,
+        value_name = "COMMAND",
+        conflicts_with = "oauth2_token_url"
+    )]
+    pub token_cmd: Option<String>,
+
+    /// Obtain a bearer token from this OAuth2 token endpoint with the client credentials grant
+    #[arg(
+        long,
+        global = true,
+        value_name = "URL",
+        requires_all = ["oauth2_client_id", "oauth2_client_secret"]
+    )]
+    pub oauth2_token_url: Option<String>,
+
+    /// Client ID used with --oauth2-token-url
+    #[arg(long, global = true, value_name = "ID", requires = "oauth2_token_url")]
+    pub oauth2_client_id: Option<String>,
+
+    /// Client secret used with --oauth2-token-url. Accepts '@path' and '${VAR}' like --token.
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECRET",
+        value_parser = metadata::resolve_secret,
+        requires = "oauth2_token_url"
+    )]
+    pub oauth2_client_secret: Option<String>,
+
+    /// Scope requested with --oauth2-token-url. Can be repeated.
+    #[arg(
+        long,
+        global = true,
+        value_name = "SCOPE",
+        requires = "oauth2_token_url"
+    )]
+    pub oauth2_scope: Vec<String>,
+}
+
+impl AuthArgs {
+    /// Builds the credentials given by the flags, falling back to the `token_cmd` of the profile.
+    /// The OAuth2 token endpoint is reached through `proxy`, if any.
+    pub fn credentials(
+        &self,
+        profile: &Profile,
+        proxy: Option<&Proxy>,
+    ) -> Option<Arc<dyn Credentials>> {
+        if let Some(token) = &self.token {
+            return Some(Arc::new(StaticToken::new(token)));
+        }
+
+        if let Some(url) = &self.oauth2_token_url {
+            let mut credentials = ClientCredentials::new(
+                url,
+                self.oauth2_client_id.clone().unwrap_or_default(),
+                self.oauth2_client_secret.clone().unwrap_or_default(),
+            );
+            for scope in &self.oauth2_scope {
+                credentials = credentials.scope(scope);
+            }
+            if let Some(proxy) = proxy {
+                credentials = credentials.proxy(proxy.clone());
+            }
+            return Some(Arc::new(credentials));
+        }
+
+        self.token_cmd
+            .as_ref()
+            .or(profile.token_cmd.as_ref())
+            .map(|command| Arc::new(TokenCommand::new(command)) as Arc<dyn Credentials>)
+    }
+}
+
+impl ConnectionArgs {
+    /// Builds the connection options, taking the defaults the flags do not set from the profile.
+    pub fn options(&self, profile: &Profile) -> ConnectOptions {
+        let protocol = self.protocol.or(profile.protocol).unwrap_or_default();
+
+        let mut options = ConnectOptions::new()
+            .protocol(protocol.into())
+            .http2(self.http2)
+            .proxy_from_env(!self.no_proxy);
+
+        if let Some(compression) = self.compress {
+            options = options.send_compressed(compression.into());
+        }
+        for compression in &self.accept_compression {
+            options = options.accept_compressed((*compression).into());
+        }
+        if let Some(limit) = self.max_decoding_message_size {
+            options = options.max_decoding_message_size(limit);
+        }
+        if let Some(limit) = self.max_encoding_message_size {
+            options = options.max_encoding_message_size(limit);
+        }
+        if let Some(proxy) = &self.proxy {
+            options = options.proxy(proxy.clone());
+        }
+        if let Some(authority) = &self.authority {
+            options = options.authority(authority);
+        }
+        if let Some(address) = &self.connect_to {
+            options = options.connect_to(address);
+        }
+        if let Some(host) = &self.reflection_host {
+            options = options.reflection_host(host);
+        }
+        if let Some(load_balancing) = self.load_balancing {
+            options = options.load_balancing(load_balancing.into());
+        }
+        if let Some(retries) = self.retry {
+            let mut policy = RetryPolicy::new(retries.saturating_add(1));
+            if !self.retry_codes.is_empty() {
+                policy = policy.retryable_status_codes(self.retry_codes.clone());
+            }
+            options = options
+                .retry_policy(policy)
+                .retry_on_connect(self.retry_on_connect);
+        }
+        if let Some(config) = &self.service_config {
+            options = options.service_config(config.clone());
+        }
+        if let Some(credentials) = self.auth.credentials(profile, self.proxy.as_ref()) {
+            options = options.credentials(credentials);
+        }
+
+        options
+    }
+}
+
+/// The wire protocol, selected with `--protocol` or in a profile.
+#[derive(ValueEnum, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Protocol {
+    /// Native gRPC over HTTP/2
+    #[default]
+    Grpc,
+    /// gRPC-Web with binary messages, e.g. through Envoy or tonic-web
+    GrpcWeb,
+    /// gRPC-Web with base64 encoded messages
+    GrpcWebText,
+    /// Connect with protobuf messages
+    Connect,
+    /// Connect with JSON messages
+    ConnectJson,
+}
+
+impl From<Protocol> for connector::Protocol {
+    fn from(protocol: Protocol) -> Self {
+        match protocol {
+            Protocol::Grpc => connector::Protocol::Grpc,
+            Protocol::GrpcWeb => connector::Protocol::GrpcWeb,
+            Protocol::GrpcWebText => connector::Protocol::GrpcWebText,
+            Protocol::Connect => connector::Protocol::Connect,
+            Protocol::ConnectJson => connector::Protocol::ConnectJson,
+        }
+    }
+}
+
+/// How calls are spread over the addresses of a `dns:///` URI, selected with `--load-balancing`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum LoadBalancing {
+    /// Send every call to the first address accepting a connection
+    PickFirst,
+    /// Connect to every address, and send each call to the next one in turn (native gRPC only)
+    RoundRobin,
+}
+
+impl From<LoadBalancing> for connector::LoadBalancing {
+    fn from(load_balancing: LoadBalancing) -> Self {
+        match load_balancing {
+            LoadBalancing::PickFirst => connector::LoadBalancing::PickFirst,
+            LoadBalancing::RoundRobin => connector::LoadBalancing::RoundRobin,
+        }
+    }
+}
+
+/// A compression encoding, selected with `--compress` and `--accept-compression`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The encoding name, as sent in the `grpc-encoding` header.
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+impl From<Compression> for CompressionEncoding {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Gzip => CompressionEncoding::Gzip,
+            Compression::Zstd => CompressionEncoding::Zstd,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Perform a gRPC call to a server.
     ///
-    /// Requires a server URI. Can optionally use a local file descriptor set.
+    /// Requires a server URI, either as a flag or from a profile. Can optionally use a local file descriptor set.
     Call {
         /// Endpoint (package.Service/Method)
-        #[arg(value_parser = parse_endpoint)]
+        #[arg(value_parser = parse_endpoint, add = ArgValueCandidates::new(completion::endpoints))]
         endpoint: (String, String),
 
-        /// The server URI to connect to (e.g. http://localhost:50051)
+        /// The server URI to connect to (e.g. http://localhost:50051, dns:///app:50051 or unix:///run/app.sock)
         #[arg(long, short = 'u')]
-        uri: String,
+        uri: Option<String>,
 
         /// "JSON body (Object for Unary, Array for Streaming)"
-        #[arg(long, short = 'b', value_parser = parse_body)]
+        #[arg(long, short = 'b', value_parser = parse_body, add = ArgValueCompleter::new(completion::body))]
         body: serde_json::Value,
 
+        /// Header 'key:value'. Values can be '@path' to read a file, reference environment variables
+        /// with '${VAR}', and are base64 for '-bin' keys. Can be used multiple times, also with the same key.
         #[arg(short = 'H', long = "header", value_parser = parse_header)]
         headers: Vec<(String, String)>,
 
         /// Optional path to a file descriptor set (.bin) to use instead of reflection
         #[arg(long, short = 'f')]
         file_descriptor_set: Option<PathBuf>,
+
+        #[command(flatten)]
+        checks: ResponseChecks,
+
+        /// Append the call (request, response, metadata and timing) to a session file
+        #[arg(long, value_name = "FILE")]
+        record: Option<PathBuf>,
+
+        /// Print the request metadata, and the response headers and trailers
+        #[arg(long, short = 'v')]
+        verbose: bool,
+
+        /// Send the call to every address the URI resolves to, printing the result of each backend.
+        /// Checks must pass on every backend.
+        #[arg(long, conflicts_with_all = ["record", "connect_to"])]
+        all_endpoints: bool,
+    },
+
+    /// Run a multi-step workflow described in a YAML file.
+    ///
+    /// Steps share a single connection and schema, and can capture values from
+    /// previous responses to use in later requests.
+    Run {
+        /// Path to the workflow file (.yaml)
+        workflow: PathBuf,
+
+        /// The server URI to connect to. Overrides the `uri` defined in the workflow file.
+        #[arg(long, short = 'u')]
+        uri: Option<String>,
+
+        /// Optional path to a file descriptor set (.bin) to use instead of reflection
+        #[arg(long, short = 'f')]
+        file_descriptor_set: Option<PathBuf>,
+    },
+
+    /// Replay the calls of a recorded session and compare the responses.
+    ///
+    /// Exits with a non-zero status if any response differs from the recording.
+    Replay {
+        /// Path to the session file recorded with `call --record`
+        session: PathBuf,
+
+        /// The server URI to connect to. Overrides the URI of every recorded call.
+        #[arg(long, short = 'u')]
+        uri: Option<String>,
+
+        /// Optional path to a file descriptor set (.bin) to use instead of reflection
+        #[arg(long, short = 'f')]
+        file_descriptor_set: Option<PathBuf>,
+
+        /// JSONPath of a field to exclude from the comparison (e.g. '$.responses[*].createdAt')
+        #[arg(long = "ignore", value_name = "JSONPATH")]
+        ignores: Vec<JsonPath>,
     },
 
     /// List available services.
     ///
-    /// Requires EITHER a server URI (Reflection) OR a file descriptor set (Offline).
+    /// Requires EITHER a server URI (Reflection) OR a file descriptor set (Offline), as flags or from a profile.
     List {
         #[command(flatten)]
         source: SourceSelection,
+
+        /// Also list the methods of each service, with their request and response types
+        #[arg(long, conflicts_with = "tree")]
+        methods: bool,
+
+        /// List the services grouped by package, along with their methods
+        #[arg(long)]
+        tree: bool,
+    },
+
+    /// Search the services, methods, messages, fields, enums and enum values of the schema by name or comment.
+    ///
+    /// Requires EITHER a server URI (Reflection) OR a file descriptor set (Offline), as flags or from a profile.
+    Search {
+        #[command(flatten)]
+        source: SourceSelection,
+
+        /// The text to search for (e.g. 'isbn', '*Request', '^Get.*Book$')
+        pattern: String,
+
+        /// How the pattern is matched
+        #[arg(long, value_enum, default_value_t = SearchMode::Substring)]
+        mode: SearchMode,
+
+        /// Only search this package and its subpackages, along with their dependencies. Can be used multiple times.
+        #[arg(long = "package", value_name = "PACKAGE")]
+        packages: Vec<String>,
     },
 
     /// Describe a service, message or enum.
     ///
-    /// Requires EITHER a server URI (Reflection) OR a file descriptor set (Offline).
+    /// Requires EITHER a server URI (Reflection) OR a file descriptor set (Offline), as flags or from a profile.
     Describe {
         #[command(flatten)]
         source: SourceSelection,
 
         /// Fully qualified name (e.g. my.package.Service)
+        #[arg(add = ArgValueCandidates::new(completion::symbols))]
         symbol: String,
     },
 
-    /// Generate Markdown documentation for a service.
+    /// Generate Markdown documentation or an HTML site for a service, or a whole schema.
     Doc {
         #[command(flatten)]
         source: SourceSelection,
 
         /// Fully qualified service name (e.g. my.package.MyService)
-        symbol: String,
+        #[arg(
+            required_unless_present = "all",
+            conflicts_with = "all",
+            add = ArgValueCandidates::new(completion::services)
+        )]
+        symbol: Option<String>,
+
+        /// Document every service, message and enum of the schema instead of a single service
+        #[arg(long)]
+        all: bool,
 
-        /// Output directory for the generated markdown files
+        /// Only document this package and its subpackages, along with their dependencies. Can be used multiple times.
+        #[arg(long = "package", value_name = "PACKAGE", conflicts_with = "symbol")]
+        packages: Vec<String>,
+
+        /// Output directory for the generated files
         #[arg(long, short = 'o')]
         output: PathBuf,
+
+        /// Format of the generated documentation
+        #[arg(long, value_enum, default_value_t = DocFormat::Markdown)]
+        format: DocFormat,
+
+        /// Directory with the templates to generate the documentation with, instead of a built-in format
+        #[arg(long, value_name = "DIR", conflicts_with = "format")]
+        template: Option<PathBuf>,
+
+        /// Embed a Mermaid diagram of each service and its dependencies in the Markdown documentation (the default format)
+        #[arg(long, conflicts_with_all = ["template", "format"])]
+        graph: bool,
+    },
+
+    /// Export messages as JSON Schema, or the HTTP routes of services as OpenAPI.
+    ///
+    /// Requires EITHER a server URI (Reflection) OR a file descriptor set (Offline), as flags or from a profile.
+    Export {
+        #[command(flatten)]
+        source: SourceSelection,
+
+        /// Fully qualified name of a message, enum or service (e.g. my.package.MyMessage). Exports the whole schema if omitted.
+        #[arg(add = ArgValueCandidates::new(completion::symbols))]
+        symbol: Option<String>,
+
+        /// Only export this package and its subpackages, along with their dependencies. Can be used multiple times.
+        #[arg(long = "package", value_name = "PACKAGE", conflicts_with = "symbol")]
+        packages: Vec<String>,
+
+        /// Format of the exported document
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        /// File to write the document to, instead of the standard output
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
     },
+
+    /// Draw a diagram of a service, message or enum and its dependencies.
+    ///
+    /// Requires EITHER a server URI (Reflection) OR a file descriptor set (Offline), as flags or from a profile.
+    Graph {
+        #[command(flatten)]
+        source: SourceSelection,
+
+        /// Fully qualified name (e.g. my.package.Service)
+        #[arg(add = ArgValueCandidates::new(completion::symbols))]
+        symbol: String,
+
+        /// Format of the diagram
+        #[arg(long, value_enum, default_value_t = GraphFormat::Mermaid)]
+        format: GraphFormat,
+
+        /// File to write the diagram to, instead of the standard output
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
+
+    /// Print the script registering the shell completions of granc.
+    ///
+    /// Besides commands and flags, the endpoints, symbols and JSON fields of '--body' are completed from the
+    /// schema, resolved from the flags already typed or the profile. Load it from the shell profile, e.g.
+    /// 'source <(granc completions bash)' in ~/.bashrc.
+    Completions {
+        /// The shell to complete in
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+/// A shell supported by `completions`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Elvish,
+    Fish,
+    Powershell,
+    Zsh,
 }
 
+/// The format of the documentation generated by `doc`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum DocFormat {
+    /// One Markdown file per package, and an index
+    Markdown,
+    /// A static site with a package tree, search and cross-linked definitions
+    Html,
+}
+
+/// The format of the document generated by `export`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// JSON Schema definitions of the messages and enums, following the proto3 JSON mapping
+    Jsonschema,
+    /// OpenAPI 3.1 paths of the methods annotated with `google.api.http`
+    Openapi,
+}
+
+/// The format of the diagram drawn by `graph`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    /// A Mermaid flowchart, rendered by GitHub, GitLab and most Markdown tools
+    Mermaid,
+    /// A Graphviz digraph, in the DOT language
+    Dot,
+}
+
+/// How the pattern of `search` is matched.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum SearchMode {
+    /// The name, full name or a comment line contains the pattern, ignoring case
+    Substring,
+    /// The name, full name or a comment line matches the pattern, where '*' is any text and '?' any character, ignoring case
+    Glob,
+    /// The name, full name or a comment line matches the regular expression
+    Regex,
+}
+
+/// Checks performed on the response of a call, turning `granc call` into a contract test.
+///
+/// When any check fails, the process exits with a non-zero code.
 #[derive(Args, Debug)]
-#[group(required = true, multiple = false)] // Enforces: Either URI OR FileDescriptorSet, never both.
+pub struct ResponseChecks {
+    /// Expected gRPC status code of the response (e.g. OK, NOT_FOUND)
+    #[arg(long, value_parser = assertion::parse_code)]
+    pub expect_status: Option<Code>,
+
+    /// Expectation on a response field: 'jsonpath == value' or 'jsonpath != value'. Can be used multiple times.
+    #[arg(long = "expect")]
+    pub expectations: Vec<Expectation>,
+
+    /// Compare the response against a JSON snapshot file, which must exist unless --update-snapshots is given
+    #[arg(long)]
+    pub snapshot: Option<PathBuf>,
+
+    /// JSONPath of a field to ignore when comparing against the snapshot (e.g. $.createdAt). Can be used multiple times.
+    #[arg(long = "snapshot-ignore", requires = "snapshot")]
+    pub snapshot_ignores: Vec<JsonPath>,
+
+    /// Create or rewrite the snapshot with the current response instead of comparing against it
+    #[arg(long, requires = "snapshot")]
+    pub update_snapshots: bool,
+}
+
+#[derive(Args, Debug)]
+#[group(multiple = false)] // Enforces: Either URI OR FileDescriptorSet, never both.
 pub struct SourceSelection {
     /// The server URI to use for reflection-based introspection
     #[arg(long, short = 'u')]
@@ -94,21 +657,19 @@ pub enum Source {
 }
 
 impl SourceSelection {
-    pub fn value(self) -> Source {
-        if let Some(uri) = self.uri {
-            Source::Uri(uri)
-        } else if let Some(path) = self.file_descriptor_set {
-            Source::File(path)
-        } else {
-            // This is unreachable because `clap` verifies the group requirements before we ever get here.
-            unreachable!(
-                "Clap ensures exactly one argument (uri or file) is present via #[group(required = true)]"
-            )
-        }
+    /// Returns the selected source, falling back to the profile when no flag is given.
+    ///
+    /// A profile with a descriptor set is used offline, even if it also defines a URI.
+    pub fn value(self, profile: &Profile) -> Option<Source> {
+        self.uri
+            .map(Source::Uri)
+            .or_else(|| self.file_descriptor_set.map(Source::File))
+            .or_else(|| profile.file_descriptor_set.clone().map(Source::File))
+            .or_else(|| profile.uri.clone().map(Source::Uri))
     }
 }
 
-fn parse_endpoint(value: &str) -> Result<(String, String), String> {
+pub fn parse_endpoint(value: &str) -> Result<(String, String), String> {
     let (service, method) = value.split_once('/').ok_or_else(|| {
         format!("Invalid endpoint format: '{value}'. Expected 'package.Service/Method'",)
     })?;
@@ -121,9 +682,50 @@ fn parse_endpoint(value: &str) -> Result<(String, String), String> {
 }
 
 fn parse_header(s: &str) -> Result<(String, String), String> {
-    s.split_once(':')
-        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
-        .ok_or_else(|| "Format must be 'key:value'".to_string())
+    let (key, value) = s
+        .split_once(':')
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .ok_or_else(|| "Format must be 'key:value'".to_string())?;
+
+    Ok((key.to_string(), metadata::resolve_value(key, value)?))
+}
+
+/// Reads and parses a service config JSON file.
+fn parse_service_config(path: &str) -> Result<ServiceConfig, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read service config '{path}': {e}"))?;
+
+    ServiceConfig::from_json(&json).map_err(|e| e.to_string())
+}
+
+/// Parses a size in bytes, with an optional unit: `B`, `KB`, `MB` or `GB` (powers of 1024).
+fn parse_size(value: &str) -> Result<usize, String> {
+    let value = value.trim();
+    let (number, unit) = value.split_at(
+        value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(value.len()),
+    );
+
+    let number: usize = number
+        .parse()
+        .map_err(|_| format!("Invalid size '{value}', expected a number of bytes like 16MB"))?;
+
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "Invalid size unit '{unit}', expected B, KB, MB or GB"
+            ));
+        }
+    };
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("Size '{value}' is too large"))
 }
 
 fn parse_body(value: &str) -> Result<serde_json::Value, String> {
@@ -161,7 +763,7 @@ mod tests {
                     endpoint,
                     ("helloworld.Greeter".to_string(), "SayHello".to_string())
                 );
-                assert_eq!(uri, "http://localhost:50051");
+                assert_eq!(uri.as_deref(), Some("http://localhost:50051"));
                 assert_eq!(body, serde_json::json!({"name": "Ferris"}));
                 assert!(file_descriptor_set.is_none());
             }
@@ -225,7 +827,7 @@ mod tests {
                 body,
                 ..
             } => {
-                assert_eq!(uri, "http://localhost:50051");
+                assert_eq!(uri.as_deref(), Some("http://localhost:50051"));
                 assert_eq!(file_descriptor_set.unwrap().to_str().unwrap(), "desc.bin");
                 assert_eq!(body, serde_json::json!({}));
                 assert_eq!(headers[0], ("auth".to_string(), "bearer".to_string()));
@@ -240,7 +842,7 @@ mod tests {
         let cli = Cli::try_parse_from(&args).expect("Parsing failed");
 
         match cli.command {
-            Commands::List { source } => {
+            Commands::List { source, .. } => {
                 assert_eq!(source.uri.unwrap(), "http://localhost:50051");
                 assert!(source.file_descriptor_set.is_none());
             }
@@ -254,7 +856,7 @@ mod tests {
         let cli = Cli::try_parse_from(&args).expect("Parsing failed");
 
         match cli.command {
-            Commands::List { source } => {
+            Commands::List { source, .. } => {
                 assert_eq!(
                     source.file_descriptor_set.unwrap().to_str().unwrap(),
                     "desc.bin"
@@ -265,6 +867,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_list_command_methods() {
+        let args = vec!["granc", "list", "-f", "desc.bin", "--methods"];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::List { methods, tree, .. } => {
+                assert!(methods);
+                assert!(!tree);
+            }
+            _ => panic!("Expected List command"),
+        }
+
+        let args = vec!["granc", "list", "-f", "desc.bin", "--methods", "--tree"];
+        assert!(Cli::try_parse_from(&args).is_err());
+    }
+
+    #[test]
+    fn test_search_command() {
+        let args = vec![
+            "granc",
+            "search",
+            "*Request",
+            "--mode",
+            "glob",
+            "--package",
+            "library",
+            "--uri",
+            "http://localhost:50051",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Search {
+                pattern,
+                mode,
+                packages,
+                source,
+            } => {
+                assert_eq!(pattern, "*Request");
+                assert_eq!(mode, SearchMode::Glob);
+                assert_eq!(packages, ["library"]);
+                assert_eq!(source.uri.unwrap(), "http://localhost:50051");
+            }
+            _ => panic!("Expected Search command"),
+        }
+
+        let args = vec!["granc", "search", "isbn", "-f", "desc.bin"];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Search { mode, .. } => assert_eq!(mode, SearchMode::Substring),
+            _ => panic!("Expected Search command"),
+        }
+    }
+
+    #[test]
+    fn test_completions_command() {
+        let args = vec!["granc", "completions", "zsh"];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Completions { shell } => assert_eq!(shell, Shell::Zsh),
+            _ => panic!("Expected Completions command"),
+        }
+
+        let args = vec!["granc", "completions", "cmd"];
+        assert!(Cli::try_parse_from(&args).is_err());
+    }
+
     #[test]
     fn test_describe_command() {
         let args = vec![
@@ -303,10 +975,13 @@ mod tests {
                 symbol,
                 source,
                 output,
+                format,
+                ..
             } => {
-                assert_eq!(symbol, "my.package.Service");
+                assert_eq!(symbol.unwrap(), "my.package.Service");
                 assert_eq!(source.uri.unwrap(), "http://localhost:50051");
                 assert_eq!(output.to_str().unwrap(), "./docs");
+                assert_eq!(format, DocFormat::Markdown);
             }
             _ => panic!("Expected Doc command"),
         }
@@ -322,6 +997,8 @@ mod tests {
             "descriptors.bin",
             "-o",
             "./docs",
+            "--format",
+            "html",
         ];
         let cli = Cli::try_parse_from(&args).expect("Parsing failed");
 
@@ -330,8 +1007,11 @@ mod tests {
                 symbol,
                 source,
                 output,
+                format,
+                ..
             } => {
-                assert_eq!(symbol, "my.package.Service");
+                assert_eq!(format, DocFormat::Html);
+                assert_eq!(symbol.unwrap(), "my.package.Service");
                 assert_eq!(
                     source.file_descriptor_set.unwrap().to_str().unwrap(),
                     "descriptors.bin"
@@ -342,39 +1022,769 @@ mod tests {
         }
     }
 
-    // --- Failure Cases ---
-
     #[test]
-    fn test_fail_invalid_json_body() {
-        let args = vec!["granc", "call", "s/m", "-u", "x", "--body", "{invalid_json"];
-        let err = Cli::try_parse_from(&args).unwrap_err();
-        // Should verify that the error comes from the body parser
-        assert!(err.to_string().contains("Invalid JSON"));
+    fn test_doc_command_all() {
+        let args = vec![
+            "granc",
+            "doc",
+            "--all",
+            "--package",
+            "library",
+            "--package",
+            "billing.v1",
+            "-f",
+            "descriptors.bin",
+            "-o",
+            "./docs",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Doc {
+                symbol,
+                all,
+                packages,
+                ..
+            } => {
+                assert!(symbol.is_none());
+                assert!(all);
+                assert_eq!(packages, vec!["library", "billing.v1"]);
+            }
+            _ => panic!("Expected Doc command"),
+        }
+
+        // Either a service or --all, and --package only filters --all
+        for args in [
+            vec!["granc", "doc", "-f", "x.bin", "-o", "docs"],
+            vec![
+                "granc",
+                "doc",
+                "my.Service",
+                "--all",
+                "-f",
+                "x.bin",
+                "-o",
+                "docs",
+            ],
+            vec![
+                "granc",
+                "doc",
+                "my.Service",
+                "--package",
+                "my",
+                "-f",
+                "x.bin",
+                "-o",
+                "docs",
+            ],
+        ] {
+            assert!(Cli::try_parse_from(&args).is_err());
+        }
     }
 
     #[test]
-    fn test_fail_invalid_endpoint_format() {
+    fn test_doc_command_template() {
         let args = vec![
             "granc",
-            "call",
-            "OnlyServiceNoMethod", // Missing '/'
-            "-u",
-            "x",
-            "-b",
-            "{}",
+            "doc",
+            "--all",
+            "-f",
+            "descriptors.bin",
+            "-o",
+            "./docs",
+            "--template",
+            "./templates",
         ];
-        let err = Cli::try_parse_from(&args).unwrap_err();
-        assert!(err.to_string().contains("Invalid endpoint format"));
-    }
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
 
-    #[test]
-    fn test_fail_list_requires_source() {
+        match cli.command {
+            Commands::Doc { template, .. } => {
+                assert_eq!(template, Some(PathBuf::from("./templates")));
+            }
+            _ => panic!("Expected Doc command"),
+        }
+
+        // Templates replace the built-in formats
+        let args = vec![
+            "granc",
+            "doc",
+            "--all",
+            "-f",
+            "descriptors.bin",
+            "-o",
+            "./docs",
+            "--template",
+            "./templates",
+            "--format",
+            "html",
+        ];
+        assert!(Cli::try_parse_from(&args).is_err());
+    }
+
+    #[test]
+    fn test_doc_command_graph() {
+        let args = vec![
+            "granc",
+            "doc",
+            "library.LibraryService",
+            "-f",
+            "descriptors.bin",
+            "-o",
+            "./docs",
+            "--graph",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Doc { graph, .. } => assert!(graph),
+            _ => panic!("Expected Doc command"),
+        }
+
+        // Templates get the diagrams of the services in their model instead, and the HTML site
+        // has none
+        for conflicting in [["--template", "./templates"], ["--format", "html"]] {
+            let mut args = args.clone();
+            args.extend(conflicting);
+            assert!(Cli::try_parse_from(&args).is_err());
+        }
+    }
+
+    #[test]
+    fn test_export_command() {
+        let args = vec![
+            "granc",
+            "export",
+            "library.Book",
+            "--format",
+            "jsonschema",
+            "-f",
+            "descriptors.bin",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Export {
+                symbol,
+                format,
+                output,
+                ..
+            } => {
+                assert_eq!(symbol.as_deref(), Some("library.Book"));
+                assert_eq!(format, ExportFormat::Jsonschema);
+                assert!(output.is_none());
+            }
+            _ => panic!("Expected Export command"),
+        }
+
+        let args = vec![
+            "granc",
+            "export",
+            "--package",
+            "library",
+            "--format",
+            "openapi",
+            "-u",
+            "http://localhost:50051",
+            "-o",
+            "openapi.json",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Export {
+                symbol,
+                packages,
+                format,
+                output,
+                ..
+            } => {
+                assert!(symbol.is_none());
+                assert_eq!(packages, vec!["library"]);
+                assert_eq!(format, ExportFormat::Openapi);
+                assert_eq!(output, Some(PathBuf::from("openapi.json")));
+            }
+            _ => panic!("Expected Export command"),
+        }
+
+        // The format is required, and --package only filters a whole schema export
+        for args in [
+            vec!["granc", "export", "-f", "x.bin"],
+            vec![
+                "granc",
+                "export",
+                "my.Message",
+                "--package",
+                "my",
+                "--format",
+                "jsonschema",
+                "-f",
+                "x.bin",
+            ],
+        ] {
+            assert!(Cli::try_parse_from(&args).is_err());
+        }
+    }
+
+    #[test]
+    fn test_graph_command() {
+        let args = vec!["granc", "graph", "library.LibraryService", "-f", "x.bin"];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Graph {
+                symbol,
+                format,
+                output,
+                ..
+            } => {
+                assert_eq!(symbol, "library.LibraryService");
+                assert_eq!(format, GraphFormat::Mermaid);
+                assert!(output.is_none());
+            }
+            _ => panic!("Expected Graph command"),
+        }
+
+        let args = vec![
+            "granc",
+            "graph",
+            "library.Book",
+            "--format",
+            "dot",
+            "-u",
+            "http://localhost:50051",
+            "-o",
+            "book.dot",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Graph { format, output, .. } => {
+                assert_eq!(format, GraphFormat::Dot);
+                assert_eq!(output, Some(PathBuf::from("book.dot")));
+            }
+            _ => panic!("Expected Graph command"),
+        }
+    }
+
+    #[test]
+    fn test_call_command_with_checks() {
+        let args = vec![
+            "granc",
+            "call",
+            "svc/mthd",
+            "-u",
+            "http://localhost:50051",
+            "-b",
+            "{}",
+            "--expect-status",
+            "NOT_FOUND",
+            "--expect",
+            "$.title == Dune",
+            "--expect",
+            "$.pages != 0",
+            "--snapshot",
+            "snapshot.json",
+            "--snapshot-ignore",
+            "$.createdAt",
+            "--update-snapshots",
+        ];
+
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Call { checks, .. } => {
+                assert_eq!(checks.expect_status, Some(Code::NotFound));
+                assert_eq!(checks.expectations.len(), 2);
+                assert_eq!(checks.snapshot.unwrap().to_str().unwrap(), "snapshot.json");
+                assert_eq!(checks.snapshot_ignores.len(), 1);
+                assert!(checks.update_snapshots);
+            }
+            _ => panic!("Expected Call command"),
+        }
+    }
+
+    #[test]
+    fn test_run_command() {
+        let args = vec![
+            "granc",
+            "run",
+            "workflow.yaml",
+            "--uri",
+            "http://localhost:50051",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Run {
+                workflow,
+                uri,
+                file_descriptor_set,
+            } => {
+                assert_eq!(workflow.to_str().unwrap(), "workflow.yaml");
+                assert_eq!(uri.unwrap(), "http://localhost:50051");
+                assert!(file_descriptor_set.is_none());
+            }
+            _ => panic!("Expected Run command"),
+        }
+    }
+
+    #[test]
+    fn test_run_command_uri_from_workflow() {
+        let args = vec!["granc", "run", "workflow.yaml", "-f", "desc.bin"];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Run {
+                uri,
+                file_descriptor_set,
+                ..
+            } => {
+                assert!(uri.is_none());
+                assert_eq!(file_descriptor_set.unwrap().to_str().unwrap(), "desc.bin");
+            }
+            _ => panic!("Expected Run command"),
+        }
+    }
+
+    #[test]
+    fn test_call_command_with_record() {
+        let args = vec![
+            "granc",
+            "call",
+            "s.S/M",
+            "-u",
+            "http://localhost:50051",
+            "-b",
+            "{}",
+            "--record",
+            "session.json",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Call { record, .. } => {
+                assert_eq!(record.unwrap().to_str().unwrap(), "session.json");
+            }
+            _ => panic!("Expected Call command"),
+        }
+    }
+
+    #[test]
+    fn test_replay_command() {
+        let args = vec![
+            "granc",
+            "replay",
+            "session.json",
+            "--uri",
+            "http://localhost:50052",
+            "--ignore",
+            "$.responses[*].createdAt",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Replay {
+                session,
+                uri,
+                file_descriptor_set,
+                ignores,
+            } => {
+                assert_eq!(session.to_str().unwrap(), "session.json");
+                assert_eq!(uri.unwrap(), "http://localhost:50052");
+                assert!(file_descriptor_set.is_none());
+                assert_eq!(ignores.len(), 1);
+            }
+            _ => panic!("Expected Replay command"),
+        }
+    }
+
+    #[test]
+    fn test_call_command_repeated_headers() {
+        let args = vec![
+            "granc",
+            "call",
+            "s.S/M",
+            "-u",
+            "http://localhost:50051",
+            "-b",
+            "{}",
+            "-H",
+            "x-tag:a",
+            "-H",
+            "x-tag:b",
+            "-H",
+            "x-trace-bin:AP9n",
+            "-v",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::Call {
+                headers, verbose, ..
+            } => {
+                assert_eq!(
+                    headers,
+                    [
+                        ("x-tag".to_string(), "a".to_string()),
+                        ("x-tag".to_string(), "b".to_string()),
+                        ("x-trace-bin".to_string(), "AP9n".to_string()),
+                    ]
+                );
+                assert!(verbose);
+            }
+            _ => panic!("Expected Call command"),
+        }
+    }
+
+    #[test]
+    fn test_profile_provides_uri() {
+        let args = vec!["granc", "call", "s.S/M", "-b", "{}", "--profile", "staging"];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        assert_eq!(cli.profile.as_deref(), Some("staging"));
+        match cli.command {
+            Commands::Call { uri, .. } => assert!(uri.is_none()),
+            _ => panic!("Expected Call command"),
+        }
+
+        let args = vec!["granc", "-p", "staging", "list"];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        match cli.command {
+            Commands::List { source, .. } => {
+                let profile = Profile {
+                    uri: Some("http://staging:50051".to_string()),
+                    ..Default::default()
+                };
+                assert!(
+                    matches!(source.value(&profile), Some(Source::Uri(uri)) if uri == "http://staging:50051")
+                );
+            }
+            _ => panic!("Expected List command"),
+        }
+    }
+
+    #[test]
+    fn test_connection_flags() {
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "http://localhost",
+            "--compress",
+            "gzip",
+            "--accept-compression",
+            "gzip,zstd",
+            "--max-decoding-message-size",
+            "16MB",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        assert_eq!(cli.connection.compress, Some(Compression::Gzip));
+        assert_eq!(
+            cli.connection.accept_compression,
+            vec![Compression::Gzip, Compression::Zstd]
+        );
+        assert_eq!(cli.connection.max_decoding_message_size, Some(16 << 20));
+        assert_eq!(cli.connection.max_encoding_message_size, None);
+    }
+
+    #[test]
+    fn test_proxy_flags() {
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "x",
+            "--proxy",
+            "socks5h://localhost:1080",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        assert_eq!(
+            cli.connection.proxy,
+            Some(Proxy::parse("socks5h://localhost:1080").unwrap())
+        );
+        assert!(!cli.connection.no_proxy);
+
+        let args = vec!["granc", "list", "-u", "x", "--proxy", "ftp://proxy"];
+        let err = Cli::try_parse_from(&args).unwrap_err();
+        assert!(err.to_string().contains("Unsupported proxy scheme"));
+
+        let args = vec!["granc", "list", "-u", "x", "--proxy", "proxy", "--no-proxy"];
+        assert!(Cli::try_parse_from(&args).is_err());
+    }
+
+    #[test]
+    fn test_routing_flags() {
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "http://ingress:80",
+            "--authority",
+            "api.example.com",
+            "--connect-to",
+            "127.0.0.1:8080",
+            "--reflection-host",
+            "api",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        assert_eq!(cli.connection.authority.as_deref(), Some("api.example.com"));
+        assert_eq!(cli.connection.connect_to.as_deref(), Some("127.0.0.1:8080"));
+        assert_eq!(cli.connection.reflection_host.as_deref(), Some("api"));
+    }
+
+    #[test]
+    fn test_auth_flags() {
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "http://localhost:50051",
+            "--oauth2-token-url",
+            "http://auth:8080/token",
+            "--oauth2-client-id",
+            "granc",
+            "--oauth2-client-secret",
+            "s3cret",
+            "--oauth2-scope",
+            "read",
+            "--oauth2-scope",
+            "write",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+        let auth = &cli.connection.auth;
+
+        assert_eq!(auth.oauth2_client_id.as_deref(), Some("granc"));
+        assert_eq!(auth.oauth2_client_secret.as_deref(), Some("s3cret"));
+        assert_eq!(auth.oauth2_scope, vec!["read", "write"]);
+        assert!(auth.credentials(&Profile::default(), None).is_some());
+
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "x",
+            "--token-cmd",
+            "gcloud auth print-access-token",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+        assert_eq!(
+            cli.connection.auth.token_cmd.as_deref(),
+            Some("gcloud auth print-access-token")
+        );
+    }
+
+    #[test]
+    fn test_retry_flags() {
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "http://localhost:50051",
+            "--retry",
+            "3",
+            "--retry-codes",
+            "UNAVAILABLE,resource_exhausted",
+            "--retry-on-connect",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        assert_eq!(cli.connection.retry, Some(3));
+        assert_eq!(
+            cli.connection.retry_codes,
+            vec![Code::Unavailable, Code::ResourceExhausted]
+        );
+        assert!(cli.connection.retry_on_connect);
+
+        let args = vec!["granc", "list", "-u", "x", "--retry-on-connect"];
+        assert!(Cli::try_parse_from(&args).is_err());
+
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "x",
+            "--retry",
+            "1",
+            "--retry-codes",
+            "SLOW",
+        ];
+        assert!(Cli::try_parse_from(&args).is_err());
+    }
+
+    #[test]
+    fn test_load_balancing_flags() {
+        let args = vec![
+            "granc",
+            "call",
+            "echo.EchoService/UnaryEcho",
+            "-u",
+            "dns:///echo:50051",
+            "-b",
+            "{}",
+            "--load-balancing",
+            "round-robin",
+            "--all-endpoints",
+        ];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+
+        assert_eq!(
+            cli.connection.load_balancing,
+            Some(LoadBalancing::RoundRobin)
+        );
+        match cli.command {
+            Commands::Call { all_endpoints, .. } => assert!(all_endpoints),
+            _ => panic!("Expected Call command"),
+        }
+
+        // Every backend is reached with its own address override
+        for conflicting in [["--record", "session.json"], ["--connect-to", "echo:8080"]] {
+            let mut args = args.clone();
+            args.extend(conflicting);
+            assert!(Cli::try_parse_from(&args).is_err());
+        }
+    }
+
+    #[test]
+    fn test_service_config_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("service_config.json");
+        std::fs::write(
+            &path,
+            r#"{ "methodConfig": [{ "name": [{}], "retryPolicy": {
+                "maxAttempts": 3, "initialBackoff": "0.1s", "maxBackoff": "1s",
+                "backoffMultiplier": 2, "retryableStatusCodes": ["UNAVAILABLE"] } }] }"#,
+        )
+        .unwrap();
+
+        let path = path.to_str().unwrap();
+        let args = vec!["granc", "list", "-u", "x", "--service-config", path];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+        assert!(cli.connection.service_config.is_some());
+
+        std::fs::write(
+            path,
+            r#"{ "methodConfig": [{ "name": [{}], "retryPolicy": {} }] }"#,
+        )
+        .unwrap();
+        assert!(Cli::try_parse_from(&args).is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("4KB"), Ok(4 << 10));
+        assert_eq!(parse_size("16mib"), Ok(16 << 20));
+        assert_eq!(parse_size("2 G"), Ok(2 << 30));
+
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("4TB").is_err());
+        assert!(parse_size(&format!("{}GB", usize::MAX)).is_err());
+    }
+
+    #[test]
+    fn test_list_source_optional_with_profile() {
         let args = vec!["granc", "list"];
+        let cli = Cli::try_parse_from(&args).expect("Parsing failed");
+        // The source can come from a profile, so it is only required once the profile is resolved
+        match cli.command {
+            Commands::List { source, .. } => assert!(source.value(&Profile::default()).is_none()),
+            _ => panic!("Expected List command"),
+        }
+    }
+
+    // --- Failure Cases ---
+
+    #[test]
+    fn test_fail_conflicting_auth_flags() {
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "x",
+            "--token",
+            "a",
+            "--token-cmd",
+            "b",
+        ];
+        assert!(Cli::try_parse_from(&args).is_err());
+
+        // The client credentials are required
+        let args = vec![
+            "granc",
+            "list",
+            "-u",
+            "x",
+            "--oauth2-token-url",
+            "http://auth/token",
+        ];
+        assert!(Cli::try_parse_from(&args).is_err());
+
+        let args = vec!["granc", "list", "-u", "x", "--oauth2-scope", "read"];
+        assert!(Cli::try_parse_from(&args).is_err());
+    }
+
+    #[test]
+    fn test_fail_header_from_missing_file() {
+        let args = vec![
+            "granc",
+            "call",
+            "s.S/M",
+            "-u",
+            "x",
+            "-b",
+            "{}",
+            "-H",
+            "authorization:@/surely/missing/token",
+        ];
+        let err = Cli::try_parse_from(&args).unwrap_err();
+        assert!(err.to_string().contains("Failed to read header value"));
+    }
+
+    #[test]
+    fn test_fail_invalid_json_body() {
+        let args = vec!["granc", "call", "s/m", "-u", "x", "--body", "{invalid_json"];
+        let err = Cli::try_parse_from(&args).unwrap_err();
+        // Should verify that the error comes from the body parser
+        assert!(err.to_string().contains("Invalid JSON"));
+    }
+
+    #[test]
+    fn test_fail_invalid_expectations() {
+        let base = ["granc", "call", "s/m", "-u", "x", "-b", "{}"];
+
+        let args = [base.as_slice(), &["--expect-status", "NOPE"]].concat();
+        let err = Cli::try_parse_from(&args).unwrap_err();
+        assert!(err.to_string().contains("Unknown gRPC status code"));
+
+        let args = [base.as_slice(), &["--expect", "$.title"]].concat();
+        let err = Cli::try_parse_from(&args).unwrap_err();
+        assert!(err.to_string().contains("Invalid expectation"));
+
+        // Snapshot options require a snapshot file
+        let args = [base.as_slice(), &["--update-snapshots"]].concat();
         let err = Cli::try_parse_from(&args).unwrap_err();
-        // Clap error for missing required arguments in group
         assert!(err.kind() == clap::error::ErrorKind::MissingRequiredArgument);
     }
 
+    #[test]
+    fn test_fail_invalid_endpoint_format() {
+        let args = vec![
+            "granc",
+            "call",
+            "OnlyServiceNoMethod", // Missing '/'
+            "-u",
+            "x",
+            "-b",
+            "{}",
+        ];
+        let err = Cli::try_parse_from(&args).unwrap_err();
+        assert!(err.to_string().contains("Invalid endpoint format"));
+    }
+
     #[test]
     fn test_fail_list_mutual_exclusion() {
         let args = vec![
@@ -0,0 +1,567 @@
+//! # Completion
+//!
+//! Dynamic shell completions, answered by `granc` itself when the shell sets `COMPLETE=<shell>`
+//! (see `granc completions <shell>`).
+//!
+//! Besides subcommands and flags, the values that depend on the schema are completed:
+//!
+//! + Endpoints (`package.Service/Method`) of `call`, and the symbols of `describe`, `doc`, `graph` and `export`.
+//! + The JSON field names of the `--body` of `call`, following the input message of the endpoint.
+//!
+//! The schema is resolved from the flags already typed (`--file-descriptor-set`, `--uri`, `--profile`...), as the
+//! command would. Schemas fetched by reflection are cached for a few minutes, so that completing is instant.
+use crate::cli::{Cli, ConnectionArgs, Source};
+use crate::config::Profile;
+use crate::docgen::{example, package::Packages};
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use clap_complete::{CompleteEnv, engine::CompletionCandidate};
+use granc_core::{
+    client::GrancClient,
+    connector::ConnectOptions,
+    prost_reflect::{DescriptorPool, Kind, MessageDescriptor, MethodDescriptor},
+};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+/// How long a schema fetched by reflection is reused.
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// How long to wait for the server, as the shell is blocked meanwhile.
+const REFLECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The shell asking for completions, as `COMPLETE` is unset before the completers run.
+static SHELL: OnceLock<String> = OnceLock::new();
+
+/// Answers the completion requests of the shell and exits, or returns when not completing.
+///
+/// Must be called outside of any async runtime, as completers block on reflection.
+pub fn complete() {
+    if let Ok(shell) = std::env::var("COMPLETE") {
+        let _ = SHELL.set(shell);
+    }
+
+    CompleteEnv::with_factory(Cli::command).complete();
+}
+
+/// Whether the shell inserts candidates as they are, so that they must be quoted (bash), while others escape them.
+fn inserts_raw_candidates() -> bool {
+    SHELL
+        .get()
+        .and_then(|shell| Path::new(shell).file_stem())
+        .is_some_and(|name| name == "bash")
+}
+
+/// Completes the endpoints of `call`.
+pub fn endpoints() -> Vec<CompletionCandidate> {
+    let Some(pool) = CompletionContext::from_args().and_then(|context| context.descriptor_pool())
+    else {
+        return vec![];
+    };
+
+    let mut services: Vec<_> = pool.services().collect();
+    services.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+    services
+        .iter()
+        .flat_map(|service| service.methods())
+        .map(|method| {
+            CompletionCandidate::new(format!(
+                "{}/{}",
+                method.parent_service().full_name(),
+                method.name()
+            ))
+            .help(Some(method_shape(&method).into()))
+        })
+        .collect()
+}
+
+/// Completes the services of `doc`.
+pub fn services() -> Vec<CompletionCandidate> {
+    let Some(pool) = CompletionContext::from_args().and_then(|context| context.descriptor_pool())
+    else {
+        return vec![];
+    };
+
+    let mut services: Vec<_> = pool.services().map(|s| s.full_name().to_string()).collect();
+    services.sort();
+
+    services.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Completes the services, messages and enums of `describe`, `graph` and `export`.
+pub fn symbols() -> Vec<CompletionCandidate> {
+    let Some(pool) = CompletionContext::from_args().and_then(|context| context.descriptor_pool())
+    else {
+        return vec![];
+    };
+
+    let packages = Packages::from_pool(&pool, &[]);
+    let mut symbols: Vec<_> = packages
+        .values()
+        .flat_map(|package| {
+            let services = package.services.iter().map(|s| s.full_name().to_string());
+            let messages = package.messages.iter().map(|m| m.full_name().to_string());
+            let enums = package.enums.iter().map(|e| e.full_name().to_string());
+            services.chain(messages).chain(enums).collect::<Vec<_>>()
+        })
+        .collect();
+    symbols.sort();
+
+    symbols.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Completes the `--body` of `call`, from the input message of the endpoint.
+pub fn body(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(context) = CompletionContext::from_args() else {
+        return vec![];
+    };
+
+    let Some(method) = context.method() else {
+        return vec![];
+    };
+
+    body_candidates(
+        &method,
+        &current.to_string_lossy(),
+        inserts_raw_candidates(),
+    )
+    .into_iter()
+    .map(CompletionCandidate::new)
+    .collect()
+}
+
+/// `unary`, `server streaming`, `client streaming` or `bidirectional streaming`.
+fn method_shape(method: &MethodDescriptor) -> &'static str {
+    match (method.is_client_streaming(), method.is_server_streaming()) {
+        (false, false) => "unary",
+        (false, true) => "server streaming",
+        (true, false) => "client streaming",
+        (true, true) => "bidirectional streaming",
+    }
+}
+
+/// The flags typed so far on the command line being completed.
+struct CompletionContext {
+    source: Option<Source>,
+    options: ConnectOptions,
+    /// The endpoint of `call`, if already typed.
+    endpoint: Option<(String, String)>,
+}
+
+impl CompletionContext {
+    /// Parses the command line being completed, passed by the shell after `--`.
+    fn from_args() -> Option<Self> {
+        let mut args: Vec<_> = std::env::args_os()
+            .skip_while(|arg| arg != "--")
+            .skip(1)
+            .collect();
+
+        // The word being completed is partial, and would stop the parsing if invalid. Its position is given by
+        // the scripts of bash, zsh and elvish, the others complete the last word.
+        let index = std::env::var("_CLAP_COMPLETE_INDEX")
+            .ok()
+            .and_then(|index| index.parse().ok())
+            .unwrap_or(args.len().saturating_sub(1));
+        if index < args.len() {
+            args.remove(index);
+        }
+
+        // The command line is incomplete, so errors are ignored to get the flags that are set
+        let matches = Cli::command()
+            .ignore_errors(true)
+            .try_get_matches_from(args)
+            .ok()?;
+
+        Some(Self::from_matches(&matches))
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Self {
+        let profile =
+            Profile::load(arg::<String>(matches, "profile").as_deref()).unwrap_or_default();
+        let options = ConnectionArgs::from_arg_matches(matches)
+            .unwrap_or_default()
+            .options(&profile);
+
+        let subcommand = matches.subcommand().map(|(_, matches)| matches);
+        let flag = |id| subcommand.and_then(|matches| arg::<String>(matches, id));
+
+        // A descriptor set is preferred, as for the commands
+        let source = subcommand
+            .and_then(|matches| arg::<PathBuf>(matches, "file_descriptor_set"))
+            .map(Source::File)
+            .or_else(|| flag("uri").map(Source::Uri))
+            .or_else(|| profile.file_descriptor_set.clone().map(Source::File))
+            .or_else(|| profile.uri.clone().map(Source::Uri));
+
+        CompletionContext {
+            source,
+            options,
+            endpoint: subcommand.and_then(|matches| arg(matches, "endpoint")),
+        }
+    }
+
+    fn descriptor_pool(&self) -> Option<DescriptorPool> {
+        match self.source.as_ref()? {
+            Source::File(path) => DescriptorPool::decode(std::fs::read(path).ok()?.as_slice()).ok(),
+            Source::Uri(uri) => cached_pool(uri).or_else(|| {
+                let pool = reflect(uri, &self.options)?;
+                cache_pool(uri, &pool);
+                Some(pool)
+            }),
+        }
+    }
+
+    fn method(&self) -> Option<MethodDescriptor> {
+        let (service, method) = self.endpoint.as_ref()?;
+
+        self.descriptor_pool()?
+            .get_service_by_name(service)?
+            .methods()
+            .find(|m| m.name() == method)
+    }
+}
+
+/// Returns the value of an argument, if it is defined by the command and set.
+fn arg<T: Clone + Send + Sync + 'static>(matches: &ArgMatches, id: &str) -> Option<T> {
+    matches.try_get_one::<T>(id).ok().flatten().cloned()
+}
+
+/// Fetches the whole schema of a server by reflection.
+fn reflect(uri: &str, options: &ConnectOptions) -> Option<DescriptorPool> {
+    let fetch = async {
+        let mut client = GrancClient::connect_with(uri, options.clone()).await.ok()?;
+        let services = client.list_services().await.ok()?;
+        client.fetch_descriptor_pool(&services).await.ok()
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .ok()?;
+
+    runtime
+        .block_on(async { tokio::time::timeout(REFLECTION_TIMEOUT, fetch).await })
+        .ok()
+        .flatten()
+}
+
+/// The file caching the schema of a server, in `$XDG_CACHE_HOME/granc` (`~/.cache/granc` by default).
+fn cache_path(uri: &str) -> Option<PathBuf> {
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    let name: String = uri
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    Some(cache_dir.join("granc").join(format!("{name}.bin")))
+}
+
+fn cached_pool(uri: &str) -> Option<DescriptorPool> {
+    let path = cache_path(uri)?;
+    let age = std::fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())?;
+
+    if age > CACHE_TTL {
+        return None;
+    }
+
+    DescriptorPool::decode(std::fs::read(path).ok()?.as_slice()).ok()
+}
+
+/// Caching is best effort, failures only make the next completion slower.
+fn cache_pool(uri: &str, pool: &DescriptorPool) {
+    if let Some(path) = cache_path(uri) {
+        let _ = path
+            .parent()
+            .map(std::fs::create_dir_all)
+            .transpose()
+            .and_then(|_| std::fs::write(&path, pool.encode_to_vec()));
+    }
+}
+
+/// Where the end of a partial JSON body is, while walking it.
+enum Scope {
+    /// In an object of the given message (`None` for maps, `Struct`s, or unknown fields).
+    Object(Option<MessageDescriptor>),
+    /// In an array of messages of the given type (`None` for scalars).
+    Array(Option<MessageDescriptor>),
+}
+
+/// Completes a partial JSON body: the whole example payload when empty, or the field names of the object
+/// being written, e.g. `{"book": {"ti` completes to `{"book": {"title":`.
+///
+/// With `shell_quote`, candidates are single-quoted for the shell, unless the user already opened the quote.
+fn body_candidates(method: &MethodDescriptor, current: &str, shell_quote: bool) -> Vec<String> {
+    let input = method.input();
+
+    // The opening quote of the shell is part of the word with some shells
+    let (quote, body) = match current.strip_prefix('\'') {
+        Some(body) => ("'", body),
+        None if shell_quote => ("'", current),
+        None => ("", current),
+    };
+
+    if body.is_empty() {
+        let Ok(payload) = example::to_string(&input) else {
+            return vec![];
+        };
+
+        let payload = if method.is_client_streaming() {
+            format!("[{payload}]")
+        } else {
+            payload
+        };
+
+        // The whole payload is a complete argument, so the quote is closed
+        let close = if shell_quote { "'" } else { "" };
+
+        return vec![format!("{quote}{payload}{close}")];
+    }
+
+    let mut stack: Vec<Scope> = vec![];
+    let mut key: Option<String> = None;
+    let mut expecting_key = false;
+    let mut string_start = None;
+    let mut escaped = false;
+
+    for (i, c) in body.char_indices() {
+        if let Some(start) = string_start {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => {
+                    if expecting_key {
+                        key = Some(body[start..i].to_string());
+                        expecting_key = false;
+                    }
+                    string_start = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => string_start = Some(i + 1),
+            '{' => {
+                let message = match stack.last() {
+                    None => Some(input.clone()),
+                    Some(Scope::Array(message)) => message.clone(),
+                    Some(Scope::Object(message)) => {
+                        field_message(message.as_ref(), key.as_deref(), false)
+                    }
+                };
+                stack.push(Scope::Object(message));
+                expecting_key = true;
+            }
+            '[' => {
+                let message = match stack.last() {
+                    None => Some(input.clone()),
+                    Some(Scope::Object(message)) => {
+                        field_message(message.as_ref(), key.as_deref(), true)
+                    }
+                    Some(Scope::Array(_)) => None,
+                };
+                stack.push(Scope::Array(message));
+                expecting_key = false;
+            }
+            '}' | ']' => {
+                stack.pop();
+                expecting_key = false;
+            }
+            ',' => expecting_key = matches!(stack.last(), Some(Scope::Object(_))),
+            ':' => expecting_key = false,
+            _ => {}
+        }
+    }
+
+    let Some(Scope::Object(Some(message))) = stack.last() else {
+        return vec![];
+    };
+
+    if !expecting_key {
+        return vec![];
+    }
+
+    // Either in the quotes of a key, or before them
+    let (prefix, partial) = match string_start {
+        Some(start) => (&body[..start], &body[start..]),
+        None if body.trim_end().ends_with(['{', ',']) => (body, ""),
+        None => return vec![],
+    };
+
+    let open_quote = if string_start.is_some() { "" } else { "\"" };
+
+    message
+        .fields()
+        .filter(|field| field.json_name().starts_with(partial))
+        .map(|field| format!("{quote}{prefix}{open_quote}{}\":", field.json_name()))
+        .collect()
+}
+
+/// The message of a field of `message` holding an object (or an array of objects, if `list`).
+fn field_message(
+    message: Option<&MessageDescriptor>,
+    key: Option<&str>,
+    list: bool,
+) -> Option<MessageDescriptor> {
+    let key = key?;
+    let field = message?
+        .fields()
+        .find(|field| field.json_name() == key || field.name() == key)?;
+
+    match field.kind() {
+        // Map keys are free-form
+        Kind::Message(_) if field.is_map() => None,
+        Kind::Message(m) if field.is_list() == list => Some(m),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_test_support::compiler;
+    use std::path::Path;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package shop.v1;
+
+        message Order {
+            string order_id = 1;
+            repeated Line lines = 2;
+            Customer customer = 3;
+            map<string, Line> lines_by_sku = 4;
+        }
+
+        message Line {
+            string sku = 1;
+            int32 quantity = 2;
+        }
+
+        message Customer {
+            string name = 1;
+        }
+
+        service Orders {
+            rpc Place(Order) returns (Order);
+            rpc Upload(stream Line) returns (Order);
+        }
+    "#;
+
+    fn method(name: &str) -> MethodDescriptor {
+        let file_descriptor_set = compiler::compile_protos(&[("shop.proto", PROTO)]);
+        let pool = DescriptorPool::from_file_descriptor_set(file_descriptor_set)
+            .expect("Failed to decode descriptor pool");
+
+        pool.get_service_by_name("shop.v1.Orders")
+            .unwrap()
+            .methods()
+            .find(|m| m.name() == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_body_example_candidate() {
+        assert_eq!(
+            body_candidates(&method("Place"), "", false),
+            [
+                r#"{"orderId":"","lines":[{"sku":"","quantity":0}],"customer":{"name":""},"linesBySku":{"key":{"sku":"","quantity":0}}}"#
+            ]
+        );
+        assert_eq!(
+            body_candidates(&method("Upload"), "'", false),
+            [r#"'[{"sku":"","quantity":0}]"#]
+        );
+
+        // Quoted for shells inserting candidates as they are
+        assert_eq!(
+            body_candidates(&method("Upload"), "", true),
+            [r#"'[{"sku":"","quantity":0}]'"#]
+        );
+        assert_eq!(
+            body_candidates(&method("Upload"), "'", true),
+            [r#"'[{"sku":"","quantity":0}]'"#]
+        );
+    }
+
+    #[test]
+    fn test_body_field_candidates() {
+        let place = method("Place");
+
+        assert_eq!(
+            body_candidates(&place, r#"{"li"#, false),
+            [r#"{"lines":"#, r#"{"linesBySku":"#]
+        );
+        assert_eq!(
+            body_candidates(&place, r#"'{"orderId": "a,b", "#, false),
+            [
+                r#"'{"orderId": "a,b", "orderId":"#,
+                r#"'{"orderId": "a,b", "lines":"#,
+                r#"'{"orderId": "a,b", "customer":"#,
+                r#"'{"orderId": "a,b", "linesBySku":"#,
+            ]
+        );
+
+        assert_eq!(
+            body_candidates(&place, r#"{"li"#, true),
+            [r#"'{"lines":"#, r#"'{"linesBySku":"#]
+        );
+        assert_eq!(
+            body_candidates(&place, r#"'{"cu"#, true),
+            [r#"'{"customer":"#]
+        );
+
+        // Nested messages, in arrays too
+        assert_eq!(
+            body_candidates(&place, r#"{"customer": {""#, false),
+            [r#"{"customer": {"name":"#]
+        );
+        assert_eq!(
+            body_candidates(&place, r#"{"lines": [{"sku": "a"}, {"q"#, false),
+            [r#"{"lines": [{"sku": "a"}, {"quantity":"#]
+        );
+        assert_eq!(
+            body_candidates(&method("Upload"), r#"[{"s"#, false),
+            [r#"[{"sku":"#]
+        );
+
+        // Values and map keys are not completed
+        assert!(body_candidates(&place, r#"{"orderId": "o"#, false).is_empty());
+        assert!(body_candidates(&place, r#"{"linesBySku": {"k"#, false).is_empty());
+    }
+
+    #[test]
+    fn test_completion_context() {
+        let matches = Cli::command()
+            .ignore_errors(true)
+            .try_get_matches_from([
+                "granc",
+                "call",
+                "shop.v1.Orders/Place",
+                "-f",
+                "shop.bin",
+                "--body",
+                "{\"ord",
+            ])
+            .unwrap();
+
+        let context = CompletionContext::from_matches(&matches);
+
+        assert!(
+            matches!(context.source, Some(Source::File(path)) if path == Path::new("shop.bin"))
+        );
+        assert_eq!(
+            context.endpoint,
+            Some(("shop.v1.Orders".to_string(), "Place".to_string()))
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! # Configuration
+//!
+//! This module loads named connection profiles, selected with `granc --profile <NAME>`.
+//!
+//! Profiles are read from two TOML files:
+//!
+//! 1. The user configuration: `$XDG_CONFIG_HOME/granc/config.toml` (defaults to `~/.config/granc/config.toml`).
+//! 2. The project configuration: the closest `.granc.toml` in the current directory or any of its parents.
+//!
+//! A profile defined in the project configuration replaces a profile with the same name in the user one.
+//!
+//! ```toml
+//! default_profile = "local"
+//!
+//! [profiles.local]
+//! uri = "http://localhost:50051"
+//!
+//! [profiles.staging]
+//! uri = "https://staging.example.com:443"
+//! file_descriptor_set = "./descriptors.bin"
+//! protocol = "grpc-web"
+//! color = false
+//!
+//! [profiles.staging.headers]
+//! authorization = "Bearer ${STAGING_TOKEN}"
+//! ```
+//!
+//! Header values are resolved like the values of `-H` (see [`crate::metadata`]): `${VAR}` references an
+//! environment variable and `@path` reads a file. The other values can reference environment variables with
+//! `$VAR` or `${VAR}`, and paths can start with `~`. Relative paths are resolved against the directory of the
+//! file that defines them.
+//!
+//! Flags given on the command line always take precedence over the values of the selected profile.
+use crate::{cli::Protocol, metadata};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+const PROJECT_FILE: &str = ".granc.toml";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Failed to read config file '{}': '{source}'", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid config file '{}': '{source}'", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("Profile '{0}' is not defined in any config file")]
+    UnknownProfile(String),
+    #[error("Profile '{profile}' references an undefined environment variable: '{source}'")]
+    Expansion {
+        profile: String,
+        #[source]
+        source: shellexpand::LookupError<std::env::VarError>,
+    },
+    #[error("Profile '{profile}': {message}")]
+    Header { profile: String, message: String },
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// A named set of defaults for the connection and the output.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Server URI, used when `--uri` is not given.
+    pub uri: Option<String>,
+    /// Headers sent with every call. Headers given with `-H` replace the ones with the same name.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Descriptor set to use instead of reflection, when `--file-descriptor-set` is not given.
+    pub file_descriptor_set: Option<PathBuf>,
+    /// Protocol used to talk to the server, when `--protocol` is not given.
+    pub protocol: Option<Protocol>,
+    /// Whether to color the output. Defaults to coloring when writing to a terminal.
+    pub color: Option<bool>,
+    /// Command printing the bearer token sent with every call, when no credentials flag is given.
+    /// Run by the shell, so it is not expanded.
+    pub token_cmd: Option<String>,
+}
+
+impl Profile {
+    /// Loads the profile named `name`, or the default profile when no name is given.
+    ///
+    /// Returns an empty profile when no name is given and no default profile is configured.
+    pub fn load(name: Option<&str>) -> Result<Self, ConfigError> {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let files = [user_config_path(), project_config_path(&cwd)];
+
+        let mut default_profile = None;
+        let mut profiles = BTreeMap::new();
+
+        for path in files.into_iter().flatten() {
+            let config = ConfigFile::from_path(&path)?;
+            let base = path.parent().unwrap_or(Path::new("."));
+
+            default_profile = config.default_profile.or(default_profile);
+            profiles.extend(
+                config
+                    .profiles
+                    .into_iter()
+                    .map(|(name, profile)| (name, (profile, base.to_path_buf()))),
+            );
+        }
+
+        let Some(name) = name.map(str::to_string).or(default_profile) else {
+            return Ok(Profile::default());
+        };
+
+        let (profile, base) = profiles
+            .remove(&name)
+            .ok_or_else(|| ConfigError::UnknownProfile(name.clone()))?;
+
+        let lookup = |name: &str| std::env::var(name).ok();
+
+        profile
+            .expand(&base, lookup)
+            .map_err(|source| ConfigError::Expansion {
+                profile: name.clone(),
+                source,
+            })?
+            .resolve_headers(&base, lookup)
+            .map_err(|message| ConfigError::Header {
+                profile: name,
+                message,
+            })
+    }
+
+    /// Merges the profile headers with the headers given on the command line.
+    pub fn headers(&self, cli_headers: Vec<(String, String)>) -> Vec<(String, String)> {
+        let mut headers: Vec<_> = self
+            .headers
+            .iter()
+            .filter(|(key, _)| !cli_headers.iter().any(|(k, _)| k.eq_ignore_ascii_case(key)))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        headers.extend(cli_headers);
+        headers
+    }
+
+    /// Expands environment variables and `~` in every value but the headers, resolving relative paths against
+    /// `base`.
+    ///
+    /// Variables, `HOME` included, are read with `lookup`.
+    fn expand(
+        self,
+        base: &Path,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, shellexpand::LookupError<std::env::VarError>> {
+        let expand = |value: &str| {
+            shellexpand::full_with_context(
+                value,
+                || lookup("HOME"),
+                |name| lookup(name).map(Some).ok_or(std::env::VarError::NotPresent),
+            )
+            .map(|v| v.into_owned())
+        };
+
+        let file_descriptor_set = self
+            .file_descriptor_set
+            .map(|path| expand(&path.to_string_lossy()).map(PathBuf::from))
+            .transpose()?
+            .map(|path| base.join(path));
+
+        Ok(Self {
+            uri: self.uri.as_deref().map(expand).transpose()?,
+            headers: self.headers,
+            file_descriptor_set,
+            protocol: self.protocol,
+            color: self.color,
+            token_cmd: self.token_cmd,
+        })
+    }
+}
+
+impl Profile {
+    /// Resolves the header values like the values of `-H`, reading `@path` files relative to `base`.
+    fn resolve_headers(
+        mut self,
+        base: &Path,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, String> {
+        self.headers = self
+            .headers
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value.strip_prefix('@') {
+                    Some(path) => format!("@{}", base.join(path).display()),
+                    None => value,
+                };
+                let value = metadata::resolve_value_with(&key, &value, &lookup)?;
+                Ok((key, value))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(self)
+    }
+}
+
+impl ConfigFile {
+    fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        toml::from_str(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_dir.join("granc").join("config.toml")).filter(|path| path.is_file())
+}
+
+fn project_config_path(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let toml = r#"
+            default_profile = "local"
+
+            [profiles.local]
+            uri = "http://localhost:50051"
+
+            [profiles.staging]
+            uri = "https://staging:443"
+            file_descriptor_set = "descriptors.bin"
+            protocol = "grpc-web-text"
+            color = false
+
+            [profiles.staging.headers]
+            authorization = "Bearer ${TOKEN}"
+        "#;
+
+        let config: ConfigFile = toml::from_str(toml).expect("Parsing failed");
+        assert_eq!(config.default_profile.as_deref(), Some("local"));
+
+        let lookup = |name: &str| (name == "TOKEN").then(|| "secret".to_string());
+
+        let staging = config.profiles["staging"]
+            .clone()
+            .expand(Path::new("/project"), lookup)
+            .unwrap()
+            .resolve_headers(Path::new("/project"), lookup)
+            .unwrap();
+
+        assert_eq!(staging.uri.as_deref(), Some("https://staging:443"));
+        assert_eq!(staging.headers["authorization"], "Bearer secret");
+        assert_eq!(
+            staging.file_descriptor_set.unwrap(),
+            Path::new("/project/descriptors.bin")
+        );
+        assert_eq!(staging.protocol, Some(Protocol::GrpcWebText));
+        assert_eq!(staging.color, Some(false));
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let profile = Profile {
+            uri: Some("${UNDEFINED}".to_string()),
+            file_descriptor_set: Some(PathBuf::from("~/descriptors.bin")),
+            ..Default::default()
+        };
+
+        assert!(profile.clone().expand(Path::new("."), |_| None).is_err());
+
+        let profile = Profile {
+            uri: None,
+            ..profile
+        };
+        let expanded = profile
+            .expand(Path::new("."), |name| {
+                (name == "HOME").then(|| "/home/ferris".to_string())
+            })
+            .unwrap();
+        assert_eq!(
+            expanded.file_descriptor_set.unwrap(),
+            Path::new("/home/ferris/descriptors.bin")
+        );
+    }
+
+    #[test]
+    fn test_resolve_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("tenant.txt"), "acme\n").unwrap();
+
+        let profile = Profile {
+            headers: BTreeMap::from([
+                ("authorization".to_string(), "Bearer ${TOKEN}".to_string()),
+                ("x-signature".to_string(), "a$b$${c}".to_string()),
+                ("x-tenant".to_string(), "@tenant.txt".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        // Headers are resolved like the values of `-H`, with files relative to the config file
+        let lookup = |name: &str| (name == "TOKEN").then(|| "secret".to_string());
+        let resolved = profile.clone().resolve_headers(dir.path(), lookup).unwrap();
+
+        assert_eq!(resolved.headers["authorization"], "Bearer secret");
+        assert_eq!(resolved.headers["x-signature"], "a$b${c}");
+        assert_eq!(resolved.headers["x-tenant"], "acme");
+
+        assert!(profile.resolve_headers(dir.path(), |_| None).is_err());
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let toml = r#"
+            [profiles.local]
+            url = "http://localhost:50051"
+        "#;
+
+        assert!(toml::from_str::<ConfigFile>(toml).is_err());
+    }
+
+    #[test]
+    fn test_cli_headers_take_precedence() {
+        let profile = Profile {
+            headers: BTreeMap::from([
+                ("authorization".to_string(), "Bearer profile".to_string()),
+                ("x-tenant".to_string(), "acme".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let headers = profile.headers(vec![(
+            "Authorization".to_string(),
+            "Bearer cli".to_string(),
+        )]);
+
+        assert_eq!(
+            headers,
+            [
+                ("x-tenant".to_string(), "acme".to_string()),
+                ("Authorization".to_string(), "Bearer cli".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_project_config_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.path().join(PROJECT_FILE), "").unwrap();
+
+        assert_eq!(
+            project_config_path(&nested).unwrap(),
+            dir.path().join(PROJECT_FILE)
+        );
+    }
+}
@@ -1,2 +1,7 @@
+pub mod example;
+pub mod graph;
+pub mod html;
 pub mod markdown;
-mod package;
+pub mod model;
+pub mod package;
+pub mod template;
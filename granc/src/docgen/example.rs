@@ -0,0 +1,233 @@
+//! # Example
+//!
+//! Builds example JSON payloads of messages, following the proto3 JSON mapping used by `granc call`.
+//!
+//! Every field is set to an example of its type: the zero value of scalars, the first value of enums, and a single
+//! element in repeated and map fields. Only the first field of each `oneof` is set, and fields that would recurse
+//! into a message being built, or hold an `Any`, are left empty, so payloads are always valid.
+use granc_core::prost_reflect::{
+    DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, SerializeOptions,
+};
+use serde_json::{Map, Value, json};
+use std::collections::HashSet;
+
+/// Returns an example JSON payload of a message.
+pub(crate) fn message(message: &MessageDescriptor) -> Value {
+    message_value(message, &mut vec![])
+}
+
+/// Returns an example JSON payload of a message, pretty printed with its fields in the order of the schema.
+pub(crate) fn to_string_pretty(message: &MessageDescriptor) -> Result<String, serde_json::Error> {
+    let mut out = vec![];
+    serialize(message, &mut serde_json::Serializer::pretty(&mut out))?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// Returns an example JSON payload of a message on a single line, with its fields in the order of the schema.
+pub(crate) fn to_string(message: &MessageDescriptor) -> Result<String, serde_json::Error> {
+    let mut out = vec![];
+    serialize(message, &mut serde_json::Serializer::new(&mut out))?;
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+fn serialize<W: std::io::Write, F: serde_json::ser::Formatter>(
+    message: &MessageDescriptor,
+    serializer: &mut serde_json::Serializer<W, F>,
+) -> Result<(), serde_json::Error> {
+    let example = DynamicMessage::deserialize(message.clone(), self::message(message))?;
+
+    // Unlike `serde_json::Value`, messages are serialized in field order
+    let options = SerializeOptions::new().skip_default_fields(false);
+    example.serialize_with_options(serializer, &options)
+}
+
+/// `stack` holds the full names of the messages being built, to break cycles.
+fn message_value(message: &MessageDescriptor, stack: &mut Vec<String>) -> Value {
+    if let Some(value) = well_known_type(message, stack) {
+        return value;
+    }
+
+    stack.push(message.full_name().to_string());
+
+    let mut object = Map::new();
+    let mut oneofs = HashSet::new();
+
+    for field in message.fields() {
+        let oneof = field
+            .containing_oneof()
+            .filter(|oneof| !oneof.is_synthetic());
+
+        if oneof.is_some_and(|oneof| !oneofs.insert(oneof.name().to_string())) {
+            continue;
+        }
+
+        if let Some(value) = field_value(&field, stack) {
+            object.insert(field.json_name().to_string(), value);
+        }
+    }
+
+    stack.pop();
+    Value::Object(object)
+}
+
+/// Returns the example of a field, or `None` if the field can't be set without recursing.
+fn field_value(field: &FieldDescriptor, stack: &mut Vec<String>) -> Option<Value> {
+    match field.kind() {
+        Kind::Message(entry) if field.is_map() => {
+            let key = map_key(&entry.map_entry_key_field().kind());
+            let value = kind_value(&entry.map_entry_value_field().kind(), stack);
+
+            Some(Value::Object(value.map(|v| (key, v)).into_iter().collect()))
+        }
+        kind if field.is_list() => {
+            Some(Value::Array(kind_value(&kind, stack).into_iter().collect()))
+        }
+        kind => kind_value(&kind, stack),
+    }
+}
+
+fn kind_value(kind: &Kind, stack: &mut Vec<String>) -> Option<Value> {
+    let value = match kind {
+        Kind::Message(m) if stack.iter().any(|name| name == m.full_name()) => return None,
+        // The `@type` of an `Any` can't be known from the schema
+        Kind::Message(m) if m.full_name() == "google.protobuf.Any" => return None,
+        Kind::Message(m) => message_value(m, stack),
+        Kind::Enum(e) if e.full_name() == "google.protobuf.NullValue" => Value::Null,
+        Kind::Enum(e) => json!(e.values().next().map(|v| v.name().to_string())),
+        // 64-bit integers are strings in JSON
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 | Kind::Uint64 | Kind::Fixed64 => json!("0"),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 | Kind::Uint32 | Kind::Fixed32 => json!(0),
+        Kind::Double | Kind::Float => json!(0.0),
+        Kind::Bool => json!(false),
+        Kind::String | Kind::Bytes => json!(""),
+    };
+
+    Some(value)
+}
+
+/// Map keys are always strings in JSON.
+fn map_key(kind: &Kind) -> String {
+    match kind {
+        Kind::String => "key".to_string(),
+        Kind::Bool => "false".to_string(),
+        _ => "0".to_string(),
+    }
+}
+
+/// Returns the example of a well-known type with a special JSON representation.
+fn well_known_type(message: &MessageDescriptor, stack: &mut Vec<String>) -> Option<Value> {
+    let value = match message.full_name() {
+        "google.protobuf.Timestamp" => json!("1970-01-01T00:00:00Z"),
+        "google.protobuf.Duration" => json!("0s"),
+        "google.protobuf.FieldMask" => json!(""),
+        "google.protobuf.Struct" => json!({}),
+        "google.protobuf.ListValue" => json!([]),
+        "google.protobuf.Value" => Value::Null,
+        // Wrappers are represented by their value
+        name if name.starts_with("google.protobuf.") && name.ends_with("Value") => {
+            let field = message.get_field_by_name("value")?;
+            kind_value(&field.kind(), stack)?
+        }
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::{DescriptorPool, DynamicMessage};
+    use granc_test_support::compiler;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package shop.v1;
+
+        import "google/protobuf/timestamp.proto";
+        import "google/protobuf/wrappers.proto";
+        import "google/protobuf/struct.proto";
+        import "google/protobuf/any.proto";
+
+        enum Status {
+            STATUS_UNSPECIFIED = 0;
+            STATUS_OPEN = 1;
+        }
+
+        message Order {
+            string id = 1;
+            int64 total = 2;
+            double discount = 3;
+            bool paid = 4;
+            bytes signature = 5;
+            Status status = 6;
+            repeated Line lines = 7;
+            map<int32, Line> lines_by_number = 8;
+            google.protobuf.Timestamp created_at = 9;
+            google.protobuf.StringValue note = 10;
+            google.protobuf.Struct metadata = 11;
+            optional uint32 priority = 12;
+
+            oneof payment {
+                string card = 13;
+                string iban = 14;
+            }
+
+            Order parent = 15;
+            google.protobuf.Any extension = 16;
+            repeated google.protobuf.Any extensions = 17;
+        }
+
+        message Line {
+            string sku = 1;
+            repeated Order orders = 2;
+        }
+    "#;
+
+    #[test]
+    fn test_message_example() {
+        let file_descriptor_set = compiler::compile_protos(&[("shop.proto", PROTO)]);
+        let pool = DescriptorPool::from_file_descriptor_set(file_descriptor_set)
+            .expect("Failed to decode descriptor pool");
+        let descriptor = pool.get_message_by_name("shop.v1.Order").unwrap();
+
+        let example = message(&descriptor);
+
+        assert_eq!(
+            example,
+            json!({
+                "id": "",
+                "total": "0",
+                "discount": 0.0,
+                "paid": false,
+                "signature": "",
+                "status": "STATUS_UNSPECIFIED",
+                "lines": [{ "sku": "", "orders": [] }],
+                "linesByNumber": { "0": { "sku": "", "orders": [] } },
+                "createdAt": "1970-01-01T00:00:00Z",
+                "note": "",
+                "metadata": {},
+                "priority": 0,
+                "card": "",
+                "extensions": []
+            })
+        );
+
+        // The example is a valid request body
+        DynamicMessage::deserialize(descriptor, example).expect("Invalid example");
+    }
+
+    #[test]
+    fn test_example_field_order() {
+        let file_descriptor_set = compiler::compile_protos(&[("shop.proto", PROTO)]);
+        let pool = DescriptorPool::from_file_descriptor_set(file_descriptor_set)
+            .expect("Failed to decode descriptor pool");
+        let descriptor = pool.get_message_by_name("shop.v1.Line").unwrap();
+
+        assert!(
+            to_string_pretty(&descriptor)
+                .unwrap()
+                .starts_with("{\n  \"sku\": \"\",\n  \"orders\": [\n    {\n      \"id\": \"\",\n      \"total\": \"0\",")
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! # Graph
+//!
+//! Draws the dependency graph of a set of [`Packages`] as a Mermaid flowchart or a Graphviz (DOT) digraph.
+//!
+//! Services, methods, messages and enums are the nodes, grouped by package. Services point to their methods,
+//! methods to their request and response messages, and messages to the messages and enums of their fields
+//! (the values of map fields), labelled with the field name.
+use super::package::Packages;
+use granc_core::prost_reflect::{Kind, MessageDescriptor};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NodeKind {
+    Service,
+    Method,
+    Message,
+    Enum,
+}
+
+struct Node {
+    /// The fully qualified name of the element, unique in the graph.
+    key: String,
+    /// The name of the element, relative to its package.
+    label: String,
+    kind: NodeKind,
+}
+
+struct Edge {
+    from: String,
+    to: String,
+    label: Option<String>,
+}
+
+pub(crate) struct Graph {
+    /// The nodes of each package, sorted by package name.
+    packages: BTreeMap<String, Vec<Node>>,
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Renders the graph as a Mermaid flowchart.
+    pub fn mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+
+        for (package, nodes) in &self.packages {
+            let indent = if package.is_empty() {
+                "    "
+            } else {
+                out.push_str(&format!(
+                    "    subgraph {}[\"{package}\"]\n",
+                    cluster_id(package)
+                ));
+                "        "
+            };
+
+            for node in nodes {
+                let id = mermaid_id(&node.key);
+                let label = &node.label;
+
+                let shape = match node.kind {
+                    NodeKind::Service => format!("{id}[[\"{label}\"]]"),
+                    NodeKind::Method => format!("{id}(\"{label}\")"),
+                    NodeKind::Message => format!("{id}[\"{label}\"]"),
+                    NodeKind::Enum => format!("{id}{{{{\"{label}\"}}}}"),
+                };
+
+                out.push_str(&format!("{indent}{shape}\n"));
+            }
+
+            if !package.is_empty() {
+                out.push_str("    end\n");
+            }
+        }
+
+        for edge in &self.edges {
+            let from = mermaid_id(&edge.from);
+            let to = mermaid_id(&edge.to);
+
+            match &edge.label {
+                Some(label) => out.push_str(&format!("    {from} -->|{label}| {to}\n")),
+                None => out.push_str(&format!("    {from} --> {to}\n")),
+            }
+        }
+
+        out
+    }
+
+    /// Renders the graph as a Graphviz digraph, in the DOT language.
+    pub fn dot(&self) -> String {
+        let mut out = String::from("digraph {\n    rankdir=LR;\n");
+
+        for (package, nodes) in &self.packages {
+            let indent = if package.is_empty() {
+                "    "
+            } else {
+                out.push_str(&format!("    subgraph \"{}\" {{\n", cluster_id(package)));
+                out.push_str(&format!("        label=\"{package}\";\n"));
+                "        "
+            };
+
+            for node in nodes {
+                let shape = match node.kind {
+                    NodeKind::Service => "component",
+                    NodeKind::Method => "ellipse",
+                    NodeKind::Message => "box",
+                    NodeKind::Enum => "hexagon",
+                };
+
+                out.push_str(&format!(
+                    "{indent}\"{}\" [label=\"{}\", shape={shape}];\n",
+                    node.key, node.label
+                ));
+            }
+
+            if !package.is_empty() {
+                out.push_str("    }\n");
+            }
+        }
+
+        for edge in &self.edges {
+            match &edge.label {
+                Some(label) => out.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"{label}\"];\n",
+                    edge.from, edge.to
+                )),
+                None => out.push_str(&format!("    \"{}\" -> \"{}\";\n", edge.from, edge.to)),
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    fn push_node(&mut self, package: &str, key: &str, kind: NodeKind) {
+        let label = key
+            .strip_prefix(package)
+            .and_then(|name| name.strip_prefix('.'))
+            .unwrap_or(key)
+            .to_string();
+
+        self.packages
+            .entry(package.to_string())
+            .or_default()
+            .push(Node {
+                key: key.to_string(),
+                label,
+                kind,
+            });
+    }
+
+    fn push_edge(&mut self, from: &str, to: &str, label: Option<&str>) {
+        self.edges.push(Edge {
+            from: from.to_string(),
+            to: to.to_string(),
+            label: label.map(str::to_string),
+        });
+    }
+
+    fn push_message(&mut self, message: &MessageDescriptor) {
+        self.push_node(
+            message.package_name(),
+            message.full_name(),
+            NodeKind::Message,
+        );
+
+        for field in message.fields() {
+            // Map fields point to the value of their entry
+            let kind = match field.kind() {
+                Kind::Message(entry) if field.is_map() => entry.map_entry_value_field().kind(),
+                kind => kind,
+            };
+
+            match kind {
+                Kind::Message(m) => {
+                    self.push_edge(message.full_name(), m.full_name(), Some(field.name()))
+                }
+                Kind::Enum(e) => {
+                    self.push_edge(message.full_name(), e.full_name(), Some(field.name()))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl From<&Packages> for Graph {
+    fn from(packages: &Packages) -> Self {
+        let mut graph = Graph {
+            packages: BTreeMap::new(),
+            edges: vec![],
+        };
+
+        let mut packages: Vec<_> = packages.values().collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for package in packages {
+            let mut services = package.services.clone();
+            services.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+            for service in services {
+                graph.push_node(&package.name, service.full_name(), NodeKind::Service);
+
+                for method in service.methods() {
+                    let input = if method.is_client_streaming() {
+                        "stream request"
+                    } else {
+                        "request"
+                    };
+
+                    let output = if method.is_server_streaming() {
+                        "stream response"
+                    } else {
+                        "response"
+                    };
+
+                    graph.push_node(&package.name, method.full_name(), NodeKind::Method);
+                    graph.push_edge(service.full_name(), method.full_name(), None);
+                    graph.push_edge(method.full_name(), method.input().full_name(), Some(input));
+                    graph.push_edge(
+                        method.full_name(),
+                        method.output().full_name(),
+                        Some(output),
+                    );
+                }
+            }
+
+            let mut messages = package.messages.clone();
+            messages.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+            for message in messages {
+                graph.push_message(&message);
+            }
+
+            let mut enums = package.enums.clone();
+            enums.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+            for enum_desc in enums {
+                graph.push_node(&package.name, enum_desc.full_name(), NodeKind::Enum);
+            }
+        }
+
+        graph
+    }
+}
+
+/// The identifier of the subgraph of a package.
+fn cluster_id(package: &str) -> String {
+    format!("cluster_{}", mermaid_id(package))
+}
+
+/// Mermaid identifiers can't contain dots.
+fn mermaid_id(key: &str) -> String {
+    key.replace('.', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::client::Descriptor;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package shop.v1;
+
+        enum Status {
+            STATUS_UNSPECIFIED = 0;
+        }
+
+        message Order {
+            repeated Line lines = 1;
+            map<string, Status> statuses = 2;
+            Line.Kind kind = 3;
+        }
+
+        message Line {
+            enum Kind {
+                KIND_UNSPECIFIED = 0;
+            }
+
+            string sku = 1;
+        }
+
+        service Orders {
+            rpc Place(stream Order) returns (Order);
+        }
+    "#;
+
+    fn pool() -> DescriptorPool {
+        let file_descriptor_set = compiler::compile_protos(&[("shop.proto", PROTO)]);
+        DescriptorPool::from_file_descriptor_set(file_descriptor_set)
+            .expect("Failed to decode descriptor pool")
+    }
+
+    #[test]
+    fn test_mermaid_service_graph() {
+        let service = pool().get_service_by_name("shop.v1.Orders").unwrap();
+        let graph = Graph::from(&Packages::from(service));
+
+        assert_eq!(
+            graph.mermaid(),
+            r#"flowchart LR
+    subgraph cluster_shop_v1["shop.v1"]
+        shop_v1_Orders[["Orders"]]
+        shop_v1_Orders_Place("Orders.Place")
+        shop_v1_Line["Line"]
+        shop_v1_Order["Order"]
+        shop_v1_Line_Kind{{"Line.Kind"}}
+        shop_v1_Status{{"Status"}}
+    end
+    shop_v1_Orders --> shop_v1_Orders_Place
+    shop_v1_Orders_Place -->|stream request| shop_v1_Order
+    shop_v1_Orders_Place -->|response| shop_v1_Order
+    shop_v1_Order -->|lines| shop_v1_Line
+    shop_v1_Order -->|statuses| shop_v1_Status
+    shop_v1_Order -->|kind| shop_v1_Line_Kind
+"#
+        );
+    }
+
+    #[test]
+    fn test_dot_message_graph() {
+        let message = pool().get_message_by_name("shop.v1.Order").unwrap();
+        let graph = Graph::from(&Packages::from(Descriptor::MessageDescriptor(message)));
+
+        // Only the message and its dependencies are drawn
+        assert_eq!(
+            graph.dot(),
+            r#"digraph {
+    rankdir=LR;
+    subgraph "cluster_shop_v1" {
+        label="shop.v1";
+        "shop.v1.Line" [label="Line", shape=box];
+        "shop.v1.Order" [label="Order", shape=box];
+        "shop.v1.Line.Kind" [label="Line.Kind", shape=hexagon];
+        "shop.v1.Status" [label="Status", shape=hexagon];
+    }
+    "shop.v1.Order" -> "shop.v1.Line" [label="lines"];
+    "shop.v1.Order" -> "shop.v1.Status" [label="statuses"];
+    "shop.v1.Order" -> "shop.v1.Line.Kind" [label="kind"];
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,759 @@
+//! # HTML Documentation
+//!
+//! Generates a standalone static site documenting a set of [`Packages`]:
+//!
+//! + `index.html`: The entry point, linking to every service and package.
+//! + `<package>.html`: One page per package, with syntax highlighted and cross-linked definitions.
+//!   Nested messages and enums are collapsible, under the message declaring them.
+//! + `search-index.js`: Every service, method, message and enum, searched from the sidebar.
+//! + `style.css` and `script.js`: The assets shared by every page.
+//!
+//! Every page has a sidebar with the package tree, built from the [`Packages`] dependency graph.
+use super::package::{Package, Packages, nested_messages};
+use crate::formatter::{
+    MessageMember, enum_values, field_default, field_label, kind_name, message_members,
+};
+use crate::options;
+use granc_core::prost_reflect::{
+    DynamicMessage, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor, MethodDescriptor,
+    ServiceDescriptor,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+const STYLE: &str = include_str!("html/style.css");
+const SCRIPT: &str = include_str!("html/script.js");
+
+pub fn generate(output_dir: PathBuf, packages: Packages) -> std::io::Result<()> {
+    if !output_dir.exists() {
+        fs::create_dir_all(&output_dir)?;
+    }
+
+    let mut package_names: Vec<_> = packages.names().cloned().collect();
+    package_names.sort();
+
+    let mut site = Site {
+        packages: package_names.iter().cloned().collect(),
+        search_index: vec![],
+    };
+
+    for package in packages.values() {
+        let filename = format!("{}.html", package.name);
+        let content = site.package_content(package);
+        let sidebar = sidebar(&package_names, Some(&package.name));
+
+        fs::write(
+            output_dir.join(&filename),
+            page(&package.name, &sidebar, &content),
+        )?;
+        println!("Generated: {}", filename);
+    }
+
+    let content = index_content(&packages.services(), &package_names);
+    let sidebar = sidebar(&package_names, None);
+    fs::write(
+        output_dir.join("index.html"),
+        page("Documentation Index", &sidebar, &content),
+    )?;
+    println!("Generated: index.html");
+
+    site.search_index.sort_by(|a, b| a.name.cmp(&b.name));
+    let search_index = serde_json::to_string(&site.search_index).map_err(std::io::Error::other)?;
+    fs::write(
+        output_dir.join("search-index.js"),
+        format!("window.SEARCH_INDEX = {search_index};\n"),
+    )?;
+    fs::write(output_dir.join("style.css"), STYLE)?;
+    fs::write(output_dir.join("script.js"), SCRIPT)?;
+    println!("Generated: search-index.js, style.css, script.js");
+
+    Ok(())
+}
+
+/// An entry of the search index, pointing to the section documenting a symbol.
+#[derive(Serialize)]
+struct SearchEntry {
+    kind: &'static str,
+    name: String,
+    url: String,
+}
+
+/// The state shared while rendering the pages of the site.
+struct Site {
+    /// The packages having a page, the only ones types can link to.
+    packages: HashSet<String>,
+    search_index: Vec<SearchEntry>,
+}
+
+impl Site {
+    fn package_content(&mut self, package: &Package) -> String {
+        let mut out = format!("<h1>Package <code>{}</code></h1>\n", escape(&package.name));
+
+        let mut services = package.services.clone();
+        services.sort_by(|a, b| a.name().cmp(b.name()));
+
+        for service in services {
+            self.write_service(&mut out, &service);
+        }
+
+        // Nested types are documented under their parent, when it is documented as well
+        let messages: HashSet<_> = package.messages.iter().map(|m| m.full_name()).collect();
+        let is_top_level = |parent: Option<MessageDescriptor>| {
+            parent.is_none_or(|parent| !messages.contains(parent.full_name()))
+        };
+
+        let mut top_level_messages: Vec<_> = package
+            .messages
+            .iter()
+            .filter(|m| is_top_level(m.parent_message()))
+            .cloned()
+            .collect();
+        top_level_messages.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+        for message in top_level_messages {
+            self.write_message(&mut out, &message);
+        }
+
+        let mut top_level_enums: Vec<_> = package
+            .enums
+            .iter()
+            .filter(|e| is_top_level(e.parent_message()))
+            .cloned()
+            .collect();
+        top_level_enums.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+        for enum_desc in top_level_enums {
+            self.write_enum(&mut out, &enum_desc);
+        }
+
+        out
+    }
+
+    fn write_service(&mut self, out: &mut String, service: &ServiceDescriptor) {
+        self.index("service", service.package_name(), service.full_name());
+
+        out.push_str(&format!(
+            "<section id=\"{}\">\n<h2>{}{}</h2>\n",
+            escape(service.full_name()),
+            escape(service.name()),
+            deprecation_badge(&service.options())
+        ));
+
+        out.push_str("<h3>Definition</h3>\n<pre class=\"proto\"><code>");
+        out.push_str(&self.service_definition(service));
+        out.push_str("</code></pre>\n");
+
+        out.push_str("<h3>Methods</h3>\n<ul class=\"methods\">\n");
+        for method in service.methods() {
+            self.index("method", service.package_name(), method.full_name());
+
+            out.push_str(&format!(
+                "<li id=\"{}\"><code>{}</code>: {} &rarr; {}{}</li>\n",
+                escape(method.full_name()),
+                escape(method.name()),
+                self.method_type(&method.input(), method.is_client_streaming()),
+                self.method_type(&method.output(), method.is_server_streaming()),
+                deprecation_badge(&method.options())
+            ));
+        }
+        out.push_str("</ul>\n</section>\n");
+    }
+
+    fn write_message(&mut self, out: &mut String, message: &MessageDescriptor) {
+        self.index("message", message.package_name(), message.full_name());
+
+        out.push_str(&format!(
+            "<section id=\"{}\">\n<h2>{}{}</h2>\n",
+            escape(message.full_name()),
+            escape(message.name()),
+            deprecation_badge(&message.options())
+        ));
+
+        out.push_str("<pre class=\"proto\"><code>");
+        out.push_str(&self.message_definition(message));
+        out.push_str("</code></pre>\n");
+
+        for nested in nested_messages(message) {
+            out.push_str(&format!(
+                "<details class=\"nested\">\n<summary>message {}</summary>\n",
+                escape(nested.name())
+            ));
+            self.write_message(out, &nested);
+            out.push_str("</details>\n");
+        }
+
+        for nested in message.child_enums() {
+            out.push_str(&format!(
+                "<details class=\"nested\">\n<summary>enum {}</summary>\n",
+                escape(nested.name())
+            ));
+            self.write_enum(out, &nested);
+            out.push_str("</details>\n");
+        }
+
+        out.push_str("</section>\n");
+    }
+
+    fn write_enum(&mut self, out: &mut String, enum_desc: &EnumDescriptor) {
+        self.index("enum", enum_desc.package_name(), enum_desc.full_name());
+
+        out.push_str(&format!(
+            "<section id=\"{}\">\n<h2>{}{}</h2>\n",
+            escape(enum_desc.full_name()),
+            escape(enum_desc.name()),
+            deprecation_badge(&enum_desc.options())
+        ));
+
+        out.push_str("<pre class=\"proto\"><code>");
+        out.push_str(&enum_definition(enum_desc));
+        out.push_str("</code></pre>\n</section>\n");
+    }
+
+    fn index(&mut self, kind: &'static str, package: &str, full_name: &str) {
+        self.search_index.push(SearchEntry {
+            kind,
+            name: full_name.to_string(),
+            url: format!("{package}.html#{full_name}"),
+        });
+    }
+
+    fn service_definition(&self, service: &ServiceDescriptor) -> String {
+        let mut out = format!(
+            "{} {} {{\n",
+            keyword("service"),
+            declaration_html(service.name(), &service.options())
+        );
+
+        let options = option_statements(&service.options(), "  ");
+        if !options.is_empty() {
+            out.push_str(&options);
+            out.push('\n');
+        }
+
+        for method in service.methods() {
+            out.push_str(&format!("  {}\n", self.method_definition(&method)));
+        }
+
+        out.push('}');
+        out
+    }
+
+    fn method_definition(&self, method: &MethodDescriptor) -> String {
+        let stream = |streaming: bool| {
+            if streaming {
+                format!("{} ", keyword("stream"))
+            } else {
+                String::new()
+            }
+        };
+
+        let signature = format!(
+            "{} {}({}{}) {} ({}{})",
+            keyword("rpc"),
+            declaration_html(method.name(), &method.options()),
+            stream(method.is_client_streaming()),
+            self.message_link(&method.input()),
+            keyword("returns"),
+            stream(method.is_server_streaming()),
+            self.message_link(&method.output())
+        );
+
+        // Methods are indented in their service
+        let options = option_statements(&method.options(), "    ");
+
+        if options.is_empty() {
+            format!("{signature};")
+        } else {
+            format!("{signature} {{\n{options}  }}")
+        }
+    }
+
+    fn message_definition(&self, message: &MessageDescriptor) -> String {
+        let mut out = format!(
+            "{} {} {{\n",
+            keyword("message"),
+            declaration_html(message.name(), &message.options())
+        );
+        out.push_str(&option_statements(&message.options(), "  "));
+
+        for member in message_members(message) {
+            match member {
+                MessageMember::Field(field) => {
+                    out.push_str(&format!("  {}\n", self.field_definition(&field)));
+                }
+                MessageMember::Oneof(oneof) => {
+                    out.push_str(&format!(
+                        "  {} {} {{\n",
+                        keyword("oneof"),
+                        name(oneof.name())
+                    ));
+                    out.push_str(&option_statements(&oneof.options(), "    "));
+                    for field in oneof.fields() {
+                        out.push_str(&format!("    {}\n", self.field_definition(&field)));
+                    }
+                    out.push_str("  }\n");
+                }
+            }
+        }
+
+        out.push('}');
+        out
+    }
+
+    fn field_definition(&self, field: &FieldDescriptor) -> String {
+        let label = match field_label(field) {
+            "" => String::new(),
+            label => format!("{} ", keyword(label)),
+        };
+
+        format!(
+            "{}{} {} = {}{};",
+            label,
+            self.field_type(field),
+            member_html(field.name(), &field.options()),
+            number(field.number()),
+            option_list(&field.options(), field_default(field))
+        )
+    }
+
+    fn field_type(&self, field: &FieldDescriptor) -> String {
+        match field.kind() {
+            Kind::Message(entry) if field.is_map() => format!(
+                "{}&lt;{}, {}&gt;",
+                keyword("map"),
+                self.kind(entry.map_entry_key_field().kind()),
+                self.kind(entry.map_entry_value_field().kind())
+            ),
+            kind => self.kind(kind),
+        }
+    }
+
+    fn kind(&self, kind: Kind) -> String {
+        match kind {
+            Kind::Message(m) => self.message_link(&m),
+            Kind::Enum(e) => self.type_link(e.package_name(), e.full_name()),
+            scalar => format!("<span class=\"scalar\">{}</span>", kind_name(&scalar)),
+        }
+    }
+
+    fn message_link(&self, message: &MessageDescriptor) -> String {
+        self.type_link(message.package_name(), message.full_name())
+    }
+
+    fn type_link(&self, package: &str, full_name: &str) -> String {
+        if self.packages.contains(package) {
+            format!(
+                "<a href=\"{}.html#{}\">{}</a>",
+                escape(package),
+                escape(full_name),
+                escape(full_name)
+            )
+        } else {
+            escape(full_name)
+        }
+    }
+
+    fn method_type(&self, message: &MessageDescriptor, streaming: bool) -> String {
+        let link = self.message_link(message);
+
+        if streaming {
+            format!("<span class=\"stream\">stream</span> {link}")
+        } else {
+            link
+        }
+    }
+}
+
+fn enum_definition(enum_desc: &EnumDescriptor) -> String {
+    let mut out = format!(
+        "{} {} {{\n",
+        keyword("enum"),
+        declaration_html(enum_desc.name(), &enum_desc.options())
+    );
+    out.push_str(&option_statements(&enum_desc.options(), "  "));
+
+    for value in enum_values(enum_desc) {
+        out.push_str(&format!(
+            "  {} = {}{};\n",
+            member_html(value.name(), &value.options()),
+            number(value.number()),
+            option_list(&value.options(), None)
+        ));
+    }
+
+    out.push('}');
+    out
+}
+
+fn index_content(services: &[ServiceDescriptor], package_names: &[String]) -> String {
+    let mut out = String::from("<h1>Documentation Index</h1>\n");
+
+    out.push_str("<h2>Services</h2>\n<ul>\n");
+    for service in services {
+        out.push_str(&format!(
+            "<li><a href=\"{}.html#{}\"><strong>{}</strong></a></li>\n",
+            escape(service.package_name()),
+            escape(service.full_name()),
+            escape(service.full_name())
+        ));
+    }
+    out.push_str("</ul>\n");
+
+    out.push_str("<h2>Packages</h2>\n<ul>\n");
+    for package in package_names {
+        out.push_str(&format!(
+            "<li><a href=\"{0}.html\">{0}</a></li>\n",
+            escape(package)
+        ));
+    }
+    out.push_str("</ul>\n");
+
+    out
+}
+
+/// A node of the package tree, one per segment of the package names.
+#[derive(Default)]
+struct PackageTree {
+    /// The full name of the package ending at this segment, if it has a page.
+    package: Option<String>,
+    children: BTreeMap<String, PackageTree>,
+}
+
+impl PackageTree {
+    fn new(package_names: &[String]) -> Self {
+        let mut root = PackageTree::default();
+
+        for package in package_names {
+            let node = package.split('.').fold(&mut root, |node, segment| {
+                node.children.entry(segment.to_string()).or_default()
+            });
+            node.package = Some(package.clone());
+        }
+
+        root
+    }
+
+    fn render(&self, out: &mut String, current: Option<&str>) {
+        for (segment, node) in &self.children {
+            out.push_str("<li>");
+
+            match &node.package {
+                Some(package) => {
+                    let class = if current == Some(package.as_str()) {
+                        " class=\"active\""
+                    } else {
+                        ""
+                    };
+                    out.push_str(&format!(
+                        "<a href=\"{}.html\"{}>{}</a>",
+                        escape(package),
+                        class,
+                        escape(segment)
+                    ));
+                }
+                None => out.push_str(&escape(segment)),
+            }
+
+            if !node.children.is_empty() {
+                out.push_str("\n<ul>\n");
+                node.render(out, current);
+                out.push_str("</ul>\n");
+            }
+
+            out.push_str("</li>\n");
+        }
+    }
+}
+
+fn sidebar(package_names: &[String], current: Option<&str>) -> String {
+    let mut out = String::from(
+        "<a class=\"home\" href=\"index.html\">Documentation</a>\n\
+         <input id=\"search\" type=\"search\" placeholder=\"Search types and methods\" autocomplete=\"off\">\n\
+         <ul id=\"search-results\"></ul>\n\
+         <h2>Packages</h2>\n<ul class=\"tree\">\n",
+    );
+
+    PackageTree::new(package_names).render(&mut out, current);
+
+    out.push_str("</ul>\n");
+    out
+}
+
+fn page(title: &str, sidebar: &str, content: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{}</title>
+<link rel="stylesheet" href="style.css">
+</head>
+<body>
+<nav class="sidebar">
+{sidebar}</nav>
+<main>
+{content}</main>
+<script src="search-index.js"></script>
+<script src="script.js"></script>
+</body>
+</html>
+"#,
+        escape(title)
+    )
+}
+
+fn keyword(keyword: &str) -> String {
+    format!("<span class=\"kw\">{keyword}</span>")
+}
+
+fn name(name: &str) -> String {
+    format!("<span class=\"name\">{}</span>", escape(name))
+}
+
+/// Renders the name of a service, method, message or enum as a `name` span, wrapped in a
+/// `deprecated` span (struck through by the stylesheet) when its options deprecate it.
+fn declaration_html(declared: &str, options: &DynamicMessage) -> String {
+    if options::is_deprecated(options) {
+        format!("<span class=\"deprecated\">{}</span>", name(declared))
+    } else {
+        name(declared)
+    }
+}
+
+/// Renders the name of a field or enum value as plain escaped text, wrapped in a `deprecated`
+/// span when its options deprecate it.
+fn member_html(field: &str, options: &DynamicMessage) -> String {
+    if options::is_deprecated(options) {
+        format!("<span class=\"deprecated\">{}</span>", escape(field))
+    } else {
+        escape(field)
+    }
+}
+
+fn deprecation_badge(options: &DynamicMessage) -> &'static str {
+    if options::is_deprecated(options) {
+        "<span class=\"badge\">deprecated</span>"
+    } else {
+        ""
+    }
+}
+
+/// Renders options as `option` statements, one per line.
+fn option_statements(options: &DynamicMessage, indent: &str) -> String {
+    options::decode(options)
+        .iter()
+        .map(|option| {
+            format!(
+                "{indent}{} {};\n",
+                keyword("option"),
+                escape(&option.to_string())
+            )
+        })
+        .collect()
+}
+
+/// Renders the options of a field or enum value as a bracketed list, after the default value of a proto2 field.
+fn option_list(options: &DynamicMessage, default: Option<String>) -> String {
+    let options: Vec<_> = default
+        .map(|default| format!("default = {default}"))
+        .into_iter()
+        .chain(options::decode(options).iter().map(ToString::to_string))
+        .map(|option| escape(&option))
+        .collect();
+
+    if options.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", options.join(", "))
+    }
+}
+
+fn number(number: impl std::fmt::Display) -> String {
+    format!("<span class=\"num\">{number}</span>")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    fn compile_service(proto: &str, service: &str) -> ServiceDescriptor {
+        let file_descriptor_set = compiler::compile_protos(&[("test.proto", proto)]);
+        DescriptorPool::from_file_descriptor_set(file_descriptor_set)
+            .expect("Failed to decode descriptor pool")
+            .get_service_by_name(service)
+            .expect("Service not found")
+    }
+
+    #[test]
+    fn test_generate_site() {
+        let proto = r#"
+            syntax = "proto3";
+            package shop.v1;
+
+            message Order {
+                message Line {
+                    string sku = 1;
+                    Status status = 2;
+                }
+
+                enum Status {
+                    UNKNOWN = 0;
+                    SHIPPED = 1;
+                }
+
+                repeated Line lines = 1;
+                map<string, int32> quantities = 2;
+            }
+
+            service Orders {
+                rpc Get(Order) returns (stream Order);
+            }
+        "#;
+
+        let service = compile_service(proto, "shop.v1.Orders");
+        let dir = tempfile::tempdir().unwrap();
+
+        generate(dir.path().to_path_buf(), Packages::from(service)).unwrap();
+
+        for file in ["index.html", "style.css", "script.js"] {
+            assert!(dir.path().join(file).exists(), "{file} missing");
+        }
+
+        let page = fs::read_to_string(dir.path().join("shop.v1.html")).unwrap();
+
+        // Field types link to their definitions, nested types are collapsible under their parent
+        assert!(
+            page.contains(
+                r#"<a href="shop.v1.html#shop.v1.Order.Line">shop.v1.Order.Line</a> lines"#
+            )
+        );
+        assert!(page.contains(
+            r#"<details class="nested">
+<summary>message Line</summary>
+<section id="shop.v1.Order.Line">"#
+        ));
+        assert!(page.contains(r#"<summary>enum Status</summary>"#));
+        assert_eq!(
+            page.matches(r#"<section id="shop.v1.Order.Line">"#).count(),
+            1
+        );
+        assert!(page.contains(r#"<span class="kw">map</span>&lt;<span class="scalar">string</span>, <span class="scalar">int32</span>&gt; quantities"#));
+        assert!(page.contains(r#"<span class="stream">stream</span>"#));
+
+        // The package tree nests `v1` under `shop`
+        assert!(
+            page.contains(
+                "<li>shop\n<ul>\n<li><a href=\"shop.v1.html\" class=\"active\">v1</a></li>"
+            )
+        );
+
+        let search_index = fs::read_to_string(dir.path().join("search-index.js")).unwrap();
+        assert!(search_index.contains(
+            r#"{"kind":"method","name":"shop.v1.Orders.Get","url":"shop.v1.html#shop.v1.Orders.Get"}"#
+        ));
+        assert!(search_index.contains(
+            r#"{"kind":"enum","name":"shop.v1.Order.Status","url":"shop.v1.html#shop.v1.Order.Status"}"#
+        ));
+    }
+
+    #[test]
+    fn test_deprecated_elements() {
+        let proto = r#"
+            syntax = "proto3";
+            package shop.v1;
+
+            message Order {
+                option deprecated = true;
+
+                string id = 1;
+                string note = 2 [deprecated = true];
+            }
+
+            service Orders {
+                rpc Get(Order) returns (Order) {
+                    option deprecated = true;
+                }
+            }
+        "#;
+
+        let service = compile_service(proto, "shop.v1.Orders");
+        let dir = tempfile::tempdir().unwrap();
+
+        generate(dir.path().to_path_buf(), Packages::from(service)).unwrap();
+
+        let page = fs::read_to_string(dir.path().join("shop.v1.html")).unwrap();
+
+        // Options are part of the definitions, deprecated elements are struck through and badged
+        assert!(page.contains(
+            r#"<span class="kw">rpc</span> <span class="deprecated"><span class="name">Get</span></span>"#
+        ));
+        assert!(page.contains("{\n    <span class=\"kw\">option</span> deprecated = true;\n  }"));
+        assert!(page.contains(r#"<h2>Order<span class="badge">deprecated</span></h2>"#));
+        assert!(page.contains(
+            r#"<span class="deprecated">note</span> = <span class="num">2</span> [deprecated = true];"#
+        ));
+        assert!(
+            page.contains(r#"<span class="scalar">string</span> id = <span class="num">1</span>;"#)
+        );
+    }
+
+    #[test]
+    fn test_message_definition() {
+        let proto = r#"
+            syntax = "proto2";
+            package shop.v1;
+
+            message Order {
+                required string id = 2;
+                optional string note = 1 [default = "<none>"];
+                oneof payment {
+                    string card = 3;
+                    string voucher = 4;
+                }
+            }
+
+            service Orders {
+                rpc Get(Order) returns (Order);
+            }
+        "#;
+
+        let service = compile_service(proto, "shop.v1.Orders");
+        let dir = tempfile::tempdir().unwrap();
+
+        generate(dir.path().to_path_buf(), Packages::from(service)).unwrap();
+
+        let page = fs::read_to_string(dir.path().join("shop.v1.html")).unwrap();
+
+        // Fields are declared like `describe` does: in source order, with their labels, defaults and oneofs
+        let string = r#"<span class="scalar">string</span>"#;
+        let definition = format!(
+            "{{\n  <span class=\"kw\">required</span> {string} id = <span class=\"num\">2</span>;\n  \
+             <span class=\"kw\">optional</span> {string} note = <span class=\"num\">1</span> \
+             [default = &quot;&lt;none&gt;&quot;];\n  \
+             <span class=\"kw\">oneof</span> <span class=\"name\">payment</span> {{\n    \
+             {string} card = <span class=\"num\">3</span>;\n    \
+             {string} voucher = <span class=\"num\">4</span>;\n  }}\n}}"
+        );
+        assert!(page.contains(&definition), "{page}");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape(r#"<a href="x">&</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+}
@@ -0,0 +1,52 @@
+// Filters the entries of `search-index.js` as the search box is typed in.
+(function () {
+  const input = document.getElementById("search");
+  const results = document.getElementById("search-results");
+  const index = window.SEARCH_INDEX || [];
+
+  input.addEventListener("input", function () {
+    const query = input.value.trim().toLowerCase();
+    results.replaceChildren();
+
+    if (!query) {
+      return;
+    }
+
+    index
+      .filter((entry) => entry.name.toLowerCase().includes(query))
+      .slice(0, 50)
+      .forEach((entry) => {
+        const kind = document.createElement("span");
+        kind.className = "kind";
+        kind.textContent = entry.kind;
+
+        const link = document.createElement("a");
+        link.href = entry.url;
+        link.textContent = entry.name;
+
+        const item = document.createElement("li");
+        item.append(kind, link);
+        results.append(item);
+      });
+  });
+
+  // Nested types are collapsed, so their ancestors are opened when they are linked to
+  function openTarget() {
+    const id = decodeURIComponent(window.location.hash.slice(1));
+    const target = id && document.getElementById(id);
+
+    if (!target) {
+      return;
+    }
+
+    for (let node = target; node; node = node.parentElement) {
+      if (node.tagName === "DETAILS") {
+        node.open = true;
+      }
+    }
+    target.scrollIntoView();
+  }
+
+  window.addEventListener("hashchange", openTarget);
+  openTarget();
+})();
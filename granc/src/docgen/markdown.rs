@@ -1,10 +1,19 @@
+use super::example;
+use super::graph::Graph;
+use super::model::FieldModel;
 use super::package::{Package, Packages};
 use crate::formatter::FormattedString;
-use granc_core::prost_reflect::{EnumDescriptor, Kind, MessageDescriptor, ServiceDescriptor};
+use crate::options;
+use granc_core::prost_reflect::{
+    DynamicMessage, EnumDescriptor, MessageDescriptor, MethodDescriptor, ServiceDescriptor,
+};
 use std::fs;
 use std::path::PathBuf;
 
-pub fn generate(output_dir: PathBuf, service: ServiceDescriptor) -> std::io::Result<()> {
+/// Generates one Markdown file per package, and an index.
+///
+/// With `graph`, every service is documented along with a Mermaid diagram of its dependencies.
+pub fn generate(output_dir: PathBuf, packages: Packages, graph: bool) -> std::io::Result<()> {
     // Disable colors for plain text generation
     colored::control::set_override(false);
 
@@ -12,20 +21,18 @@ pub fn generate(output_dir: PathBuf, service: ServiceDescriptor) -> std::io::Res
         fs::create_dir_all(&output_dir)?;
     }
 
-    let packages = Packages::from(service.clone());
-
     for package in packages.values() {
         let filename = format!("{}.md", package.name);
         let path = output_dir.join(&filename);
 
-        let out = generate_package_file(package)?;
+        let out = generate_package_file(package, graph)?;
 
         fs::write(path, out)?;
         println!("Generated: {}", filename);
     }
 
     let path = output_dir.join("index.md");
-    let out = generate_index(&service, &packages)?;
+    let out = generate_index(&packages)?;
     fs::write(path, out)?;
     println!("Generated: index.md");
 
@@ -34,19 +41,27 @@ pub fn generate(output_dir: PathBuf, service: ServiceDescriptor) -> std::io::Res
     Ok(())
 }
 
-fn generate_index(
-    entry_service: &ServiceDescriptor,
-    packages: &Packages,
-) -> std::io::Result<String> {
+fn generate_index(packages: &Packages) -> std::io::Result<String> {
     let mut out = String::new();
 
     out.push_str("# Documentation Index\n\n\n");
 
-    let svc_package = entry_service.package_name();
-    let svc_link = format!("{}.md#{}", svc_package, entry_service.name());
+    let services = packages.services();
+
+    out.push_str(if services.len() == 1 {
+        "## Service\n\n"
+    } else {
+        "## Services\n\n"
+    });
 
-    out.push_str("## Service\n\n");
-    out.push_str(&format!("- [**{}**]({})\n", entry_service.name(), svc_link));
+    if services.is_empty() {
+        out.push_str("*None*\n");
+    }
+
+    for service in services {
+        let svc_link = resolve_link(service.package_name(), service.name());
+        out.push_str(&format!("- [**{}**]({})\n", service.full_name(), svc_link));
+    }
 
     out.push_str("\n## Packages\n\n");
 
@@ -65,7 +80,7 @@ fn generate_index(
     Ok(out)
 }
 
-fn generate_package_file(package: &Package) -> std::io::Result<String> {
+fn generate_package_file(package: &Package, graph: bool) -> std::io::Result<String> {
     let mut out = String::new();
 
     let mut services = package.services.clone();
@@ -74,7 +89,8 @@ fn generate_package_file(package: &Package) -> std::io::Result<String> {
     for service in services {
         write_anchor(&mut out, service.name());
         out.push_str(&format!("## {}\n\n", service.name()));
-        write_service_content(&mut out, &service);
+        write_deprecation(&mut out, &service.options());
+        write_service_content(&mut out, &service, graph)?;
         out.push_str("---\n\n");
     }
 
@@ -84,6 +100,7 @@ fn generate_package_file(package: &Package) -> std::io::Result<String> {
     for message in messages {
         write_anchor(&mut out, message.name());
         out.push_str(&format!("## {}\n\n", message.name()));
+        write_deprecation(&mut out, &message.options());
         write_message_content(&mut out, &message);
         out.push_str("---\n\n");
     }
@@ -94,6 +111,7 @@ fn generate_package_file(package: &Package) -> std::io::Result<String> {
     for enum_desc in enums {
         write_anchor(&mut out, enum_desc.name());
         out.push_str(&format!("## {}\n\n", enum_desc.name()));
+        write_deprecation(&mut out, &enum_desc.options());
         write_enum_content(&mut out, &enum_desc);
         out.push_str("---\n\n");
     }
@@ -105,15 +123,32 @@ fn write_anchor(out: &mut String, name: &str) {
     out.push_str(&format!("<a id=\"{name}\"></a>\n"));
 }
 
-fn write_service_content(out: &mut String, service: &ServiceDescriptor) {
+fn write_deprecation(out: &mut String, options: &DynamicMessage) {
+    if options::is_deprecated(options) {
+        out.push_str("> **Deprecated**\n\n");
+    }
+}
+
+fn write_service_content(
+    out: &mut String,
+    service: &ServiceDescriptor,
+    graph: bool,
+) -> std::io::Result<()> {
     out.push_str("### Definition\n\n```protobuf\n");
     out.push_str(&format!("package {};\n\n", service.package_name()));
     out.push_str(&FormattedString::from(service.clone()).0);
     out.push_str("\n```\n\n");
 
+    if graph {
+        out.push_str("### Diagram\n\n```mermaid\n");
+        out.push_str(&Graph::from(&Packages::from(service.clone())).mermaid());
+        out.push_str("```\n\n");
+    }
+
     out.push_str("### Methods\n\n");
     for method in service.methods() {
         out.push_str(&format!("#### `{}`\n\n", method.name()));
+        write_deprecation(out, &method.options());
 
         let input = method.input();
         let output = method.output();
@@ -127,8 +162,46 @@ fn write_service_content(out: &mut String, service: &ServiceDescriptor) {
             output.name(),
             output_link
         ));
+
+        // Options are shown in the definition of the service
         out.push('\n');
+
+        write_method_examples(out, &method)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a `granc call` sending an example request to the method, and an example response.
+fn write_method_examples(out: &mut String, method: &MethodDescriptor) -> std::io::Result<()> {
+    let request = example::to_string_pretty(&method.input()).map_err(std::io::Error::other)?;
+    let response = example::to_string_pretty(&method.output()).map_err(std::io::Error::other)?;
+
+    // Client streaming methods take an array of messages
+    let body = if method.is_client_streaming() {
+        format!("[\n  {}\n]", request.replace('\n', "\n  "))
+    } else {
+        request
+    };
+
+    out.push_str("Example request:\n\n```bash\n");
+    out.push_str(&format!(
+        "granc call {}/{} --uri http://localhost:50051 --body '{}'\n",
+        method.parent_service().full_name(),
+        method.name(),
+        body
+    ));
+    out.push_str("```\n\n");
+
+    if method.is_server_streaming() {
+        out.push_str("Example response (each message of the stream):\n\n```json\n");
+    } else {
+        out.push_str("Example response:\n\n```json\n");
     }
+    out.push_str(&response);
+    out.push_str("\n```\n\n");
+
+    Ok(())
 }
 
 fn write_message_content(out: &mut String, message: &MessageDescriptor) {
@@ -137,37 +210,35 @@ fn write_message_content(out: &mut String, message: &MessageDescriptor) {
     out.push_str(&FormattedString::from(message.clone()).0);
     out.push_str("\n```\n\n");
 
-    out.push_str("### Dependencies\n\n");
-    let mut has_deps = false;
-
-    for field in message.fields() {
-        match field.kind() {
-            Kind::Message(m) => {
-                has_deps = true;
-                let link = resolve_link(m.package_name(), m.name());
-                out.push_str(&format!(
-                    "- Field `{}`: [{}]({})\n",
-                    field.name(),
-                    m.name(),
-                    link
-                ));
-            }
-            Kind::Enum(e) => {
-                has_deps = true;
-                let link = resolve_link(e.package_name(), e.name());
-                out.push_str(&format!(
-                    "- Field `{}`: [{}]({})\n",
-                    field.name(),
-                    e.name(),
-                    link
-                ));
-            }
-            _ => {}
-        }
+    let fields: Vec<_> = message.fields().map(|f| FieldModel::from(&f)).collect();
+
+    if fields.is_empty() {
+        return;
     }
 
-    if !has_deps {
-        out.push_str("*None*\n");
+    out.push_str("### Fields\n\n");
+    out.push_str("| Field | JSON | Type | Label | Number | Deprecated | Description |\n");
+    out.push_str("| --- | --- | --- | --- | --- | --- | --- |\n");
+
+    for field in fields {
+        // Messages and enums link to their definition, map types are written in full
+        let type_name = match &field.type_ref {
+            Some(t) if t.full_name == field.type_name => {
+                format!("[{}]({})", t.name, resolve_link(&t.package, &t.name))
+            }
+            _ => format!("`{}`", field.type_name),
+        };
+
+        out.push_str(&format!(
+            "| `{}` | `{}` | {} | {} | {} | {} | {} |\n",
+            field.name,
+            field.json_name,
+            type_name,
+            field.label,
+            field.number,
+            if field.deprecated { "Yes" } else { "" },
+            table_cell(field.comments.as_deref().unwrap_or_default())
+        ));
     }
     out.push('\n');
 }
@@ -179,7 +250,75 @@ fn write_enum_content(out: &mut String, enum_desc: &EnumDescriptor) {
     out.push_str("\n```\n\n");
 }
 
+/// Fits a text in a single table cell.
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
 fn resolve_link(package: &str, name: &str) -> String {
     // Always link to local file + anchor
     format!("{}.md#{}", package, name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    #[test]
+    fn test_generate() {
+        let proto = r#"
+            syntax = "proto3";
+            package shop.v1;
+
+            message Order {
+                // The lines | one per item
+                repeated Line lines = 1;
+                map<string, int32> quantities = 2;
+                string note = 3 [deprecated = true];
+            }
+
+            message Line {
+                string sku = 1;
+            }
+
+            service Orders {
+                rpc Get(Order) returns (Order) {
+                    option deprecated = true;
+                }
+            }
+        "#;
+
+        let file_descriptor_set = compiler::compile_protos(&[("test.proto", proto)]);
+        let service = DescriptorPool::from_file_descriptor_set(file_descriptor_set)
+            .unwrap()
+            .get_service_by_name("shop.v1.Orders")
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        generate(dir.path().to_path_buf(), Packages::from(service), false).unwrap();
+
+        let index = fs::read_to_string(dir.path().join("index.md")).unwrap();
+        assert!(index.contains("- [**shop.v1.Orders**](shop.v1.md#Orders)\n"));
+
+        let page = fs::read_to_string(dir.path().join("shop.v1.md")).unwrap();
+
+        // Message types link to their definition, the comments fit in a single cell
+        assert!(page.contains(
+            "| `lines` | `lines` | [Line](shop.v1.md#Line) | repeated | 1 |  | The lines \\| one per item |\n"
+        ));
+        assert!(page.contains("| `note` | `note` | `string` |  | 3 | Yes |  |\n"));
+
+        // Map fields are written in full, without a section for their entry type
+        assert!(
+            page.contains("| `quantities` | `quantities` | `map<string, int32>` |  | 2 |  |  |\n")
+        );
+        assert!(!page.contains("QuantitiesEntry"));
+
+        assert!(page.contains("#### `Get`\n\n> **Deprecated**\n\n"));
+        assert!(page.contains(
+            "granc call shop.v1.Orders/Get --uri http://localhost:50051 --body '{\n  \"lines\": ["
+        ));
+    }
+}
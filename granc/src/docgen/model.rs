@@ -0,0 +1,330 @@
+//! # Model
+//!
+//! A serializable view of [`Packages`], handed to user-provided templates.
+//!
+//! Every element carries its fully qualified name, the comments written above it in the `.proto` file
+//! (when the descriptors include source info, e.g. `protoc --include_source_info`), and its options as JSON,
+//! extensions included (e.g. `{"deprecated": true, "[google.api.http]": {"get": "/v1/books"}}`).
+use super::graph::Graph;
+use super::package::{Package, Packages};
+use crate::formatter::{field_label, field_type_name};
+use crate::options::is_deprecated;
+use granc_core::prost_reflect::{
+    DynamicMessage, EnumDescriptor, EnumValueDescriptor, FieldDescriptor, FileDescriptor, Kind,
+    MessageDescriptor, MethodDescriptor, SerializeOptions, ServiceDescriptor,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Serialize, Clone)]
+pub(crate) struct PackagesModel {
+    /// Every package, sorted by name.
+    pub packages: Vec<PackageModel>,
+    /// Every service of the packages, sorted by full name.
+    pub services: Vec<ServiceModel>,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct PackageModel {
+    pub name: String,
+    pub services: Vec<ServiceModel>,
+    pub messages: Vec<MessageModel>,
+    pub enums: Vec<EnumModel>,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct ServiceModel {
+    pub name: String,
+    pub full_name: String,
+    pub package: String,
+    pub comments: Option<String>,
+    pub options: Value,
+    pub deprecated: bool,
+    pub methods: Vec<MethodModel>,
+    /// A Mermaid diagram of the service and its dependencies.
+    pub graph: String,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct MethodModel {
+    pub name: String,
+    pub full_name: String,
+    pub input: TypeModel,
+    pub output: TypeModel,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+    pub comments: Option<String>,
+    pub options: Value,
+    pub deprecated: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct MessageModel {
+    pub name: String,
+    pub full_name: String,
+    pub package: String,
+    pub comments: Option<String>,
+    pub options: Value,
+    pub deprecated: bool,
+    pub fields: Vec<FieldModel>,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct FieldModel {
+    pub name: String,
+    pub json_name: String,
+    pub number: u32,
+    /// `repeated`, `optional`, `required` or empty.
+    pub label: &'static str,
+    /// The type as written in a `.proto` file (e.g. `int32`, `my.package.Message`, `map<string, int32>`).
+    pub type_name: String,
+    /// The message or enum of the field (the value of map fields), if not a scalar.
+    pub type_ref: Option<TypeModel>,
+    /// The `oneof` the field belongs to, if any.
+    pub oneof: Option<String>,
+    pub comments: Option<String>,
+    pub options: Value,
+    pub deprecated: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct EnumModel {
+    pub name: String,
+    pub full_name: String,
+    pub package: String,
+    pub comments: Option<String>,
+    pub options: Value,
+    pub deprecated: bool,
+    pub values: Vec<EnumValueModel>,
+}
+
+#[derive(Serialize, Clone)]
+pub(crate) struct EnumValueModel {
+    pub name: String,
+    pub number: i32,
+    pub comments: Option<String>,
+    pub options: Value,
+    pub deprecated: bool,
+}
+
+/// A reference to a message or enum, with what is needed to link to its documentation.
+#[derive(Serialize, Clone)]
+pub(crate) struct TypeModel {
+    pub name: String,
+    pub full_name: String,
+    pub package: String,
+}
+
+impl From<&Packages> for PackagesModel {
+    fn from(packages: &Packages) -> Self {
+        let mut packages: Vec<_> = packages.values().map(PackageModel::from).collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // Services are listed both under their package and at the top level
+        let mut services: Vec<_> = packages
+            .iter()
+            .flat_map(|p| p.services.iter().cloned())
+            .collect();
+        services.sort_by(|a, b| a.full_name.cmp(&b.full_name));
+
+        PackagesModel { packages, services }
+    }
+}
+
+impl From<&Package> for PackageModel {
+    fn from(package: &Package) -> Self {
+        let mut services: Vec<_> = package.services.iter().map(ServiceModel::from).collect();
+        services.sort_by(|a, b| a.full_name.cmp(&b.full_name));
+
+        let mut messages: Vec<_> = package.messages.iter().map(MessageModel::from).collect();
+        messages.sort_by(|a, b| a.full_name.cmp(&b.full_name));
+
+        let mut enums: Vec<_> = package.enums.iter().map(EnumModel::from).collect();
+        enums.sort_by(|a, b| a.full_name.cmp(&b.full_name));
+
+        PackageModel {
+            name: package.name.clone(),
+            services,
+            messages,
+            enums,
+        }
+    }
+}
+
+impl From<&ServiceDescriptor> for ServiceModel {
+    fn from(service: &ServiceDescriptor) -> Self {
+        let options = options(&service.options());
+
+        ServiceModel {
+            name: service.name().to_string(),
+            full_name: service.full_name().to_string(),
+            package: service.package_name().to_string(),
+            comments: comments(&service.parent_file(), service.path()),
+            deprecated: is_deprecated(&service.options()),
+            options,
+            methods: service.methods().map(|m| MethodModel::from(&m)).collect(),
+            graph: Graph::from(&Packages::from(service.clone())).mermaid(),
+        }
+    }
+}
+
+impl From<&MethodDescriptor> for MethodModel {
+    fn from(method: &MethodDescriptor) -> Self {
+        let options = options(&method.options());
+
+        MethodModel {
+            name: method.name().to_string(),
+            full_name: method.full_name().to_string(),
+            input: TypeModel::from(&method.input()),
+            output: TypeModel::from(&method.output()),
+            client_streaming: method.is_client_streaming(),
+            server_streaming: method.is_server_streaming(),
+            comments: comments(&method.parent_file(), method.path()),
+            deprecated: is_deprecated(&method.options()),
+            options,
+        }
+    }
+}
+
+impl From<&MessageDescriptor> for MessageModel {
+    fn from(message: &MessageDescriptor) -> Self {
+        let options = options(&message.options());
+
+        MessageModel {
+            name: message.name().to_string(),
+            full_name: message.full_name().to_string(),
+            package: message.package_name().to_string(),
+            comments: comments(&message.parent_file(), message.path()),
+            deprecated: is_deprecated(&message.options()),
+            options,
+            fields: message.fields().map(|f| FieldModel::from(&f)).collect(),
+        }
+    }
+}
+
+impl From<&FieldDescriptor> for FieldModel {
+    fn from(field: &FieldDescriptor) -> Self {
+        let options = options(&field.options());
+
+        // Map fields are typed by the value of their entry
+        let kind = match field.kind() {
+            Kind::Message(entry) if field.is_map() => entry.map_entry_value_field().kind(),
+            kind => kind,
+        };
+
+        let type_ref = match &kind {
+            Kind::Message(m) => Some(TypeModel::from(m)),
+            Kind::Enum(e) => Some(TypeModel::from(e)),
+            _ => None,
+        };
+
+        FieldModel {
+            name: field.name().to_string(),
+            json_name: field.json_name().to_string(),
+            number: field.number(),
+            label: field_label(field),
+            type_name: field_type_name(field),
+            type_ref,
+            oneof: field
+                .containing_oneof()
+                .filter(|oneof| !oneof.is_synthetic())
+                .map(|oneof| oneof.name().to_string()),
+            comments: comments(&field.parent_file(), field.path()),
+            deprecated: is_deprecated(&field.options()),
+            options,
+        }
+    }
+}
+
+impl From<&EnumDescriptor> for EnumModel {
+    fn from(enum_desc: &EnumDescriptor) -> Self {
+        let options = options(&enum_desc.options());
+
+        EnumModel {
+            name: enum_desc.name().to_string(),
+            full_name: enum_desc.full_name().to_string(),
+            package: enum_desc.package_name().to_string(),
+            comments: comments(&enum_desc.parent_file(), enum_desc.path()),
+            deprecated: is_deprecated(&enum_desc.options()),
+            options,
+            values: enum_desc
+                .values()
+                .map(|v| EnumValueModel::from(&v))
+                .collect(),
+        }
+    }
+}
+
+impl From<&EnumValueDescriptor> for EnumValueModel {
+    fn from(value: &EnumValueDescriptor) -> Self {
+        let options = options(&value.options());
+
+        EnumValueModel {
+            name: value.name().to_string(),
+            number: value.number(),
+            comments: comments(&value.parent_file(), value.path()),
+            deprecated: is_deprecated(&value.options()),
+            options,
+        }
+    }
+}
+
+impl From<&MessageDescriptor> for TypeModel {
+    fn from(message: &MessageDescriptor) -> Self {
+        TypeModel {
+            name: message.name().to_string(),
+            full_name: message.full_name().to_string(),
+            package: message.package_name().to_string(),
+        }
+    }
+}
+
+impl From<&EnumDescriptor> for TypeModel {
+    fn from(enum_desc: &EnumDescriptor) -> Self {
+        TypeModel {
+            name: enum_desc.name().to_string(),
+            full_name: enum_desc.full_name().to_string(),
+            package: enum_desc.package_name().to_string(),
+        }
+    }
+}
+
+/// Returns the comments written above a descriptor, or trailing on its line, trimmed.
+///
+/// `path` locates the descriptor in its file, as in the `SourceCodeInfo` of the file.
+pub(crate) fn comments(file: &FileDescriptor, path: &[i32]) -> Option<String> {
+    let location = file
+        .file_descriptor_proto()
+        .source_code_info
+        .as_ref()?
+        .location
+        .iter()
+        .find(|location| location.path == path)?;
+
+    let comments: Vec<_> = location
+        .leading_comments
+        .iter()
+        .chain(location.trailing_comments.iter())
+        .map(|comment| {
+            comment
+                .lines()
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim()
+                .to_string()
+        })
+        .filter(|comment| !comment.is_empty())
+        .collect();
+
+    (!comments.is_empty()).then(|| comments.join("\n\n"))
+}
+
+/// Returns the options that are set, as JSON keyed by their proto names (extensions as `[full.name]`).
+fn options(options: &DynamicMessage) -> Value {
+    let serialize_options = SerializeOptions::new().use_proto_field_name(true);
+
+    options
+        .serialize_with_options(serde_json::value::Serializer, &serialize_options)
+        .unwrap_or_default()
+}
@@ -3,10 +3,11 @@
 //! This module defines two types that provide all the information needed to generate documentation about a protobuffer project:
 //!
 //! + [`Package`]: Contains the required data for other modules to be able to generate documentation about a single package.
-//! + [`Packages`]: A collection of packages. It can be constructed from a single Service descriptor.
+//! + [`Packages`]: A collection of packages. It can be constructed from a single descriptor, or from
+//!   a whole descriptor pool.
 use granc_core::{
     client::Descriptor,
-    prost_reflect::{EnumDescriptor, Kind, MessageDescriptor, ServiceDescriptor},
+    prost_reflect::{DescriptorPool, EnumDescriptor, Kind, MessageDescriptor, ServiceDescriptor},
 };
 use std::collections::{HashMap, hash_map::Keys};
 
@@ -49,7 +50,7 @@ impl From<Descriptor> for Package {
 }
 
 /// A collection of protobuffer packages.
-/// It can be constructed from a `ServiceDescriptor`.
+/// It can be constructed from a `Descriptor`, or from a `DescriptorPool` with [`Packages::from_pool`].
 /// Packages are constructed after building a graph of all the descriptor dependencies.
 /// This graph removes duplication of dependencies and ensures the quality of the information provided by each `Package`.
 pub(crate) struct Packages(HashMap<String, Package>);
@@ -62,11 +63,65 @@ impl Packages {
     pub fn names(&self) -> Keys<'_, String, Package> {
         self.0.keys()
     }
+
+    /// Every service of the packages, sorted by full name.
+    pub fn services(&self) -> Vec<ServiceDescriptor> {
+        let mut services: Vec<_> = self
+            .values()
+            .flat_map(|package| package.services.iter().cloned())
+            .collect();
+        services.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+        services
+    }
+
+    /// Collects the services, messages and enums of a descriptor pool, including the ones not reachable
+    /// from any service, along with their dependencies.
+    ///
+    /// Only the packages matching `filters` (a package name, or one of its parents) are collected, although
+    /// their dependencies are always included. Without filters, every package except the well-known types
+    /// of `google.protobuf` is collected.
+    pub fn from_pool(pool: &DescriptorPool, filters: &[String]) -> Self {
+        let selected = |package: &str| {
+            if filters.is_empty() {
+                return package != "google.protobuf";
+            }
+
+            filters.iter().any(|filter| {
+                package == filter
+                    || package
+                        .strip_prefix(filter.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+        };
+
+        let mut descriptors = HashMap::new();
+
+        for service in pool.services().filter(|s| selected(s.package_name())) {
+            descriptors = collect_service_dependencies(descriptors, &service);
+            descriptors.insert(
+                service.full_name().to_string(),
+                Descriptor::ServiceDescriptor(service),
+            );
+        }
+
+        for message in pool.all_messages().filter(|m| selected(m.package_name())) {
+            descriptors = collect_message(descriptors, &message);
+        }
+
+        for enum_desc in pool.all_enums().filter(|e| selected(e.package_name())) {
+            descriptors.insert(
+                enum_desc.full_name().to_string(),
+                Descriptor::EnumDescriptor(enum_desc),
+            );
+        }
+
+        Packages(group_descriptors_by_package(descriptors.into_values()))
+    }
 }
 
 impl From<ServiceDescriptor> for Packages {
     fn from(value: ServiceDescriptor) -> Self {
-        let mut descriptors = collect_service_dependencies(&value);
+        let mut descriptors = collect_service_dependencies(HashMap::new(), &value);
 
         descriptors.insert(
             value.full_name().to_string(),
@@ -78,6 +133,21 @@ impl From<ServiceDescriptor> for Packages {
     }
 }
 
+impl From<Descriptor> for Packages {
+    fn from(value: Descriptor) -> Self {
+        let descriptors = match value {
+            Descriptor::ServiceDescriptor(service) => return Packages::from(service),
+            Descriptor::MessageDescriptor(message) => collect_message(HashMap::new(), &message),
+            Descriptor::EnumDescriptor(enum_desc) => HashMap::from([(
+                enum_desc.full_name().to_string(),
+                Descriptor::EnumDescriptor(enum_desc),
+            )]),
+        };
+
+        Packages(group_descriptors_by_package(descriptors.into_values()))
+    }
+}
+
 fn group_descriptors_by_package(
     descriptors: impl IntoIterator<Item = Descriptor>,
 ) -> HashMap<String, Package> {
@@ -97,21 +167,43 @@ fn group_descriptors_by_package(
         })
 }
 
-fn collect_service_dependencies(service: &ServiceDescriptor) -> HashMap<String, Descriptor> {
+fn collect_service_dependencies(
+    descriptors: HashMap<String, Descriptor>,
+    service: &ServiceDescriptor,
+) -> HashMap<String, Descriptor> {
     service
         .methods()
         .flat_map(|m| [m.input(), m.output()])
-        .fold(HashMap::new(), |mut acc, d| {
-            let message_name = d.full_name().to_string();
+        .fold(descriptors, |acc, d| collect_message(acc, &d))
+}
 
-            if acc.contains_key(&message_name) {
-                return acc;
-            }
+/// Collects a message and its dependencies, unless it was already collected.
+///
+/// Map entries are described by their map fields, so only their dependencies are collected.
+fn collect_message(
+    mut descriptors: HashMap<String, Descriptor>,
+    message: &MessageDescriptor,
+) -> HashMap<String, Descriptor> {
+    if message.is_map_entry() {
+        return collect_message_dependencies(descriptors, message);
+    }
 
-            acc.insert(message_name, Descriptor::MessageDescriptor(d.clone()));
+    let message_name = message.full_name().to_string();
 
-            collect_message_dependencies(acc, &d)
-        })
+    if descriptors.contains_key(&message_name) {
+        return descriptors;
+    }
+
+    descriptors.insert(message_name, Descriptor::MessageDescriptor(message.clone()));
+
+    collect_message_dependencies(descriptors, message)
+}
+
+/// The messages declared inside `message`, without the entries of its map fields.
+pub(crate) fn nested_messages(
+    message: &MessageDescriptor,
+) -> impl Iterator<Item = MessageDescriptor> {
+    message.child_messages().filter(|m| !m.is_map_entry())
 }
 
 fn collect_message_dependencies(
@@ -121,17 +213,7 @@ fn collect_message_dependencies(
     message
         .fields()
         .fold(descriptors, |mut acc, field| match field.kind() {
-            Kind::Message(m) => {
-                let message_name = m.full_name().to_string();
-
-                if acc.contains_key(&message_name) {
-                    return acc;
-                }
-
-                acc.insert(message_name, Descriptor::MessageDescriptor(m.clone()));
-
-                collect_message_dependencies(acc, &m)
-            }
+            Kind::Message(m) => collect_message(acc, &m),
             Kind::Enum(e) => {
                 acc.insert(e.full_name().to_string(), Descriptor::EnumDescriptor(e));
                 acc
@@ -283,4 +365,108 @@ mod tests {
         assert_eq!(common_pkg.services.len(), 0);
         assert_eq!(common_pkg.enums.len(), 0);
     }
+
+    #[test]
+    fn test_map_entries_are_not_collected() {
+        let proto = r#"
+            syntax = "proto3";
+            package shop;
+
+            message Line {
+                int32 quantity = 1;
+            }
+
+            message Order {
+                map<string, Line> lines_by_sku = 1;
+            }
+
+            service Orders {
+                rpc Place(Order) returns (Order);
+            }
+        "#;
+
+        let pool = compile_protos(&[("shop.proto", proto)]);
+
+        let service = pool.get_service_by_name("shop.Orders").unwrap();
+        let packages = [Packages::from(service), Packages::from_pool(&pool, &[])];
+
+        // The value type of the map is collected, not its entry
+        for packages in packages {
+            let mut names: Vec<_> = packages.0["shop"]
+                .messages
+                .iter()
+                .map(|m| m.name().to_string())
+                .collect();
+            names.sort();
+            assert_eq!(names, ["Line", "Order"]);
+        }
+
+        let order = pool.get_message_by_name("shop.Order").unwrap();
+        assert_eq!(order.child_messages().count(), 1);
+        assert_eq!(nested_messages(&order).count(), 0);
+    }
+
+    #[test]
+    fn test_packages_from_pool() {
+        let common_proto = r#"
+            syntax = "proto3";
+            package common;
+
+            import "google/protobuf/timestamp.proto";
+
+            message Shared {
+                google.protobuf.Timestamp created_at = 1;
+            }
+
+            message Unused {
+                string id = 1;
+            }
+        "#;
+
+        let app_proto = r#"
+            syntax = "proto3";
+            package app.v1;
+
+            import "common.proto";
+
+            enum Standalone {
+                STANDALONE_UNSPECIFIED = 0;
+            }
+
+            service AppService {
+                rpc Get(common.Shared) returns (common.Shared);
+            }
+        "#;
+
+        let pool = compile_protos(&[("common.proto", common_proto), ("app.proto", app_proto)]);
+
+        // Types not reachable from any service are included, the well-known types only as dependencies
+        let packages = Packages::from_pool(&pool, &[]);
+
+        let mut names: Vec<_> = packages.names().cloned().collect();
+        names.sort();
+        assert_eq!(names, ["app.v1", "common", "google.protobuf"]);
+
+        let app_pkg = packages.0.get("app.v1").unwrap();
+        assert_eq!(app_pkg.services.len(), 1);
+        assert_eq!(app_pkg.enums[0].name(), "Standalone");
+
+        assert_eq!(packages.0.get("common").unwrap().messages.len(), 2);
+
+        let well_known = packages.0.get("google.protobuf").unwrap();
+        assert_eq!(well_known.messages.len(), 1);
+        assert_eq!(well_known.messages[0].name(), "Timestamp");
+
+        // Filters match packages and their subpackages, dependencies are still included
+        let packages = Packages::from_pool(&pool, &["app".to_string()]);
+
+        let mut names: Vec<_> = packages.names().cloned().collect();
+        names.sort();
+        assert_eq!(names, ["app.v1", "common", "google.protobuf"]);
+        assert_eq!(packages.0.get("common").unwrap().messages.len(), 1);
+        assert_eq!(packages.services()[0].full_name(), "app.v1.AppService");
+
+        let packages = Packages::from_pool(&pool, &["ap".to_string()]);
+        assert_eq!(packages.names().count(), 0);
+    }
 }
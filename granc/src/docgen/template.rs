@@ -0,0 +1,221 @@
+//! # Templates
+//!
+//! Generates documentation from user-provided templates, written in the Jinja2 syntax (rendered with `minijinja`).
+//! Templates receive the [`model`](super::model) of the packages, so any layout can be produced: Docusaurus or
+//! MkDocs pages, Confluence wiki markup...
+//!
+//! The template directory contains:
+//!
+//! + `package.<ext>`: Rendered once per package into `<package>.<ext>`, with `package`, `packages` and
+//!   `services` in its context.
+//! + `index.<ext>` (optional): Rendered once into `index.<ext>`, with `packages` and `services` in its context.
+//!
+//! Any other file of the directory can be used from them with `include`, `import` or `extends`.
+//! HTML templates (`.html`) are auto-escaped.
+use super::model::PackagesModel;
+use super::package::Packages;
+use minijinja::{Environment, context};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PACKAGE_TEMPLATE: &str = "package";
+const INDEX_TEMPLATE: &str = "index";
+
+/// Errors that can occur while generating documentation from templates.
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("No 'package.<ext>' template found in '{0}'")]
+    MissingPackageTemplate(PathBuf),
+    #[error("Failed to render template: {0:#}")]
+    Render(#[from] minijinja::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub fn generate(
+    output_dir: PathBuf,
+    template_dir: &Path,
+    packages: Packages,
+) -> Result<(), TemplateError> {
+    let package_template = find_template(template_dir, PACKAGE_TEMPLATE)?
+        .ok_or_else(|| TemplateError::MissingPackageTemplate(template_dir.to_path_buf()))?;
+    let index_template = find_template(template_dir, INDEX_TEMPLATE)?;
+
+    let mut env = Environment::new();
+    env.set_loader(minijinja::path_loader(template_dir));
+    // Block tags do not leave blank lines behind, which matters for whitespace sensitive formats like Markdown
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+
+    if !output_dir.exists() {
+        fs::create_dir_all(&output_dir)?;
+    }
+
+    let model = PackagesModel::from(&packages);
+    let template = env.get_template(&package_template)?;
+
+    for package in &model.packages {
+        let filename = package_template.replacen(PACKAGE_TEMPLATE, &package.name, 1);
+
+        let out = template.render(context! {
+            package => package,
+            packages => model.packages,
+            services => model.services,
+        })?;
+
+        fs::write(output_dir.join(&filename), out)?;
+        println!("Generated: {}", filename);
+    }
+
+    if let Some(index_template) = index_template {
+        let out = env.get_template(&index_template)?.render(context! {
+            packages => model.packages,
+            services => model.services,
+        })?;
+
+        fs::write(output_dir.join(&index_template), out)?;
+        println!("Generated: {}", index_template);
+    }
+
+    Ok(())
+}
+
+/// Finds the file named `<name>.<ext>` in the template directory, returning its file name.
+fn find_template(template_dir: &Path, name: &str) -> std::io::Result<Option<String>> {
+    let prefix = format!("{name}.");
+
+    for entry in fs::read_dir(template_dir)? {
+        let entry = entry?;
+        let filename = entry.file_name().to_string_lossy().to_string();
+
+        if entry.file_type()?.is_file() && filename.starts_with(&prefix) {
+            return Ok(Some(filename));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package shop.v1;
+
+        // An order placed by a customer.
+        message Order {
+            // The lines of the order.
+            repeated Line lines = 1;
+            map<string, int32> quantities = 2;
+            string note = 3 [deprecated = true];
+        }
+
+        message Line {
+            string sku = 1;
+        }
+
+        // Manages orders.
+        service Orders {
+            // Places an order.
+            rpc Place(Order) returns (stream Order);
+        }
+    "#;
+
+    fn packages() -> Packages {
+        let bytes = compiler::compile_protos_to_bytes(&[("shop.proto", PROTO)]);
+        let pool =
+            DescriptorPool::decode(bytes.as_slice()).expect("Failed to decode descriptor pool");
+        Packages::from_pool(&pool, &[])
+    }
+
+    #[test]
+    fn test_generate_from_templates() {
+        let templates = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        fs::write(
+            templates.path().join("package.mdx"),
+            r#"{% import "_macros.mdx" as m %}
+# {{ package.name }}
+{% for service in package.services %}
+## {{ service.name }}
+{{ service.comments }}
+{% for method in service.methods %}
+- {{ method.name }}({{ method.input.name }}) -> {{ m.stream(method.server_streaming) }}{{ method.output.name }}: {{ method.comments }}
+{% endfor %}
+{% endfor %}
+{% for message in package.messages %}
+## {{ message.name }}
+{% for field in message.fields %}
+| {{ field.name }} | {{ field.json_name }} | {{ field.label }} | {{ field.type_name }} | {{ "deprecated" if field.deprecated }} | {{ field.comments or "" }} |
+{% endfor %}
+{% endfor %}
+"#,
+        )
+        .unwrap();
+        fs::write(
+            templates.path().join("_macros.mdx"),
+            r#"{% macro stream(streaming) %}{% if streaming %}stream {% endif %}{% endmacro %}"#,
+        )
+        .unwrap();
+        fs::write(
+            templates.path().join("index.mdx"),
+            r#"{% for service in services %}
+- [{{ service.full_name }}]({{ service.package }}.mdx)
+{% endfor %}
+"#,
+        )
+        .unwrap();
+
+        generate(output.path().to_path_buf(), templates.path(), packages()).unwrap();
+
+        let page = fs::read_to_string(output.path().join("shop.v1.mdx")).unwrap();
+
+        assert!(page.starts_with("# shop.v1\n"));
+        assert!(page.contains("## Orders\nManages orders.\n"));
+        assert!(page.contains("- Place(Order) -> stream Order: Places an order.\n"));
+        assert!(page.contains(
+            "| lines | lines | repeated | shop.v1.Line |  | The lines of the order. |\n"
+        ));
+        assert!(page.contains("| quantities | quantities |  | map<string, int32> |  |  |\n"));
+        assert!(page.contains("| note | note |  | string | deprecated |  |\n"));
+
+        // Map entries are not documented as messages
+        assert!(!page.contains("QuantitiesEntry"));
+
+        let index = fs::read_to_string(output.path().join("index.mdx")).unwrap();
+        assert_eq!(index, "- [shop.v1.Orders](shop.v1.mdx)\n");
+
+        // Partials are not rendered on their own
+        assert!(!output.path().join("_macros.mdx").exists());
+    }
+
+    #[test]
+    fn test_example_templates() {
+        let templates = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples/templates/mkdocs");
+        let output = tempfile::tempdir().unwrap();
+
+        generate(output.path().to_path_buf(), &templates, packages()).unwrap();
+
+        let page = fs::read_to_string(output.path().join("shop.v1.md")).unwrap();
+        assert!(page.contains("An order placed by a customer."));
+        assert!(output.path().join("index.md").exists());
+    }
+
+    #[test]
+    fn test_missing_package_template() {
+        let templates = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        let result = generate(output.path().to_path_buf(), templates.path(), packages());
+
+        assert!(matches!(
+            result,
+            Err(TemplateError::MissingPackageTemplate(_))
+        ));
+    }
+}
@@ -0,0 +1,8 @@
+//! # Export
+//!
+//! Converts protobuffer descriptors into the schema formats consumed by non-gRPC tooling:
+//!
+//! + [`json_schema`]: JSON Schema documents describing the JSON payloads of messages and enums.
+//! + [`openapi`]: OpenAPI documents describing the HTTP routes of services annotated with `google.api.http`.
+pub mod json_schema;
+pub mod openapi;
@@ -0,0 +1,417 @@
+//! # JSON Schema
+//!
+//! Generates JSON Schema (draft 2020-12) documents following the proto3 JSON mapping, the same one used by
+//! the `JsonCodec` to transcode requests and responses:
+//!
+//! + Fields are named by their JSON name (`lowerCamelCase` by default).
+//! + 64-bit integers are strings, enums are the names of their values and bytes are base64 strings.
+//! + Well-known types with a special JSON representation (`Timestamp`, `Duration`, `Struct`, wrappers...)
+//!   are inlined with that representation.
+//! + At most one field of each `oneof` can be set.
+//!
+//! Messages and enums are shared [`Definitions`], referenced by their fully qualified name.
+use crate::docgen::package::Packages;
+use crate::options;
+use granc_core::{
+    client::Descriptor,
+    prost_reflect::{Cardinality, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor},
+};
+use serde_json::{Map, Value, json};
+
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Generates a document validating the JSON payloads of a message or an enum.
+///
+/// For a service, the document only contains the definitions of the requests and responses of its methods.
+pub fn generate(descriptor: Descriptor) -> Value {
+    let mut definitions = Definitions::new("#/$defs/");
+
+    let root = match descriptor {
+        Descriptor::MessageDescriptor(message) => Some(definitions.message(&message)),
+        Descriptor::EnumDescriptor(enum_desc) => Some(definitions.enumeration(&enum_desc)),
+        Descriptor::ServiceDescriptor(service) => {
+            for method in service.methods() {
+                definitions.message(&method.input());
+                definitions.message(&method.output());
+            }
+            None
+        }
+    };
+
+    let mut document = Map::new();
+    document.insert("$schema".to_string(), json!(DIALECT));
+
+    if let Some(Value::Object(root)) = root {
+        document.extend(root);
+    }
+
+    document.insert(
+        "$defs".to_string(),
+        Value::Object(definitions.into_schemas()),
+    );
+    Value::Object(document)
+}
+
+/// Generates a document with the definitions of every message and enum of the packages.
+pub fn generate_all(packages: &Packages) -> Value {
+    let mut definitions = Definitions::new("#/$defs/");
+
+    for package in packages.values() {
+        for message in &package.messages {
+            definitions.message(message);
+        }
+
+        for enum_desc in &package.enums {
+            definitions.enumeration(enum_desc);
+        }
+    }
+
+    json!({
+        "$schema": DIALECT,
+        "$defs": definitions.into_schemas(),
+    })
+}
+
+/// The schemas of the messages and enums referenced so far, by fully qualified name.
+pub(crate) struct Definitions {
+    /// The location of the definitions in the document (e.g. `#/$defs/`).
+    ref_prefix: &'static str,
+    schemas: Map<String, Value>,
+}
+
+impl Definitions {
+    pub fn new(ref_prefix: &'static str) -> Self {
+        Definitions {
+            ref_prefix,
+            schemas: Map::new(),
+        }
+    }
+
+    pub fn into_schemas(self) -> Map<String, Value> {
+        self.schemas
+    }
+
+    /// Returns the schema of the value of a field, including repeated and map fields.
+    pub fn field(&mut self, field: &FieldDescriptor) -> Value {
+        match field.kind() {
+            Kind::Message(entry) if field.is_map() => json!({
+                "type": "object",
+                "additionalProperties": self.kind(entry.map_entry_value_field().kind()),
+            }),
+            kind if field.is_list() => json!({
+                "type": "array",
+                "items": self.kind(kind),
+            }),
+            kind => self.kind(kind),
+        }
+    }
+
+    /// Returns a reference to the definition of a message, adding it along with its dependencies if missing.
+    ///
+    /// Well-known types with a special JSON representation are inlined instead.
+    pub fn message(&mut self, message: &MessageDescriptor) -> Value {
+        if let Some(schema) = well_known_type(message) {
+            return schema;
+        }
+
+        let name = message.full_name();
+
+        if !self.schemas.contains_key(name) {
+            // Reserve the name before walking the fields, so recursive messages are only visited once
+            self.schemas.insert(name.to_string(), Value::Null);
+            let schema = self.message_schema(message);
+            self.schemas.insert(name.to_string(), schema);
+        }
+
+        self.reference(name)
+    }
+
+    /// Returns a reference to the definition of an enum, adding it if missing.
+    pub fn enumeration(&mut self, enum_desc: &EnumDescriptor) -> Value {
+        // `NullValue` is the `null` of a `google.protobuf.Value`
+        if enum_desc.full_name() == "google.protobuf.NullValue" {
+            return json!({ "type": "null" });
+        }
+
+        let name = enum_desc.full_name();
+
+        if !self.schemas.contains_key(name) {
+            let values: Vec<_> = enum_desc.values().map(|v| v.name().to_string()).collect();
+
+            self.schemas.insert(
+                name.to_string(),
+                json!({
+                    "title": enum_desc.name(),
+                    "type": "string",
+                    "enum": values,
+                }),
+            );
+        }
+
+        self.reference(name)
+    }
+
+    fn reference(&self, name: &str) -> Value {
+        json!({ "$ref": format!("{}{}", self.ref_prefix, name) })
+    }
+
+    fn kind(&mut self, kind: Kind) -> Value {
+        match kind {
+            Kind::Message(m) => self.message(&m),
+            Kind::Enum(e) => self.enumeration(&e),
+            scalar => scalar_schema(&scalar),
+        }
+    }
+
+    fn message_schema(&mut self, message: &MessageDescriptor) -> Value {
+        let mut properties = Map::new();
+        let mut required = vec![];
+
+        for field in message.fields() {
+            let mut schema = self.field(&field);
+
+            if options::is_deprecated(&field.options()) {
+                schema["deprecated"] = json!(true);
+            }
+
+            // Only proto2 has required fields
+            if field.cardinality() == Cardinality::Required {
+                required.push(field.json_name().to_string());
+            }
+
+            properties.insert(field.json_name().to_string(), schema);
+        }
+
+        let mut schema = json!({
+            "title": message.name(),
+            "type": "object",
+            "properties": properties,
+        });
+
+        if !required.is_empty() {
+            schema["required"] = json!(required);
+        }
+
+        let oneofs: Vec<_> = message
+            .oneofs()
+            .filter(|oneof| !oneof.is_synthetic())
+            .map(|oneof| {
+                let names: Vec<_> = oneof.fields().map(|f| f.json_name().to_string()).collect();
+                at_most_one_of(&names)
+            })
+            .collect();
+
+        if !oneofs.is_empty() {
+            schema["allOf"] = json!(oneofs);
+        }
+
+        schema
+    }
+}
+
+/// A constraint allowing at most one of the properties to be present.
+///
+/// Exactly one of the subschemas holds when a single property is present, or when none of them is.
+fn at_most_one_of(names: &[String]) -> Value {
+    let present: Vec<_> = names.iter().map(|n| json!({ "required": [n] })).collect();
+
+    let mut alternatives = present.clone();
+    alternatives.push(json!({ "not": { "anyOf": present } }));
+
+    json!({ "oneOf": alternatives })
+}
+
+/// The JSON representation of the well-known types that are not mapped as regular messages.
+pub(crate) fn well_known_type(message: &MessageDescriptor) -> Option<Value> {
+    let schema = match message.full_name() {
+        "google.protobuf.Timestamp" => json!({ "type": "string", "format": "date-time" }),
+        "google.protobuf.Duration" => {
+            json!({ "type": "string", "pattern": "^-?[0-9]+(\\.[0-9]+)?s$" })
+        }
+        "google.protobuf.FieldMask" => json!({ "type": "string" }),
+        "google.protobuf.Struct" => json!({ "type": "object" }),
+        "google.protobuf.ListValue" => json!({ "type": "array" }),
+        // Any JSON value
+        "google.protobuf.Value" => json!({}),
+        "google.protobuf.Any" => json!({
+            "type": "object",
+            "properties": { "@type": { "type": "string" } },
+            "required": ["@type"],
+        }),
+        "google.protobuf.DoubleValue"
+        | "google.protobuf.FloatValue"
+        | "google.protobuf.Int64Value"
+        | "google.protobuf.UInt64Value"
+        | "google.protobuf.Int32Value"
+        | "google.protobuf.UInt32Value"
+        | "google.protobuf.BoolValue"
+        | "google.protobuf.StringValue"
+        | "google.protobuf.BytesValue" => {
+            // Wrappers are represented by their wrapped value
+            let value = message.get_field_by_name("value")?;
+            scalar_schema(&value.kind())
+        }
+        _ => return None,
+    };
+
+    Some(schema)
+}
+
+fn scalar_schema(kind: &Kind) -> Value {
+    match kind {
+        Kind::Double => json!({ "type": "number", "format": "double" }),
+        Kind::Float => json!({ "type": "number", "format": "float" }),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            json!({ "type": "integer", "format": "int32" })
+        }
+        Kind::Uint32 | Kind::Fixed32 => {
+            json!({ "type": "integer", "format": "uint32", "minimum": 0 })
+        }
+        // 64-bit integers are strings, as they do not fit in a JavaScript number
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            json!({ "type": "string", "format": "int64" })
+        }
+        Kind::Uint64 | Kind::Fixed64 => json!({ "type": "string", "format": "uint64" }),
+        Kind::Bool => json!({ "type": "boolean" }),
+        Kind::String => json!({ "type": "string" }),
+        Kind::Bytes => json!({ "type": "string", "contentEncoding": "base64" }),
+        Kind::Message(_) | Kind::Enum(_) => unreachable!("not a scalar type"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    fn compile_protos(proto: &str) -> DescriptorPool {
+        let file_descriptor_set = compiler::compile_protos(&[("test.proto", proto)]);
+        DescriptorPool::from_file_descriptor_set(file_descriptor_set)
+            .expect("Failed to decode descriptor pool")
+    }
+
+    #[test]
+    fn test_message_schema() {
+        let proto = r#"
+            syntax = "proto3";
+            package shop.v1;
+
+            import "google/protobuf/timestamp.proto";
+            import "google/protobuf/wrappers.proto";
+
+            enum Status {
+                STATUS_UNSPECIFIED = 0;
+                SHIPPED = 1;
+            }
+
+            message Order {
+                int64 order_id = 1;
+                Status status = 2;
+                repeated Order children = 3;
+                map<string, uint32> quantities = 4;
+                bytes signature = 5;
+                google.protobuf.Timestamp created_at = 6;
+                google.protobuf.StringValue note = 7 [deprecated = true];
+
+                oneof payment {
+                    string card = 8;
+                    string voucher = 9;
+                }
+
+                optional double discount = 10;
+            }
+        "#;
+
+        let pool = compile_protos(proto);
+        let order = pool.get_message_by_name("shop.v1.Order").unwrap();
+
+        let document = generate(Descriptor::MessageDescriptor(order));
+
+        assert_eq!(document["$schema"], DIALECT);
+        assert_eq!(document["$ref"], "#/$defs/shop.v1.Order");
+
+        let defs = document["$defs"].as_object().unwrap();
+        let mut names: Vec<_> = defs.keys().collect();
+        names.sort();
+        assert_eq!(names, ["shop.v1.Order", "shop.v1.Status"]);
+
+        let properties = &defs["shop.v1.Order"]["properties"];
+
+        assert_eq!(
+            properties["orderId"],
+            json!({ "type": "string", "format": "int64" })
+        );
+        assert_eq!(
+            properties["status"],
+            json!({ "$ref": "#/$defs/shop.v1.Status" })
+        );
+        assert_eq!(
+            properties["children"],
+            json!({ "type": "array", "items": { "$ref": "#/$defs/shop.v1.Order" } })
+        );
+        assert_eq!(
+            properties["quantities"],
+            json!({
+                "type": "object",
+                "additionalProperties": { "type": "integer", "format": "uint32", "minimum": 0 }
+            })
+        );
+        assert_eq!(
+            properties["signature"],
+            json!({ "type": "string", "contentEncoding": "base64" })
+        );
+        assert_eq!(
+            properties["createdAt"],
+            json!({ "type": "string", "format": "date-time" })
+        );
+        assert_eq!(
+            properties["note"],
+            json!({ "type": "string", "deprecated": true })
+        );
+
+        // Proto3 `optional` fields are not real oneofs
+        assert_eq!(
+            defs["shop.v1.Order"]["allOf"],
+            json!([{
+                "oneOf": [
+                    { "required": ["card"] },
+                    { "required": ["voucher"] },
+                    { "not": { "anyOf": [{ "required": ["card"] }, { "required": ["voucher"] }] } }
+                ]
+            }])
+        );
+
+        assert_eq!(
+            defs["shop.v1.Status"],
+            json!({ "title": "Status", "type": "string", "enum": ["STATUS_UNSPECIFIED", "SHIPPED"] })
+        );
+    }
+
+    #[test]
+    fn test_generate_all() {
+        let proto = r#"
+            syntax = "proto3";
+            package catalog;
+
+            message Item {
+                map<string, string> labels = 1;
+            }
+
+            enum Color {
+                COLOR_UNSPECIFIED = 0;
+            }
+        "#;
+
+        let pool = compile_protos(proto);
+        let document = generate_all(&Packages::from_pool(&pool, &[]));
+
+        assert!(document.get("$ref").is_none());
+
+        // Map entries are only represented as objects
+        let defs = document["$defs"].as_object().unwrap();
+        let mut names: Vec<_> = defs.keys().collect();
+        names.sort();
+        assert_eq!(names, ["catalog.Color", "catalog.Item"]);
+    }
+}
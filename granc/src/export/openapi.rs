@@ -0,0 +1,611 @@
+//! # OpenAPI
+//!
+//! Generates OpenAPI 3.1 documents from the `google.api.http` annotations of the methods, as served by
+//! HTTP/JSON transcoding gateways (grpc-gateway, Envoy, Cloud Endpoints...):
+//!
+//! + Path templates become paths, with their variables (e.g. `{name=shelves/*}`) as path parameters whose
+//!   segment pattern (`shelves/*`) is kept as a regular expression.
+//! + The `body` of the rule selects the request body, the remaining fields become query parameters.
+//! + The `response_body` of the rule selects the response, the whole output message by default.
+//!
+//! Messages and enums are described in `components/schemas` with the [`json_schema`](super::json_schema)
+//! mapping. Methods without annotation are not exported, and two rules bound to the same path and HTTP method are
+//! reported as a [`RouteCollision`], as a document can only describe one of them.
+use super::json_schema::{self, Definitions};
+use granc_core::prost_reflect::{
+    DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, MethodDescriptor, ServiceDescriptor,
+};
+use serde_json::{Map, Value, json};
+use std::collections::{HashMap, hash_map::Entry};
+
+const HTTP_RULE_EXTENSION: &str = "google.api.http";
+
+/// Two HTTP rules bound to the same path and HTTP method.
+#[derive(Debug, thiserror::Error)]
+#[error("'{} {path}' is bound by both {first} and {second}", .method.to_uppercase())]
+pub struct RouteCollision {
+    pub method: String,
+    pub path: String,
+    pub first: String,
+    pub second: String,
+}
+
+/// Generates a document with the HTTP routes of the services.
+///
+/// Returns `None` if no method of the services has a `google.api.http` annotation.
+pub fn generate(services: &[ServiceDescriptor]) -> Result<Option<Value>, RouteCollision> {
+    let mut definitions = Definitions::new("#/components/schemas/");
+    let mut paths = Map::new();
+    let mut tags = vec![];
+    // The gRPC method bound to each path and HTTP method
+    let mut routes: HashMap<(String, String), String> = HashMap::new();
+
+    for service in services {
+        let mut annotated = false;
+
+        for method in service.methods() {
+            for (index, rule) in http_rules(&method).into_iter().enumerate() {
+                annotated = true;
+
+                // Additional bindings get a distinct operation id
+                let operation_id = match index {
+                    0 => format!("{}_{}", service.name(), method.name()),
+                    _ => format!("{}_{}_{}", service.name(), method.name(), index + 1),
+                };
+
+                let (path, operation) = operation(&mut definitions, &method, &rule, operation_id);
+
+                let grpc_method = format!("{}/{}", service.full_name(), method.name());
+                match routes.entry((path.clone(), rule.method.clone())) {
+                    Entry::Occupied(entry) => {
+                        return Err(RouteCollision {
+                            method: rule.method,
+                            path,
+                            first: entry.get().clone(),
+                            second: grpc_method,
+                        });
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(grpc_method);
+                    }
+                }
+
+                let item = paths
+                    .entry(path)
+                    .or_insert_with(|| Value::Object(Map::new()));
+                item[rule.method.as_str()] = operation;
+            }
+        }
+
+        if annotated {
+            tags.push(json!({ "name": service.full_name() }));
+        }
+    }
+
+    if paths.is_empty() {
+        return Ok(None);
+    }
+
+    let title = match services {
+        [service] => service.full_name(),
+        _ => "gRPC API",
+    };
+
+    Ok(Some(json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": title,
+            "version": "version not set",
+        },
+        "tags": tags,
+        "paths": paths,
+        "components": {
+            "schemas": definitions.into_schemas(),
+        },
+    })))
+}
+
+/// A binding of a method to an HTTP route, decoded from a `google.api.HttpRule`.
+struct HttpRule {
+    /// The lowercase HTTP method (e.g. `get`).
+    method: String,
+    path: String,
+    body: String,
+    response_body: String,
+}
+
+/// Decodes the `google.api.http` annotation of a method, along with its additional bindings.
+fn http_rules(method: &MethodDescriptor) -> Vec<HttpRule> {
+    let Some(extension) = method
+        .parent_pool()
+        .get_extension_by_name(HTTP_RULE_EXTENSION)
+    else {
+        return vec![];
+    };
+
+    let options = method.options();
+
+    if !options.has_extension(&extension) {
+        return vec![];
+    }
+
+    let Some(rule) = options.get_extension(&extension).as_message().cloned() else {
+        return vec![];
+    };
+
+    let additional_bindings = rule
+        .get_field_by_name("additional_bindings")
+        .and_then(|bindings| bindings.as_list().map(<[_]>::to_vec))
+        .unwrap_or_default();
+
+    std::iter::once(rule)
+        .chain(
+            additional_bindings
+                .iter()
+                .filter_map(|b| b.as_message().cloned()),
+        )
+        .filter_map(|rule| decode_rule(&rule))
+        .collect()
+}
+
+fn decode_rule(rule: &DynamicMessage) -> Option<HttpRule> {
+    let (method, path) = ["get", "put", "post", "delete", "patch"]
+        .into_iter()
+        .find(|method| rule.has_field_by_name(method))
+        .map(|method| (method.to_string(), string_field(rule, method)))
+        .or_else(|| {
+            // Custom verbs (e.g. HEAD) are only exported if OpenAPI supports them
+            let custom = rule.get_field_by_name("custom")?.as_message()?.clone();
+            let kind = string_field(&custom, "kind").to_lowercase();
+            ["head", "options", "trace"]
+                .contains(&kind.as_str())
+                .then(|| (kind, string_field(&custom, "path")))
+        })?;
+
+    Some(HttpRule {
+        method,
+        path,
+        body: string_field(rule, "body"),
+        response_body: string_field(rule, "response_body"),
+    })
+}
+
+fn string_field(message: &DynamicMessage, name: &str) -> String {
+    message
+        .get_field_by_name(name)
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Builds the operation of an HTTP rule, returning it along with its OpenAPI path.
+fn operation(
+    definitions: &mut Definitions,
+    method: &MethodDescriptor,
+    rule: &HttpRule,
+    operation_id: String,
+) -> (String, Value) {
+    let input = method.input();
+    let (path, variables) = parse_template(&rule.path);
+
+    let mut parameters = vec![];
+
+    for variable in &variables {
+        let mut schema = resolve_field(&input, &variable.field)
+            .map(|field| definitions.field(&field))
+            .unwrap_or_else(|| json!({ "type": "string" }));
+
+        if let (Some(pattern), Some(schema)) = (&variable.pattern, schema.as_object_mut()) {
+            schema.insert("pattern".to_string(), json!(pattern));
+        }
+
+        parameters.push(json!({
+            "name": variable.field,
+            "in": "path",
+            "required": true,
+            "schema": schema,
+        }));
+    }
+
+    // Every field not bound to the path or the body can be sent as a query parameter
+    if rule.body != "*" {
+        let bound = |field: &FieldDescriptor| {
+            field.name() == rule.body
+                || variables
+                    .iter()
+                    .any(|v| v.field.split('.').next() == Some(field.name()))
+        };
+
+        for field in input
+            .fields()
+            .filter(|f| !bound(f) && is_query_parameter(f))
+        {
+            parameters.push(json!({
+                "name": field.json_name(),
+                "in": "query",
+                "schema": definitions.field(&field),
+            }));
+        }
+    }
+
+    let mut operation = json!({
+        "operationId": operation_id,
+        "tags": [method.parent_service().full_name()],
+    });
+
+    if !parameters.is_empty() {
+        operation["parameters"] = json!(parameters);
+    }
+
+    let request_schema = match rule.body.as_str() {
+        "" => None,
+        "*" => Some(definitions.message(&input)),
+        field => input
+            .get_field_by_name(field)
+            .map(|field| definitions.field(&field)),
+    };
+
+    if let Some(schema) = request_schema {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema } },
+        });
+    }
+
+    let output = method.output();
+    let response_schema = output
+        .get_field_by_name(&rule.response_body)
+        .map(|field| definitions.field(&field))
+        .unwrap_or_else(|| definitions.message(&output));
+
+    operation["responses"] = json!({
+        "200": {
+            "description": "A successful response.",
+            "content": { "application/json": { "schema": response_schema } },
+        }
+    });
+
+    (path, operation)
+}
+
+/// A variable of a path template, e.g. `{name=shelves/*}`.
+#[derive(Debug, PartialEq)]
+struct PathVariable {
+    /// The path of the field the variable is bound to (e.g. `name`).
+    field: String,
+    /// The segments matched by the variable as an anchored regular expression, when they are not the
+    /// default single segment (e.g. `^shelves/[^/]+$` for `shelves/*`).
+    pattern: Option<String>,
+}
+
+/// Converts a path template (e.g. `/v1/{name=shelves/*}/books`) into an OpenAPI path (`/v1/{name}/books`),
+/// returning it along with its variables.
+fn parse_template(template: &str) -> (String, Vec<PathVariable>) {
+    let mut path = String::new();
+    let mut variables = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+
+        let variable = &rest[start + 1..start + end];
+        let (name, segments) = match variable.split_once('=') {
+            Some((name, segments)) => (name, Some(segments)),
+            None => (variable, None),
+        };
+
+        path.push_str(&rest[..start]);
+        path.push_str(&format!("{{{name}}}"));
+        variables.push(PathVariable {
+            field: name.to_string(),
+            pattern: segments.filter(|s| *s != "*").map(segments_pattern),
+        });
+
+        rest = &rest[start + end + 1..];
+    }
+
+    path.push_str(rest);
+    (path, variables)
+}
+
+/// Translates the segments of a variable (`*` for one segment, `**` for any number of them) into a regular
+/// expression.
+fn segments_pattern(segments: &str) -> String {
+    let segments: Vec<_> = segments
+        .split('/')
+        .map(|segment| match segment {
+            "*" => "[^/]+".to_string(),
+            "**" => ".+".to_string(),
+            literal => regex::escape(literal),
+        })
+        .collect();
+
+    format!("^{}$", segments.join("/"))
+}
+
+/// Finds the field designated by a dotted path (e.g. `book.id`) in a message.
+fn resolve_field(message: &MessageDescriptor, path: &str) -> Option<FieldDescriptor> {
+    let (first, rest) = match path.split_once('.') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (path, None),
+    };
+
+    let field = message.get_field_by_name(first)?;
+
+    match (rest, field.kind()) {
+        (None, _) => Some(field),
+        (Some(rest), Kind::Message(nested)) => resolve_field(&nested, rest),
+        (Some(_), _) => None,
+    }
+}
+
+/// Gateways only map scalar values (or lists of them) from the query string.
+fn is_query_parameter(field: &FieldDescriptor) -> bool {
+    match field.kind() {
+        _ if field.is_map() => false,
+        Kind::Message(m) => json_schema::well_known_type(&m).is_some_and(|schema| {
+            !matches!(schema["type"].as_str(), None | Some("object" | "array"))
+        }),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    const HTTP_PROTO: &str = r#"
+        syntax = "proto3";
+        package google.api;
+
+        message HttpRule {
+            string selector = 1;
+            oneof pattern {
+                string get = 2;
+                string put = 3;
+                string post = 4;
+                string delete = 5;
+                string patch = 6;
+                CustomHttpPattern custom = 8;
+            }
+            string body = 7;
+            string response_body = 12;
+            repeated HttpRule additional_bindings = 11;
+        }
+
+        message CustomHttpPattern {
+            string kind = 1;
+            string path = 2;
+        }
+    "#;
+
+    const ANNOTATIONS_PROTO: &str = r#"
+        syntax = "proto3";
+        package google.api;
+
+        import "google/api/http.proto";
+        import "google/protobuf/descriptor.proto";
+
+        extend google.protobuf.MethodOptions {
+            HttpRule http = 72295728;
+        }
+    "#;
+
+    fn compile_service(proto: &str, service: &str) -> ServiceDescriptor {
+        // Decoded from bytes to keep the `google.api.http` options
+        let bytes = compiler::compile_protos_to_bytes(&[
+            ("google/api/http.proto", HTTP_PROTO),
+            ("google/api/annotations.proto", ANNOTATIONS_PROTO),
+            ("test.proto", proto),
+        ]);
+        DescriptorPool::decode(bytes.as_slice())
+            .expect("Failed to decode descriptor pool")
+            .get_service_by_name(service)
+            .expect("Service not found")
+    }
+
+    #[test]
+    fn test_generate_paths() {
+        let proto = r#"
+            syntax = "proto3";
+            package library.v1;
+
+            import "google/api/annotations.proto";
+
+            message Book {
+                string id = 1;
+                string title = 2;
+            }
+
+            message GetBookRequest {
+                string name = 1;
+                bool include_reviews = 2;
+                Book filter = 3;
+            }
+
+            message UpdateBookRequest {
+                Book book = 1;
+                int64 revision = 2;
+            }
+
+            message ListBooksResponse {
+                repeated Book books = 1;
+            }
+
+            service Library {
+                rpc GetBook(GetBookRequest) returns (Book) {
+                    option (google.api.http) = {
+                        get: "/v1/{name=shelves/*/books/*}"
+                        additional_bindings { get: "/v1/books/{name}" }
+                    };
+                }
+
+                rpc UpdateBook(UpdateBookRequest) returns (Book) {
+                    option (google.api.http) = {
+                        patch: "/v1/books/{book.id}"
+                        body: "book"
+                    };
+                }
+
+                rpc ListBooks(GetBookRequest) returns (ListBooksResponse) {
+                    option (google.api.http) = {
+                        post: "/v1/books:search"
+                        body: "*"
+                        response_body: "books"
+                    };
+                }
+
+                rpc Internal(Book) returns (Book);
+            }
+        "#;
+
+        let service = compile_service(proto, "library.v1.Library");
+        let document = generate(&[service])
+            .expect("Routes collided")
+            .expect("No paths generated");
+
+        assert_eq!(document["openapi"], "3.1.0");
+        assert_eq!(document["info"]["title"], "library.v1.Library");
+
+        let paths = document["paths"].as_object().unwrap();
+        let mut names: Vec<_> = paths.keys().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "/v1/books/{book.id}",
+                "/v1/books/{name}",
+                "/v1/books:search",
+                "/v1/{name}"
+            ]
+        );
+
+        // Unbound scalar fields are query parameters, messages are not
+        let get = &paths["/v1/{name}"]["get"];
+        assert_eq!(get["operationId"], "Library_GetBook");
+        assert_eq!(
+            get["parameters"],
+            json!([
+                {
+                    "name": "name",
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string", "pattern": "^shelves/[^/]+/books/[^/]+$" },
+                },
+                { "name": "includeReviews", "in": "query", "schema": { "type": "boolean" } },
+            ])
+        );
+        assert!(get.get("requestBody").is_none());
+        assert_eq!(
+            paths["/v1/books/{name}"]["get"]["operationId"],
+            "Library_GetBook_2"
+        );
+
+        let patch = &paths["/v1/books/{book.id}"]["patch"];
+        assert_eq!(
+            patch["parameters"],
+            json!([
+                { "name": "book.id", "in": "path", "required": true, "schema": { "type": "string" } },
+                { "name": "revision", "in": "query", "schema": { "type": "string", "format": "int64" } },
+            ])
+        );
+        assert_eq!(
+            patch["requestBody"]["content"]["application/json"]["schema"],
+            json!({ "$ref": "#/components/schemas/library.v1.Book" })
+        );
+
+        let post = &paths["/v1/books:search"]["post"];
+        assert!(post.get("parameters").is_none());
+        assert_eq!(
+            post["requestBody"]["content"]["application/json"]["schema"],
+            json!({ "$ref": "#/components/schemas/library.v1.GetBookRequest" })
+        );
+        assert_eq!(
+            post["responses"]["200"]["content"]["application/json"]["schema"],
+            json!({ "type": "array", "items": { "$ref": "#/components/schemas/library.v1.Book" } })
+        );
+
+        let schemas = document["components"]["schemas"].as_object().unwrap();
+        assert!(schemas.contains_key("library.v1.Book"));
+        assert!(!schemas.contains_key("library.v1.ListBooksResponse"));
+    }
+
+    #[test]
+    fn test_generate_without_annotations() {
+        let proto = r#"
+            syntax = "proto3";
+            package plain;
+
+            message Empty {}
+
+            service Plain {
+                rpc Ping(Empty) returns (Empty);
+            }
+        "#;
+
+        let service = compile_service(proto, "plain.Plain");
+        assert!(generate(&[service]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_generate_route_collision() {
+        let proto = r#"
+            syntax = "proto3";
+            package library.v1;
+
+            import "google/api/annotations.proto";
+
+            message Book {
+                string name = 1;
+            }
+
+            service Library {
+                rpc GetBook(Book) returns (Book) {
+                    option (google.api.http) = { get: "/v1/{name=books/*}" };
+                }
+
+                rpc FindBook(Book) returns (Book) {
+                    option (google.api.http) = { get: "/v1/{name=authors/*}" };
+                }
+            }
+        "#;
+
+        let service = compile_service(proto, "library.v1.Library");
+        let collision = generate(&[service]).expect_err("Routes should collide");
+
+        assert_eq!(
+            collision.to_string(),
+            "'GET /v1/{name}' is bound by both library.v1.Library/GetBook and library.v1.Library/FindBook"
+        );
+    }
+
+    #[test]
+    fn test_parse_template() {
+        let variable = |field: &str, pattern: Option<&str>| PathVariable {
+            field: field.to_string(),
+            pattern: pattern.map(str::to_string),
+        };
+
+        assert_eq!(
+            parse_template("/v1/{name=projects/*/books/*}:publish"),
+            (
+                "/v1/{name}:publish".to_string(),
+                vec![variable("name", Some("^projects/[^/]+/books/[^/]+$"))]
+            )
+        );
+        assert_eq!(
+            parse_template("/v1/{parent}/items/{item.id=*}/{path=files/**}"),
+            (
+                "/v1/{parent}/items/{item.id}/{path}".to_string(),
+                vec![
+                    variable("parent", None),
+                    variable("item.id", None),
+                    variable("path", Some("^files/.+$")),
+                ]
+            )
+        );
+    }
+}
@@ -1,12 +1,22 @@
+use crate::{
+    config::ConfigError,
+    docgen::package::nested_messages,
+    options,
+    search::SearchResult,
+    session::{ReplayReport, SessionError},
+    snapshot::{Change, SnapshotError, SnapshotOutcome},
+    workflow::{StepReport, WorkflowError},
+};
 use colored::*;
 use granc_core::{
     client::{Descriptor, DynamicResponse, online, online_without_reflection},
     prost_reflect::{
-        self, EnumDescriptor, Kind, MessageDescriptor, MethodDescriptor, ServiceDescriptor,
+        self, Cardinality, DynamicMessage, EnumDescriptor, EnumValueDescriptor, FieldDescriptor,
+        Kind, MessageDescriptor, MethodDescriptor, OneofDescriptor, ServiceDescriptor, Syntax,
     },
     tonic::Status,
 };
-use std::fmt::Display;
+use std::{collections::HashSet, fmt::Display, net::SocketAddr};
 
 /// A wrapper struct for a formatted, colored string.
 ///
@@ -15,8 +25,40 @@ pub struct FormattedString(pub String);
 
 pub struct ServiceList(pub Vec<String>);
 
+/// The services of `list --methods`, each followed by its methods.
+pub struct MethodList(pub Vec<ServiceDescriptor>);
+
+/// The services of `list --tree`, grouped by package, with their methods.
+pub struct ServiceTree(pub Vec<ServiceDescriptor>);
+
+pub struct SearchResults(pub Vec<SearchResult>);
+
 pub struct GenericError<T: Display>(pub &'static str, pub T);
 
+pub struct WorkflowSuccess;
+
+pub struct ExpectationFailures(pub Vec<String>);
+
+/// A titled list of metadata entries (headers or trailers).
+pub struct Metadata(pub &'static str, pub Vec<(String, String)>);
+
+/// The header of the result of one backend, in `call --all-endpoints`.
+pub struct Backend {
+    pub index: usize,
+    pub total: usize,
+    pub address: SocketAddr,
+}
+
+pub struct BackendSummary {
+    pub total: usize,
+    pub failed: usize,
+}
+
+pub struct ReplaySummary {
+    pub total: usize,
+    pub mismatched: usize,
+}
+
 impl std::fmt::Display for FormattedString {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f)?;
@@ -118,6 +160,211 @@ impl From<online::GetDescriptorError> for FormattedString {
     }
 }
 
+impl From<Metadata> for FormattedString {
+    fn from(Metadata(title, entries): Metadata) -> Self {
+        let mut out = format!("{title}:").bold().to_string();
+
+        if entries.is_empty() {
+            out.push_str(&format!("\n  {}", "(none)".dimmed()));
+        }
+
+        for (key, value) in entries {
+            out.push_str(&format!("\n  {}: {}", key.cyan(), value));
+        }
+
+        FormattedString(out)
+    }
+}
+
+impl From<ConfigError> for FormattedString {
+    fn from(err: ConfigError) -> Self {
+        FormattedString(format!("{}\n\n'{}'", "Config Error:".red().bold(), err))
+    }
+}
+
+impl From<WorkflowError> for FormattedString {
+    fn from(err: WorkflowError) -> Self {
+        FormattedString(format!("{}\n\n'{}'", "Workflow Failed:".red().bold(), err))
+    }
+}
+
+impl From<StepReport> for FormattedString {
+    fn from(report: StepReport) -> Self {
+        let mark = if report.failures.is_empty() {
+            "✔".green().bold()
+        } else {
+            "✘".red().bold()
+        };
+
+        let mut out = format!(
+            "{} [{}/{}] {} ({})\n",
+            mark,
+            report.index,
+            report.total,
+            report.name.bold(),
+            report.endpoint.cyan()
+        );
+
+        out.push_str(&FormattedString::from(report.response).0);
+
+        for failure in report.failures {
+            out.push_str(&format!("\n  {} {}", "-".red(), failure.red()));
+        }
+
+        FormattedString(out)
+    }
+}
+
+impl From<WorkflowSuccess> for FormattedString {
+    fn from(_: WorkflowSuccess) -> Self {
+        FormattedString(
+            "Workflow completed successfully."
+                .green()
+                .bold()
+                .to_string(),
+        )
+    }
+}
+
+impl From<ExpectationFailures> for FormattedString {
+    fn from(ExpectationFailures(failures): ExpectationFailures) -> Self {
+        let mut out = format!("{}\n", "Expectations Failed:".red().bold());
+        for failure in failures {
+            out.push_str(&format!("\n  {} {}", "-".red(), failure));
+        }
+        FormattedString(out)
+    }
+}
+
+impl From<SnapshotError> for FormattedString {
+    fn from(err: SnapshotError) -> Self {
+        FormattedString(format!("{}\n\n'{}'", "Snapshot Error:".red().bold(), err))
+    }
+}
+
+impl From<SnapshotOutcome> for FormattedString {
+    fn from(outcome: SnapshotOutcome) -> Self {
+        match outcome {
+            SnapshotOutcome::Missing(path) => FormattedString(format!(
+                "{} '{}' does not exist, run with --update-snapshots to create it.",
+                "Snapshot Missing:".red().bold(),
+                path.display()
+            )),
+            SnapshotOutcome::Created => {
+                FormattedString("Snapshot created.".yellow().bold().to_string())
+            }
+            SnapshotOutcome::Updated => {
+                FormattedString("Snapshot updated.".yellow().bold().to_string())
+            }
+            SnapshotOutcome::Matched => {
+                FormattedString("Snapshot matches.".green().bold().to_string())
+            }
+            SnapshotOutcome::Mismatched(changes) => FormattedString(format!(
+                "{}\n{}",
+                "Snapshot Mismatch:".red().bold(),
+                format_changes(changes)
+            )),
+        }
+    }
+}
+
+impl From<SessionError> for FormattedString {
+    fn from(err: SessionError) -> Self {
+        FormattedString(format!("{}\n\n'{}'", "Session Error:".red().bold(), err))
+    }
+}
+
+impl From<ReplayReport> for FormattedString {
+    fn from(report: ReplayReport) -> Self {
+        let mark = if report.changes.is_empty() {
+            "✔".green().bold()
+        } else {
+            "✘".red().bold()
+        };
+
+        FormattedString(format!(
+            "{} [{}/{}] {} ({}){}",
+            mark,
+            report.index,
+            report.total,
+            report.endpoint.bold(),
+            report.uri.cyan(),
+            format_changes(report.changes)
+        ))
+    }
+}
+
+impl From<ReplaySummary> for FormattedString {
+    fn from(summary: ReplaySummary) -> Self {
+        if summary.mismatched == 0 {
+            FormattedString(
+                format!("All {} calls match the recording.", summary.total)
+                    .green()
+                    .bold()
+                    .to_string(),
+            )
+        } else {
+            FormattedString(
+                format!(
+                    "{} of {} calls differ from the recording.",
+                    summary.mismatched, summary.total
+                )
+                .red()
+                .bold()
+                .to_string(),
+            )
+        }
+    }
+}
+
+impl From<Backend> for FormattedString {
+    fn from(backend: Backend) -> Self {
+        FormattedString(format!(
+            "[{}/{}] {}",
+            backend.index,
+            backend.total,
+            backend.address.to_string().cyan().bold()
+        ))
+    }
+}
+
+impl From<BackendSummary> for FormattedString {
+    fn from(summary: BackendSummary) -> Self {
+        if summary.failed == 0 {
+            FormattedString(
+                format!("All {} backends passed.", summary.total)
+                    .green()
+                    .bold()
+                    .to_string(),
+            )
+        } else {
+            FormattedString(
+                format!("{} of {} backends failed.", summary.failed, summary.total)
+                    .red()
+                    .bold()
+                    .to_string(),
+            )
+        }
+    }
+}
+
+/// Renders a structural diff, one colored line per change.
+fn format_changes(changes: Vec<Change>) -> String {
+    changes
+        .into_iter()
+        .map(|change| {
+            let line = match change {
+                Change::Added(path, value) => format!("+ {path}: {value}").green(),
+                Change::Removed(path, value) => format!("- {path}: {value}").red(),
+                Change::Modified(path, expected, actual) => {
+                    format!("~ {path}: {expected} -> {actual}").yellow()
+                }
+            };
+            format!("\n  {line}")
+        })
+        .collect()
+}
+
 impl From<ServiceList> for FormattedString {
     fn from(ServiceList(services): ServiceList) -> Self {
         if services.is_empty() {
@@ -133,6 +380,100 @@ impl From<ServiceList> for FormattedString {
     }
 }
 
+impl From<MethodList> for FormattedString {
+    fn from(MethodList(services): MethodList) -> Self {
+        if services.is_empty() {
+            return FormattedString("No services found.".yellow().to_string());
+        }
+
+        let mut out = String::new();
+        out.push_str("Available Services:\n");
+        for svc in services {
+            out.push_str(&format!("  - {}\n", svc.full_name().green()));
+            for method in svc.methods() {
+                out.push_str(&format!("      {}\n", method_signature(&method)));
+            }
+        }
+        FormattedString(out.trim_end().to_string())
+    }
+}
+
+impl From<ServiceTree> for FormattedString {
+    fn from(ServiceTree(services): ServiceTree) -> Self {
+        if services.is_empty() {
+            return FormattedString("No services found.".yellow().to_string());
+        }
+
+        let mut packages: Vec<(String, Vec<ServiceDescriptor>)> = vec![];
+        for svc in services {
+            match packages
+                .iter_mut()
+                .find(|(name, _)| name == svc.package_name())
+            {
+                Some((_, services)) => services.push(svc),
+                None => packages.push((svc.package_name().to_string(), vec![svc])),
+            }
+        }
+        packages.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut out = String::new();
+        for (package, services) in packages {
+            let package = if package.is_empty() {
+                "(no package)".to_string()
+            } else {
+                package
+            };
+            out.push_str(&format!("{}\n", package.cyan()));
+
+            for (i, svc) in services.iter().enumerate() {
+                let last_service = i + 1 == services.len();
+                let (branch, prefix) = if last_service {
+                    ("└── ", "    ")
+                } else {
+                    ("├── ", "│   ")
+                };
+                out.push_str(&format!("{branch}{}\n", svc.name().green()));
+
+                let methods: Vec<_> = svc.methods().collect();
+                for (j, method) in methods.iter().enumerate() {
+                    let branch = if j + 1 == methods.len() {
+                        "└── "
+                    } else {
+                        "├── "
+                    };
+                    out.push_str(&format!("{prefix}{branch}{}\n", method_signature(method)));
+                }
+            }
+        }
+        FormattedString(out.trim_end().to_string())
+    }
+}
+
+impl From<SearchResults> for FormattedString {
+    fn from(SearchResults(results): SearchResults) -> Self {
+        if results.is_empty() {
+            return FormattedString("No results found.".yellow().to_string());
+        }
+
+        let mut out = String::new();
+        for result in results {
+            out.push_str(&format!(
+                "{} {}\n",
+                format!("{:<10}", result.kind).cyan(),
+                result.path.green()
+            ));
+            if let Some(comment) = result.comment {
+                out.push_str(&format!(
+                    "{:<10} {}\n",
+                    "",
+                    format!("// {comment}").dimmed()
+                ));
+            }
+        }
+        FormattedString(out.trim_end().to_string())
+    }
+}
+
 impl From<Descriptor> for FormattedString {
     fn from(value: Descriptor) -> Self {
         match value {
@@ -149,15 +490,22 @@ impl From<ServiceDescriptor> for FormattedString {
         out.push_str(&format!(
             "{} {} {{\n",
             "service".cyan(),
-            service.name().green()
+            declared_name(service.name(), options::is_deprecated(&service.options()))
         ));
 
+        let options = option_statements(&service.options());
+        if !options.is_empty() {
+            out.push_str(&options);
+            out.push('\n');
+        }
+
         for method in service.methods() {
-            out.push_str("  ");
             // Reuse the From<MethodDescriptor> implementation
             let method_fmt = FormattedString::from(method);
-            out.push_str(&method_fmt.0);
-            out.push_str("\n\n");
+            for line in method_fmt.0.lines() {
+                out.push_str(&format!("  {line}\n"));
+            }
+            out.push('\n');
         }
         out.push('}');
         FormattedString(out)
@@ -166,84 +514,83 @@ impl From<ServiceDescriptor> for FormattedString {
 
 impl From<MethodDescriptor> for FormattedString {
     fn from(method: MethodDescriptor) -> Self {
-        let input_stream = if method.is_client_streaming() {
-            format!("{} ", "stream".cyan())
-        } else {
-            "".to_string()
-        };
-        let output_stream = if method.is_server_streaming() {
-            format!("{} ", "stream".cyan())
-        } else {
-            "".to_string()
-        };
+        let signature = format!("{} {}", "rpc".cyan(), method_signature(&method));
+        let options = option_statements(&method.options());
 
-        FormattedString(format!(
-            "{} {}({}{}) {} ({}{});",
-            "rpc".cyan(),
-            method.name().green(),
-            input_stream,
-            method.input().full_name().yellow(),
-            "returns".cyan(),
-            output_stream,
-            method.output().full_name().yellow()
-        ))
+        if options.is_empty() {
+            FormattedString(format!("{signature};"))
+        } else {
+            FormattedString(format!("{signature} {{\n{options}}}"))
+        }
     }
 }
 
+/// `Name(stream Input) returns (stream Output)`, with `stream` for each streaming side.
+fn method_signature(method: &MethodDescriptor) -> String {
+    let input_stream = if method.is_client_streaming() {
+        format!("{} ", "stream".cyan())
+    } else {
+        "".to_string()
+    };
+    let output_stream = if method.is_server_streaming() {
+        format!("{} ", "stream".cyan())
+    } else {
+        "".to_string()
+    };
+
+    format!(
+        "{}({}{}) {} ({}{})",
+        declared_name(method.name(), options::is_deprecated(&method.options())),
+        input_stream,
+        method.input().full_name().yellow(),
+        "returns".cyan(),
+        output_stream,
+        method.output().full_name().yellow()
+    )
+}
+
 impl From<MessageDescriptor> for FormattedString {
     fn from(message: MessageDescriptor) -> Self {
         let mut out = String::new();
         out.push_str(&format!(
             "{} {} {{\n",
             "message".cyan(),
-            message.name().green()
+            declared_name(message.name(), options::is_deprecated(&message.options()))
         ));
+        out.push_str(&option_statements(&message.options()));
 
-        for field in message.fields() {
-            let label = if field.is_list() {
-                format!("{} ", "repeated".cyan())
-            } else {
-                "".to_string()
-            };
-
-            let type_name = match field.kind() {
-                Kind::Double => "double".yellow(),
-                Kind::Float => "float".yellow(),
-                Kind::Int32 => "int32".yellow(),
-                Kind::Int64 => "int64".yellow(),
-                Kind::Uint32 => "uint32".yellow(),
-                Kind::Uint64 => "uint64".yellow(),
-                Kind::Sint32 => "sint32".yellow(),
-                Kind::Sint64 => "sint64".yellow(),
-                Kind::Fixed32 => "fixed32".yellow(),
-                Kind::Fixed64 => "fixed64".yellow(),
-                Kind::Sfixed32 => "sfixed32".yellow(),
-                Kind::Sfixed64 => "sfixed64".yellow(),
-                Kind::Bool => "bool".yellow(),
-                Kind::String => "string".yellow(),
-                Kind::Bytes => "bytes".yellow(),
-                Kind::Message(m) => m.full_name().yellow(),
-                Kind::Enum(e) => e.full_name().yellow(),
-            };
+        let reserved: Vec<_> = message
+            .reserved_ranges()
+            .map(|range| reserved_range(range.start, range.end - 1, MAX_FIELD_NUMBER))
+            .collect();
+        out.push_str(&reserved_statements(&reserved, message.reserved_names()));
 
-            if field.is_map() {
-                out.push_str(&format!(
-                    "  // map entry: {} {} = {};\n",
-                    type_name,
-                    field.name(),
-                    field.number()
-                ));
-            } else {
-                out.push_str(&format!(
-                    "  {}{}{} {} = {};\n",
-                    label,
-                    type_name,
-                    " ".normal(), // Reset color
-                    field.name(),
-                    field.number()
-                ));
+        for member in message_members(&message) {
+            match member {
+                MessageMember::Field(field) => {
+                    out.push_str(&format!("  {}\n", field_definition(&field)));
+                }
+                MessageMember::Oneof(oneof) => {
+                    out.push_str(&format!("  {} {} {{\n", "oneof".cyan(), oneof.name()));
+                    out.push_str(&indent(&option_statements(&oneof.options())));
+                    for field in oneof.fields() {
+                        out.push_str(&format!("    {}\n", field_definition(&field)));
+                    }
+                    out.push_str("  }\n");
+                }
             }
         }
+
+        for nested in nested_messages(&message) {
+            out.push('\n');
+            out.push_str(&indent(&FormattedString::from(nested).0));
+        }
+
+        for nested in message.child_enums() {
+            out.push('\n');
+            out.push_str(&indent(&FormattedString::from(nested).0));
+        }
+
         out.push('}');
         FormattedString(out)
     }
@@ -255,14 +602,25 @@ impl From<EnumDescriptor> for FormattedString {
         out.push_str(&format!(
             "{} {} {{\n",
             "enum".cyan(),
-            enum_desc.name().green()
+            declared_name(
+                enum_desc.name(),
+                options::is_deprecated(&enum_desc.options())
+            )
         ));
+        out.push_str(&option_statements(&enum_desc.options()));
+
+        let reserved: Vec<_> = enum_desc
+            .reserved_ranges()
+            .map(|range| reserved_range(*range.start(), *range.end(), i32::MAX))
+            .collect();
+        out.push_str(&reserved_statements(&reserved, enum_desc.reserved_names()));
 
-        for val in enum_desc.values() {
+        for val in enum_values(&enum_desc) {
             out.push_str(&format!(
-                "  {} = {};\n",
-                val.name(),
-                val.number().to_string().purple()
+                "  {} = {}{};\n",
+                field_name(val.name(), options::is_deprecated(&val.options())),
+                val.number().to_string().purple(),
+                option_list(&val.options(), None)
             ));
         }
         out.push('}');
@@ -270,3 +628,435 @@ impl From<EnumDescriptor> for FormattedString {
         FormattedString(out)
     }
 }
+
+/// The largest field number, `max` in reserved ranges.
+const MAX_FIELD_NUMBER: u32 = 536_870_911;
+
+/// A member of a message as declared in a `.proto` file: a field, or a oneof along with its fields.
+pub(crate) enum MessageMember {
+    Field(FieldDescriptor),
+    Oneof(OneofDescriptor),
+}
+
+/// The members of a message in the order of the source. A oneof is declared once, where its first field is.
+pub(crate) fn message_members(message: &MessageDescriptor) -> Vec<MessageMember> {
+    let mut oneofs = HashSet::new();
+
+    // `fields()` is sorted by number, the descriptor keeps the order of the source
+    message
+        .descriptor_proto()
+        .field
+        .iter()
+        .filter_map(|field| message.get_field(field.number() as u32))
+        .filter_map(
+            |field| match field.containing_oneof().filter(|o| !o.is_synthetic()) {
+                None => Some(MessageMember::Field(field)),
+                Some(oneof) => oneofs
+                    .insert(oneof.name().to_string())
+                    .then_some(MessageMember::Oneof(oneof)),
+            },
+        )
+        .collect()
+}
+
+/// The values of an enum in the order of the source.
+pub(crate) fn enum_values(enum_desc: &EnumDescriptor) -> Vec<EnumValueDescriptor> {
+    // `values()` is sorted by number, the descriptor keeps the order of the source
+    enum_desc
+        .enum_descriptor_proto()
+        .value
+        .iter()
+        .filter_map(|value| enum_desc.get_value_by_name(value.name()))
+        .collect()
+}
+
+fn field_definition(field: &FieldDescriptor) -> String {
+    let label = match field_label(field) {
+        "" => String::new(),
+        label => format!("{} ", label.cyan()),
+    };
+
+    format!(
+        "{}{} {} = {}{};",
+        label,
+        field_type_name(field).yellow(),
+        field_name(field.name(), options::is_deprecated(&field.options())),
+        field.number(),
+        option_list(&field.options(), field_default(field))
+    )
+}
+
+/// The default value of a proto2 field as written in a `.proto` file, declared along with the options.
+pub(crate) fn field_default(field: &FieldDescriptor) -> Option<String> {
+    field
+        .field_descriptor_proto()
+        .default_value
+        .as_ref()
+        .map(|value| match field.kind() {
+            Kind::String => format!("\"{}\"", c_escape(value)),
+            // Bytes are already escaped in the descriptor
+            Kind::Bytes => format!("\"{value}\""),
+            _ => value.clone(),
+        })
+}
+
+/// Escapes a string default like `protoc` does: quotes, backslashes and control characters get C-style
+/// escapes, and bytes outside of printable ASCII are written in octal.
+fn c_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'\n' => escaped.push_str("\\n"),
+            b'\r' => escaped.push_str("\\r"),
+            b'\t' => escaped.push_str("\\t"),
+            b'"' => escaped.push_str("\\\""),
+            b'\'' => escaped.push_str("\\'"),
+            b'\\' => escaped.push_str("\\\\"),
+            b' '..=b'~' => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+
+    escaped
+}
+
+/// The label of a field as written in a `.proto` file: `repeated`, `optional`, `required` or empty.
+pub(crate) fn field_label(field: &FieldDescriptor) -> &'static str {
+    let proto3_optional = field
+        .field_descriptor_proto()
+        .proto3_optional
+        .unwrap_or(false);
+
+    match field.cardinality() {
+        _ if field.is_map() => "",
+        Cardinality::Repeated => "repeated",
+        Cardinality::Required => "required",
+        _ if proto3_optional => "optional",
+        // Fields of a oneof have no label
+        _ if field.containing_oneof().is_some() => "",
+        Cardinality::Optional if field.parent_file().syntax() == Syntax::Proto2 => "optional",
+        Cardinality::Optional => "",
+    }
+}
+
+/// The type of a field as written in a `.proto` file (e.g. `int32`, `my.package.Message`, `map<string, int32>`).
+pub(crate) fn field_type_name(field: &FieldDescriptor) -> String {
+    match field.kind() {
+        Kind::Message(entry) if field.is_map() => format!(
+            "map<{}, {}>",
+            kind_name(&entry.map_entry_key_field().kind()),
+            kind_name(&entry.map_entry_value_field().kind())
+        ),
+        kind => kind_name(&kind),
+    }
+}
+
+pub(crate) fn kind_name(kind: &Kind) -> String {
+    match kind {
+        Kind::Message(m) => m.full_name().to_string(),
+        Kind::Enum(e) => e.full_name().to_string(),
+        Kind::Double => "double".to_string(),
+        Kind::Float => "float".to_string(),
+        Kind::Int32 => "int32".to_string(),
+        Kind::Int64 => "int64".to_string(),
+        Kind::Uint32 => "uint32".to_string(),
+        Kind::Uint64 => "uint64".to_string(),
+        Kind::Sint32 => "sint32".to_string(),
+        Kind::Sint64 => "sint64".to_string(),
+        Kind::Fixed32 => "fixed32".to_string(),
+        Kind::Fixed64 => "fixed64".to_string(),
+        Kind::Sfixed32 => "sfixed32".to_string(),
+        Kind::Sfixed64 => "sfixed64".to_string(),
+        Kind::Bool => "bool".to_string(),
+        Kind::String => "string".to_string(),
+        Kind::Bytes => "bytes".to_string(),
+    }
+}
+
+/// Renders an inclusive range of reserved numbers, e.g. `5`, `9 to 11` or `100 to max`.
+fn reserved_range<T: PartialEq + Display>(start: T, end: T, max: T) -> String {
+    if start == end {
+        start.to_string()
+    } else if end == max {
+        format!("{start} to max")
+    } else {
+        format!("{start} to {end}")
+    }
+}
+
+fn reserved_statements<'a>(ranges: &[String], names: impl Iterator<Item = &'a str>) -> String {
+    let mut out = String::new();
+
+    if !ranges.is_empty() {
+        out.push_str(&format!("  {} {};\n", "reserved".cyan(), ranges.join(", ")));
+    }
+
+    let names: Vec<_> = names.map(|name| format!("\"{name}\"")).collect();
+    if !names.is_empty() {
+        out.push_str(&format!("  {} {};\n", "reserved".cyan(), names.join(", ")));
+    }
+
+    out
+}
+
+/// Indents every line of a nested declaration.
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_string()
+            } else {
+                format!("  {line}\n")
+            }
+        })
+        .collect::<String>()
+}
+
+/// Colors the name of a service, method, message or enum for the terminal, struck through when
+/// deprecated.
+fn declared_name(name: &str, deprecated: bool) -> ColoredString {
+    if deprecated {
+        name.green().strikethrough()
+    } else {
+        name.green()
+    }
+}
+
+/// Styles the name of a field or enum value for the terminal, struck through when deprecated.
+fn field_name(name: &str, deprecated: bool) -> ColoredString {
+    if deprecated {
+        name.strikethrough()
+    } else {
+        name.normal()
+    }
+}
+
+/// Renders the options of a service, method, message or enum as `option` statements, one per line.
+fn option_statements(options: &DynamicMessage) -> String {
+    options::decode(options)
+        .into_iter()
+        .map(|option| format!("  {} {};\n", "option".cyan(), option))
+        .collect()
+}
+
+/// Renders the options of a field or enum value as a bracketed list, e.g. ` [deprecated = true]`.
+fn option_list(options: &DynamicMessage, default: Option<String>) -> String {
+    let options: Vec<_> = default
+        .map(|default| format!("default = {default}"))
+        .into_iter()
+        .chain(options::decode(options).iter().map(ToString::to_string))
+        .collect();
+
+    if options.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", options.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    fn compile_protos(files: &[(&str, &str)]) -> DescriptorPool {
+        let file_descriptor_set = compiler::compile_protos(files);
+        DescriptorPool::from_file_descriptor_set(file_descriptor_set)
+            .expect("Failed to decode descriptor pool")
+    }
+
+    #[test]
+    fn test_message_definition() {
+        let proto = r#"
+            syntax = "proto3";
+            package shop.v1;
+
+            message Order {
+                reserved 4, 9 to 11, 100 to max;
+                reserved "legacy";
+
+                message Line {
+                    string sku = 1;
+                }
+
+                enum Kind {
+                    reserved 5;
+                    KIND_UNSPECIFIED = 0;
+                    KIND_RUSH = 2;
+                    KIND_SLOW = 1;
+                }
+
+                string id = 2;
+                optional string note = 1;
+                oneof payment {
+                    string card = 3;
+                    string iban = 5;
+                }
+                repeated Line lines = 6;
+                map<string, Line> lines_by_sku = 7;
+                Kind kind = 8;
+            }
+        "#;
+
+        colored::control::set_override(false);
+
+        let pool = compile_protos(&[("shop.proto", proto)]);
+        let message = pool.get_message_by_name("shop.v1.Order").unwrap();
+
+        // Declarations keep the order of the source, map entries are only declared as map fields
+        assert_eq!(
+            FormattedString::from(message).0,
+            r#"message Order {
+  reserved 4, 9 to 11, 100 to max;
+  reserved "legacy";
+  string id = 2;
+  optional string note = 1;
+  oneof payment {
+    string card = 3;
+    string iban = 5;
+  }
+  repeated shop.v1.Order.Line lines = 6;
+  map<string, shop.v1.Order.Line> lines_by_sku = 7;
+  shop.v1.Order.Kind kind = 8;
+
+  message Line {
+    string sku = 1;
+  }
+
+  enum Kind {
+    reserved 5;
+    KIND_UNSPECIFIED = 0;
+    KIND_RUSH = 2;
+    KIND_SLOW = 1;
+  }
+}"#
+        );
+    }
+
+    #[test]
+    fn test_proto2_message_definition() {
+        let proto = r#"
+            syntax = "proto2";
+            package legacy;
+
+            message Record {
+                required string id = 1;
+                optional int32 count = 2 [default = 5];
+                optional string name = 3 [default = "a \"b\"", deprecated = true];
+                optional string note = 6 [default = "it's\n\tcaf\303\251\001"];
+                oneof choice {
+                    int32 a = 4;
+                    string b = 5;
+                }
+            }
+        "#;
+
+        colored::control::set_override(false);
+
+        let pool = compile_protos(&[("legacy.proto", proto)]);
+        let message = pool.get_message_by_name("legacy.Record").unwrap();
+
+        assert_eq!(
+            FormattedString::from(message).0,
+            r#"message Record {
+  required string id = 1;
+  optional int32 count = 2 [default = 5];
+  optional string name = 3 [default = "a \"b\"", deprecated = true];
+  optional string note = 6 [default = "it\'s\n\tcaf\303\251\001"];
+  oneof choice {
+    int32 a = 4;
+    string b = 5;
+  }
+}"#
+        );
+    }
+
+    #[test]
+    fn test_service_tree_and_method_list() {
+        let shop = r#"
+            syntax = "proto3";
+            package shop.v1;
+
+            message Order {}
+
+            service Orders {
+                rpc PlaceOrder(Order) returns (Order);
+                rpc WatchOrders(Order) returns (stream Order);
+            }
+
+            service Payments {
+                rpc Pay(Order) returns (Order);
+            }
+        "#;
+        let billing = r#"
+            syntax = "proto3";
+            package billing;
+
+            message Invoice {}
+
+            service Invoices {
+                rpc Sync(stream Invoice) returns (stream Invoice);
+            }
+        "#;
+        let health = r#"
+            syntax = "proto3";
+
+            message Ping {}
+
+            service Health {
+                rpc Check(Ping) returns (Ping);
+            }
+        "#;
+
+        colored::control::set_override(false);
+
+        let pool = compile_protos(&[
+            ("shop.proto", shop),
+            ("billing.proto", billing),
+            ("health.proto", health),
+        ]);
+        let services = || {
+            let mut services: Vec<_> = pool.services().collect();
+            services.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+            services
+        };
+
+        // Packages are sorted, services without one go to the "(no package)" bucket
+        assert_eq!(
+            FormattedString::from(ServiceTree(services())).0,
+            r#"(no package)
+└── Health
+    └── Check(Ping) returns (Ping)
+billing
+└── Invoices
+    └── Sync(stream billing.Invoice) returns (stream billing.Invoice)
+shop.v1
+├── Orders
+│   ├── PlaceOrder(shop.v1.Order) returns (shop.v1.Order)
+│   └── WatchOrders(shop.v1.Order) returns (stream shop.v1.Order)
+└── Payments
+    └── Pay(shop.v1.Order) returns (shop.v1.Order)"#
+        );
+
+        assert_eq!(
+            FormattedString::from(MethodList(services())).0,
+            r#"Available Services:
+  - Health
+      Check(Ping) returns (Ping)
+  - billing.Invoices
+      Sync(stream billing.Invoice) returns (stream billing.Invoice)
+  - shop.v1.Orders
+      PlaceOrder(shop.v1.Order) returns (shop.v1.Order)
+      WatchOrders(shop.v1.Order) returns (stream shop.v1.Order)
+  - shop.v1.Payments
+      Pay(shop.v1.Order) returns (shop.v1.Order)"#
+        );
+
+        assert_eq!(
+            FormattedString::from(ServiceTree(vec![])).0,
+            "No services found."
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! # JSONPath
+//!
+//! A minimal JSONPath implementation used to select values from JSON responses.
+//!
+//! Only the subset needed to address a single value is supported:
+//!
+//! * `$` - The root value.
+//! * `.field` or `['field']` - A member of an object.
+//! * `[0]` - An element of an array (negative indexes count from the end).
+//! * `[*]` - Every element of an array. Wildcards can only be used to remove values
+//!   (e.g. ignoring `$[*].createdAt` in every message of a stream), never to select one.
+//!
+//! E.g. `$.books[0].id` or `$['book-list'][-1]`.
+use serde::{Deserialize, Deserializer, de::Error};
+use std::{fmt::Display, str::FromStr};
+
+/// A parsed JSONPath expression.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    raw: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+impl JsonPath {
+    /// Returns the value addressed by this path, if present.
+    pub fn select<'a>(&self, value: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+        self.segments
+            .iter()
+            .try_fold(value, |current, segment| match (segment, current) {
+                (Segment::Key(key), serde_json::Value::Object(map)) => map.get(key),
+                (Segment::Index(index), serde_json::Value::Array(items)) => {
+                    items.get(resolve_index(*index, items.len())?)
+                }
+                _ => None,
+            })
+    }
+
+    /// Removes every value addressed by this path.
+    pub fn remove(&self, value: &mut serde_json::Value) {
+        remove(&self.segments, value);
+    }
+}
+
+fn remove(segments: &[Segment], value: &mut serde_json::Value) {
+    let Some((segment, rest)) = segments.split_first() else {
+        return;
+    };
+
+    match (segment, value) {
+        (Segment::Key(key), serde_json::Value::Object(map)) if rest.is_empty() => {
+            map.remove(key);
+        }
+        (Segment::Key(key), serde_json::Value::Object(map)) => {
+            if let Some(child) = map.get_mut(key) {
+                remove(rest, child);
+            }
+        }
+        (Segment::Index(index), serde_json::Value::Array(items)) => {
+            let Some(index) = resolve_index(*index, items.len()) else {
+                return;
+            };
+
+            if rest.is_empty() {
+                items.remove(index);
+            } else {
+                remove(rest, &mut items[index]);
+            }
+        }
+        (Segment::Wildcard, serde_json::Value::Array(items)) if rest.is_empty() => items.clear(),
+        (Segment::Wildcard, serde_json::Value::Array(items)) => {
+            items.iter_mut().for_each(|item| remove(rest, item));
+        }
+        _ => {}
+    }
+}
+
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 {
+        len.checked_sub(index.unsigned_abs() as usize)?
+    } else {
+        index as usize
+    };
+
+    (index < len).then_some(index)
+}
+
+impl Display for JsonPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = s.trim();
+        let mut rest = raw
+            .strip_prefix('$')
+            .ok_or_else(|| format!("Invalid JSONPath '{raw}': it must start with '$'"))?;
+
+        let mut segments = vec![];
+
+        while !rest.is_empty() {
+            if let Some(after_dot) = rest.strip_prefix('.') {
+                let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+                let key = &after_dot[..end];
+
+                if key.is_empty() {
+                    return Err(format!("Invalid JSONPath '{raw}': empty field name"));
+                }
+
+                segments.push(Segment::Key(key.to_string()));
+                rest = &after_dot[end..];
+            } else if let Some(after_bracket) = rest.strip_prefix('[') {
+                let end = after_bracket
+                    .find(']')
+                    .ok_or_else(|| format!("Invalid JSONPath '{raw}': missing ']'"))?;
+                let inner = after_bracket[..end].trim();
+
+                let segment = match inner
+                    .strip_prefix('\'')
+                    .and_then(|s| s.strip_suffix('\''))
+                    .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')))
+                {
+                    Some(key) => Segment::Key(key.to_string()),
+                    None if inner == "*" => Segment::Wildcard,
+                    None => Segment::Index(inner.parse().map_err(|_| {
+                        format!("Invalid JSONPath '{raw}': '{inner}' is not an index")
+                    })?),
+                };
+
+                segments.push(segment);
+                rest = &after_bracket[end + 1..];
+            } else {
+                return Err(format!(
+                    "Invalid JSONPath '{raw}': unexpected characters '{rest}'"
+                ));
+            }
+        }
+
+        Ok(JsonPath {
+            raw: raw.to_string(),
+            segments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn path(s: &str) -> JsonPath {
+        s.parse().expect("Invalid path")
+    }
+
+    #[test]
+    fn test_select_nested_values() {
+        let value = json!({
+            "book": { "id": "42", "tags": ["a", "b", "c"] },
+            "dashed-key": true
+        });
+
+        assert_eq!(path("$").select(&value), Some(&value));
+        assert_eq!(path("$.book.id").select(&value), Some(&json!("42")));
+        assert_eq!(path("$.book.tags[1]").select(&value), Some(&json!("b")));
+        assert_eq!(path("$.book.tags[-1]").select(&value), Some(&json!("c")));
+        assert_eq!(path("$['dashed-key']").select(&value), Some(&json!(true)));
+        assert_eq!(path("$.book.missing").select(&value), None);
+        assert_eq!(path("$.book.tags[10]").select(&value), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut value = json!({
+            "id": 1,
+            "createdAt": "2026-01-01",
+            "items": [{ "id": 1, "createdAt": "x" }, { "id": 2, "createdAt": "y" }]
+        });
+
+        path("$.createdAt").remove(&mut value);
+        path("$.items[*].createdAt").remove(&mut value);
+        path("$.missing.field").remove(&mut value);
+
+        assert_eq!(
+            value,
+            json!({ "id": 1, "items": [{ "id": 1 }, { "id": 2 }] })
+        );
+
+        path("$.items[-1]").remove(&mut value);
+        assert_eq!(value, json!({ "id": 1, "items": [{ "id": 1 }] }));
+
+        // Wildcards never select a single value
+        assert_eq!(path("$.items[*]").select(&value), None);
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        assert!("book.id".parse::<JsonPath>().is_err());
+        assert!("$.".parse::<JsonPath>().is_err());
+        assert!("$[abc]".parse::<JsonPath>().is_err());
+        assert!("$[0".parse::<JsonPath>().is_err());
+    }
+}
@@ -7,20 +7,75 @@
 //!    (connecting to server vs loading local file).
 //! 3. **Execution**: Delegates request processing to `GrancClient`.
 //! 4. **Presentation**: Formats and prints data.
+mod assertion;
 mod cli;
+mod completion;
+mod config;
 mod docgen;
+mod export;
 mod formatter;
+mod jsonpath;
+mod metadata;
+mod options;
+mod search;
+mod session;
+mod snapshot;
+mod verbose;
+mod workflow;
 
 use clap::Parser;
-use cli::{Cli, Commands, Source};
-use formatter::{FormattedString, GenericError};
-use granc_core::client::{Descriptor, DynamicRequest, DynamicResponse, GrancClient};
-use std::process;
+use clap_complete::env::EnvCompleter;
+use cli::{
+    Cli, Commands, DocFormat, ExportFormat, GraphFormat, ResponseChecks, Shell, Source,
+    SourceSelection,
+};
+use config::Profile;
+use docgen::graph::Graph;
+use docgen::package::Packages;
+use formatter::{ExpectationFailures, FormattedString, GenericError, Metadata};
+use granc_core::{
+    client::{
+        Descriptor, DynamicRequest, DynamicResponse, GrancClient, Online, OnlineWithoutReflection,
+        ResponseMetadata, online_without_reflection::DynamicCallError,
+    },
+    connector::{self, ConnectOptions, Connection},
+    prost_reflect::{DescriptorPool, MethodDescriptor},
+};
+use jsonpath::JsonPath;
+use session::{RecordedCall, ReplayReport, Session};
+use snapshot::SnapshotOutcome;
+use std::{
+    path::PathBuf,
+    process,
+    time::{Duration, Instant},
+};
+use verbose::Verbose;
+use workflow::Workflow;
 
-#[tokio::main]
-async fn main() {
+fn main() {
+    // Answers the completion requests of the shell, before anything is printed. Completers block on their own
+    // runtime, so this happens before the one of the program is started.
+    completion::complete();
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|err| GenericError("Failed to start the async runtime", err))
+        .unwrap_or_exit()
+        .block_on(granc());
+}
+
+async fn granc() {
     let args = Cli::parse();
 
+    let profile = Profile::load(args.profile.as_deref()).unwrap_or_exit();
+
+    if let Some(color) = profile.color {
+        colored::control::set_override(color);
+    }
+
+    let options = args.connection.options(&profile);
+
     match args.command {
         Commands::Call {
             endpoint,
@@ -28,78 +83,578 @@ async fn main() {
             body,
             headers,
             file_descriptor_set,
+            checks,
+            record,
+            verbose,
+            all_endpoints,
         } => {
-            let response = call(endpoint, uri, body, headers, file_descriptor_set).await;
-            println!("{}", FormattedString::from(response))
+            let uri = uri
+                .or_else(|| profile.uri.clone())
+                .ok_or(GenericError(
+                    "Missing server URI",
+                    "Pass --uri or select a profile that defines one",
+                ))
+                .unwrap_or_exit();
+            let headers = profile.headers(headers);
+            let file_descriptor_set = file_descriptor_set.or(profile.file_descriptor_set);
+
+            let (service, method) = endpoint;
+            let request = DynamicRequest {
+                service,
+                method,
+                body,
+                headers,
+            };
+
+            if all_endpoints {
+                let call = CallArgs {
+                    uri: &uri,
+                    request: &request,
+                    file_descriptor_set: file_descriptor_set.as_ref(),
+                    verbose,
+                    compression: args.connection.compress,
+                };
+                if !call_all_endpoints(call, &options, &checks).await {
+                    process::exit(1);
+                }
+                return;
+            }
+
+            let called = call(
+                &uri,
+                &options,
+                request.clone(),
+                file_descriptor_set.as_ref(),
+                verbose.then_some(Verbose {
+                    compression: args.connection.compress,
+                }),
+            )
+            .await
+            .unwrap_or_exit();
+
+            print_response(&called.response, &called.metadata, verbose);
+
+            if let Some(path) = record {
+                let recorded = RecordedCall::new(
+                    &uri,
+                    &called.method,
+                    &request,
+                    &called.response,
+                    &called.metadata,
+                    called.elapsed,
+                );
+                Session::record(&path, recorded).unwrap_or_exit();
+            }
+
+            if !verify(&called.response, &checks) {
+                process::exit(1);
+            }
         }
 
-        Commands::List { source } => {
-            let services = list(source.value()).await;
+        Commands::Run {
+            workflow,
+            uri,
+            file_descriptor_set,
+        } => {
+            run(workflow, uri, file_descriptor_set, profile, &options).await;
+            println!("{}", FormattedString::from(formatter::WorkflowSuccess))
+        }
+
+        Commands::Replay {
+            session,
+            uri,
+            file_descriptor_set,
+            ignores,
+        } => {
+            let uri = uri.or(profile.uri);
+            let file_descriptor_set = file_descriptor_set.or(profile.file_descriptor_set);
+
+            if !replay(session, uri, file_descriptor_set, ignores, &options).await {
+                process::exit(1);
+            }
+        }
+
+        Commands::List {
+            source,
+            methods,
+            tree,
+        } => {
+            let source = source_or_exit(source, &profile);
+
+            if methods || tree {
+                let pool = descriptor_pool(source, &options).await;
+                let mut services: Vec<_> = pool.services().collect();
+                services.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+                let list = if tree {
+                    FormattedString::from(formatter::ServiceTree(services))
+                } else {
+                    FormattedString::from(formatter::MethodList(services))
+                };
+                println!("{list}")
+            } else {
+                let services = list(source, &options).await;
+                println!(
+                    "{}",
+                    FormattedString::from(formatter::ServiceList(services))
+                )
+            }
+        }
+
+        Commands::Search {
+            source,
+            pattern,
+            mode,
+            packages,
+        } => {
+            let pattern = search::Pattern::new(&pattern, mode)
+                .map_err(|e| GenericError("Invalid pattern", e))
+                .unwrap_or_exit();
+
+            let pool = descriptor_pool(source_or_exit(source, &profile), &options).await;
+            let results = search::search(&Packages::from_pool(&pool, &packages), &pattern);
             println!(
                 "{}",
-                FormattedString::from(formatter::ServiceList(services))
+                FormattedString::from(formatter::SearchResults(results))
             )
         }
 
         Commands::Describe { symbol, source } => {
-            let descriptor = describe(symbol, source.value()).await;
+            let descriptor = describe(symbol, source_or_exit(source, &profile), &options).await;
             println!("{}", FormattedString::from(descriptor))
         }
 
-        // Add the Doc handler
         Commands::Doc {
             symbol,
+            packages,
             source,
             output,
+            format,
+            template,
+            graph,
+            ..
         } => {
-            let descriptor = describe(symbol.clone(), source.value()).await;
+            let source = source_or_exit(source, &profile);
 
-            let service_descriptor = descriptor
-                .service_descriptor()
-                .cloned()
-                .ok_or(GenericError("The symbol must be a Service", symbol))
-                .unwrap_or_exit();
+            // Without a service, `--all` is set
+            let packages = match symbol {
+                Some(symbol) => {
+                    let descriptor = describe(symbol.clone(), source, &options).await;
 
-            docgen::markdown::generate(output, service_descriptor)
-                .map_err(|e| GenericError("Failed to generate docs", e))
-                .unwrap_or_exit();
+                    let service_descriptor = descriptor
+                        .service_descriptor()
+                        .cloned()
+                        .ok_or(GenericError("The symbol must be a Service", symbol))
+                        .unwrap_or_exit();
+
+                    Packages::from(service_descriptor)
+                }
+                None => Packages::from_pool(&descriptor_pool(source, &options).await, &packages),
+            };
+
+            match (template, format) {
+                (Some(template), _) => docgen::template::generate(output, &template, packages),
+                (None, DocFormat::Markdown) => {
+                    docgen::markdown::generate(output, packages, graph).map_err(Into::into)
+                }
+                (None, DocFormat::Html) => {
+                    docgen::html::generate(output, packages).map_err(Into::into)
+                }
+            }
+            .map_err(|e| GenericError("Failed to generate docs", e))
+            .unwrap_or_exit();
 
             println!("Documentation generated successfully.");
         }
+
+        Commands::Export {
+            symbol,
+            packages,
+            source,
+            format,
+            output,
+        } => {
+            let source = source_or_exit(source, &profile);
+            let document = export(symbol, &packages, source, format, &options).await;
+            let document = serde_json::to_string_pretty(&document)
+                .map_err(|e| GenericError("Failed to serialize the document", e))
+                .unwrap_or_exit();
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, document).unwrap_or_exit();
+                    println!("Exported: {}", path.display());
+                }
+                None => println!("{document}"),
+            }
+        }
+
+        Commands::Graph {
+            symbol,
+            source,
+            format,
+            output,
+        } => {
+            let descriptor = describe(symbol, source_or_exit(source, &profile), &options).await;
+            let graph = Graph::from(&Packages::from(descriptor));
+
+            let diagram = match format {
+                GraphFormat::Mermaid => graph.mermaid(),
+                GraphFormat::Dot => graph.dot(),
+            };
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, diagram).unwrap_or_exit();
+                    println!("Generated: {}", path.display());
+                }
+                None => print!("{diagram}"),
+            }
+        }
+
+        Commands::Completions { shell } => {
+            let completer: &dyn EnvCompleter = match shell {
+                Shell::Bash => &clap_complete::env::Bash,
+                Shell::Elvish => &clap_complete::env::Elvish,
+                Shell::Fish => &clap_complete::env::Fish,
+                Shell::Powershell => &clap_complete::env::Powershell,
+                Shell::Zsh => &clap_complete::env::Zsh,
+            };
+
+            // The script calls back this binary with `COMPLETE` set, wherever it is installed
+            let bin = std::env::current_exe()
+                .map(|path| path.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| "granc".to_string());
+
+            completer
+                .write_registration("COMPLETE", "granc", "granc", &bin, &mut std::io::stdout())
+                .unwrap_or_exit();
+        }
     }
 }
 
+/// A call performed by `call`.
+struct Called {
+    method: MethodDescriptor,
+    response: DynamicResponse,
+    metadata: ResponseMetadata,
+    elapsed: Duration,
+}
+
+/// Performs a call, returning the method called and the response along with its metadata and the time it took.
 async fn call(
-    endpoint: (String, String),
-    uri: String,
-    body: serde_json::Value,
-    headers: Vec<(String, String)>,
-    file_descriptor_set: Option<std::path::PathBuf>,
-) -> DynamicResponse {
-    let (service, method) = endpoint;
-
-    let request = DynamicRequest {
-        service,
+    uri: &str,
+    options: &ConnectOptions,
+    request: DynamicRequest,
+    file_descriptor_set: Option<&PathBuf>,
+    verbose: Option<Verbose>,
+) -> Result<Called, FormattedString> {
+    let mut client = GrancClient::connect_with(uri, options.clone()).await?;
+    if let Some(verbose) = verbose {
+        client = client.with_interceptor(verbose);
+    }
+
+    let mut client = match file_descriptor_set {
+        Some(path) => {
+            let bytes = std::fs::read(path)?;
+            client.with_file_descriptor(bytes)?
+        }
+        None => {
+            let pool = client
+                .fetch_descriptor_pool(std::slice::from_ref(&request.service))
+                .await?;
+            client.with_descriptor_pool(pool)
+        }
+    };
+
+    let Some(Descriptor::ServiceDescriptor(service)) =
+        client.get_descriptor_by_symbol(&request.service)
+    else {
+        return Err(DynamicCallError::ServiceNotFound(request.service).into());
+    };
+    let method = service
+        .methods()
+        .find(|m| m.name() == request.method)
+        .ok_or_else(|| DynamicCallError::MethodNotFound(request.method.clone()))?;
+
+    let start = Instant::now();
+    let (response, metadata) = client.dynamic_with_metadata(request).await?;
+
+    Ok(Called {
         method,
-        body,
-        headers,
+        response,
+        metadata,
+        elapsed: start.elapsed(),
+    })
+}
+
+/// The call sent to every backend by `call --all-endpoints`.
+struct CallArgs<'a> {
+    uri: &'a str,
+    request: &'a DynamicRequest,
+    file_descriptor_set: Option<&'a PathBuf>,
+    verbose: bool,
+    compression: Option<cli::Compression>,
+}
+
+/// Sends the call to every address the URI resolves to, one after the other, printing the
+/// result of each backend.
+///
+/// Returns `true` if every backend answered and passed the checks.
+async fn call_all_endpoints(
+    call_args: CallArgs<'_>,
+    options: &ConnectOptions,
+    checks: &ResponseChecks,
+) -> bool {
+    let addresses = connector::resolve(call_args.uri).await.unwrap_or_exit();
+    let total = addresses.len();
+    let mut failed = 0;
+
+    for (index, address) in addresses.into_iter().enumerate() {
+        println!(
+            "{}",
+            FormattedString::from(formatter::Backend {
+                index: index + 1,
+                total,
+                address,
+            })
+        );
+
+        let options = options.clone().connect_to(address.to_string());
+        let verbose = call_args.verbose.then_some(Verbose {
+            compression: call_args.compression,
+        });
+
+        let result = call(
+            call_args.uri,
+            &options,
+            call_args.request.clone(),
+            call_args.file_descriptor_set,
+            verbose,
+        )
+        .await;
+
+        match result {
+            Ok(called) => {
+                print_response(&called.response, &called.metadata, call_args.verbose);
+                if !verify(&called.response, checks) {
+                    failed += 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "{}",
+        FormattedString::from(formatter::BackendSummary { total, failed })
+    );
+
+    failed == 0
+}
+
+/// Prints the response of a call, followed by its trailers in verbose mode.
+fn print_response(response: &DynamicResponse, metadata: &ResponseMetadata, verbose: bool) {
+    println!("{}", FormattedString::from(response.clone()));
+
+    // Printed after the response body, like trailers are received after the messages
+    if verbose {
+        println!(
+            "{}",
+            FormattedString::from(Metadata(
+                "Response Trailers",
+                metadata::pairs(&metadata.trailers)
+            ))
+        );
+    }
+}
+
+/// Runs the requested checks against a response, printing any failure.
+///
+/// Returns `true` if every check passed.
+fn verify(response: &DynamicResponse, checks: &ResponseChecks) -> bool {
+    let failures = assertion::check(checks.expect_status, &checks.expectations, response);
+    let mut passed = failures.is_empty();
+
+    if !passed {
+        eprintln!("{}", FormattedString::from(ExpectationFailures(failures)));
+    }
+
+    if let Some(path) = &checks.snapshot {
+        let outcome = snapshot::verify(
+            path,
+            response,
+            &checks.snapshot_ignores,
+            checks.update_snapshots,
+        )
+        .unwrap_or_exit();
+
+        if let SnapshotOutcome::Missing(_) | SnapshotOutcome::Mismatched(_) = outcome {
+            passed = false;
+            eprintln!("{}", FormattedString::from(outcome));
+        } else {
+            println!("{}", FormattedString::from(outcome));
+        }
+    }
+
+    passed
+}
+
+async fn run(
+    path: PathBuf,
+    uri: Option<String>,
+    file_descriptor_set: Option<PathBuf>,
+    profile: Profile,
+    options: &ConnectOptions,
+) {
+    let workflow = Workflow::from_path(&path)
+        .unwrap_or_exit()
+        .with_default_headers(profile.headers);
+
+    // The profile selects the environment, so its URI takes precedence over the one in the workflow file
+    let uri = uri
+        .or(profile.uri)
+        .or_else(|| workflow.uri.clone())
+        .ok_or(GenericError(
+            "Missing server URI",
+            "Pass --uri, select a profile that defines one or set `uri` in the workflow file",
+        ))
+        .unwrap_or_exit();
+
+    let file_descriptor_set = file_descriptor_set.or(profile.file_descriptor_set);
+
+    let mut client = connect(&uri, options).await;
+
+    // Resolve the schema once so every step reuses the same descriptor pool
+    let mut client = match file_descriptor_set {
+        Some(path) => {
+            let bytes = std::fs::read(path).unwrap_or_exit();
+            client.with_file_descriptor(bytes).unwrap_or_exit()
+        }
+        None => {
+            let pool = client
+                .fetch_descriptor_pool(&workflow.services())
+                .await
+                .unwrap_or_exit();
+            client.with_descriptor_pool(pool)
+        }
     };
 
-    let mut client = GrancClient::connect(&uri).await.unwrap_or_exit();
+    workflow
+        .run(&mut client, |report| {
+            println!("{}", FormattedString::from(report))
+        })
+        .await
+        .unwrap_or_exit();
+}
+
+/// Replays every call of a recorded session, printing a report for each of them.
+///
+/// Returns `true` if every response matches its recording.
+async fn replay(
+    path: PathBuf,
+    uri: Option<String>,
+    file_descriptor_set: Option<PathBuf>,
+    ignores: Vec<JsonPath>,
+    options: &ConnectOptions,
+) -> bool {
+    let session = Session::load(&path).unwrap_or_exit();
+    let total = session.calls.len();
+    let mut mismatched = 0;
+
+    // Calls are usually recorded against a single server, so the connection is reused
+    // until a call targets a different URI
+    let mut connection: Option<(String, GrancClient<OnlineWithoutReflection<Connection>>)> = None;
+
+    for (index, call) in session.calls.iter().enumerate() {
+        let target = uri.clone().unwrap_or_else(|| call.uri.clone());
+
+        let client = match &mut connection {
+            Some((current, client)) if *current == target => client,
+            _ => {
+                let client =
+                    connect_for_replay(&target, options, &session, &file_descriptor_set).await;
+                &mut connection.insert((target.clone(), client)).1
+            }
+        };
+
+        let response = client.dynamic(call.request()).await.unwrap_or_exit();
+        let changes = call.compare(&response, &ignores);
+
+        if !changes.is_empty() {
+            mismatched += 1;
+        }
 
-    if let Some(path) = file_descriptor_set {
-        let bytes = std::fs::read(path).unwrap_or_exit();
-        let mut client = client.with_file_descriptor(bytes).unwrap_or_exit();
-        client.dynamic(request).await.unwrap_or_exit()
-    } else {
-        client.dynamic(request).await.unwrap_or_exit()
+        println!(
+            "{}",
+            FormattedString::from(ReplayReport {
+                index: index + 1,
+                total,
+                uri: target,
+                endpoint: format!("{}/{}", call.service, call.method),
+                changes,
+            })
+        );
+    }
+
+    println!(
+        "{}",
+        FormattedString::from(formatter::ReplaySummary { total, mismatched })
+    );
+
+    mismatched == 0
+}
+
+async fn connect_for_replay(
+    uri: &str,
+    options: &ConnectOptions,
+    session: &Session,
+    file_descriptor_set: &Option<PathBuf>,
+) -> GrancClient<OnlineWithoutReflection<Connection>> {
+    let mut client = connect(uri, options).await;
+
+    match file_descriptor_set {
+        Some(path) => {
+            let bytes = std::fs::read(path).unwrap_or_exit();
+            client.with_file_descriptor(bytes).unwrap_or_exit()
+        }
+        None => {
+            let mut services: Vec<_> = session.calls.iter().map(|c| c.service.clone()).collect();
+            services.sort();
+            services.dedup();
+
+            let pool = client
+                .fetch_descriptor_pool(&services)
+                .await
+                .unwrap_or_exit();
+            client.with_descriptor_pool(pool)
+        }
     }
 }
 
-async fn list(source: Source) -> Vec<String> {
+async fn connect(uri: &str, options: &ConnectOptions) -> GrancClient<Online<Connection>> {
+    GrancClient::connect_with(uri, options.clone())
+        .await
+        .unwrap_or_exit()
+}
+
+fn source_or_exit(source: SourceSelection, profile: &Profile) -> Source {
+    source
+        .value(profile)
+        .ok_or(GenericError(
+            "Missing schema source",
+            "Pass --uri, --file-descriptor-set or select a profile that defines one",
+        ))
+        .unwrap_or_exit()
+}
+
+async fn list(source: Source, options: &ConnectOptions) -> Vec<String> {
     match source {
         Source::Uri(uri) => {
-            let mut client = GrancClient::connect(&uri).await.unwrap_or_exit();
+            let mut client = connect(&uri, options).await;
             client
                 .list_services()
                 .await
@@ -115,10 +670,10 @@ async fn list(source: Source) -> Vec<String> {
     }
 }
 
-async fn describe(symbol: String, source: Source) -> Descriptor {
+async fn describe(symbol: String, source: Source, options: &ConnectOptions) -> Descriptor {
     match source {
         Source::Uri(uri) => {
-            let mut client = GrancClient::connect(&uri).await.unwrap_or_exit();
+            let mut client = connect(&uri, options).await;
             client
                 .get_descriptor_by_symbol(&symbol)
                 .await
@@ -136,6 +691,75 @@ async fn describe(symbol: String, source: Source) -> Descriptor {
     }
 }
 
+/// Generates the document of a symbol, or of the whole schema (optionally filtered by package).
+async fn export(
+    symbol: Option<String>,
+    packages: &[String],
+    source: Source,
+    format: ExportFormat,
+    options: &ConnectOptions,
+) -> serde_json::Value {
+    match (format, symbol) {
+        (ExportFormat::Jsonschema, Some(symbol)) => {
+            export::json_schema::generate(describe(symbol, source, options).await)
+        }
+        (ExportFormat::Jsonschema, None) => {
+            let pool = descriptor_pool(source, options).await;
+            export::json_schema::generate_all(&Packages::from_pool(&pool, packages))
+        }
+        (ExportFormat::Openapi, symbol) => {
+            let services = match symbol {
+                Some(symbol) => {
+                    let descriptor = describe(symbol.clone(), source, options).await;
+                    let service = descriptor
+                        .service_descriptor()
+                        .cloned()
+                        .ok_or(GenericError("The symbol must be a Service", symbol))
+                        .unwrap_or_exit();
+                    vec![service]
+                }
+                None => {
+                    let pool = descriptor_pool(source, options).await;
+                    Packages::from_pool(&pool, packages).services()
+                }
+            };
+
+            export::openapi::generate(&services)
+                .map_err(|e| GenericError("Conflicting HTTP routes:", e))
+                .unwrap_or_exit()
+                .ok_or(GenericError(
+                    "No HTTP routes found",
+                    "Annotate the methods with the google.api.http option to export them",
+                ))
+                .unwrap_or_exit()
+        }
+    }
+}
+
+/// Loads the whole schema: the file descriptor set, or every service listed by reflection.
+async fn descriptor_pool(source: Source, options: &ConnectOptions) -> DescriptorPool {
+    match source {
+        Source::Uri(uri) => {
+            let mut client = connect(&uri, options).await;
+            let services = client
+                .list_services()
+                .await
+                .map_err(|e| GenericError("Failed to list services:", e))
+                .unwrap_or_exit();
+
+            client
+                .fetch_descriptor_pool(&services)
+                .await
+                .unwrap_or_exit()
+        }
+
+        Source::File(path) => {
+            let fd_bytes = std::fs::read(path).unwrap_or_exit();
+            DescriptorPool::decode(fd_bytes.as_slice()).unwrap_or_exit()
+        }
+    }
+}
+
 // Utility trait to standardize the way we handle errors in the program
 trait UnwrapOrExit<T, E> {
     fn unwrap_or_exit(self) -> T;
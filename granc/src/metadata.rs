@@ -0,0 +1,210 @@
+//! # Metadata
+//!
+//! This module resolves the values of request headers given with `-H key:value`, and of secrets
+//! such as `--token`, and renders response metadata as text.
+//!
+//! Header values can be given in three ways:
+//!
+//! * `key:value` - A literal value.
+//! * `key:@path` - The contents of a file.
+//! * `key:Bearer ${TOKEN}` - A value referencing environment variables with `${VAR}`.
+//!
+//! Only the `${VAR}` form is expanded, so values containing a `$` (tokens, signatures...) are sent
+//! as given. A literal `${` is written `$${`.
+//!
+//! Keys ending in `-bin` carry binary values, which are expected in base64. When read from a file,
+//! the raw bytes of the file are encoded automatically.
+use base64::{Engine, engine::general_purpose::STANDARD};
+use granc_core::tonic::{codegen::http::HeaderMap, metadata::MetadataMap};
+
+/// Resolves the value of a request header, reading files and expanding environment variables.
+pub fn resolve_value(key: &str, value: &str) -> Result<String, String> {
+    resolve_value_with(key, value, |name| std::env::var(name).ok())
+}
+
+/// Like [`resolve_value`], reading the variables with `lookup` instead of from the process environment.
+pub fn resolve_value_with(
+    key: &str,
+    value: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    resolve(value, key.ends_with("-bin"), lookup).map_err(|e| match e {
+        Unresolved::Unreadable(path, e) => {
+            format!("Failed to read header value from '{path}': {e}")
+        }
+        Unresolved::NotUtf8(_) => {
+            format!("Header '{key}' must be valid UTF-8, use a '-bin' key for binary values")
+        }
+        Unresolved::UndefinedVariable(name) => {
+            format!("Header '{key}' references an undefined environment variable: '{name}'")
+        }
+    })
+}
+
+/// Resolves a value given outside of a header, e.g. a token, the same way as header values.
+pub fn resolve_secret(value: &str) -> Result<String, String> {
+    resolve(value, false, |name| std::env::var(name).ok()).map_err(|e| match e {
+        Unresolved::Unreadable(path, e) => format!("Failed to read '{path}': {e}"),
+        Unresolved::NotUtf8(path) => format!("'{path}' must contain valid UTF-8"),
+        Unresolved::UndefinedVariable(name) => {
+            format!("Undefined environment variable: '{name}'")
+        }
+    })
+}
+
+/// Why a value could not be resolved.
+enum Unresolved {
+    Unreadable(String, std::io::Error),
+    NotUtf8(String),
+    UndefinedVariable(String),
+}
+
+/// Reads the file of an `@path` value, base64-encoded when `binary`, or expands its variables.
+fn resolve(
+    value: &str,
+    binary: bool,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, Unresolved> {
+    if let Some(path) = value.strip_prefix('@') {
+        let bytes = std::fs::read(path).map_err(|e| Unresolved::Unreadable(path.to_string(), e))?;
+
+        return if binary {
+            Ok(STANDARD.encode(bytes))
+        } else {
+            String::from_utf8(bytes)
+                .map(|s| s.trim_end_matches(['\r', '\n']).to_string())
+                .map_err(|_| Unresolved::NotUtf8(path.to_string()))
+        };
+    }
+
+    expand(value, lookup).map_err(Unresolved::UndefinedVariable)
+}
+
+/// Replaces every `${VAR}` with the value of the variable, and `$${` with `${`. Any other `$` is kept.
+///
+/// Returns the name of the first undefined variable as the error.
+fn expand(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        // An escaped `$${`
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start]);
+            out.push('{');
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        // An unterminated `${` is kept as is
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+
+        let name = &rest[start + 2..start + end];
+        out.push_str(&rest[..start]);
+        out.push_str(&lookup(name).ok_or_else(|| name.to_string())?);
+        rest = &rest[start + end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Returns every entry of a metadata map as text, in order, including repeated keys.
+///
+/// Binary (`-bin`) values are rendered in base64, as they are sent on the wire.
+pub fn pairs(metadata: &MetadataMap) -> Vec<(String, String)> {
+    // Binary values are stored encoded, so every value is read the same way
+    let headers: &HeaderMap = metadata.as_ref();
+
+    headers
+        .iter()
+        .map(|(key, value)| {
+            (
+                key.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_literal_and_env() {
+        let lookup = |name: &str| (name == "TOKEN").then(|| "secret".to_string());
+        let resolve = |value| resolve_value_with("authorization", value, lookup);
+
+        assert_eq!(resolve("42").unwrap(), "42");
+        assert_eq!(resolve("Bearer ${TOKEN}").unwrap(), "Bearer secret");
+        assert_eq!(resolve("${TOKEN}:${TOKEN}").unwrap(), "secret:secret");
+        assert!(resolve("Bearer ${UNDEFINED}").is_err());
+
+        // Only `${VAR}` is expanded, other values containing `$` are sent as given
+        assert_eq!(resolve("a$TOKEN$$b$").unwrap(), "a$TOKEN$$b$");
+        assert_eq!(resolve("sig=${unterminated").unwrap(), "sig=${unterminated");
+        assert_eq!(resolve("$${TOKEN}").unwrap(), "${TOKEN}");
+    }
+
+    #[test]
+    fn test_resolve_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let token = dir.path().join("token.txt");
+        std::fs::write(&token, "secret\n").unwrap();
+        let value = format!("@{}", token.display());
+        assert_eq!(resolve_value("authorization", &value).unwrap(), "secret");
+
+        let binary = dir.path().join("data.bin");
+        std::fs::write(&binary, [0x00, 0xff, b'g']).unwrap();
+        let value = format!("@{}", binary.display());
+        assert_eq!(resolve_value("x-data-bin", &value).unwrap(), "AP9n");
+
+        // Binary content needs a binary key
+        assert!(resolve_value("x-data", &value).is_err());
+        assert!(resolve_value("x-data", "@/surely/missing/file").is_err());
+    }
+
+    #[test]
+    fn test_resolve_secret() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret = dir.path().join("secret.txt");
+        std::fs::write(&secret, "s3cret\n").unwrap();
+
+        let value = format!("@{}", secret.display());
+        assert_eq!(resolve_secret(&value).unwrap(), "s3cret");
+        assert_eq!(resolve_secret("s3cret").unwrap(), "s3cret");
+
+        // Errors do not mention a header
+        let error = resolve_secret("${GRANC_SURELY_UNDEFINED}").unwrap_err();
+        assert_eq!(
+            error,
+            "Undefined environment variable: 'GRANC_SURELY_UNDEFINED'"
+        );
+        let error = resolve_secret("@/surely/missing/file").unwrap_err();
+        assert!(error.starts_with("Failed to read '/surely/missing/file'"));
+    }
+
+    #[test]
+    fn test_pairs() {
+        let mut metadata = MetadataMap::new();
+        metadata.append("x-id", "1".parse().unwrap());
+        metadata.append("x-id", "2".parse().unwrap());
+        metadata.append_bin(
+            "x-data-bin",
+            granc_core::tonic::metadata::MetadataValue::from_bytes(&[0x00, 0xff, b'g']),
+        );
+
+        assert_eq!(
+            pairs(&metadata),
+            [
+                ("x-id".to_string(), "1".to_string()),
+                ("x-id".to_string(), "2".to_string()),
+                ("x-data-bin".to_string(), "AP9n".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! # Options
+//!
+//! Decodes the options set on descriptors (`deprecated`, `google.api.http`, `google.api.field_behavior`, custom
+//! extensions...) into the syntax of a `.proto` file, for `describe` and the documentation.
+//!
+//! Extensions are only decoded when their definition is part of the descriptor pool, which is the case when the
+//! file declaring them is imported.
+use granc_core::prost_reflect::{DynamicMessage, Kind, Value};
+use std::fmt::Display;
+
+/// A single option, e.g. `deprecated = true` or `(google.api.http) = { get: "/v1/books/{id}" }`.
+pub struct ProtoOption {
+    /// The name of the option, in parentheses for extensions.
+    pub name: String,
+    /// The value of the option, in the protobuf text format.
+    pub value: String,
+}
+
+impl Display for ProtoOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.name, self.value)
+    }
+}
+
+/// Returns the options that are set, standard options first. Repeated options are returned once per value.
+pub fn decode(options: &DynamicMessage) -> Vec<ProtoOption> {
+    let fields = options
+        .fields()
+        // Options that could not be resolved by the compiler, never set in a compiled descriptor
+        .filter(|(field, _)| field.name() != "uninterpreted_option")
+        .map(|(field, value)| (field.name().to_string(), field.kind(), value));
+
+    let extensions = options.extensions().map(|(extension, value)| {
+        (
+            format!("({})", extension.full_name()),
+            extension.kind(),
+            value,
+        )
+    });
+
+    fields
+        .chain(extensions)
+        .flat_map(|(name, kind, value)| {
+            let values = match value {
+                Value::List(values) => values.iter().collect(),
+                value => vec![value],
+            };
+
+            values
+                .into_iter()
+                .map(|value| ProtoOption {
+                    name: name.clone(),
+                    value: value_text(&kind, value),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Whether the `deprecated` option is set to `true`.
+pub fn is_deprecated(options: &DynamicMessage) -> bool {
+    options
+        .get_field_by_name("deprecated")
+        .is_some_and(|value| value.as_bool() == Some(true))
+}
+
+fn value_text(kind: &Kind, value: &Value) -> String {
+    match (kind, value) {
+        (Kind::Enum(enum_desc), Value::EnumNumber(number)) => enum_desc
+            .get_value(*number)
+            .map(|value| value.name().to_string())
+            .unwrap_or_else(|| number.to_string()),
+        (_, Value::Message(message)) => {
+            // The pretty text format, on a single line
+            let fields: Vec<_> = format!("{message:#}")
+                .lines()
+                .map(str::trim)
+                .map(str::to_string)
+                .collect();
+
+            if fields.is_empty() {
+                "{}".to_string()
+            } else {
+                format!("{{ {} }}", fields.join(" "))
+            }
+        }
+        (_, value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    const HTTP_PROTO: &str = r#"
+        syntax = "proto3";
+        package google.api;
+
+        import "google/protobuf/descriptor.proto";
+
+        extend google.protobuf.MethodOptions {
+            HttpRule http = 72295728;
+        }
+
+        extend google.protobuf.FieldOptions {
+            repeated FieldBehavior field_behavior = 1052;
+        }
+
+        enum FieldBehavior {
+            FIELD_BEHAVIOR_UNSPECIFIED = 0;
+            OPTIONAL = 1;
+            REQUIRED = 2;
+            OUTPUT_ONLY = 3;
+        }
+
+        message HttpRule {
+            oneof pattern {
+                string get = 2;
+                string post = 4;
+            }
+            string body = 7;
+        }
+    "#;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package shop.v1;
+
+        import "google/api/http.proto";
+
+        message Order {
+            string id = 1 [(google.api.field_behavior) = REQUIRED, (google.api.field_behavior) = OUTPUT_ONLY];
+            string note = 2 [deprecated = true];
+            string sku = 3;
+        }
+
+        service Orders {
+            rpc Place(Order) returns (Order) {
+                option deprecated = true;
+                option (google.api.http) = { post: "/v1/orders" body: "*" };
+            }
+        }
+    "#;
+
+    fn pool() -> DescriptorPool {
+        let bytes = compiler::compile_protos_to_bytes(&[
+            ("google/api/http.proto", HTTP_PROTO),
+            ("shop.proto", PROTO),
+        ]);
+        DescriptorPool::decode(bytes.as_slice()).expect("Failed to decode descriptor pool")
+    }
+
+    fn decoded(options: &DynamicMessage) -> Vec<String> {
+        decode(options).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_decode_options() {
+        let pool = pool();
+        let order = pool.get_message_by_name("shop.v1.Order").unwrap();
+
+        let id = order.get_field_by_name("id").unwrap();
+        assert_eq!(
+            decoded(&id.options()),
+            [
+                "(google.api.field_behavior) = REQUIRED",
+                "(google.api.field_behavior) = OUTPUT_ONLY"
+            ]
+        );
+
+        let note = order.get_field_by_name("note").unwrap();
+        assert_eq!(decoded(&note.options()), ["deprecated = true"]);
+        assert!(is_deprecated(&note.options()));
+
+        let sku = order.get_field_by_name("sku").unwrap();
+        assert!(decoded(&sku.options()).is_empty());
+        assert!(!is_deprecated(&sku.options()));
+
+        let method = pool
+            .get_service_by_name("shop.v1.Orders")
+            .unwrap()
+            .methods()
+            .next()
+            .unwrap();
+        assert_eq!(
+            decoded(&method.options()),
+            [
+                "deprecated = true",
+                r#"(google.api.http) = { post: "/v1/orders" body: "*" }"#
+            ]
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! # Search
+//!
+//! Finds the services, methods, messages, fields, enums and enum values of a schema whose name, fully qualified
+//! name or comments match a pattern.
+//!
+//! Comments are only searched when the descriptors include source info (e.g. `protoc --include_source_info`).
+use crate::cli::SearchMode;
+use crate::docgen::model::comments;
+use crate::docgen::package::Packages;
+use granc_core::prost_reflect::{EnumDescriptor, FileDescriptor, MessageDescriptor};
+use regex::{Regex, RegexBuilder};
+use std::fmt::Display;
+
+/// A compiled search pattern.
+pub enum Pattern {
+    /// Case-insensitive substring, stored lowercased.
+    Substring(String),
+    /// Globs are compiled to anchored, case-insensitive regular expressions.
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, mode: SearchMode) -> Result<Self, regex::Error> {
+        match mode {
+            SearchMode::Substring => Ok(Pattern::Substring(pattern.to_lowercase())),
+            SearchMode::Glob => RegexBuilder::new(&glob_to_regex(pattern))
+                .case_insensitive(true)
+                .build()
+                .map(Pattern::Regex),
+            SearchMode::Regex => Regex::new(pattern).map(Pattern::Regex),
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Substring(pattern) => text.to_lowercase().contains(pattern),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResultKind {
+    Service,
+    Method,
+    Message,
+    Field,
+    Enum,
+    EnumValue,
+}
+
+impl Display for ResultKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            ResultKind::Service => "service",
+            ResultKind::Method => "method",
+            ResultKind::Message => "message",
+            ResultKind::Field => "field",
+            ResultKind::Enum => "enum",
+            ResultKind::EnumValue => "enum value",
+        };
+
+        f.pad(kind)
+    }
+}
+
+#[derive(Debug)]
+pub struct SearchResult {
+    pub kind: ResultKind,
+    /// The fully qualified path of the element (`package.Service/Method` for methods).
+    pub path: String,
+    /// The matching line of the comments, when the name does not match.
+    pub comment: Option<String>,
+}
+
+/// Searches every element of the packages, sorted by package, in the order of the schema.
+pub fn search(packages: &Packages, pattern: &Pattern) -> Vec<SearchResult> {
+    let mut search = Search {
+        pattern,
+        results: vec![],
+    };
+
+    let mut packages: Vec<_> = packages.values().collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for package in packages {
+        let mut services = package.services.clone();
+        services.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+        for service in services {
+            let file = service.parent_file();
+            search.visit(
+                ResultKind::Service,
+                service.name(),
+                service.full_name(),
+                &file,
+                service.path(),
+            );
+
+            for method in service.methods() {
+                search.visit(
+                    ResultKind::Method,
+                    method.name(),
+                    &format!("{}/{}", service.full_name(), method.name()),
+                    &file,
+                    method.path(),
+                );
+            }
+        }
+
+        let mut messages = package.messages.clone();
+        messages.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+        for message in messages {
+            search.visit_message(&message);
+        }
+
+        let mut enums = package.enums.clone();
+        enums.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+        for enum_desc in enums {
+            search.visit_enum(&enum_desc);
+        }
+    }
+
+    search.results
+}
+
+struct Search<'a> {
+    pattern: &'a Pattern,
+    results: Vec<SearchResult>,
+}
+
+impl Search<'_> {
+    fn visit(
+        &mut self,
+        kind: ResultKind,
+        name: &str,
+        path: &str,
+        file: &FileDescriptor,
+        at: &[i32],
+    ) {
+        let comment = if self.pattern.is_match(name) || self.pattern.is_match(path) {
+            None
+        } else {
+            let line = comments(file, at).and_then(|comments| {
+                comments
+                    .lines()
+                    .find(|line| self.pattern.is_match(line))
+                    .map(str::to_string)
+            });
+
+            if line.is_none() {
+                return;
+            }
+
+            line
+        };
+
+        self.results.push(SearchResult {
+            kind,
+            path: path.to_string(),
+            comment,
+        });
+    }
+
+    fn visit_message(&mut self, message: &MessageDescriptor) {
+        let file = message.parent_file();
+        self.visit(
+            ResultKind::Message,
+            message.name(),
+            message.full_name(),
+            &file,
+            message.path(),
+        );
+
+        for field in message.fields() {
+            self.visit(
+                ResultKind::Field,
+                field.name(),
+                field.full_name(),
+                &file,
+                field.path(),
+            );
+        }
+    }
+
+    fn visit_enum(&mut self, enum_desc: &EnumDescriptor) {
+        let file = enum_desc.parent_file();
+        self.visit(
+            ResultKind::Enum,
+            enum_desc.name(),
+            enum_desc.full_name(),
+            &file,
+            enum_desc.path(),
+        );
+
+        for value in enum_desc.values() {
+            // Enum values are scoped like their enum, so they are shown under it
+            self.visit(
+                ResultKind::EnumValue,
+                value.name(),
+                &format!("{}.{}", enum_desc.full_name(), value.name()),
+                &file,
+                value.path(),
+            );
+        }
+    }
+}
+
+/// Translates a glob (`*` for any text, `?` for any character) to an anchored regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::prost_reflect::DescriptorPool;
+    use granc_test_support::compiler;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package shop.v1;
+
+        // An order placed by a customer.
+        message Order {
+            string id = 1;
+            // The ISBN of the book ordered.
+            string book_isbn = 2;
+            Status status = 3;
+        }
+
+        enum Status {
+            STATUS_UNSPECIFIED = 0;
+            STATUS_PAID = 1;
+        }
+
+        // Manages orders.
+        service Orders {
+            // Places an order, paid upfront.
+            rpc PlaceOrder(Order) returns (Order);
+            rpc GetOrder(Order) returns (Order);
+        }
+    "#;
+
+    fn packages() -> Packages {
+        let bytes = compiler::compile_protos_to_bytes(&[("shop.proto", PROTO)]);
+        let pool =
+            DescriptorPool::decode(bytes.as_slice()).expect("Failed to decode descriptor pool");
+        Packages::from_pool(&pool, &[])
+    }
+
+    fn results(pattern: &str, mode: SearchMode) -> Vec<(ResultKind, String, Option<String>)> {
+        let pattern = Pattern::new(pattern, mode).unwrap();
+        search(&packages(), &pattern)
+            .into_iter()
+            .map(|result| (result.kind, result.path, result.comment))
+            .collect()
+    }
+
+    #[test]
+    fn test_substring_search() {
+        assert_eq!(
+            results("paid", SearchMode::Substring),
+            [
+                (
+                    ResultKind::Method,
+                    "shop.v1.Orders/PlaceOrder".to_string(),
+                    Some("Places an order, paid upfront.".to_string())
+                ),
+                (
+                    ResultKind::EnumValue,
+                    "shop.v1.Status.STATUS_PAID".to_string(),
+                    None
+                ),
+            ]
+        );
+
+        assert_eq!(
+            results("ISBN", SearchMode::Substring),
+            [(
+                ResultKind::Field,
+                "shop.v1.Order.book_isbn".to_string(),
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn test_glob_search() {
+        let paths: Vec<_> = results("*order", SearchMode::Glob)
+            .into_iter()
+            .map(|(kind, path, _)| (kind, path))
+            .collect();
+
+        assert_eq!(
+            paths,
+            [
+                (ResultKind::Method, "shop.v1.Orders/PlaceOrder".to_string()),
+                (ResultKind::Method, "shop.v1.Orders/GetOrder".to_string()),
+                (ResultKind::Message, "shop.v1.Order".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_regex_search() {
+        let paths: Vec<_> = results("^STATUS_", SearchMode::Regex)
+            .into_iter()
+            .map(|(_, path, _)| path)
+            .collect();
+
+        assert_eq!(
+            paths,
+            [
+                "shop.v1.Status.STATUS_UNSPECIFIED",
+                "shop.v1.Status.STATUS_PAID"
+            ]
+        );
+
+        assert!(Pattern::new("(", SearchMode::Regex).is_err());
+    }
+}
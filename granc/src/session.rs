@@ -0,0 +1,443 @@
+//! # Sessions
+//!
+//! This module implements recording (`granc call --record <FILE>`) and replaying (`granc replay <FILE>`)
+//! of gRPC calls.
+//!
+//! A session file is a JSON document holding every recorded call with everything that was sent and
+//! received: endpoint, request metadata and messages, response headers, messages, trailers, final status
+//! and timing. The format is versioned so it can be committed and reused as a test fixture, served by the
+//! `SessionServer` of `granc-test-support`:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "calls": [
+//!     {
+//!       "uri": "http://localhost:50051",
+//!       "service": "echo.EchoService",
+//!       "method": "UnaryEcho",
+//!       "kind": "unary",
+//!       "metadata": [["authorization", "Bearer token"]],
+//!       "requests": [{ "message": "hello" }],
+//!       "headers": [["content-type", "application/grpc"]],
+//!       "responses": [{ "message": "hello" }],
+//!       "trailers": [["grpc-status", "0"]],
+//!       "status": { "code": "OK", "message": "" },
+//!       "duration_ms": 3
+//!     }
+//!   ]
+//! }
+//! ```
+use crate::{
+    assertion::code_name,
+    jsonpath::JsonPath,
+    metadata,
+    snapshot::{self, Change},
+};
+use granc_core::{
+    client::{DynamicRequest, DynamicResponse, ResponseMetadata},
+    prost_reflect::MethodDescriptor,
+};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, time::Duration};
+
+/// The current version of the session file format.
+const VERSION: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("Failed to access session file: '{0}'")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid session file: '{0}'")]
+    Parse(#[from] serde_json::Error),
+    #[error("Unsupported session file version {0}, expected {VERSION}")]
+    UnsupportedVersion(u32),
+}
+
+/// A collection of recorded calls.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    version: u32,
+    pub calls: Vec<RecordedCall>,
+}
+
+/// The streaming shape of a recorded call.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallKind {
+    Unary,
+    ServerStreaming,
+    ClientStreaming,
+    Bidirectional,
+}
+
+/// The final status of a recorded call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedStatus {
+    pub code: String,
+    pub message: String,
+}
+
+/// Everything that was sent and received during a single call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub uri: String,
+    pub service: String,
+    pub method: String,
+    pub kind: CallKind,
+    /// Request metadata (headers).
+    pub metadata: Vec<(String, String)>,
+    /// Request messages.
+    pub requests: Vec<serde_json::Value>,
+    /// Response headers.
+    pub headers: Vec<(String, String)>,
+    /// Response messages.
+    pub responses: Vec<serde_json::Value>,
+    /// Response trailers.
+    pub trailers: Vec<(String, String)>,
+    pub status: RecordedStatus,
+    pub duration_ms: u64,
+}
+
+/// The outcome of replaying a single recorded call.
+pub struct ReplayReport {
+    pub index: usize,
+    pub total: usize,
+    pub uri: String,
+    pub endpoint: String,
+    /// Differences between the recorded and the new response. Empty if they match.
+    pub changes: Vec<Change>,
+}
+
+impl Session {
+    /// Loads a session file.
+    pub fn load(path: &Path) -> Result<Self, SessionError> {
+        let session: Session = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+        if session.version != VERSION {
+            return Err(SessionError::UnsupportedVersion(session.version));
+        }
+
+        Ok(session)
+    }
+
+    /// Appends a call to the session file at `path`, creating the file if it does not exist.
+    pub fn record(path: &Path, call: RecordedCall) -> Result<(), SessionError> {
+        let mut session = if path.exists() {
+            Session::load(path)?
+        } else {
+            Session {
+                version: VERSION,
+                calls: vec![],
+            }
+        };
+
+        session.calls.push(call);
+
+        std::fs::write(path, serde_json::to_string_pretty(&session)? + "\n")?;
+        Ok(())
+    }
+}
+
+impl RecordedCall {
+    pub fn new(
+        uri: &str,
+        method: &MethodDescriptor,
+        request: &DynamicRequest,
+        response: &DynamicResponse,
+        metadata: &ResponseMetadata,
+        duration: Duration,
+    ) -> Self {
+        let kind = match (method.is_client_streaming(), method.is_server_streaming()) {
+            (false, false) => CallKind::Unary,
+            (false, true) => CallKind::ServerStreaming,
+            (true, false) => CallKind::ClientStreaming,
+            (true, true) => CallKind::Bidirectional,
+        };
+
+        let requests = match &request.body {
+            serde_json::Value::Array(items) => items.clone(),
+            other => vec![other.clone()],
+        };
+
+        let (responses, status) = outcome(response);
+
+        Self {
+            uri: uri.to_string(),
+            service: request.service.clone(),
+            method: request.method.clone(),
+            kind,
+            metadata: request.headers.clone(),
+            requests,
+            headers: metadata::pairs(&metadata.headers),
+            responses,
+            trailers: metadata::pairs(&metadata.trailers),
+            status,
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+
+    /// Rebuilds the request that was originally sent.
+    pub fn request(&self) -> DynamicRequest {
+        let body = match self.kind {
+            CallKind::Unary | CallKind::ServerStreaming => {
+                self.requests.first().cloned().unwrap_or_default()
+            }
+            CallKind::ClientStreaming | CallKind::Bidirectional => {
+                serde_json::Value::Array(self.requests.clone())
+            }
+        };
+
+        DynamicRequest {
+            service: self.service.clone(),
+            method: self.method.clone(),
+            body,
+            headers: self.metadata.clone(),
+        }
+    }
+
+    /// Returns the differences between the recorded response messages and status and a new response.
+    ///
+    /// Metadata and timing are not compared, as they usually change between calls.
+    pub fn compare(&self, response: &DynamicResponse, ignored: &[JsonPath]) -> Vec<Change> {
+        let (responses, status) = outcome(response);
+
+        let mut expected = comparable(&self.responses, &self.status);
+        let mut actual = comparable(&responses, &status);
+
+        for path in ignored {
+            path.remove(&mut expected);
+            path.remove(&mut actual);
+        }
+
+        snapshot::diff(&expected, &actual)
+    }
+}
+
+fn comparable(responses: &[serde_json::Value], status: &RecordedStatus) -> serde_json::Value {
+    serde_json::json!({ "responses": responses, "status": status })
+}
+
+/// Splits a response into its messages and its final status.
+fn outcome(response: &DynamicResponse) -> (Vec<serde_json::Value>, RecordedStatus) {
+    let ok = RecordedStatus {
+        code: code_name(granc_core::tonic::Code::Ok),
+        message: String::new(),
+    };
+
+    let from_status = |status: &granc_core::tonic::Status| RecordedStatus {
+        code: code_name(status.code()),
+        message: status.message().to_string(),
+    };
+
+    match response {
+        DynamicResponse::Unary(Ok(value)) => (vec![value.clone()], ok),
+        DynamicResponse::Unary(Err(status)) | DynamicResponse::Streaming(Err(status)) => {
+            (vec![], from_status(status))
+        }
+        DynamicResponse::Streaming(Ok(items)) => {
+            let messages = items.iter().filter_map(|i| i.as_ref().ok().cloned());
+            let status = items
+                .iter()
+                .find_map(|i| i.as_ref().err())
+                .map_or(ok, from_status);
+            (messages.collect(), status)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use granc_core::{
+        client::GrancClient,
+        prost_reflect::DescriptorPool,
+        tonic::{Code, Status},
+    };
+    use granc_test_support::{compiler, session::SessionServer};
+    use serde_json::json;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package echo;
+
+        message EchoMessage {
+            string message = 1;
+        }
+
+        service EchoService {
+            rpc Echo(EchoMessage) returns (EchoMessage);
+            rpc BidiEcho(stream EchoMessage) returns (stream EchoMessage);
+        }
+    "#;
+
+    fn method(name: &str) -> MethodDescriptor {
+        let bytes = compiler::compile_protos_to_bytes(&[("echo.proto", PROTO)]);
+        let pool =
+            DescriptorPool::decode(bytes.as_slice()).expect("Failed to decode descriptor pool");
+        pool.get_service_by_name("echo.EchoService")
+            .and_then(|service| service.methods().find(|m| m.name() == name))
+            .expect("Method not found")
+    }
+
+    fn request(method: &str, body: serde_json::Value) -> DynamicRequest {
+        DynamicRequest {
+            service: "echo.EchoService".to_string(),
+            method: method.to_string(),
+            body,
+            headers: vec![("authorization".to_string(), "Bearer x".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+
+        let mut metadata = ResponseMetadata::default();
+        metadata
+            .trailers
+            .insert("grpc-status", "0".parse().unwrap());
+
+        let unary = RecordedCall::new(
+            "http://localhost:50051",
+            &method("Echo"),
+            &request("Echo", json!({ "message": "hi" })),
+            &DynamicResponse::Unary(Ok(json!({ "message": "hi" }))),
+            &metadata,
+            Duration::from_millis(5),
+        );
+
+        let bidi = RecordedCall::new(
+            "http://localhost:50051",
+            &method("BidiEcho"),
+            &request("BidiEcho", json!([{ "message": "a" }, { "message": "b" }])),
+            &DynamicResponse::Streaming(Ok(vec![
+                Ok(json!({ "message": "a" })),
+                Err(Status::aborted("stop")),
+            ])),
+            &ResponseMetadata::default(),
+            Duration::from_millis(7),
+        );
+
+        Session::record(&path, unary).unwrap();
+        Session::record(&path, bidi).unwrap();
+
+        let session = Session::load(&path).unwrap();
+        assert_eq!(session.calls.len(), 2);
+
+        let unary = &session.calls[0];
+        assert_eq!(unary.kind, CallKind::Unary);
+        assert_eq!(unary.status.code, "OK");
+        assert_eq!(
+            unary.trailers,
+            [("grpc-status".to_string(), "0".to_string())]
+        );
+        assert_eq!(unary.request().body, json!({ "message": "hi" }));
+        assert_eq!(unary.request().headers, unary.metadata);
+
+        let bidi = &session.calls[1];
+        assert_eq!(bidi.kind, CallKind::Bidirectional);
+        assert_eq!(bidi.responses, [json!({ "message": "a" })]);
+        assert_eq!(bidi.status.code, "ABORTED");
+        assert_eq!(
+            bidi.request().body,
+            json!([{ "message": "a" }, { "message": "b" }])
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        let recorded = RecordedCall::new(
+            "http://localhost:50051",
+            &method("Echo"),
+            &request("Echo", json!({})),
+            &DynamicResponse::Unary(Ok(json!({ "id": 1, "updatedAt": "monday" }))),
+            &ResponseMetadata::default(),
+            Duration::ZERO,
+        );
+
+        let same = DynamicResponse::Unary(Ok(json!({ "id": 1, "updatedAt": "tuesday" })));
+        let ignored = ["$.responses[*].updatedAt".parse().unwrap()];
+        assert!(recorded.compare(&same, &ignored).is_empty());
+        assert_eq!(recorded.compare(&same, &[]).len(), 1);
+
+        let failed = DynamicResponse::Unary(Err(Status::not_found("gone")));
+        assert_eq!(recorded.compare(&failed, &[]).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_replay_as_fixture() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+
+        let mut metadata = ResponseMetadata::default();
+        metadata
+            .headers
+            .insert("x-request-id", "42".parse().unwrap());
+
+        let calls = [
+            (
+                "Echo",
+                json!({ "message": "hi" }),
+                DynamicResponse::Unary(Ok(json!({ "message": "hi" }))),
+            ),
+            (
+                "BidiEcho",
+                json!([{ "message": "a" }, { "message": "b" }]),
+                DynamicResponse::Streaming(Ok(vec![
+                    Ok(json!({ "message": "a" })),
+                    Err(Status::aborted("stop")),
+                ])),
+            ),
+        ];
+
+        for (name, body, response) in &calls {
+            let call = RecordedCall::new(
+                "http://localhost:50051",
+                &method(name),
+                &request(name, body.clone()),
+                response,
+                &metadata,
+                Duration::ZERO,
+            );
+            Session::record(&path, call).unwrap();
+        }
+
+        // The recorded session is served back by the mock server of granc-test-support
+        let bytes = compiler::compile_protos_to_bytes(&[("echo.proto", PROTO)]);
+        let server = SessionServer::load(&path, &bytes);
+        let mut client = GrancClient::from(server)
+            .with_file_descriptor(bytes)
+            .unwrap();
+
+        let session = Session::load(&path).unwrap();
+        for recorded in &session.calls {
+            let (response, metadata) = client
+                .dynamic_with_metadata(recorded.request())
+                .await
+                .unwrap();
+
+            assert!(recorded.compare(&response, &[]).is_empty());
+            assert_eq!(metadata.headers.get("x-request-id").unwrap(), "42");
+        }
+
+        // Each recorded call is replayed once
+        let response = client.dynamic(session.calls[0].request()).await.unwrap();
+        assert!(
+            matches!(response, DynamicResponse::Unary(Err(status)) if status.code() == Code::Unimplemented)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        std::fs::write(&path, r#"{ "version": 99, "calls": [] }"#).unwrap();
+
+        assert!(matches!(
+            Session::load(&path),
+            Err(SessionError::UnsupportedVersion(99))
+        ));
+    }
+}
@@ -0,0 +1,266 @@
+//! # Snapshots
+//!
+//! This module implements snapshot testing for `granc call --snapshot <FILE>`.
+//!
+//! A snapshot is the JSON representation of a response stored on disk. Subsequent calls compare their
+//! response against it and report a structural diff of every added, removed or changed field.
+//! Fields that legitimately change between calls (timestamps, generated ids...) can be ignored with
+//! JSONPath expressions.
+use crate::{assertion::code_name, jsonpath::JsonPath};
+use granc_core::{client::DynamicResponse, tonic::Status};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("Failed to access snapshot file: '{0}'")]
+    Io(#[from] std::io::Error),
+    #[error("Snapshot file is not valid JSON: '{0}'")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// The result of verifying a response against a snapshot.
+pub enum SnapshotOutcome {
+    /// The snapshot does not exist, and is only created when updating snapshots.
+    Missing(PathBuf),
+    /// The snapshot did not exist and has been created.
+    Created,
+    /// The snapshot has been overwritten with the current response.
+    Updated,
+    /// The response matches the snapshot.
+    Matched,
+    /// The response differs from the snapshot.
+    Mismatched(Vec<Change>),
+}
+
+/// A single difference between the snapshot and the response.
+pub enum Change {
+    Added(String, serde_json::Value),
+    Removed(String, serde_json::Value),
+    Modified(String, serde_json::Value, serde_json::Value),
+}
+
+/// Compares a response against the snapshot stored at `path`.
+///
+/// The snapshot is only written when `update` is set, so that a missing snapshot fails the check
+/// rather than silently recording whatever the server returns.
+pub fn verify(
+    path: &Path,
+    response: &DynamicResponse,
+    ignored: &[JsonPath],
+    update: bool,
+) -> Result<SnapshotOutcome, SnapshotError> {
+    let actual = document(response);
+    let existed = path.exists();
+
+    if update {
+        std::fs::write(path, serde_json::to_string_pretty(&actual)? + "\n")?;
+
+        return Ok(if existed {
+            SnapshotOutcome::Updated
+        } else {
+            SnapshotOutcome::Created
+        });
+    }
+
+    if !existed {
+        return Ok(SnapshotOutcome::Missing(path.to_path_buf()));
+    }
+
+    let mut expected: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let mut actual = actual;
+
+    for path in ignored {
+        path.remove(&mut expected);
+        path.remove(&mut actual);
+    }
+
+    let changes = diff(&expected, &actual);
+
+    Ok(if changes.is_empty() {
+        SnapshotOutcome::Matched
+    } else {
+        SnapshotOutcome::Mismatched(changes)
+    })
+}
+
+/// Returns the JSON document stored in snapshots for a response.
+///
+/// Successful responses are stored as their JSON body (an array of messages for streaming calls),
+/// while errors are stored as `{"error": {"code": "NOT_FOUND", "message": "..."}}`.
+fn document(response: &DynamicResponse) -> serde_json::Value {
+    match response {
+        DynamicResponse::Unary(Ok(value)) => value.clone(),
+        DynamicResponse::Unary(Err(status)) | DynamicResponse::Streaming(Err(status)) => {
+            error_document(status)
+        }
+        DynamicResponse::Streaming(Ok(items)) => items
+            .iter()
+            .map(|item| match item {
+                Ok(value) => value.clone(),
+                Err(status) => error_document(status),
+            })
+            .collect(),
+    }
+}
+
+fn error_document(status: &Status) -> serde_json::Value {
+    serde_json::json!({
+        "error": {
+            "code": code_name(status.code()),
+            "message": status.message(),
+        }
+    })
+}
+
+/// Computes the structural differences between two JSON values.
+pub fn diff(expected: &serde_json::Value, actual: &serde_json::Value) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_at("$".to_string(), expected, actual, &mut changes);
+    changes
+}
+
+fn diff_at(
+    path: String,
+    expected: &serde_json::Value,
+    actual: &serde_json::Value,
+    changes: &mut Vec<Change>,
+) {
+    match (expected, actual) {
+        (serde_json::Value::Object(expected), serde_json::Value::Object(actual)) => {
+            for (key, expected_value) in expected {
+                let child = child_path(&path, key);
+                match actual.get(key) {
+                    Some(actual_value) => diff_at(child, expected_value, actual_value, changes),
+                    None => changes.push(Change::Removed(child, expected_value.clone())),
+                }
+            }
+
+            for (key, actual_value) in actual {
+                if !expected.contains_key(key) {
+                    changes.push(Change::Added(child_path(&path, key), actual_value.clone()));
+                }
+            }
+        }
+        (serde_json::Value::Array(expected), serde_json::Value::Array(actual)) => {
+            for i in 0..expected.len().max(actual.len()) {
+                let child = format!("{path}[{i}]");
+                match (expected.get(i), actual.get(i)) {
+                    (Some(e), Some(a)) => diff_at(child, e, a, changes),
+                    (Some(e), None) => changes.push(Change::Removed(child, e.clone())),
+                    (None, Some(a)) => changes.push(Change::Added(child, a.clone())),
+                    (None, None) => unreachable!("index is bounded by the longest array"),
+                }
+            }
+        }
+        (expected, actual) if expected != actual => {
+            changes.push(Change::Modified(path, expected.clone(), actual.clone()))
+        }
+        _ => {}
+    }
+}
+
+fn child_path(parent: &str, key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        format!("{parent}.{key}")
+    } else {
+        format!("{parent}['{key}']")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff() {
+        let expected = json!({
+            "id": "1",
+            "title": "Dune",
+            "tags": ["a", "b"],
+            "author-name": "Frank"
+        });
+        let actual = json!({
+            "id": "1",
+            "title": "Dune Messiah",
+            "tags": ["a"],
+            "author-name": "Frank",
+            "pages": 256
+        });
+
+        let changes = diff(&expected, &actual);
+
+        assert_eq!(changes.len(), 3);
+        assert!(
+            changes
+                .iter()
+                .any(|c| matches!(c, Change::Modified(p, _, _) if p == "$.title"))
+        );
+        assert!(
+            changes
+                .iter()
+                .any(|c| matches!(c, Change::Removed(p, v) if p == "$.tags[1]" && v == "b"))
+        );
+        assert!(
+            changes
+                .iter()
+                .any(|c| matches!(c, Change::Added(p, v) if p == "$.pages" && v == 256))
+        );
+
+        assert!(diff(&expected, &expected).is_empty());
+    }
+
+    #[test]
+    fn test_document() {
+        let streaming = DynamicResponse::Streaming(Ok(vec![
+            Ok(json!({ "id": 1 })),
+            Err(Status::not_found("gone")),
+        ]));
+
+        assert_eq!(
+            document(&streaming),
+            json!([
+                { "id": 1 },
+                { "error": { "code": "NOT_FOUND", "message": "gone" } }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_verify_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        let ignored = ["$.createdAt".parse().unwrap()];
+
+        let first = DynamicResponse::Unary(Ok(json!({ "id": 1, "createdAt": "monday" })));
+        let second = DynamicResponse::Unary(Ok(json!({ "id": 1, "createdAt": "tuesday" })));
+        let changed = DynamicResponse::Unary(Ok(json!({ "id": 2, "createdAt": "tuesday" })));
+
+        assert!(matches!(
+            verify(&path, &first, &ignored, false).unwrap(),
+            SnapshotOutcome::Missing(missing) if missing == path
+        ));
+        assert!(!path.exists());
+
+        assert!(matches!(
+            verify(&path, &first, &ignored, true).unwrap(),
+            SnapshotOutcome::Created
+        ));
+        assert!(matches!(
+            verify(&path, &second, &ignored, false).unwrap(),
+            SnapshotOutcome::Matched
+        ));
+        assert!(matches!(
+            verify(&path, &changed, &ignored, false).unwrap(),
+            SnapshotOutcome::Mismatched(changes) if changes.len() == 1
+        ));
+        assert!(matches!(
+            verify(&path, &changed, &ignored, true).unwrap(),
+            SnapshotOutcome::Updated
+        ));
+        assert!(matches!(
+            verify(&path, &changed, &[], false).unwrap(),
+            SnapshotOutcome::Matched
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+//! # Verbose Output
+//!
+//! This module implements the output of `call --verbose` as a `granc-core` [`Interceptor`],
+//! printing the request metadata before the call, and the response headers and compression
+//! after it.
+//!
+//! The response trailers are printed by the caller, after the response body.
+use crate::{
+    cli::Compression,
+    formatter::{FormattedString, Metadata},
+    metadata,
+};
+use granc_core::{
+    client::{
+        DynamicRequest,
+        interceptor::{CallOutcome, Interceptor},
+    },
+    prost_reflect::MethodDescriptor,
+    tonic::Status,
+};
+
+/// Prints the metadata exchanged with the server around a call.
+#[derive(Debug)]
+pub struct Verbose {
+    /// The encoding requests are compressed with, if any.
+    pub compression: Option<Compression>,
+}
+
+impl Interceptor for Verbose {
+    fn before_call(
+        &self,
+        _: &MethodDescriptor,
+        request: &mut DynamicRequest,
+    ) -> Result<(), Status> {
+        println!(
+            "{}",
+            FormattedString::from(Metadata("Request Metadata", request.headers.clone()))
+        );
+        Ok(())
+    }
+
+    fn after_call(&self, _: &MethodDescriptor, _: &DynamicRequest, outcome: &CallOutcome<'_>) {
+        let headers = &outcome.metadata.headers;
+
+        println!(
+            "{}",
+            FormattedString::from(Metadata("Response Headers", metadata::pairs(headers)))
+        );
+
+        let request_encoding = self.compression.map_or("identity", Compression::name);
+        let response_encoding = headers
+            .get("grpc-encoding")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("identity");
+
+        println!(
+            "{}",
+            FormattedString::from(Metadata(
+                "Compression",
+                vec![
+                    ("request".to_string(), request_encoding.to_string()),
+                    ("response".to_string(), response_encoding.to_string()),
+                ]
+            ))
+        );
+    }
+}
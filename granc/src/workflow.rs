@@ -0,0 +1,452 @@
+//! # Workflow
+//!
+//! This module implements `granc run`, which executes a sequence of calls described in a YAML file.
+//!
+//! Each step names a `package.Service/Method`, a body and optional headers. Strings in bodies and headers
+//! can reference variables with `${name}`, which resolve to values captured from previous responses
+//! (via JSONPath) or, as a fallback, to environment variables.
+//!
+//! ```yaml
+//! uri: http://localhost:50051
+//! headers:
+//!   authorization: Bearer ${TOKEN}
+//! steps:
+//!   - name: create
+//!     call: library.LibraryService/CreateBook
+//!     body: { "title": "Dune" }
+//!     capture:
+//!       book_id: $.id
+//!   - name: get
+//!     call: library.LibraryService/GetBook
+//!     body: { "id": "${book_id}" }
+//!     expect:
+//!       status: OK
+//!       fields:
+//!         $.title: Dune
+//! ```
+//!
+//! All steps share a single connection and a single descriptor pool.
+use crate::{
+    assertion::{self, Expectation},
+    cli,
+    jsonpath::JsonPath,
+};
+use granc_core::{
+    client::online_without_reflection::DynamicCallError,
+    client::{DynamicRequest, DynamicResponse, GrancClient, OnlineWithoutReflection},
+    connector::Connection,
+    tonic::Code,
+};
+use serde::{Deserialize, Deserializer, de::Error as _};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkflowError {
+    #[error("Failed to read workflow file: '{0}'")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid workflow file: '{0}'")]
+    Parse(#[from] serde_yaml::Error),
+    #[error("Step '{step}' references an undefined variable '{name}'")]
+    UndefinedVariable { step: String, name: String },
+    #[error("Step '{step}' could not capture '{name}': path '{path}' not found in the response")]
+    CaptureFailed {
+        step: String,
+        name: String,
+        path: JsonPath,
+    },
+    #[error("Step '{step}' failed: '{source}'")]
+    Call {
+        step: String,
+        #[source]
+        source: DynamicCallError,
+    },
+    #[error("Step '{0}' did not meet its expectations")]
+    ExpectationFailed(String),
+}
+
+/// A sequence of calls to execute against a single server.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+    /// Default server URI, used when `--uri` is not given.
+    pub uri: Option<String>,
+    /// Headers attached to every step.
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    name: Option<String>,
+    #[serde(deserialize_with = "deserialize_endpoint")]
+    call: (String, String),
+    #[serde(default = "empty_object")]
+    body: serde_json::Value,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    capture: BTreeMap<String, JsonPath>,
+    #[serde(default)]
+    expect: Expectations,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expectations {
+    #[serde(default = "ok_code", deserialize_with = "deserialize_code")]
+    status: Code,
+    #[serde(default, deserialize_with = "deserialize_fields")]
+    fields: Vec<Expectation>,
+}
+
+impl Default for Expectations {
+    fn default() -> Self {
+        Self {
+            status: ok_code(),
+            fields: vec![],
+        }
+    }
+}
+
+/// The outcome of a single executed step.
+pub struct StepReport {
+    pub index: usize,
+    pub total: usize,
+    pub name: String,
+    pub endpoint: String,
+    pub response: DynamicResponse,
+    /// Human readable descriptions of the expectations that were not met.
+    pub failures: Vec<String>,
+}
+
+impl Workflow {
+    pub fn from_path(path: &Path) -> Result<Self, WorkflowError> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&content)?)
+    }
+
+    /// Adds headers to every step, unless the workflow already defines a header with the same name.
+    pub fn with_default_headers(mut self, headers: BTreeMap<String, String>) -> Self {
+        for (key, value) in headers {
+            if !self.headers.keys().any(|k| k.eq_ignore_ascii_case(&key)) {
+                self.headers.insert(key, value);
+            }
+        }
+        self
+    }
+
+    /// Returns the distinct services called by the workflow, used to resolve the schema up front.
+    pub fn services(&self) -> Vec<String> {
+        let mut services: Vec<_> = self.steps.iter().map(|s| s.call.0.clone()).collect();
+        services.sort();
+        services.dedup();
+        services
+    }
+
+    /// Executes every step in order, stopping at the first step that fails.
+    ///
+    /// `on_step` is invoked after each step completes, including the failing one.
+    pub async fn run(
+        self,
+        client: &mut GrancClient<OnlineWithoutReflection<Connection>>,
+        mut on_step: impl FnMut(StepReport),
+    ) -> Result<(), WorkflowError> {
+        let mut variables = HashMap::new();
+        let total = self.steps.len();
+
+        for (index, step) in self.steps.into_iter().enumerate() {
+            let (service, method) = step.call;
+            let endpoint = format!("{service}/{method}");
+            let name = step.name.unwrap_or_else(|| endpoint.clone());
+
+            let undefined = |var: String| WorkflowError::UndefinedVariable {
+                step: name.clone(),
+                name: var,
+            };
+
+            let headers = merge_headers(&self.headers, &step.headers)
+                .map(|(k, v)| Ok((k.clone(), interpolate_str(v, &variables)?)))
+                .collect::<Result<Vec<_>, String>>()
+                .map_err(undefined)?;
+
+            let body = interpolate(step.body, &variables).map_err(undefined)?;
+
+            let request = DynamicRequest {
+                service,
+                method,
+                body,
+                headers,
+            };
+
+            let response = client
+                .dynamic(request)
+                .await
+                .map_err(|source| WorkflowError::Call {
+                    step: name.clone(),
+                    source,
+                })?;
+
+            let failures =
+                assertion::check(Some(step.expect.status), &step.expect.fields, &response);
+            let passed = failures.is_empty();
+            let value = assertion::response_value(&response);
+
+            on_step(StepReport {
+                index: index + 1,
+                total,
+                name: name.clone(),
+                endpoint,
+                response,
+                failures,
+            });
+
+            if !passed {
+                return Err(WorkflowError::ExpectationFailed(name));
+            }
+
+            for (var, path) in step.capture {
+                let captured = value
+                    .as_ref()
+                    .and_then(|value| path.select(value))
+                    .cloned()
+                    .ok_or_else(|| WorkflowError::CaptureFailed {
+                        step: name.clone(),
+                        name: var.clone(),
+                        path: path.clone(),
+                    })?;
+
+                variables.insert(var, captured);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces `${name}` references in every string of a JSON value.
+///
+/// A string consisting of a single reference is replaced by the referenced value itself,
+/// so captured numbers, objects or arrays keep their JSON type.
+fn interpolate(
+    value: serde_json::Value,
+    variables: &HashMap<String, serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    match value {
+        serde_json::Value::String(s) => {
+            if let Some(name) = s
+                .strip_prefix("${")
+                .and_then(|s| s.strip_suffix('}'))
+                .filter(|name| !name.contains(['$', '{', '}']))
+            {
+                return lookup(name, variables);
+            }
+            interpolate_str(&s, variables).map(serde_json::Value::String)
+        }
+        serde_json::Value::Array(items) => items
+            .into_iter()
+            .map(|item| interpolate(item, variables))
+            .collect::<Result<_, _>>()
+            .map(serde_json::Value::Array),
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(k, v)| Ok((k, interpolate(v, variables)?)))
+            .collect::<Result<_, _>>()
+            .map(serde_json::Value::Object),
+        other => Ok(other),
+    }
+}
+
+/// Replaces `${name}` references inside a string with the textual representation of their values.
+fn interpolate_str(
+    input: &str,
+    variables: &HashMap<String, serde_json::Value>,
+) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            // Unterminated reference, keep it as literal text
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+
+        match lookup(&after[..end], variables)? {
+            serde_json::Value::String(s) => out.push_str(&s),
+            other => out.push_str(&other.to_string()),
+        }
+
+        rest = &after[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn lookup(
+    name: &str,
+    variables: &HashMap<String, serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    variables
+        .get(name)
+        .cloned()
+        .or_else(|| std::env::var(name).ok().map(serde_json::Value::String))
+        .ok_or_else(|| name.to_string())
+}
+
+fn empty_object() -> serde_json::Value {
+    serde_json::Value::Object(Default::default())
+}
+
+fn ok_code() -> Code {
+    Code::Ok
+}
+
+fn deserialize_endpoint<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<(String, String), D::Error> {
+    cli::parse_endpoint(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+fn deserialize_fields<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Expectation>, D::Error> {
+    BTreeMap::<String, serde_json::Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(path, value)| {
+            let path = path.parse().map_err(D::Error::custom)?;
+            Ok(Expectation::equals(path, value))
+        })
+        .collect()
+}
+
+/// The headers of the workflow, overridden by the headers of the step with the same name.
+fn merge_headers<'a>(
+    workflow: &'a BTreeMap<String, String>,
+    step: &'a BTreeMap<String, String>,
+) -> impl Iterator<Item = (&'a String, &'a String)> {
+    workflow
+        .iter()
+        .filter(|(key, _)| !step.keys().any(|k| k.eq_ignore_ascii_case(key)))
+        .chain(step.iter())
+}
+
+fn deserialize_code<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Code, D::Error> {
+    assertion::parse_code(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_workflow() {
+        let yaml = r#"
+            uri: http://localhost:50051
+            headers:
+              authorization: Bearer ${TOKEN}
+            steps:
+              - name: create
+                call: library.LibraryService/CreateBook
+                body: { "title": "Dune" }
+                capture:
+                  book_id: $.id
+              - call: library.LibraryService/GetBook
+                body: { "id": "${book_id}" }
+                expect:
+                  status: NOT_FOUND
+                  fields:
+                    $.title: Dune
+        "#;
+
+        let workflow: Workflow = serde_yaml::from_str(yaml).expect("Parsing failed");
+
+        assert_eq!(workflow.uri.as_deref(), Some("http://localhost:50051"));
+        assert_eq!(workflow.steps.len(), 2);
+        assert_eq!(workflow.services(), ["library.LibraryService"]);
+
+        let create = &workflow.steps[0];
+        assert_eq!(create.name.as_deref(), Some("create"));
+        assert_eq!(create.expect.status, Code::Ok);
+        assert!(create.capture.contains_key("book_id"));
+
+        let get = &workflow.steps[1];
+        assert_eq!(get.call.1, "GetBook");
+        assert_eq!(get.expect.status, Code::NotFound);
+        assert_eq!(get.expect.fields.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_workflow_invalid_endpoint() {
+        let yaml = r#"
+            steps:
+              - call: NoMethod
+        "#;
+
+        let err = serde_yaml::from_str::<Workflow>(yaml).unwrap_err();
+        assert!(err.to_string().contains("Invalid endpoint format"));
+    }
+
+    #[test]
+    fn test_merge_headers() {
+        let workflow = BTreeMap::from([
+            ("Authorization".to_string(), "Bearer workflow".to_string()),
+            ("x-tenant".to_string(), "acme".to_string()),
+        ]);
+        let step = BTreeMap::from([("authorization".to_string(), "Bearer step".to_string())]);
+
+        let headers: Vec<_> = merge_headers(&workflow, &step).collect();
+        assert_eq!(
+            headers,
+            [
+                (&"x-tenant".to_string(), &"acme".to_string()),
+                (&"authorization".to_string(), &"Bearer step".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let variables = HashMap::from([
+            ("id".to_string(), json!(42)),
+            ("name".to_string(), json!("Ferris")),
+            ("tags".to_string(), json!(["a", "b"])),
+        ]);
+
+        let body = json!({
+            "id": "${id}",
+            "greeting": "Hello ${name}, you are number ${id}",
+            "tags": "${tags}",
+            "nested": [{ "name": "${name}" }],
+            "untouched": 1
+        });
+
+        let result = interpolate(body, &variables).unwrap();
+
+        assert_eq!(
+            result,
+            json!({
+                "id": 42,
+                "greeting": "Hello Ferris, you are number 42",
+                "tags": ["a", "b"],
+                "nested": [{ "name": "Ferris" }],
+                "untouched": 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_interpolation_undefined_variable() {
+        let err = interpolate_str("Bearer ${GRANC_SURELY_UNDEFINED_VAR}", &HashMap::new());
+        assert_eq!(err.unwrap_err(), "GRANC_SURELY_UNDEFINED_VAR");
+    }
+}